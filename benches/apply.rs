@@ -0,0 +1,44 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use zebra::database::{Database, TableTransaction};
+
+// Latency of executing a small transaction on a large `Table`, when
+// forking every branch (threshold 1), when forking only branches of
+// at least the default threshold, and when never forking
+fn small_batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("small_batch");
+
+    let databases: [(&str, Database<u32, u32>); 3] = [
+        ("fork_all", Database::with_parallel_threshold(1)),
+        ("default", Database::new()),
+        ("sequential", Database::with_parallel_threshold(usize::MAX)),
+    ];
+
+    for (name, database) in databases {
+        let mut table = database.table_with_records((0..65536).map(|i| (i, i)));
+        let mut round = 0;
+
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || {
+                    round += 1;
+
+                    let mut transaction = TableTransaction::new();
+
+                    for key in (0..65536).step_by(4096) {
+                        transaction.set(key, round).unwrap();
+                    }
+
+                    transaction
+                },
+                |transaction| table.execute(transaction),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, small_batch);
+criterion_main!(benches);
@@ -0,0 +1,195 @@
+//! Authentication paths, shared by the proofs of [`Map`]s and [`Vector`]s.
+//!
+//! An [`AuthPath`] is the sequence of sibling digests (and directions)
+//! leading from a leaf to the root of a Merkle tree. [`Map`]s and [`Vector`]s
+//! hash their leaves and internal nodes differently: [`AuthPath::verify`]
+//! takes the leaf digest and the internal node hash function as parameters,
+//! so that a single routine verifies both kinds of proofs.
+//!
+//! [`Map`]: crate::map::Map
+//! [`Vector`]: crate::vector::Vector
+//!
+//! # Examples
+//!
+//! ```
+//! use zebra::{hashing, map::Set};
+//!
+//! let mut set = Set::new();
+//! set.insert(1u32).unwrap();
+//! set.insert(2u32).unwrap();
+//!
+//! let proof = set.prove_absent(&3u32).unwrap();
+//!
+//! let path = proof.auth_path();
+//! let leaf = proof.terminal_digest();
+//!
+//! assert!(path.verify(set.commit(), leaf, hashing::hash_internal).is_ok());
+//! ```
+
+use bit_vec::BitVec;
+
+use crate::common::tree::Direction;
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+use serde::{Deserialize, Serialize};
+
+use talk::crypto::primitives::hash::Hash;
+
+#[derive(Doom)]
+pub enum AuthPathError {
+    #[doom(description("Root mismatch"))]
+    RootMismatch,
+}
+
+/// The sibling digests along the path from a leaf to the root of a
+/// Merkle tree, ordered from the leaf up.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthPath {
+    directions: BitVec,
+    siblings: Vec<Hash>,
+}
+
+impl AuthPath {
+    /// `directions` and `siblings` are ordered from the leaf up: the
+    /// `i`-th direction tells whether the `i`-th node on the path is
+    /// the `Left` or the `Right` child of its parent.
+    pub(crate) fn new<I>(directions: I, siblings: Vec<Hash>) -> Self
+    where
+        I: IntoIterator<Item = Direction>,
+    {
+        let directions = directions
+            .into_iter()
+            .map(|direction| direction == Direction::Left)
+            .collect::<BitVec>();
+
+        debug_assert_eq!(directions.len(), siblings.len());
+
+        AuthPath {
+            directions,
+            siblings,
+        }
+    }
+
+    /// Returns the number of internal nodes between the leaf and the root.
+    pub fn len(&self) -> usize {
+        self.siblings.len()
+    }
+
+    /// Returns `true` if the leaf is the root.
+    pub fn is_empty(&self) -> bool {
+        self.siblings.is_empty()
+    }
+
+    /// Returns the directions along the path, from the leaf up.
+    pub fn directions(&self) -> impl Iterator<Item = Direction> + '_ {
+        self.directions.iter().map(|left| {
+            if left {
+                Direction::Left
+            } else {
+                Direction::Right
+            }
+        })
+    }
+
+    /// Returns the sibling digests along the path, from the leaf up.
+    pub fn siblings(&self) -> &[Hash] {
+        &self.siblings
+    }
+
+    /// Computes the root obtained by hashing `leaf` up the path, where
+    /// `internal` computes the digest of an internal node from
+    /// those of its left and right children.
+    pub fn root<F>(&self, leaf: Hash, internal: F) -> Hash
+    where
+        F: Fn(Hash, Hash) -> Hash,
+    {
+        self.directions().zip(self.siblings.iter().cloned()).fold(
+            leaf,
+            |hash, (direction, sibling)| match direction {
+                Direction::Left => internal(hash, sibling),
+                Direction::Right => internal(sibling, hash),
+            },
+        )
+    }
+
+    /// Verifies that hashing `leaf` up the path (see [`root`]) yields `root`.
+    ///
+    /// # Errors
+    ///
+    /// If the roots do not match, [`RootMismatch`] is returned.
+    ///
+    /// [`root`]: crate::auth_path::AuthPath::root
+    /// [`RootMismatch`]: crate::auth_path::AuthPathError::RootMismatch
+    pub fn verify<F>(&self, root: Hash, leaf: Hash, internal: F) -> Result<(), Top<AuthPathError>>
+    where
+        F: Fn(Hash, Hash) -> Hash,
+    {
+        if self.root(leaf, internal) != root {
+            return AuthPathError::RootMismatch.fail().spot(here!());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{hashing, map::Set, vector, vector::Vector};
+
+    #[test]
+    fn map() {
+        let mut set: Set<u32> = Set::new();
+
+        for item in 0..64 {
+            set.insert(item).unwrap();
+        }
+
+        for item in 64..128 {
+            let proof = set.prove_absent(&item).unwrap();
+
+            proof
+                .auth_path()
+                .verify(
+                    set.commit(),
+                    proof.terminal_digest(),
+                    hashing::hash_internal,
+                )
+                .unwrap();
+
+            assert!(proof
+                .auth_path()
+                .verify(set.commit(), hashing::empty_node(), |left, right| {
+                    hashing::hash_internal(right, left)
+                })
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn vector() {
+        let vector = Vector::<_>::new((0..37u32).collect()).unwrap();
+
+        for index in 0..37 {
+            let proof = vector.prove(index).unwrap();
+
+            proof
+                .auth_path()
+                .verify(
+                    vector.root(),
+                    proof.leaf_digest(&(index as u32)).unwrap(),
+                    vector::hash_internal,
+                )
+                .unwrap();
+
+            assert!(proof
+                .auth_path()
+                .verify(
+                    vector.root(),
+                    proof.leaf_digest(&(index as u32 + 1)).unwrap(),
+                    vector::hash_internal,
+                )
+                .is_err());
+        }
+    }
+}
@@ -1,3 +1,3 @@
 pub(crate) mod data;
 pub(crate) mod store;
-pub(crate) mod tree;
+pub mod tree;
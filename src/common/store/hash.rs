@@ -1,19 +1,38 @@
-use crate::common::data::Bytes;
-
-use talk::crypto::primitives::{hash, hash::HASH_LENGTH};
+use crate::{
+    common::data::Bytes,
+    hashing::{Blake3Hasher, Hasher},
+};
 
 const INTERNAL_FLAG: u8 = 0;
 const LEAF_FLAG: u8 = 1;
-const EMPTY_HASH: Bytes = Bytes([0; HASH_LENGTH]);
+const RADIX_INTERNAL_FLAG: u8 = 2;
 
 pub(crate) fn empty() -> Bytes {
-    EMPTY_HASH
+    empty_with::<Blake3Hasher>()
 }
 
 pub(crate) fn internal(left: Bytes, right: Bytes) -> Bytes {
-    hash::hash(&(INTERNAL_FLAG, left, right)).unwrap().into()
+    internal_with::<Blake3Hasher>(left, right)
 }
 
 pub(crate) fn leaf(key: Bytes, value: Bytes) -> Bytes {
-    hash::hash(&(LEAF_FLAG, key, value)).unwrap().into()
+    leaf_with::<Blake3Hasher>(key, value)
+}
+
+pub(crate) fn radix_internal(children: &[Bytes]) -> Bytes {
+    Blake3Hasher::hash(&(RADIX_INTERNAL_FLAG, children))
+        .unwrap()
+        .into()
+}
+
+pub(crate) fn empty_with<H: Hasher>() -> Bytes {
+    H::empty().into()
+}
+
+pub(crate) fn internal_with<H: Hasher>(left: Bytes, right: Bytes) -> Bytes {
+    H::hash(&(INTERNAL_FLAG, left, right)).unwrap().into()
+}
+
+pub(crate) fn leaf_with<H: Hasher>(key: Bytes, value: Bytes) -> Bytes {
+    H::hash(&(LEAF_FLAG, key, value)).unwrap().into()
 }
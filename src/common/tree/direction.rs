@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
 
+/// A branch of a Merkle-prefix tree: `Left` for a `1` bit, `Right` for a `0` bit.
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
-pub(crate) enum Direction {
+pub enum Direction {
     Left,
     Right,
 }
@@ -2,8 +2,6 @@ mod direction;
 mod path;
 mod prefix;
 
-use path::PathIterator;
-
-pub(crate) use direction::Direction;
-pub(crate) use path::Path;
-pub(crate) use prefix::Prefix;
+pub use direction::Direction;
+pub use path::{Path, PathIterator};
+pub use prefix::Prefix;
@@ -4,21 +4,30 @@ use std::ops::Index;
 
 use talk::crypto::primitives::hash::{Hash, HASH_LENGTH};
 
+/// The sequence of [`Direction`]s (one per bit of a key's hash) leading
+/// to a key in a Merkle-prefix tree.
+///
+/// [`Direction`]: crate::tree::Direction
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
-pub(crate) struct Path(Bytes);
+pub struct Path(Bytes);
 
 pub(crate) const EMPTY_PATH: Bytes = Bytes([0; HASH_LENGTH]);
 
 impl Path {
-    pub fn empty() -> Self {
+    /// The number of [`Direction`]s in a `Path`, i.e., the number of bits in a hash.
+    ///
+    /// [`Direction`]: crate::tree::Direction
+    pub const LEN: usize = 8 * HASH_LENGTH;
+
+    pub(crate) fn empty() -> Self {
         Path(EMPTY_PATH)
     }
 
-    pub fn reaches(&self, hash: Bytes) -> bool {
+    pub(crate) fn reaches(&self, hash: Bytes) -> bool {
         self.0 == hash
     }
 
-    pub fn set(&mut self, index: u8, value: Direction) {
+    pub(crate) fn set(&mut self, index: u8, value: Direction) {
         let (byte, bit) = Path::split(index);
 
         if value == Direction::Left {
@@ -28,7 +37,7 @@ impl Path {
         }
     }
 
-    pub fn deepeq(lho: &Path, rho: &Path, depth: u8) -> bool {
+    pub(crate) fn deepeq(lho: &Path, rho: &Path, depth: u8) -> bool {
         let (full, overflow) = Path::split(depth);
 
         if lho.0 .0[0..full] != rho.0 .0[0..full] {
@@ -43,6 +52,30 @@ impl Path {
         }
     }
 
+    /// Returns the depth of the first [`Direction`] on which the `Path`
+    /// and `other` differ (i.e., the length of their common prefix),
+    /// or `None` if the two are equal.
+    ///
+    /// [`Direction`]: crate::tree::Direction
+    pub fn divergence(&self, other: &Path) -> Option<u8> {
+        let (lho, rho) = (&self.0 .0, &other.0 .0);
+
+        lho.iter()
+            .zip(rho.iter())
+            .enumerate()
+            .find(|(_, (lho, rho))| lho != rho)
+            .map(|(byte, (lho, rho))| (8 * byte + (lho ^ rho).leading_zeros() as usize) as u8)
+    }
+
+    /// Returns the `level`-th digit of the path, reading it as a
+    /// sequence of `bits`-bit digits (`Left` being a set bit).
+    pub(crate) fn digit(&self, level: usize, bits: u8) -> usize {
+        (0..bits).fold(0, |digit, offset| {
+            let index = (level * (bits as usize) + (offset as usize)) as u8;
+            (digit << 1) | ((self[index] == Direction::Left) as usize)
+        })
+    }
+
     fn split(index: u8) -> (usize, u8) {
         ((index / 8) as usize, index % 8)
     }
@@ -81,7 +114,7 @@ impl Index<u8> for Path {
     }
 }
 
-pub(crate) struct PathIterator {
+pub struct PathIterator {
     path: Path,
     cursor: usize,
 }
@@ -161,6 +194,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn digit() {
+        use Direction::{Left as L, Right as R};
+
+        let path = Path::from_directions(vec![L, R, R, L, R, R, R, L]);
+
+        assert_eq!(path.digit(0, 1), 1);
+        assert_eq!(path.digit(1, 1), 0);
+        assert_eq!(path.digit(0, 4), 0b1001);
+        assert_eq!(path.digit(1, 4), 0b0001);
+        assert_eq!(path.digit(0, 8), 0b10010001);
+        assert_eq!(path.digit(2, 4), 0);
+    }
+
     #[test]
     fn ordering() {
         use Direction::{Left as L, Right as R};
@@ -180,4 +227,38 @@ mod tests {
 
         assert!(&Path::from_directions(lesser) < &Path::from_directions(greater));
     }
+
+    #[test]
+    fn divergence() {
+        use Direction::{Left as L, Right as R};
+
+        let path = Path::from(hash::hash(&0u32).unwrap());
+        assert_eq!(path.divergence(&path), None);
+
+        assert_eq!(
+            Path::from_directions(vec![L]).divergence(&Path::from_directions(vec![R])),
+            Some(0)
+        );
+
+        assert_eq!(
+            Path::from_directions(vec![L, R, L, L, R, L, L, L, L, L])
+                .divergence(&Path::from_directions(vec![L, R, L, L, R, L, R, R, R, R])),
+            Some(6)
+        );
+
+        let mut last = path;
+        let flipped = if path[255] == L { R } else { L };
+        last.set(255, flipped);
+
+        assert_eq!(path.divergence(&last), Some(255));
+        assert_eq!(last.divergence(&path), Some(255));
+
+        for (lho, rho) in (0u32..64).zip(64u32..128) {
+            let lho = Path::from(hash::hash(&lho).unwrap());
+            let rho = Path::from(hash::hash(&rho).unwrap());
+
+            let depth = lho.into_iter().zip(rho).take_while(|(l, r)| l == r).count();
+            assert_eq!(lho.divergence(&rho), Some(depth as u8));
+        }
+    }
 }
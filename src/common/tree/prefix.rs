@@ -2,13 +2,28 @@ use crate::common::tree::{Direction, Path, PathIterator};
 
 use std::{iter::Take, ops::Index};
 
+/// The first `depth` [`Direction`]s of a [`Path`], identifying a
+/// position (and the subtree below it) in a Merkle-prefix tree.
+///
+/// [`Direction`]: crate::tree::Direction
+/// [`Path`]: crate::tree::Path
 #[derive(Debug, Clone, Copy)]
-pub(crate) struct Prefix {
+pub struct Prefix {
     path: Path,
     depth: u8,
 }
 
 impl Prefix {
+    /// The maximum depth of a `Prefix`. Depths are stored on 8 bits: a
+    /// `Prefix` can span all but the last [`Direction`] of a [`Path`]
+    /// (see [`Path::LEN`]), which is far deeper than any Merkle-prefix
+    /// tree grows short of a hash collision.
+    ///
+    /// [`Direction`]: crate::tree::Direction
+    /// [`Path`]: crate::tree::Path
+    /// [`Path::LEN`]: crate::tree::Path::LEN
+    pub const MAX_DEPTH: u8 = u8::MAX;
+
     pub fn root() -> Self {
         Prefix {
             path: Path::empty(),
@@ -16,19 +31,26 @@ impl Prefix {
         }
     }
 
-    pub fn common(lho: Path, rho: Path) -> Self {
-        let depth = lho
-            .into_iter()
-            .zip(rho)
-            .take_while(|(left, right)| left == right)
-            .count();
-
+    /// Returns the longest `Prefix` shared by `lho` and `rho` (see
+    /// [`Path::divergence`]).
+    ///
+    /// Equal `Path`s share all of their [`Direction`]s, which no `Prefix`
+    /// can span: in that case, the `Prefix` is [`MAX_DEPTH`] deep.
+    ///
+    /// [`Path::divergence`]: crate::tree::Path::divergence
+    /// [`Direction`]: crate::tree::Direction
+    /// [`MAX_DEPTH`]: crate::tree::Prefix::MAX_DEPTH
+    pub fn common(lho: &Path, rho: &Path) -> Self {
         Prefix {
-            path: lho,
-            depth: depth as u8,
+            path: *lho,
+            depth: lho.divergence(rho).unwrap_or(Prefix::MAX_DEPTH),
         }
     }
 
+    /// Returns the number of [`Direction`]s in the `Prefix`,
+    /// i.e., the depth of its position in the tree.
+    ///
+    /// [`Direction`]: crate::tree::Direction
     pub fn depth(&self) -> u8 {
         self.depth
     }
@@ -44,15 +66,59 @@ impl Prefix {
         }
     }
 
+    /// Returns the `Prefix` of the left child.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `Prefix` is [`MAX_DEPTH`] deep (see [`checked_left`]).
+    ///
+    /// [`MAX_DEPTH`]: crate::tree::Prefix::MAX_DEPTH
+    /// [`checked_left`]: crate::tree::Prefix::checked_left
     pub fn left(&self) -> Self {
         self.child(Direction::Left)
     }
 
+    /// Returns the `Prefix` of the right child.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `Prefix` is [`MAX_DEPTH`] deep (see [`checked_right`]).
+    ///
+    /// [`MAX_DEPTH`]: crate::tree::Prefix::MAX_DEPTH
+    /// [`checked_right`]: crate::tree::Prefix::checked_right
     pub fn right(&self) -> Self {
         self.child(Direction::Right)
     }
 
+    /// Returns the `Prefix` of the left child, or `None`
+    /// if the `Prefix` is [`MAX_DEPTH`] deep.
+    ///
+    /// [`MAX_DEPTH`]: crate::tree::Prefix::MAX_DEPTH
+    pub fn checked_left(&self) -> Option<Self> {
+        self.checked_child(Direction::Left)
+    }
+
+    /// Returns the `Prefix` of the right child, or `None`
+    /// if the `Prefix` is [`MAX_DEPTH`] deep.
+    ///
+    /// [`MAX_DEPTH`]: crate::tree::Prefix::MAX_DEPTH
+    pub fn checked_right(&self) -> Option<Self> {
+        self.checked_child(Direction::Right)
+    }
+
+    fn checked_child(&self, direction: Direction) -> Option<Self> {
+        if self.depth < Prefix::MAX_DEPTH {
+            Some(self.child(direction))
+        } else {
+            None
+        }
+    }
+
     fn child(&self, direction: Direction) -> Self {
+        if self.depth == Prefix::MAX_DEPTH {
+            panic!("`child` does not exist (would exceed `MAX_DEPTH`)");
+        }
+
         let mut path = self.path;
         path.set(self.depth, direction);
 
@@ -97,6 +163,8 @@ mod tests {
 
     use std::vec::Vec;
 
+    use talk::crypto::primitives::hash;
+
     impl Prefix {
         pub fn new(path: Path, depth: u8) -> Self {
             Prefix { path, depth }
@@ -184,6 +252,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn depth() {
+        let mut prefix = Prefix::root();
+        assert_eq!(prefix.depth(), 0);
+
+        for depth in 1..=Prefix::MAX_DEPTH {
+            prefix = if depth % 2 == 0 {
+                prefix.left()
+            } else {
+                prefix.right()
+            };
+
+            assert_eq!(prefix.depth(), depth);
+        }
+
+        assert_eq!(prefix.ancestor(Prefix::MAX_DEPTH), Prefix::root());
+
+        assert!(prefix.checked_left().is_none());
+        assert!(prefix.checked_right().is_none());
+
+        assert_eq!(
+            prefix.ancestor(1).checked_left(),
+            Some(prefix.ancestor(1).left())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "would exceed `MAX_DEPTH`")]
+    fn beyond_max_depth() {
+        let prefix = Prefix::new(Path::empty(), Prefix::MAX_DEPTH);
+        prefix.left();
+    }
+
     #[test]
     fn iterator() {
         use Direction::{Left as L, Right as R};
@@ -221,81 +322,100 @@ mod tests {
     fn common() {
         use Direction::{Left as L, Right as R};
 
-        assert_eq!(
-            Prefix::common(Path::from_directions(vec![]), Path::from_directions(vec![])),
-            Prefix::from_directions(vec![])
-        );
-
         assert_eq!(
             Prefix::common(
-                Path::from_directions(vec![]),
-                Path::from_directions(vec![L, R, L])
+                &Path::from_directions(vec![]),
+                &Path::from_directions(vec![L, R, L])
             ),
             Prefix::from_directions(vec![])
         );
 
         assert_eq!(
             Prefix::common(
-                Path::from_directions(vec![L, L]),
-                Path::from_directions(vec![L, R, L])
+                &Path::from_directions(vec![L, L]),
+                &Path::from_directions(vec![L, R, L])
             ),
             Prefix::from_directions(vec![L])
         );
 
         assert_eq!(
             Prefix::common(
-                Path::from_directions(vec![R]),
-                Path::from_directions(vec![L, R, L])
+                &Path::from_directions(vec![R]),
+                &Path::from_directions(vec![L, R, L])
             ),
             Prefix::from_directions(vec![])
         );
 
         assert_eq!(
             Prefix::common(
-                Path::from_directions(vec![R, R, L]),
-                Path::from_directions(vec![L])
+                &Path::from_directions(vec![R, R, L]),
+                &Path::from_directions(vec![L])
             ),
             Prefix::from_directions(vec![])
         );
 
         assert_eq!(
             Prefix::common(
-                Path::from_directions(vec![L, R, L, L]),
-                Path::from_directions(vec![L, R, L])
+                &Path::from_directions(vec![L, R, L, L]),
+                &Path::from_directions(vec![L, R, L])
             ),
             Prefix::from_directions(vec![L, R, L])
         );
 
         assert_eq!(
             Prefix::common(
-                Path::from_directions(vec![L, R, L, R]),
-                Path::from_directions(vec![L, R, L, R, L, L])
+                &Path::from_directions(vec![L, R, L, R]),
+                &Path::from_directions(vec![L, R, L, R, L, L])
             ),
             Prefix::from_directions(vec![L, R, L, R])
         );
 
         assert_eq!(
             Prefix::common(
-                Path::from_directions(vec![L, R, L, R, L, L, R, L]),
-                Path::from_directions(vec![R, R, L, R, L, L, R, L])
+                &Path::from_directions(vec![L, R, L, R, L, L, R, L]),
+                &Path::from_directions(vec![R, R, L, R, L, L, R, L])
             ),
             Prefix::from_directions(vec![])
         );
 
         assert_eq!(
             Prefix::common(
-                Path::from_directions(vec![L, R, L, R, L, L, R, L, R, R, R, R]),
-                Path::from_directions(vec![L, R, L, R, L, L, R, L, R, R, R, L])
+                &Path::from_directions(vec![L, R, L, R, L, L, R, L, R, R, R, R]),
+                &Path::from_directions(vec![L, R, L, R, L, L, R, L, R, R, R, L])
             ),
             Prefix::from_directions(vec![L, R, L, R, L, L, R, L, R, R, R])
         );
 
         assert_eq!(
             Prefix::common(
-                Path::from_directions(vec![L, R, L, L, R, L, L, L, L, L]),
-                Path::from_directions(vec![L, R, L, L, R, L, R, R, R, R])
+                &Path::from_directions(vec![L, R, L, L, R, L, L, L, L, L]),
+                &Path::from_directions(vec![L, R, L, L, R, L, R, R, R, R])
             ),
             Prefix::from_directions(vec![L, R, L, L, R, L])
         );
+
+        let mut lho = Path::from_directions(vec![]);
+        let mut rho = lho;
+
+        lho.set(255, L);
+        rho.set(255, R);
+
+        assert_eq!(Prefix::common(&lho, &rho).depth(), 255);
+        assert!(Prefix::common(&lho, &rho).contains(&lho));
+    }
+
+    #[test]
+    fn common_identical() {
+        let empty = Path::from_directions(vec![]);
+
+        let common = Prefix::common(&empty, &empty);
+        assert_eq!(common.depth(), Prefix::MAX_DEPTH);
+        assert!(common.contains(&empty));
+
+        let path = Path::from(hash::hash(&0u32).unwrap());
+
+        let common = Prefix::common(&path, &path);
+        assert_eq!(common.depth(), Prefix::MAX_DEPTH);
+        assert!(common.contains(&path));
     }
 }
@@ -0,0 +1,355 @@
+use crate::database::errors::BackendError;
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+// The key-value pairs held by a `Backend`
+type Pairs = Vec<(Vec<u8>, Vec<u8>)>;
+
+// A batch of writes to a `Backend`: a key is either associated with a value
+// (`Some`), or removed (`None`)
+type Writes = Vec<(Vec<u8>, Option<Vec<u8>>)>;
+
+/// A durable key-value store backing the nodes of a [`Database`]
+/// (see [`Database::open`]).
+///
+/// Keys and values are opaque to the `Backend`. A [`Database`] keeps
+/// its nodes in memory, and writes its changes through to its `Backend`
+/// in batches, one for each modification of its [`Table`]s: a `Backend`
+/// only needs to apply batches atomically, and to load its contents back
+/// when the [`Database`] is reopened.
+///
+/// Batches cannot be undone: once a batch fails, the [`Database`] stops
+/// writing through to its `Backend`, and reports the failure upon the
+/// next [`Database::flush`] (or [`Database::close`]).
+///
+/// [`Database`]: crate::database::Database
+/// [`Database::open`]: crate::database::Database::open
+/// [`Table`]: crate::database::Table
+/// [`Database::flush`]: crate::database::Database::flush
+/// [`Database::close`]: crate::database::Database::close
+pub trait Backend: 'static + Send + Sync {
+    /// Applies `writes` in one batch: each write associates a key with a
+    /// value (overwriting any previous value), or removes a key (if
+    /// present). Should the process crash, the next [`load`] observes
+    /// either all or none of `writes`.
+    ///
+    /// # Errors
+    ///
+    /// If the batch fails, [`WriteFailed`] is returned.
+    ///
+    /// [`load`]: crate::database::Backend::load
+    /// [`WriteFailed`]: crate::database::errors::BackendError::WriteFailed
+    fn write(&self, writes: Writes) -> Result<(), Top<BackendError>>;
+
+    /// Makes all previous writes durable.
+    fn flush(&self) -> io::Result<()>;
+
+    /// Returns all the key-value pairs in the `Backend`, in any order.
+    fn load(&self) -> Result<Pairs, Top<BackendError>>;
+}
+
+/// A [`Backend`] over a single append-only log file.
+///
+/// Every batch of writes appends a record to the log. Upon opening, the
+/// log is replayed and compacted, so that only live key-value pairs are
+/// kept. Records lost to a crash (i.e., not [`flush`]ed) are ignored upon
+/// replay, including a partially written last batch: as a whole batch is
+/// either replayed or ignored, batches are atomic.
+///
+/// A log is laid out as a sequence of records, each starting with a
+/// one-byte tag (0 for a put, 1 for a delete, 2 for a batch). A put is
+/// followed by its key and value, a delete by its key, and a batch by
+/// the puts and deletes it holds (as a single field). Each field is
+/// prefixed with its length as a little-endian `u32`.
+///
+/// Once an append fails, the log is poisoned: as a partially written
+/// record would garble the records appended after it, all later writes
+/// and [`flush`]es fail.
+///
+/// [`Backend`]: crate::database::Backend
+/// [`flush`]: crate::database::Backend::flush
+pub struct LogBackend {
+    path: PathBuf,
+    log: Mutex<Log>,
+}
+
+struct Log {
+    writer: BufWriter<File>,
+    poisoned: bool,
+}
+
+// A record of a log: its tag, its key (or batch) and its value (for puts only)
+type Record = (u8, Vec<u8>, Option<Vec<u8>>);
+
+const PUT: u8 = 0;
+const DELETE: u8 = 1;
+const BATCH: u8 = 2;
+
+impl LogBackend {
+    /// Opens (or creates) the log at `path`.
+    ///
+    /// # Errors
+    ///
+    /// If the log cannot be read or compacted, [`OpenFailed`] is returned.
+    ///
+    /// If the log holds a record with an unknown tag, [`MalformedLog`]
+    /// is returned.
+    ///
+    /// [`OpenFailed`]: crate::database::errors::BackendError::OpenFailed
+    /// [`MalformedLog`]: crate::database::errors::BackendError::MalformedLog
+    pub fn open<P>(path: P) -> Result<Self, Top<BackendError>>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+
+        let records = match File::open(path) {
+            Ok(file) => replay(BufReader::new(file))?,
+            Err(error) if error.kind() == ErrorKind::NotFound => HashMap::new(),
+            Err(_) => return BackendError::OpenFailed.fail().spot(here!()),
+        };
+
+        // Compact the log into a temporary file, then replace the log with it
+        let mut compacted = path.as_os_str().to_owned();
+        compacted.push(".compact");
+        let compacted = PathBuf::from(compacted);
+
+        let write = || -> io::Result<()> {
+            let mut log = BufWriter::new(File::create(&compacted)?);
+
+            for (key, value) in records.iter() {
+                append(&mut log, PUT, key, Some(value))?;
+            }
+
+            log.into_inner()?.sync_all()?;
+            fs::rename(&compacted, path)
+        };
+
+        write()
+            .map_err(|_| BackendError::OpenFailed.into_top())
+            .spot(here!())?;
+
+        let log = OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map_err(|_| BackendError::OpenFailed.into_top())
+            .spot(here!())?;
+
+        Ok(LogBackend {
+            path: path.to_path_buf(),
+            log: Mutex::new(Log {
+                writer: BufWriter::new(log),
+                poisoned: false,
+            }),
+        })
+    }
+
+    fn append(&self, batch: &[u8]) -> Result<(), Top<BackendError>> {
+        let mut log = self.log.lock().unwrap();
+
+        if log.poisoned {
+            return BackendError::WriteFailed.fail().spot(here!());
+        }
+
+        if append(&mut log.writer, BATCH, batch, None).is_err() {
+            log.poisoned = true;
+            return BackendError::WriteFailed.fail().spot(here!());
+        }
+
+        Ok(())
+    }
+}
+
+impl Backend for LogBackend {
+    fn write(&self, writes: Writes) -> Result<(), Top<BackendError>> {
+        let mut batch = Vec::new();
+
+        for (key, value) in writes.iter() {
+            let tag = if value.is_some() { PUT } else { DELETE };
+            append(&mut batch, tag, key, value.as_deref()).unwrap(); // Writing to a `Vec` cannot fail
+        }
+
+        self.append(&batch)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let mut log = self.log.lock().unwrap();
+
+        if log.poisoned {
+            return Err(io::Error::other("log poisoned by a failed append"));
+        }
+
+        log.writer.flush()?;
+        log.writer.get_ref().sync_data()
+    }
+
+    fn load(&self) -> Result<Pairs, Top<BackendError>> {
+        self.flush()
+            .map_err(|_| BackendError::OpenFailed.into_top())
+            .spot(here!())?;
+
+        let log = File::open(&self.path)
+            .map_err(|_| BackendError::OpenFailed.into_top())
+            .spot(here!())?;
+
+        Ok(replay(BufReader::new(log))?.into_iter().collect())
+    }
+}
+
+fn append<W>(log: &mut W, tag: u8, key: &[u8], value: Option<&[u8]>) -> io::Result<()>
+where
+    W: Write,
+{
+    log.write_all(&[tag])?;
+    log.write_all(&(key.len() as u32).to_le_bytes())?;
+    log.write_all(key)?;
+
+    if let Some(value) = value {
+        log.write_all(&(value.len() as u32).to_le_bytes())?;
+        log.write_all(value)?;
+    }
+
+    Ok(())
+}
+
+fn replay<R>(mut log: R) -> Result<HashMap<Vec<u8>, Vec<u8>>, Top<BackendError>>
+where
+    R: Read,
+{
+    let mut records = HashMap::new();
+
+    // A truncated record can only be the last, partially written one
+    while let Some((tag, key, value)) = read_record(&mut log)? {
+        match tag {
+            BATCH => {
+                let mut batch = key.as_slice();
+
+                while let Some((tag, key, value)) = read_record(&mut batch)? {
+                    if tag == BATCH {
+                        return BackendError::MalformedLog.fail().spot(here!());
+                    }
+
+                    apply(&mut records, key, value);
+                }
+
+                // Batches are written whole: a truncated write within a batch is malformed
+                if !batch.is_empty() {
+                    return BackendError::MalformedLog.fail().spot(here!());
+                }
+            }
+            _ => apply(&mut records, key, value),
+        }
+    }
+
+    Ok(records)
+}
+
+fn apply(records: &mut HashMap<Vec<u8>, Vec<u8>>, key: Vec<u8>, value: Option<Vec<u8>>) {
+    match value {
+        Some(value) => {
+            records.insert(key, value);
+        }
+        None => {
+            records.remove(&key);
+        }
+    }
+}
+
+// Reads the next record of `log`, returning `None` if the log ends (possibly
+// amid a truncated record)
+fn read_record<R>(log: &mut R) -> Result<Option<Record>, Top<BackendError>>
+where
+    R: Read,
+{
+    let mut tag = [0u8];
+
+    match log.read(&mut tag) {
+        Ok(0) => return Ok(None),
+        Ok(_) => {}
+        Err(_) => return BackendError::OpenFailed.fail().spot(here!()),
+    }
+
+    if tag[0] > BATCH {
+        return BackendError::MalformedLog.fail().spot(here!());
+    }
+
+    let key = match read_field(log) {
+        Some(key) => key,
+        None => return Ok(None),
+    };
+
+    if tag[0] != PUT {
+        return Ok(Some((tag[0], key, None)));
+    }
+
+    Ok(read_field(log).map(|value| (tag[0], key, Some(value))))
+}
+
+fn read_field<R>(log: &mut R) -> Option<Vec<u8>>
+where
+    R: Read,
+{
+    let mut len = [0u8; 4];
+    log.read_exact(&mut len).ok()?;
+
+    let len = u32::from_le_bytes(len) as usize;
+
+    // Read through `take`, so that a corrupted length cannot trigger a huge allocation
+    let mut field = Vec::new();
+    log.by_ref().take(len as u64).read_to_end(&mut field).ok()?;
+
+    if field.len() == len {
+        Some(field)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncated() {
+        let path = std::env::temp_dir().join(format!("truncated_{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let backend = LogBackend::open(&path).unwrap();
+
+        backend
+            .write(vec![
+                (vec![0], Some(vec![0; 16])),
+                (vec![1], Some(vec![1; 16])),
+            ])
+            .unwrap();
+
+        backend
+            .write(vec![(vec![0], None), (vec![2], Some(vec![2; 16]))])
+            .unwrap();
+
+        backend.flush().unwrap();
+
+        drop(backend);
+
+        // Simulate a crash halfway through the last batch: none of its writes survive
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 8]).unwrap();
+
+        let backend = LogBackend::open(&path).unwrap();
+        let mut pairs = backend.load().unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+
+        pairs.sort();
+
+        assert_eq!(pairs, vec![(vec![0], vec![0; 16]), (vec![1], vec![1; 16])]);
+    }
+}
@@ -1,17 +1,21 @@
 use crate::{
     common::store::Field,
-    database::{CollectionResponse, CollectionSender, CollectionTransaction, Table},
+    database::{
+        CollectionDiff, CollectionResponse, CollectionSender, CollectionTransaction, Global,
+        StoreAllocator, Table,
+    },
 };
 
 use std::{collections::HashSet, hash::Hash as StdHash};
 
 use talk::crypto::primitives::hash::Hash;
 
-pub struct Collection<Item: Field>(pub(crate) Table<Item, ()>);
+pub struct Collection<Item: Field, A: StoreAllocator = Global>(pub(crate) Table<Item, (), A>);
 
-impl<Item> Collection<Item>
+impl<Item, A> Collection<Item, A>
 where
     Item: Field,
+    A: StoreAllocator,
 {
     pub fn commit(&self) -> Hash {
         self.0.commit()
@@ -24,37 +28,213 @@ where
         CollectionResponse(self.0.execute(transaction.0))
     }
 
-    pub fn send(self) -> CollectionSender<Item> {
+    pub fn send(self) -> CollectionSender<Item, A> {
         CollectionSender(self.0.send())
     }
 
+    /// Diffs `lho` against `rho`, returning the items held by only one of them.
+    ///
+    /// As with [`Table::diff`], branches that are identical in `lho` and `rho`
+    /// (i.e., share the same hash) are skipped without being traversed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lho` and `rho` belong to different [`Family`]-es.
+    ///
+    /// [`Table::diff`]: crate::database::Table::diff
+    /// [`Family`]: crate::database::Family
     pub fn diff(
-        lho: &mut Collection<Item>,
-        rho: &mut Collection<Item>,
-    ) -> (HashSet<Item>, HashSet<Item>)
+        lho: &mut Collection<Item, A>,
+        rho: &mut Collection<Item, A>,
+    ) -> CollectionDiff<Item>
     where
         Item: Clone + Eq + StdHash,
     {
-        let mut lho_minus_rho = HashSet::new();
-        let mut rho_minus_lho = HashSet::new();
+        let mut lho_only = HashSet::new();
+        let mut rho_only = HashSet::new();
 
-        for (key, (in_lho, _)) in Table::diff(&mut lho.0, &mut rho.0) {
+        for (item, (in_lho, _)) in Table::diff(&mut lho.0, &mut rho.0) {
             if in_lho.is_some() {
-                lho_minus_rho.insert(key);
+                lho_only.insert(item);
             } else {
-                rho_minus_lho.insert(key);
+                rho_only.insert(item);
             }
         }
 
-        (lho_minus_rho, rho_minus_lho)
+        CollectionDiff { lho_only, rho_only }
     }
 }
 
-impl<Item> Clone for Collection<Item>
+impl<Item, A> Clone for Collection<Item, A>
 where
     Item: Field,
+    A: StoreAllocator,
 {
     fn clone(&self) -> Self {
         Collection(self.0.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::database::Family;
+
+    use rand::seq::IteratorRandom;
+
+    fn collection_with_items<I>(family: &Family<u32>, items: I) -> Collection<u32>
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        let mut collection = family.empty_collection();
+        let mut transaction = CollectionTransaction::new();
+
+        for item in items {
+            transaction.insert(item).unwrap();
+        }
+
+        collection.execute(transaction);
+        collection
+    }
+
+    #[test]
+    fn contains() {
+        let family: Family<u32> = Family::new();
+        let mut collection = family.empty_collection();
+
+        let mut transaction = CollectionTransaction::new();
+
+        for item in 0..128 {
+            transaction.insert(item).unwrap();
+        }
+
+        collection.execute(transaction);
+
+        let mut transaction = CollectionTransaction::new();
+
+        for item in 64..128 {
+            transaction.remove(&item).unwrap();
+        }
+
+        collection.execute(transaction);
+
+        let mut transaction = CollectionTransaction::new();
+
+        let present = transaction.contains(&0).unwrap();
+        let absent = transaction.contains(&64).unwrap();
+        let never_inserted = transaction.contains(&1024).unwrap();
+
+        let response = collection.execute(transaction);
+
+        assert!(response.contains(&present));
+        assert!(!response.contains(&absent));
+        assert!(!response.contains(&never_inserted));
+    }
+
+    #[test]
+    fn diff_empty() {
+        let family: Family<u32> = Family::new();
+
+        let mut lho = family.empty_collection();
+        let mut rho = family.empty_collection();
+
+        let diff = Collection::diff(&mut lho, &mut rho);
+
+        assert!(diff.lho_only.is_empty());
+        assert!(diff.rho_only.is_empty());
+
+        let mut rho = collection_with_items(&family, 0..128);
+        let diff = Collection::diff(&mut lho, &mut rho);
+
+        assert!(diff.lho_only.is_empty());
+        assert_eq!(diff.rho_only, (0..128).collect());
+    }
+
+    #[test]
+    fn diff_identical() {
+        let family: Family<u32> = Family::new();
+
+        let mut lho = collection_with_items(&family, 0..1024);
+        let mut rho = collection_with_items(&family, 0..1024);
+
+        let diff = Collection::diff(&mut lho, &mut rho);
+
+        assert!(diff.lho_only.is_empty());
+        assert!(diff.rho_only.is_empty());
+    }
+
+    #[test]
+    fn diff_disjoint() {
+        let family: Family<u32> = Family::new();
+
+        let mut lho = collection_with_items(&family, 0..512);
+        let mut rho = collection_with_items(&family, 512..1024);
+
+        let diff = Collection::diff(&mut lho, &mut rho);
+
+        assert_eq!(diff.lho_only, (0..512).collect());
+        assert_eq!(diff.rho_only, (512..1024).collect());
+    }
+
+    #[test]
+    fn diff_overlapping() {
+        let family: Family<u32> = Family::new();
+
+        let mut lho = collection_with_items(&family, 0..768);
+        let mut rho = collection_with_items(&family, 256..1024);
+
+        let diff = Collection::diff(&mut lho, &mut rho);
+
+        assert_eq!(diff.lho_only, (0..256).collect());
+        assert_eq!(diff.rho_only, (768..1024).collect());
+
+        let diff = Collection::diff(&mut rho, &mut lho);
+
+        assert_eq!(diff.lho_only, (768..1024).collect());
+        assert_eq!(diff.rho_only, (0..256).collect());
+    }
+
+    #[test]
+    #[ignore]
+    fn diff_stress() {
+        let family: Family<u32> = Family::new();
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..512 {
+            let mut lho_items = HashSet::new();
+            let mut rho_items = HashSet::new();
+
+            for item in 0..512 {
+                let (in_lho, in_rho) =
+                    *[(false, false), (true, false), (false, true), (true, true)]
+                        .iter()
+                        .choose(&mut rng)
+                        .unwrap();
+
+                if in_lho {
+                    lho_items.insert(item);
+                }
+
+                if in_rho {
+                    rho_items.insert(item);
+                }
+            }
+
+            let mut lho = collection_with_items(&family, lho_items.iter().copied());
+            let mut rho = collection_with_items(&family, rho_items.iter().copied());
+
+            let diff = Collection::diff(&mut lho, &mut rho);
+
+            assert_eq!(
+                diff.lho_only,
+                lho_items.difference(&rho_items).copied().collect()
+            );
+
+            assert_eq!(
+                diff.rho_only,
+                rho_items.difference(&lho_items).copied().collect()
+            );
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use std::collections::HashSet;
+
+/// The outcome of diffing two [`Collection`]s (see [`Collection::diff`]).
+///
+/// [`Collection`]: crate::database::Collection
+/// [`Collection::diff`]: crate::database::Collection::diff
+#[derive(Debug, Clone)]
+pub struct CollectionDiff<Item> {
+    /// The items held by the left-hand [`Collection`] only.
+    ///
+    /// [`Collection`]: crate::database::Collection
+    pub lho_only: HashSet<Item>,
+    /// The items held by the right-hand [`Collection`] only.
+    ///
+    /// [`Collection`]: crate::database::Collection
+    pub rho_only: HashSet<Item>,
+}
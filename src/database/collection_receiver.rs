@@ -1,23 +1,43 @@
 use crate::{
     common::store::Field,
     database::{
-        errors::SyncError, Collection, CollectionAnswer, CollectionStatus, TableReceiver,
-        TableStatus,
+        errors::SyncError, Collection, CollectionAnswer, CollectionStatus, Global, Settings,
+        StoreAllocator, TableReceiver, TableStatus,
     },
 };
 
 use doomstack::Top;
 
-pub struct CollectionReceiver<Item: Field>(pub(crate) TableReceiver<Item, ()>);
+pub struct CollectionReceiver<Item: Field, A: StoreAllocator = Global>(
+    pub(crate) TableReceiver<Item, (), A>,
+);
 
-impl<Item> CollectionReceiver<Item>
+impl<Item, A> CollectionReceiver<Item, A>
 where
     Item: Field,
+    A: StoreAllocator,
 {
+    /// Returns the [`Settings`] of the transfer (see [`TableReceiver`]).
+    ///
+    /// [`Settings`]: crate::database::Settings
+    /// [`TableReceiver`]: crate::database::TableReceiver
+    pub fn settings(&self) -> &Settings {
+        &self.0.settings
+    }
+
+    /// Returns the [`Settings`] of the transfer, for adjustment. As with
+    /// a [`TableReceiver`], changes take effect from the next `learn` on.
+    ///
+    /// [`Settings`]: crate::database::Settings
+    /// [`TableReceiver`]: crate::database::TableReceiver
+    pub fn settings_mut(&mut self) -> &mut Settings {
+        &mut self.0.settings
+    }
+
     pub fn learn(
         self,
         answer: CollectionAnswer<Item>,
-    ) -> Result<CollectionStatus<Item>, Top<SyncError>> {
+    ) -> Result<CollectionStatus<Item, A>, Top<SyncError>> {
         let status = self.0.learn(answer)?;
 
         let status = match status {
@@ -25,8 +45,246 @@ where
             TableStatus::Incomplete(receiver, question) => {
                 CollectionStatus::Incomplete(CollectionReceiver(receiver), question)
             }
+            TableStatus::BudgetExceeded(receiver) => {
+                CollectionStatus::BudgetExceeded(CollectionReceiver(receiver))
+            }
         };
 
         Ok(status)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::database::{CollectionSender, CollectionTransaction, Family};
+
+    enum Transfer<'a, Item>
+    where
+        Item: Field,
+    {
+        Complete(Collection<Item>),
+        Incomplete(
+            &'a mut CollectionSender<Item>,
+            CollectionReceiver<Item>,
+            CollectionAnswer<Item>,
+        ),
+    }
+
+    fn collection_with_items<I>(family: &Family<u32>, items: I) -> Collection<u32>
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        let mut collection = family.empty_collection();
+        let mut transaction = CollectionTransaction::new();
+
+        for item in items {
+            transaction.insert(item).unwrap();
+        }
+
+        collection.execute(transaction);
+        collection
+    }
+
+    fn step<Item>(transfer: Transfer<Item>) -> Transfer<Item>
+    where
+        Item: Field,
+    {
+        match transfer {
+            Transfer::Incomplete(sender, receiver, answer) => {
+                match receiver.learn(answer).unwrap() {
+                    CollectionStatus::Complete(collection) => Transfer::Complete(collection),
+                    CollectionStatus::BudgetExceeded(..) => unreachable!(),
+                    CollectionStatus::Incomplete(receiver, question) => {
+                        let answer = sender.answer(&question).unwrap();
+                        Transfer::Incomplete(sender, receiver, answer)
+                    }
+                }
+            }
+            complete => complete,
+        }
+    }
+
+    // Runs all `transfers` one step at a time, interleaving them,
+    // checking `family` for leaks and reference counts after each step
+    fn run<'a, Item, I, const N: usize>(
+        family: &Family<Item>,
+        collections: I,
+        transfers: [(&mut CollectionSender<Item>, CollectionReceiver<Item>); N],
+    ) -> [Collection<Item>; N]
+    where
+        Item: Field,
+        I: IntoIterator<Item = &'a Collection<Item>>,
+    {
+        let mut transfers: [Transfer<Item>; N] = array_init::from_iter(
+            IntoIterator::into_iter(transfers).map(|(sender, receiver)| {
+                let hello = sender.hello();
+                Transfer::Incomplete(sender, receiver, hello)
+            }),
+        )
+        .unwrap();
+
+        let tables: Vec<_> = collections
+            .into_iter()
+            .map(|collection| &collection.0)
+            .collect();
+
+        loop {
+            transfers =
+                array_init::from_iter(IntoIterator::into_iter(transfers).map(step)).unwrap();
+
+            let receivers = transfers.iter().filter_map(|transfer| match transfer {
+                Transfer::Complete(..) => None,
+                Transfer::Incomplete(_, receiver, _) => Some(&receiver.0),
+            });
+
+            let received = transfers.iter().filter_map(|transfer| match transfer {
+                Transfer::Complete(collection) => Some(&collection.0),
+                Transfer::Incomplete(..) => None,
+            });
+
+            family
+                .0
+                .check(tables.clone().into_iter().chain(received), receivers);
+
+            if transfers
+                .iter()
+                .all(|transfer| matches!(transfer, Transfer::Complete(..)))
+            {
+                break;
+            }
+        }
+
+        array_init::from_iter(
+            IntoIterator::into_iter(transfers).map(|transfer| match transfer {
+                Transfer::Complete(collection) => collection,
+                Transfer::Incomplete(..) => unreachable!(),
+            }),
+        )
+        .unwrap()
+    }
+
+    fn assert_items<I>(collection: &Collection<u32>, items: I)
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        collection
+            .0
+            .assert_records(items.into_iter().map(|item| (item, ())));
+    }
+
+    #[test]
+    fn multiple() {
+        let alice: Family<u32> = Family::new();
+        let bob: Family<u32> = Family::new();
+
+        let original = collection_with_items(&alice, 0..256);
+        let mut sender = original.send();
+
+        let [received] = run(&bob, [], [(&mut sender, bob.receive())]);
+        assert_items(&received, 0..256);
+    }
+
+    #[test]
+    fn multiple_then_overlap() {
+        let alice: Family<u32> = Family::new();
+        let bob: Family<u32> = Family::new();
+
+        let original = collection_with_items(&alice, 0..256);
+        let mut sender = original.send();
+
+        let [first] = run(&bob, [], [(&mut sender, bob.receive())]);
+
+        let original = collection_with_items(&alice, 128..384);
+        let mut sender = original.send();
+
+        let [second] = run(&bob, [&first], [(&mut sender, bob.receive())]);
+
+        assert_items(&first, 0..256);
+        assert_items(&second, 128..384);
+    }
+
+    #[test]
+    fn multiple_interleave_multiple() {
+        let alice: Family<u32> = Family::new();
+        let bob: Family<u32> = Family::new();
+
+        let first_original = collection_with_items(&alice, 0..256);
+        let mut first_sender = first_original.send();
+
+        let second_original = collection_with_items(&alice, 256..512);
+        let mut second_sender = second_original.send();
+
+        let [first, second] = run(
+            &bob,
+            [],
+            [
+                (&mut first_sender, bob.receive()),
+                (&mut second_sender, bob.receive()),
+            ],
+        );
+
+        assert_items(&first, 0..256);
+        assert_items(&second, 256..512);
+    }
+
+    #[test]
+    fn multiple_interleave_overlap() {
+        let alice: Family<u32> = Family::new();
+        let bob: Family<u32> = Family::new();
+
+        let first_original = collection_with_items(&alice, 0..256);
+        let mut first_sender = first_original.send();
+
+        let second_original = collection_with_items(&alice, 128..384);
+        let mut second_sender = second_original.send();
+
+        let [first, second] = run(
+            &bob,
+            [],
+            [
+                (&mut first_sender, bob.receive()),
+                (&mut second_sender, bob.receive()),
+            ],
+        );
+
+        assert_items(&first, 0..256);
+        assert_items(&second, 128..384);
+    }
+
+    #[test]
+    fn tiny_window() {
+        let alice: Family<u32> = Family::new();
+        let bob: Family<u32> = Family::new();
+
+        let original = collection_with_items(&alice, 0..1024);
+        let mut sender = original.send();
+
+        let mut receiver = bob.receive();
+        receiver.settings_mut().window = 1;
+
+        let mut answer = sender.hello();
+        let mut steps = 0;
+
+        let received = loop {
+            steps += 1;
+
+            match receiver.learn(answer).unwrap() {
+                CollectionStatus::Complete(collection) => break collection,
+                CollectionStatus::BudgetExceeded(..) => unreachable!(),
+                CollectionStatus::Incomplete(receiver_t, question) => {
+                    assert!(question.0.len() <= receiver_t.settings().window);
+
+                    answer = sender.answer(&question).unwrap();
+                    receiver = receiver_t;
+                }
+            }
+        };
+
+        assert!(steps > 64);
+
+        assert_items(&received, 0..1024);
+        bob.0.check([&received.0], []);
+    }
+}
@@ -9,7 +9,13 @@ impl<Item> CollectionResponse<Item>
 where
     Item: Field,
 {
+    /// Returns `true` if the item queried by `query` (see
+    /// [`CollectionTransaction::contains`]) belonged to the [`Collection`]
+    /// when the transaction was executed.
+    ///
+    /// [`CollectionTransaction::contains`]: crate::database::CollectionTransaction::contains
+    /// [`Collection`]: crate::database::Collection
     pub fn contains(&self, query: &Query) -> bool {
-        self.0.get(&query).is_some()
+        self.0.get(query).is_some()
     }
 }
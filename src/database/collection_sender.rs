@@ -1,15 +1,21 @@
 use crate::{
     common::store::Field,
-    database::{errors::SyncError, Collection, CollectionAnswer, Question, TableSender},
+    database::{
+        errors::SyncError, Collection, CollectionAnswer, Global, Question, StoreAllocator,
+        TableSender,
+    },
 };
 
 use doomstack::Top;
 
-pub struct CollectionSender<Item: Field>(pub(crate) TableSender<Item, ()>);
+pub struct CollectionSender<Item: Field, A: StoreAllocator = Global>(
+    pub(crate) TableSender<Item, (), A>,
+);
 
-impl<Item> CollectionSender<Item>
+impl<Item, A> CollectionSender<Item, A>
 where
     Item: Field,
+    A: StoreAllocator,
 {
     pub fn hello(&mut self) -> CollectionAnswer<Item> {
         self.0.hello()
@@ -22,7 +28,7 @@ where
         self.0.answer(question)
     }
 
-    pub fn end(self) -> Collection<Item> {
+    pub fn end(self) -> Collection<Item, A> {
         Collection(self.0.end())
     }
 }
@@ -1,9 +1,13 @@
 use crate::{
     common::store::Field,
-    database::{Collection, CollectionReceiver, Question},
+    database::{Collection, CollectionReceiver, Global, Question, StoreAllocator},
 };
 
-pub enum CollectionStatus<Item: Field> {
-    Complete(Collection<Item>),
-    Incomplete(CollectionReceiver<Item>, Question),
+pub enum CollectionStatus<Item: Field, A: StoreAllocator = Global> {
+    Complete(Collection<Item, A>),
+    Incomplete(CollectionReceiver<Item, A>, Question),
+    /// See [`TableStatus::BudgetExceeded`].
+    ///
+    /// [`TableStatus::BudgetExceeded`]: crate::database::TableStatus::BudgetExceeded
+    BudgetExceeded(CollectionReceiver<Item, A>),
 }
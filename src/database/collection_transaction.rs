@@ -15,6 +15,12 @@ where
         CollectionTransaction(TableTransaction::new())
     }
 
+    /// Queries whether `item` belongs to the [`Collection`]. The answer is
+    /// read from the [`CollectionResponse`] (see [`CollectionResponse::contains`]).
+    ///
+    /// [`Collection`]: crate::database::Collection
+    /// [`CollectionResponse`]: crate::database::CollectionResponse
+    /// [`CollectionResponse::contains`]: crate::database::CollectionResponse::contains
     pub fn contains(&mut self, item: &Item) -> Result<Query, Top<QueryError>> {
         self.0.get(item)
     }
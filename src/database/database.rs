@@ -1,12 +1,35 @@
 use crate::{
-    common::store::Field,
+    common::{data::Bytes, store::Field},
     database::{
-        store::{Cell, Store},
-        Table, TableReceiver,
+        errors::{BackendError, ImportError, QueryError, StateError, SyncError},
+        interact::{import, Batch, Operation},
+        store::{Cell, Handle, Label, Store},
+        table_state, Backend, DatabaseBuilder, Global, IndexedTable, Prefer, ReadSnapshot,
+        ReceiverState, StoreAllocator, StoreStats, Table, TableReceiver, TableTransaction,
+        TxnContext,
     },
+    map::Map,
 };
 
-use talk::sync::lenders::AtomicLender;
+use doomstack::{here, Doom, ResultExt, Top};
+
+use serde::de::DeserializeOwned;
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+    sync::Arc,
+};
+
+use talk::{
+    crypto::primitives::{hash, hash::Hash},
+    sync::lenders::AtomicLender,
+};
+
+// A `Database` opened from a `Backend`, along with its recovered `Table`s
+type Opened<Key, Value> = (Database<Key, Value>, Vec<Table<Key, Value>>);
 
 /// A datastrucure for memory-efficient storage and transfer of maps with a
 /// large degree of similarity (% of key-pairs in common).
@@ -28,7 +51,15 @@ use talk::sync::lenders::AtomicLender;
 /// It is required that the keys implement `'static` and the [`Serialize`],
 /// [`Send`] and [`Sync`] traits.
 ///
+/// The maps holding the nodes of a database allocate through `A`, a
+/// [`StoreAllocator`] that defaults to the [`Global`] allocator (see
+/// [`DatabaseBuilder::allocator`]). Its [`Table`]s, receivers and senders
+/// share the same allocator.
+///
 /// [`Field`]: crate::common::store::Field
+/// [`StoreAllocator`]: crate::database::StoreAllocator
+/// [`Global`]: crate::database::Global
+/// [`DatabaseBuilder::allocator`]: crate::database::DatabaseBuilder::allocator
 /// [`Table`]: crate::database::Table
 /// [`Transaction`]: crate::database::TableTransaction
 /// [`Serialize`]: serde::Serialize
@@ -77,12 +108,13 @@ use talk::sync::lenders::AtomicLender;
 /// }
 /// ```
 
-pub struct Database<Key, Value>
+pub struct Database<Key, Value, A = Global>
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
-    pub(crate) store: Cell<Key, Value>,
+    pub(crate) store: Cell<Key, Value, A>,
 }
 
 impl<Key, Value> Database<Key, Value>
@@ -104,163 +136,1896 @@ where
         }
     }
 
-    /// Creates and assigns an empty [`Table`] to the `Database`.
+    /// Opens the `Database` persisted in `backend`, returning it along
+    /// with the [`Table`]s that were alive when it was last [`close`]d.
+    ///
+    /// From then on, changes to the `Database` are written through to
+    /// `backend`, in one atomic batch per modification of a [`Table`]
+    /// (see [`Backend::write`]). An empty `backend` yields an empty `Database`.
+    ///
+    /// # Errors
+    ///
+    /// If `backend` cannot be loaded, or does not hold a consistent
+    /// `Database`, a [`BackendError`] is returned.
+    ///
+    /// [`Table`]: crate::database::Table
+    /// [`close`]: crate::database::Database::close
+    /// [`Backend::write`]: crate::database::Backend::write
+    /// [`BackendError`]: crate::database::errors::BackendError
     ///
     /// # Examples
     ///
-    /// ```
-    /// use zebra::database::Database;
-    /// let mut database: Database<&str, i32> = Database::new();
+    /// ```no_run
+    /// use zebra::database::{Database, LogBackend, TableTransaction};
     ///
-    /// let table = database.empty_table();
+    /// let backend = LogBackend::open("database.log").unwrap();
+    /// let (database, mut tables) = Database::<u32, u32>::open(backend).unwrap();
+    ///
+    /// if tables.is_empty() {
+    ///     let mut transaction = TableTransaction::new();
+    ///     transaction.set(0, 0).unwrap();
+    ///
+    ///     let mut table = database.empty_table();
+    ///     table.execute(transaction);
+    ///
+    ///     tables.push(table);
+    /// }
+    ///
+    /// database.close().unwrap();
     /// ```
-    pub fn empty_table(&self) -> Table<Key, Value> {
-        Table::empty(self.store.clone())
+    pub fn open<B>(backend: B) -> Result<Opened<Key, Value>, Top<BackendError>>
+    where
+        Key: DeserializeOwned,
+        Value: DeserializeOwned,
+        B: Backend,
+    {
+        let backend: Arc<dyn Backend> = Arc::new(backend);
+
+        let mut store = Store::new();
+        store.load(backend.as_ref())?;
+        store.set_backend(Some(backend));
+
+        let roots: Vec<Label> = store.roots().collect();
+
+        let database = Database {
+            store: Cell::new(AtomicLender::new(store)),
+        };
+
+        // Every loaded root is already held once per `Table` it backs
+        let tables = roots
+            .into_iter()
+            .map(|root| Table::from_handle(Handle::held(database.store.clone(), root)))
+            .collect();
+
+        Ok((database, tables))
     }
 
-    /// Creates a [`TableReceiver`] assigned to this `Database`. The
-    /// receiver is used to efficiently receive a [`Table`]
-    /// from other databases and add them this one.
+    /// Creates an empty `Database` whose [`Table`]s process batches of
+    /// fewer than `threshold` operations sequentially, rather than
+    /// splitting them across threads.
     ///
-    /// See [`TableReceiver`] for more details on its operation.
+    /// Spawning a task for every branch of a small batch can cost more
+    /// than it saves: `Database::new` uses a threshold of 64 operations.
+    ///
+    /// A threshold of `usize::MAX` disables parallelism altogether, e.g. for
+    /// environments without threads: batches are then processed on the
+    /// calling thread, yielding the same [`Table`]s as in parallel.
     ///
     /// # Examples
     ///
     /// ```
     /// use zebra::database::Database;
-    /// let mut database: Database<&str, i32> = Database::new();
+    /// let mut database: Database<&str, i32> = Database::with_parallel_threshold(256);
+    /// ```
+    pub fn with_parallel_threshold(threshold: usize) -> Self {
+        let mut store = Store::new();
+        store.set_parallel_threshold(threshold);
+
+        Database {
+            store: Cell::new(AtomicLender::new(store)),
+        }
+    }
+
+    /// Returns a [`DatabaseBuilder`], to create a `Database` with
+    /// non-default tuning (see its documentation for more details).
     ///
-    /// let mut receiver = database.receive();
+    /// # Examples
     ///
-    /// // Do things with receiver...
+    /// ```
+    /// use zebra::database::Database;
     ///
+    /// let database: Database<&str, i32> = Database::builder().threads(4).build().unwrap();
     /// ```
-    pub fn receive(&self) -> TableReceiver<Key, Value> {
-        TableReceiver::new(self.store.clone())
+    pub fn builder() -> DatabaseBuilder<Key, Value> {
+        DatabaseBuilder::new()
     }
 }
 
-impl<Key, Value> Clone for Database<Key, Value>
+impl<Key, Value, A> Database<Key, Value, A>
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
-    fn clone(&self) -> Self {
-        Database {
-            store: self.store.clone(),
+    /// Makes all changes durable, then detaches the `Database` from its
+    /// [`Backend`] (if any): the [`Table`]s alive at this point are
+    /// recovered by the next [`open`].
+    ///
+    /// Changes made after `close` (including dropping [`Table`]s)
+    /// are not persisted.
+    ///
+    /// # Errors
+    ///
+    /// If a write through to the [`Backend`] failed, [`WriteFailed`] is
+    /// returned (see [`flush`]).
+    ///
+    /// If the [`Backend`] fails to flush, [`FlushFailed`] is returned.
+    ///
+    /// [`Backend`]: crate::database::Backend
+    /// [`Table`]: crate::database::Table
+    /// [`open`]: crate::database::Database::open
+    /// [`flush`]: crate::database::Database::flush
+    /// [`WriteFailed`]: crate::database::errors::BackendError::WriteFailed
+    /// [`FlushFailed`]: crate::database::errors::BackendError::FlushFailed
+    pub fn close(&self) -> Result<(), Top<BackendError>> {
+        let mut store = self.store.take();
+        store.persist();
+
+        let poisoned = store.poisoned();
+        let backend = store.set_backend(None);
+        self.store.restore(store);
+
+        match backend {
+            Some(backend) => flush(backend.as_ref(), poisoned),
+            None => Ok(()),
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Makes all changes made so far durable on the [`Backend`] (if any),
+    /// without detaching the `Database` from it: should the process crash,
+    /// the [`Table`]s alive at this point are recovered by the next [`open`].
+    ///
+    /// Changes are flushed between batches: no [`Table`] is modified
+    /// while the [`Backend`] is flushing.
+    ///
+    /// # Errors
+    ///
+    /// If a write through to the [`Backend`] failed since the `Database` was
+    /// opened, [`WriteFailed`] is returned. From the failed write on, the
+    /// `Database` no longer writes through to the [`Backend`], which is left
+    /// as it was upon failure: changes made since the last successful flush
+    /// may not be recovered by the next [`open`].
+    ///
+    /// If the [`Backend`] fails to flush, [`FlushFailed`] is returned.
+    ///
+    /// [`Backend`]: crate::database::Backend
+    /// [`Table`]: crate::database::Table
+    /// [`open`]: crate::database::Database::open
+    /// [`WriteFailed`]: crate::database::errors::BackendError::WriteFailed
+    /// [`FlushFailed`]: crate::database::errors::BackendError::FlushFailed
+    pub fn flush(&self) -> Result<(), Top<BackendError>> {
+        let mut store = self.store.take();
+        store.persist();
 
-    use crate::database::{store::Label, TableTransaction};
+        let result = match store.backend() {
+            Some(backend) => flush(backend.as_ref(), store.poisoned()),
+            None => Ok(()),
+        };
 
-    impl<Key, Value> Database<Key, Value>
-    where
-        Key: Field,
-        Value: Field,
-    {
-        pub(crate) fn table_with_records<I>(&self, records: I) -> Table<Key, Value>
-        where
-            I: IntoIterator<Item = (Key, Value)>,
-        {
-            let mut table = self.empty_table();
-            let mut transaction = TableTransaction::new();
+        self.store.restore(store);
+        result
+    }
 
-            for (key, value) in records {
-                transaction.set(key, value).unwrap();
-            }
+    /// Creates and assigns an empty [`Table`] to the `Database`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::Database;
+    /// let mut database: Database<&str, i32> = Database::new();
+    ///
+    /// let table = database.empty_table();
+    /// ```
+    pub fn empty_table(&self) -> Table<Key, Value, A> {
+        Table::empty(self.store.clone())
+    }
 
-            table.execute(transaction);
-            table
+    /// Creates and assigns to the `Database` a [`Table`] holding the
+    /// records of `map`. The commitment of the [`Table`] equals that
+    /// of `map`. Nodes shared with other [`Table`]s in the `Database`
+    /// are stored only once.
+    ///
+    /// # Errors
+    ///
+    /// If `map` is not complete (see [`Map::is_complete`]),
+    /// [`MapIncomplete`] is returned.
+    ///
+    /// [`Table`]: crate::database::Table
+    /// [`Map::is_complete`]: crate::map::Map::is_complete
+    /// [`MapIncomplete`]: crate::database::errors::ImportError::MapIncomplete
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::{database::Database, map::Map};
+    ///
+    /// let mut map: Map<&str, i32> = Map::new();
+    /// map.insert("alice", 31).unwrap();
+    ///
+    /// let database: Database<&str, i32> = Database::new();
+    /// let table = database.table_from_map(map.clone()).unwrap();
+    ///
+    /// assert_eq!(table.commit(), map.commit());
+    /// ```
+    pub fn table_from_map(
+        &self,
+        map: Map<Key, Value>,
+    ) -> Result<Table<Key, Value, A>, Top<ImportError>>
+    where
+        Key: Clone,
+        Value: Clone,
+    {
+        if !map.is_complete() {
+            return ImportError::MapIncomplete.fail().spot(here!());
         }
 
-        pub(crate) fn check<'a, I, J>(&self, tables: I, receivers: J)
-        where
-            I: IntoIterator<Item = &'a Table<Key, Value>>,
-            J: IntoIterator<Item = &'a TableReceiver<Key, Value>>,
-        {
-            let tables: Vec<&'a Table<Key, Value>> = tables.into_iter().collect();
+        let mut store = self.store.take();
 
-            let receivers: Vec<&'a TableReceiver<Key, Value>> = receivers.into_iter().collect();
+        let root = import::import(&mut store, map.root());
+        store.incref(root);
 
-            for table in &tables {
-                table.check_tree();
-            }
+        self.store.restore(store);
 
-            let table_held = tables.iter().map(|table| table.root());
+        Ok(Table::new(self.store.clone(), root))
+    }
 
-            let receiver_held = receivers.iter().map(|receiver| receiver.held()).flatten();
+    /// Creates a [`Table`] holding `records`. If a key occurs more than
+    /// once in `records`, its last occurrence is kept.
+    ///
+    /// `records` are applied to an empty [`Table`] at once, as a
+    /// [`TableTransaction`] would be: independent subtrees are built
+    /// in parallel (see [`DatabaseBuilder::parallel_threshold`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if a key or value cannot be hashed (on which
+    /// [`TableTransaction::set`] would fail).
+    ///
+    /// [`Table`]: crate::database::Table
+    /// [`TableTransaction`]: crate::database::TableTransaction
+    /// [`DatabaseBuilder::parallel_threshold`]: crate::database::DatabaseBuilder::parallel_threshold
+    /// [`TableTransaction::set`]: crate::database::TableTransaction::set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::{Database, TableTransaction};
+    ///
+    /// let database: Database<&str, i32> = Database::new();
+    ///
+    /// let table = database.table_with_records([("alice", 31), ("bob", 44), ("alice", 32)]);
+    ///
+    /// let mut transaction = TableTransaction::new();
+    /// transaction.set("alice", 32).unwrap();
+    /// transaction.set("bob", 44).unwrap();
+    ///
+    /// let mut reference = database.empty_table();
+    /// reference.execute(transaction);
+    ///
+    /// assert_eq!(table.commit(), reference.commit());
+    /// ```
+    pub fn table_with_records<I>(&self, records: I) -> Table<Key, Value, A>
+    where
+        I: IntoIterator<Item = (Key, Value)>,
+    {
+        // Later operations on a path replace earlier ones
+        let operations = records
+            .into_iter()
+            .map(|(key, value)| {
+                let operation = Operation::set(key, value).expect("failed to hash record");
+                (operation.path, operation)
+            })
+            .collect::<HashMap<_, _>>()
+            .into_values()
+            .collect();
 
-            let held: Vec<Label> = table_held.chain(receiver_held).collect();
+        let mut handle = Handle::empty(self.store.clone());
+        handle.apply(Batch::new(operations));
 
-            let mut store = self.store.take();
-            store.check_leaks(held.clone());
-            store.check_references(held.clone());
-            self.store.restore(store);
-        }
+        Table::from_handle(handle)
     }
 
-    #[test]
-    fn modify_basic() {
-        let database: Database<u32, u32> = Database::new();
-
-        let mut table = database.table_with_records((0..256).map(|i| (i, i)));
+    /// Creates a [`Table`] holding the records of both `lho` and `rho`.
+    /// If a key is held by both with different values, `prefer` decides
+    /// which value is kept.
+    ///
+    /// The two trees are traversed together: subtrees shared by `lho` and
+    /// `rho` (or held by only one of them) are reused as they are, and only
+    /// nodes along divergent branches are created. `lho` and `rho` are left
+    /// untouched. The new [`Table`] sets no expiries (see [`Table::expire`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lho` or `rho` does not belong to this `Database`.
+    ///
+    /// [`Table`]: crate::database::Table
+    /// [`Table::expire`]: crate::database::Table::expire
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::{Database, Prefer};
+    ///
+    /// let database: Database<&str, i32> = Database::new();
+    ///
+    /// let lho = database.table_with_records([("alice", 31), ("bob", 44)]);
+    ///
+    /// let rho = database.table_with_records([("bob", 45), ("carl", 27)]);
+    ///
+    /// let merged = database.merge_tables(&lho, &rho, Prefer::Right);
+    ///
+    /// let reference = database.table_with_records([("alice", 31), ("bob", 45), ("carl", 27)]);
+    ///
+    /// assert_eq!(merged.commit(), reference.commit());
+    /// ```
+    pub fn merge_tables(
+        &self,
+        lho: &Table<Key, Value, A>,
+        rho: &Table<Key, Value, A>,
+        prefer: Prefer,
+    ) -> Table<Key, Value, A> {
+        assert!(
+            lho.belongs_to(&self.store) && rho.belongs_to(&self.store),
+            "called `Database::merge_tables` with a `Table` from another `Database`"
+        );
 
-        let mut transaction = TableTransaction::new();
-        for i in 128..256 {
-            transaction.set(i, i + 1).unwrap();
-        }
-        let _ = table.execute(transaction);
-        table.assert_records((0..256).map(|i| (i, if i < 128 { i } else { i + 1 })));
+        Table::merge(lho, rho, prefer)
+    }
 
-        database.check([&table], []);
+    /// Creates a [`TableReceiver`] assigned to this `Database`. The
+    /// receiver is used to efficiently receive a [`Table`]
+    /// from other databases and add them this one.
+    ///
+    /// See [`TableReceiver`] for more details on its operation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::Database;
+    /// let mut database: Database<&str, i32> = Database::new();
+    ///
+    /// let mut receiver = database.receive();
+    ///
+    /// // Do things with receiver...
+    ///
+    /// ```
+    pub fn receive(&self) -> TableReceiver<Key, Value, A> {
+        TableReceiver::new(self.store.clone())
     }
 
-    #[test]
-    fn clone_modify_original() {
-        let database: Database<u32, u32> = Database::new();
+    /// Resumes an interrupted transfer from `state`, as obtained through
+    /// [`TableReceiver::checkpoint`] (possibly from a [`TableReceiver`] of
+    /// a previous instance of the `Database`).
+    ///
+    /// The transfer carries on from [`TableReceiver::question`]: nodes
+    /// acquired before the checkpoint are not asked for again.
+    ///
+    /// # Errors
+    ///
+    /// If `state` does not describe a tree (e.g., because it was tampered
+    /// with), [`MalformedState`] is returned.
+    ///
+    /// [`TableReceiver`]: crate::database::TableReceiver
+    /// [`TableReceiver::checkpoint`]: crate::database::TableReceiver::checkpoint
+    /// [`TableReceiver::question`]: crate::database::TableReceiver::question
+    /// [`MalformedState`]: crate::database::errors::SyncError::MalformedState
+    pub fn resume_receive(
+        &self,
+        state: ReceiverState<Key, Value>,
+    ) -> Result<TableReceiver<Key, Value, A>, Top<SyncError>> {
+        TableReceiver::resume(self.store.clone(), state)
+    }
 
-        let mut table = database.table_with_records((0..256).map(|i| (i, i)));
-        let table_clone = table.clone();
+    /// Returns a commitment to the commitments of all the [`Table`]s
+    /// currently alive in the `Database` (including those being sent
+    /// by a [`TableSender`]).
+    ///
+    /// The checkpoint does not depend on the order in which [`Table`]s
+    /// were created: two `Database`s holding the same [`Table`]s
+    /// have the same checkpoint.
+    ///
+    /// Only [`Table`]s still alive are reflected: as soon as a [`Table`]
+    /// is dropped, its root stops counting towards the checkpoint. A root
+    /// counts once per [`Table`] it backs, so that cloning a [`Table`]
+    /// changes the checkpoint as well.
+    ///
+    /// [`TableSender`]: crate::database::TableSender
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::Database;
+    /// let mut database: Database<&str, i32> = Database::new();
+    ///
+    /// let before = database.checkpoint();
+    /// let table = database.empty_table();
+    ///
+    /// assert_ne!(database.checkpoint(), before);
+    /// ```
+    pub fn checkpoint(&self) -> Hash {
+        let store = self.store.take();
+        let mut roots: Vec<Bytes> = store.roots().map(|root| root.hash()).collect();
+        self.store.restore(store);
 
-        let mut transaction = TableTransaction::new();
-        for i in 128..256 {
-            transaction.set(i, i + 1).unwrap();
-        }
-        let _response = table.execute(transaction);
-        table.assert_records((0..256).map(|i| (i, if i < 128 { i } else { i + 1 })));
-        table_clone.assert_records((0..256).map(|i| (i, i)));
+        roots.sort();
+        hash::hash(&roots).unwrap()
+    }
 
-        database.check([&table, &table_clone], []);
-        drop(table_clone);
+    /// Returns the number of [`Table`]s currently alive in the `Database`
+    /// (including those being sent by a [`TableSender`]).
+    ///
+    /// [`TableSender`]: crate::database::TableSender
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::Database;
+    /// let mut database: Database<&str, i32> = Database::new();
+    ///
+    /// let table = database.empty_table();
+    /// assert_eq!(database.table_count(), 1);
+    /// ```
+    pub fn table_count(&self) -> usize {
+        let store = self.store.take();
+        let count = store.roots().count();
+        self.store.restore(store);
 
-        table.assert_records((0..256).map(|i| (i, if i < 128 { i } else { i + 1 })));
-        database.check([&table], []);
+        count
     }
 
-    #[test]
-    fn clone_modify_drop() {
-        let database: Database<u32, u32> = Database::new();
+    /// Returns the commitment and number of records of every [`Table`]
+    /// currently alive in the `Database` (including those being sent
+    /// by a [`TableSender`]), sorted by commitment.
+    ///
+    /// [`TableSender`]: crate::database::TableSender
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::Database;
+    /// let mut database: Database<&str, i32> = Database::new();
+    ///
+    /// let first = database.empty_table();
+    /// let second = database.empty_table();
+    ///
+    /// let summary = database.tables_summary();
+    ///
+    /// assert_eq!(summary.len(), 2);
+    /// assert_eq!(summary[0], (first.commit(), 0));
+    /// ```
+    pub fn tables_summary(&self) -> Vec<(Hash, usize)> {
+        let mut store = self.store.take();
 
-        let table = database.table_with_records((0..256).map(|i| (i, i)));
-        let mut table_clone = table.clone();
+        let roots: Vec<Label> = store.roots().collect();
+        let mut cache = HashMap::new();
 
-        let mut transaction = TableTransaction::new();
-        for i in 128..256 {
-            transaction.set(i, i + 1).unwrap();
-        }
-        let _response = table_clone.execute(transaction);
-        table_clone.assert_records((0..256).map(|i| (i, if i < 128 { i } else { i + 1 })));
-        table.assert_records((0..256).map(|i| (i, i)));
+        let mut summary: Vec<(Hash, usize)> = roots
+            .into_iter()
+            .map(|root| (root.hash().into(), store.count(root, &mut cache)))
+            .collect();
 
-        database.check([&table, &table_clone], []);
-        drop(table_clone);
+        self.store.restore(store);
 
-        table.assert_records((0..256).map(|i| (i, i)));
-        database.check([&table], []);
+        summary.sort_by_key(|(commitment, _)| Bytes::from(*commitment));
+        summary
+    }
+
+    /// Returns statistics on the nodes retained by the `Database` on behalf
+    /// of all its [`Table`]s (see [`StoreStats`]).
+    ///
+    /// [`StoreStats`]: crate::database::StoreStats
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::{Database, TableTransaction};
+    /// let database: Database<&str, i32> = Database::new();
+    ///
+    /// let mut table = database.empty_table();
+    ///
+    /// let mut transaction = TableTransaction::new();
+    /// transaction.set("alice", 1).unwrap();
+    /// transaction.set("bob", 2).unwrap();
+    /// table.execute(transaction);
+    ///
+    /// let stats = database.stats();
+    /// assert_eq!(stats.leaves(), 2);
+    /// ```
+    pub fn stats(&self) -> StoreStats {
+        let store = self.store.take();
+        let stats = store.stats();
+        self.store.restore(store);
+
+        stats
+    }
+
+    /// Returns the number of nodes retained by the `Database` with no
+    /// references (see [`StoreStats`]). Such nodes exist only transiently
+    /// while a [`Table`] is being modified: outside of that, a positive
+    /// count indicates a leak.
+    ///
+    /// [`StoreStats`]: crate::database::StoreStats
+    pub fn gc_candidates(&self) -> usize {
+        let store = self.store.take();
+        let candidates = store.gc_candidates();
+        self.store.restore(store);
+
+        candidates
+    }
+
+    /// Returns the number of nodes the `Database` can hold without
+    /// reallocating. Once [`Table`]s are dropped, this can far exceed
+    /// the number of nodes actually held (see [`stats`]): [`compact`]
+    /// releases the difference.
+    ///
+    /// [`Table`]: crate::database::Table
+    /// [`stats`]: crate::database::Database::stats
+    /// [`compact`]: crate::database::Database::compact
+    pub fn capacity(&self) -> usize {
+        let store = self.store.take();
+        let capacity = store.capacity();
+        self.store.restore(store);
+
+        capacity
+    }
+
+    /// Returns an estimate of the heap memory held by the `Database`, in
+    /// bytes. The estimate accounts for all the node slots the `Database`
+    /// can hold (see [`capacity`]), and for the keys and values of its
+    /// records, each sized after `Key` and `Value` alone: memory owned by
+    /// a key or value (e.g., the buffer of a `String`) is not accounted for
+    /// (see [`memory_usage_with`]).
+    ///
+    /// [`capacity`]: crate::database::Database::capacity
+    /// [`memory_usage_with`]: crate::database::Database::memory_usage_with
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::Database;
+    /// let database: Database<u32, u32> = Database::new();
+    ///
+    /// let empty = database.memory_usage();
+    ///
+    /// let table = database.table_with_records((0..1024).map(|i| (i, i)));
+    ///
+    /// assert!(database.memory_usage() > empty);
+    /// ```
+    pub fn memory_usage(&self) -> usize {
+        let store = self.store.take();
+        let usage = store.memory_usage();
+        self.store.restore(store);
+
+        usage
+    }
+
+    /// Same as [`memory_usage`], additionally accounting for the memory
+    /// owned by each key and value, as estimated by `key_heap` and
+    /// `value_heap` respectively. Keys and values shared by multiple
+    /// [`Table`]s are only accounted for once.
+    ///
+    /// [`memory_usage`]: crate::database::Database::memory_usage
+    /// [`Table`]: crate::database::Table
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::Database;
+    /// let database: Database<u32, String> = Database::new();
+    ///
+    /// let table = database.table_with_records((0..1024).map(|i| (i, "x".repeat(64))));
+    ///
+    /// assert!(
+    ///     database.memory_usage_with(|_| 0, |value: &String| value.capacity())
+    ///         >= database.memory_usage() + 1024 * 64
+    /// );
+    /// ```
+    pub fn memory_usage_with<KeyHeap, ValueHeap>(
+        &self,
+        key_heap: KeyHeap,
+        value_heap: ValueHeap,
+    ) -> usize
+    where
+        KeyHeap: Fn(&Key) -> usize,
+        ValueHeap: Fn(&Value) -> usize,
+    {
+        let store = self.store.take();
+        let usage = store.memory_usage_with(key_heap, value_heap);
+        self.store.restore(store);
+
+        usage
+    }
+
+    /// Shrinks the memory held by the `Database` to fit the nodes of its
+    /// live [`Table`]s, releasing the capacity left over by nodes that
+    /// were dropped (see [`capacity`]). Nodes are left untouched.
+    ///
+    /// Compacting rehashes every node, and blocks all [`Table`]s of the
+    /// `Database` while doing so: it is best invoked sparingly (e.g.,
+    /// after dropping a large [`Table`]).
+    ///
+    /// [`Table`]: crate::database::Table
+    /// [`capacity`]: crate::database::Database::capacity
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::Database;
+    /// let database: Database<u32, u32> = Database::new();
+    ///
+    /// let table = database.table_with_records((0..1024).map(|i| (i, i)));
+    ///
+    /// let capacity = database.capacity();
+    ///
+    /// drop(table);
+    /// database.compact();
+    ///
+    /// assert!(database.capacity() < capacity);
+    /// ```
+    pub fn compact(&self) {
+        let mut store = self.store.take();
+        store.compact();
+        self.store.restore(store);
+    }
+
+    /// Stages writes to several [`Table`]s through the [`TxnContext`] passed
+    /// to `f` then, once `f` returns, applies all of them together.
+    ///
+    /// If the [`TxnContext`] was aborted (e.g., because a write failed to
+    /// stage), no write is applied. Otherwise, a commitment to the commitments
+    /// of all staged [`Table`]s (in staging order) is returned alongside
+    /// the value returned by `f`.
+    ///
+    /// [`TxnContext`]: crate::database::TxnContext
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::Database;
+    /// let database: Database<&str, i32> = Database::new();
+    ///
+    /// let mut checking = database.empty_table();
+    /// let mut savings = database.empty_table();
+    ///
+    /// let (_, checkpoint) = database.transact(|context| {
+    ///     let checking = context.table(&mut checking);
+    ///     let savings = context.table(&mut savings);
+    ///
+    ///     context.set(checking, "alice", 10).unwrap();
+    ///     context.set(savings, "alice", 90).unwrap();
+    /// });
+    ///
+    /// assert!(checkpoint.is_some());
+    /// ```
+    pub fn transact<'t, F, R>(&self, f: F) -> (R, Option<Hash>)
+    where
+        F: FnOnce(&mut TxnContext<'t, Key, Value, A>) -> R,
+    {
+        let mut context = TxnContext::new(self.store.clone());
+        let result = f(&mut context);
+
+        (result, context.commit())
+    }
+
+    /// Captures the [`Table`]s currently alive in the `Database` (including
+    /// those being sent by a [`TableSender`]) in a [`ReadSnapshot`].
+    ///
+    /// The snapshot keeps the contents of the captured [`Table`]s: its view
+    /// is unaffected by later modifications or drops. The snapshot does
+    /// not count as a live [`Table`] (see [`ReadSnapshot`]).
+    ///
+    /// [`TableSender`]: crate::database::TableSender
+    /// [`ReadSnapshot`]: crate::database::ReadSnapshot
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::{Database, TableTransaction};
+    /// let mut database: Database<&str, i32> = Database::new();
+    ///
+    /// let mut table = database.empty_table();
+    /// let commitment = table.commit();
+    ///
+    /// let snapshot = database.read_snapshot();
+    ///
+    /// let mut transaction = TableTransaction::new();
+    /// transaction.set("alice", 1).unwrap();
+    /// table.execute(transaction);
+    ///
+    /// assert!(snapshot.table(commitment).is_some());
+    /// assert!(snapshot.table(table.commit()).is_none());
+    /// ```
+    pub fn read_snapshot(&self) -> ReadSnapshot<Key, Value, A> {
+        let mut store = self.store.take();
+        let roots: HashSet<Label> = store.roots().collect();
+
+        // Reference the roots without holding them: the snapshot's `Table`s
+        // are not alive until retrieved
+        for root in roots.iter() {
+            store.incref(*root);
+        }
+
+        self.store.restore(store);
+
+        let roots = roots.into_iter().map(|root| (root.hash(), root)).collect();
+
+        ReadSnapshot::new(self.store.clone(), roots)
+    }
+
+    /// Wraps `table` in an [`IndexedTable`], indexing each of its records
+    /// `(key, value)` under `index_fn(&key, &value)` (or not at all, if
+    /// `index_fn` returns `None`).
+    ///
+    /// The index is built from the records currently in `table`, then kept
+    /// in sync by [`IndexedTable::execute`].
+    ///
+    /// # Errors
+    ///
+    /// If any indexed key or `IndexKey` cannot be hashed, [`HashError`]
+    /// is returned.
+    ///
+    /// [`IndexedTable`]: crate::database::IndexedTable
+    /// [`IndexedTable::execute`]: crate::database::IndexedTable::execute
+    /// [`HashError`]: crate::database::errors::QueryError::HashError
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::{Database, TableTransaction};
+    /// let database: Database<u32, String> = Database::new();
+    ///
+    /// let table = database.empty_table();
+    /// let mut table = database
+    ///     .with_index(table, |_, value: &String| Some(value.len()))
+    ///     .unwrap();
+    ///
+    /// let mut transaction = TableTransaction::new();
+    /// transaction.set(0, "zero".to_string()).unwrap();
+    /// transaction.set(1, "one".to_string()).unwrap();
+    /// table.execute(transaction).unwrap();
+    ///
+    /// assert!(table.lookup(&4).unwrap().unwrap().contains(&0).unwrap());
+    /// assert!(table.lookup(&5).unwrap().is_none());
+    /// ```
+    pub fn with_index<IndexKey, F>(
+        &self,
+        table: Table<Key, Value, A>,
+        index_fn: F,
+    ) -> Result<IndexedTable<Key, Value, IndexKey, F, A>, Top<QueryError>>
+    where
+        Key: Clone,
+        IndexKey: Field + Clone,
+        F: Fn(&Key, &Value) -> Option<IndexKey>,
+    {
+        assert!(
+            table.belongs_to(&self.store),
+            "`table` does not belong to this `Database`"
+        );
+
+        IndexedTable::new(table, index_fn)
+    }
+
+    /// Loads a [`Table`] from the `.zstate` file at `path`, as written by
+    /// [`Table::write_state`] (see its documentation for the format).
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be opened or read, [`OpenFailed`] or [`ReadFailed`]
+    /// is returned.
+    ///
+    /// If the file is not a `.zstate` file, [`MalformedHeader`] is returned.
+    ///
+    /// If the file was written in a different version of the format,
+    /// [`UnsupportedVersion`] is returned.
+    ///
+    /// If the records cannot be deserialized, do not match the record count,
+    /// or contain the same key twice, [`MalformedRecords`] is returned.
+    ///
+    /// If the records do not match the commitment in the header,
+    /// [`CommitmentMismatch`] is returned.
+    ///
+    /// [`Table`]: crate::database::Table
+    /// [`Table::write_state`]: crate::database::Table::write_state
+    /// [`OpenFailed`]: crate::database::errors::StateError::OpenFailed
+    /// [`ReadFailed`]: crate::database::errors::StateError::ReadFailed
+    /// [`MalformedHeader`]: crate::database::errors::StateError::MalformedHeader
+    /// [`UnsupportedVersion`]: crate::database::errors::StateError::UnsupportedVersion
+    /// [`MalformedRecords`]: crate::database::errors::StateError::MalformedRecords
+    /// [`CommitmentMismatch`]: crate::database::errors::StateError::CommitmentMismatch
+    pub fn read_state<P>(&self, path: P) -> Result<Table<Key, Value, A>, Top<StateError>>
+    where
+        Key: DeserializeOwned,
+        Value: DeserializeOwned,
+        P: AsRef<Path>,
+    {
+        let file = File::open(path)
+            .map_err(|_| StateError::OpenFailed.into_top())
+            .spot(here!())?;
+
+        self.read_table(BufReader::new(file))
+    }
+
+    /// Loads a [`Table`] from `bytes`, as obtained by [`Table::to_bytes`].
+    ///
+    /// The records are re-hashed upon loading: bytes that were tampered with
+    /// are rejected, unless they still match the commitment they carry.
+    ///
+    /// # Errors
+    ///
+    /// Errors are the same as for [`read_state`], except for [`OpenFailed`]
+    /// and [`ReadFailed`], which cannot occur.
+    ///
+    /// [`Table`]: crate::database::Table
+    /// [`Table::to_bytes`]: crate::database::Table::to_bytes
+    /// [`read_state`]: crate::database::Database::read_state
+    /// [`OpenFailed`]: crate::database::errors::StateError::OpenFailed
+    /// [`ReadFailed`]: crate::database::errors::StateError::ReadFailed
+    pub fn table_from_bytes(&self, bytes: &[u8]) -> Result<Table<Key, Value, A>, Top<StateError>>
+    where
+        Key: DeserializeOwned,
+        Value: DeserializeOwned,
+    {
+        self.read_table(bytes)
+    }
+
+    fn read_table<R>(&self, reader: R) -> Result<Table<Key, Value, A>, Top<StateError>>
+    where
+        Key: DeserializeOwned,
+        Value: DeserializeOwned,
+        R: Read,
+    {
+        let (commitment, records) = table_state::read(reader)?;
+
+        let mut transaction = TableTransaction::new();
+
+        for (key, value) in records {
+            transaction
+                .set(key, value)
+                .pot(StateError::MalformedRecords, here!())?;
+        }
+
+        let mut table = self.empty_table();
+        table.execute(transaction);
+
+        if table.commit() != commitment {
+            return StateError::CommitmentMismatch.fail().spot(here!());
+        }
+
+        Ok(table)
+    }
+}
+
+impl<Key, Value, A> Clone for Database<Key, Value, A>
+where
+    Key: Field,
+    Value: Field,
+    A: StoreAllocator,
+{
+    fn clone(&self) -> Self {
+        Database {
+            store: self.store.clone(),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+mod checks {
+    use super::*;
+
+    impl<Key, Value, A> Database<Key, Value, A>
+    where
+        Key: Field,
+        Value: Field,
+        A: StoreAllocator,
+    {
+        /// Panics if any of `tables` is malformed, if the `Database` holds
+        /// entries that are unreachable from `tables` and `receivers`, or if
+        /// the reference counts of any entry are inconsistent.
+        pub fn check<'a, I, J>(&self, tables: I, receivers: J)
+        where
+            I: IntoIterator<Item = &'a Table<Key, Value, A>>,
+            J: IntoIterator<Item = &'a TableReceiver<Key, Value, A>>,
+        {
+            let tables: Vec<&'a Table<Key, Value, A>> = tables.into_iter().collect();
+
+            let receivers: Vec<&'a TableReceiver<Key, Value, A>> = receivers.into_iter().collect();
+
+            for table in &tables {
+                table.check_tree();
+            }
+
+            let table_held = tables.iter().map(|table| table.root());
+
+            let receiver_held = receivers.iter().map(|receiver| receiver.held()).flatten();
+
+            let held: Vec<Label> = table_held.chain(receiver_held).collect();
+
+            let mut store = self.store.take();
+            store.check_leaks(held.clone());
+            store.check_references(held.clone());
+            self.store.restore(store);
+        }
+    }
+}
+
+fn flush(backend: &dyn Backend, poisoned: bool) -> Result<(), Top<BackendError>> {
+    if poisoned {
+        return BackendError::WriteFailed.fail().spot(here!());
+    }
+
+    backend
+        .flush()
+        .map_err(|_| BackendError::FlushFailed.into_top())
+        .spot(here!())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::database::{LogBackend, TableTransaction};
+
+    #[test]
+    fn modify_basic() {
+        let database: Database<u32, u32> = Database::new();
+
+        let mut table = database.table_with_records((0..256).map(|i| (i, i)));
+
+        let mut transaction = TableTransaction::new();
+        for i in 128..256 {
+            transaction.set(i, i + 1).unwrap();
+        }
+        let _ = table.execute(transaction);
+        table.assert_records((0..256).map(|i| (i, if i < 128 { i } else { i + 1 })));
+
+        database.check([&table], []);
+    }
+
+    #[test]
+    fn clone_modify_original() {
+        let database: Database<u32, u32> = Database::new();
+
+        let mut table = database.table_with_records((0..256).map(|i| (i, i)));
+        let table_clone = table.clone();
+
+        let mut transaction = TableTransaction::new();
+        for i in 128..256 {
+            transaction.set(i, i + 1).unwrap();
+        }
+        let _response = table.execute(transaction);
+        table.assert_records((0..256).map(|i| (i, if i < 128 { i } else { i + 1 })));
+        table_clone.assert_records((0..256).map(|i| (i, i)));
+
+        database.check([&table, &table_clone], []);
+        drop(table_clone);
+
+        table.assert_records((0..256).map(|i| (i, if i < 128 { i } else { i + 1 })));
+        database.check([&table], []);
+    }
+
+    #[test]
+    fn clone_modify_drop() {
+        let database: Database<u32, u32> = Database::new();
+
+        let table = database.table_with_records((0..256).map(|i| (i, i)));
+        let mut table_clone = table.clone();
+
+        let mut transaction = TableTransaction::new();
+        for i in 128..256 {
+            transaction.set(i, i + 1).unwrap();
+        }
+        let _response = table_clone.execute(transaction);
+        table_clone.assert_records((0..256).map(|i| (i, if i < 128 { i } else { i + 1 })));
+        table.assert_records((0..256).map(|i| (i, i)));
+
+        database.check([&table, &table_clone], []);
+        drop(table_clone);
+
+        table.assert_records((0..256).map(|i| (i, i)));
+        database.check([&table], []);
+    }
+
+    #[test]
+    fn checkpoint_order_independent() {
+        let first: Database<u32, u32> = Database::new();
+        let second: Database<u32, u32> = Database::new();
+
+        let _first_tables = [
+            first.table_with_records((0..256).map(|i| (i, i))),
+            first.table_with_records((128..512).map(|i| (i, i + 1))),
+            first.empty_table(),
+        ];
+
+        let _second_tables = [
+            second.empty_table(),
+            second.table_with_records((128..512).map(|i| (i, i + 1))),
+            second.table_with_records((0..256).map(|i| (i, i))),
+        ];
+
+        assert_eq!(first.checkpoint(), second.checkpoint());
+    }
+
+    #[test]
+    fn checkpoint_modify() {
+        let database: Database<u32, u32> = Database::new();
+        let empty = database.checkpoint();
+
+        let mut table = database.table_with_records((0..256).map(|i| (i, i)));
+        let before = database.checkpoint();
+
+        assert_ne!(before, empty);
+
+        let mut transaction = TableTransaction::new();
+        transaction.set(0, 1).unwrap();
+        let _ = table.execute(transaction);
+
+        assert_ne!(database.checkpoint(), before);
+
+        let mut transaction = TableTransaction::new();
+        transaction.set(0, 0).unwrap();
+        let _ = table.execute(transaction);
+
+        assert_eq!(database.checkpoint(), before);
+
+        drop(table);
+        assert_eq!(database.checkpoint(), empty);
+    }
+
+    #[test]
+    fn checkpoint_clone() {
+        let database: Database<u32, u32> = Database::new();
+
+        let table = database.table_with_records((0..256).map(|i| (i, i)));
+        let before = database.checkpoint();
+
+        let clone = table.clone();
+        assert_ne!(database.checkpoint(), before);
+
+        drop(clone);
+        assert_eq!(database.checkpoint(), before);
+    }
+
+    #[test]
+    fn tables_summary() {
+        let database: Database<u32, u32> = Database::new();
+
+        let tables = [
+            database.table_with_records((0..256).map(|i| (i, i))),
+            database.table_with_records((128..512).map(|i| (i, i + 1))),
+            database.table_with_records((0..16).map(|i| (i, i))),
+        ];
+
+        let mut expected: Vec<(Hash, usize)> = vec![
+            (tables[0].commit(), 256),
+            (tables[1].commit(), 384),
+            (tables[2].commit(), 16),
+        ];
+
+        expected.sort_by_key(|(commitment, _)| Bytes::from(*commitment));
+
+        assert_eq!(database.table_count(), 3);
+        assert_eq!(database.tables_summary(), expected);
+
+        drop(tables);
+
+        assert_eq!(database.table_count(), 0);
+        assert!(database.tables_summary().is_empty());
+    }
+
+    #[test]
+    fn compact() {
+        let database: Database<u32, u32> = Database::new();
+
+        let large = database.table_with_records((0..8192).map(|i| (i, i)));
+
+        let small = database.table_with_records((0..256).map(|i| (i, i + 1)));
+
+        let snapshot = small.clone();
+
+        drop(large);
+
+        let stats = database.stats();
+        let capacity = database.capacity();
+
+        database.compact();
+
+        assert!(database.capacity() < capacity);
+
+        let compacted = database.stats();
+        assert_eq!(compacted.nodes(), stats.nodes());
+        assert_eq!(compacted.references(), stats.references());
+
+        let store = database.store.take();
+        assert_eq!(compacted.nodes(), store.size());
+        database.store.restore(store);
+
+        small.assert_records((0..256).map(|i| (i, i + 1)));
+        database.check([&small, &snapshot], []);
+    }
+
+    #[test]
+    fn memory_usage() {
+        let database: Database<u32, u32> = Database::new();
+        let mut table = database.empty_table();
+
+        let mut usage = database.memory_usage();
+        let empty = usage;
+
+        for batch in 0..32 {
+            let mut transaction = TableTransaction::new();
+
+            for key in (batch * 64)..((batch + 1) * 64) {
+                transaction.set(key, key).unwrap();
+            }
+
+            table.execute(transaction);
+
+            let grown = database.memory_usage();
+            assert!(grown > usage);
+            usage = grown;
+        }
+
+        assert!(usage > empty);
+
+        drop(table);
+        database.compact();
+
+        assert!(database.memory_usage() < usage);
+    }
+
+    #[test]
+    fn memory_usage_with() {
+        fn heap(value: &String) -> usize {
+            value.capacity()
+        }
+
+        let database: Database<u32, String> = Database::new();
+        let mut table = database.table_with_records((0..256).map(|i| (i, String::new())));
+
+        let mut usage = database.memory_usage_with(|_| 0, heap);
+        assert_eq!(usage, database.memory_usage());
+
+        // Growing values leaves the number of records (hence `memory_usage`) unchanged
+        for length in 1..8 {
+            let mut transaction = TableTransaction::new();
+
+            for key in 0..256 {
+                transaction.set(key, "x".repeat(length * 64)).unwrap();
+            }
+
+            table.execute(transaction);
+
+            let grown = database.memory_usage_with(|_| 0, heap);
+            assert!(grown > usage);
+            assert!(grown >= database.memory_usage() + 256 * length * 64);
+            usage = grown;
+        }
+    }
+
+    #[test]
+    fn stats() {
+        let database: Database<u32, u32> = Database::new();
+
+        let first = database.table_with_records((0..256).map(|i| (i, i)));
+        let mut second = first.clone();
+        let third = database.table_with_records((0..128).map(|i| (i, i + 1)));
+
+        // Modify `second`, then revert it
+        let mut transaction = TableTransaction::new();
+
+        for key in 0..64 {
+            transaction.set(key, key + 2).unwrap();
+        }
+
+        for key in 200..256 {
+            transaction.remove(&key).unwrap();
+        }
+
+        second.execute(transaction);
+        assert_ne!(second.commit(), first.commit());
+
+        let mut transaction = TableTransaction::new();
+
+        for key in 0..64 {
+            transaction.set(key, key).unwrap();
+        }
+
+        for key in 200..256 {
+            transaction.set(key, key).unwrap();
+        }
+
+        second.execute(transaction);
+        assert_eq!(second.commit(), first.commit());
+
+        let stats = database.stats();
+
+        assert_eq!(stats.leaves(), 256 + 128);
+
+        let store = database.store.take();
+        assert_eq!(stats.nodes(), store.size());
+        database.store.restore(store);
+
+        assert_eq!(stats.references().values().sum::<usize>(), stats.nodes());
+        assert_eq!(database.gc_candidates(), 0);
+
+        // The retained set depends only on the live tables
+        let reference: Database<u32, u32> = Database::new();
+        let reference_first = reference.table_with_records((0..256).map(|i| (i, i)));
+
+        let _tables = [
+            reference_first.clone(),
+            reference_first,
+            reference.table_with_records((0..128).map(|i| (i, i + 1))),
+        ];
+
+        assert_eq!(stats, reference.stats());
+
+        drop(third);
+
+        let stats = database.stats();
+
+        assert_eq!(stats.leaves(), 256);
+        assert_eq!(database.gc_candidates(), 0);
+
+        database.check([&first, &second], []);
+    }
+
+    #[test]
+    fn read_snapshot() {
+        let database: Database<u32, u32> = Database::new();
+
+        let mut first = database.table_with_records((0..64).map(|i| (i, i)));
+        let second = database.table_with_records((64..128).map(|i| (i, i)));
+        let third = second.clone();
+
+        let first_commitment = first.commit();
+        let second_commitment = second.commit();
+
+        let (checkpoint, table_count) = (database.checkpoint(), database.table_count());
+
+        let snapshot = database.read_snapshot();
+        assert_eq!(snapshot.len(), 2);
+
+        // Taking a snapshot does not make its `Table`s alive
+        assert_eq!(database.checkpoint(), checkpoint);
+        assert_eq!(database.table_count(), table_count);
+
+        let mut transaction = TableTransaction::new();
+        transaction.set(0, 1).unwrap();
+        transaction.remove(&1).unwrap();
+        first.execute(transaction);
+
+        drop(second);
+        drop(third);
+
+        let snapshot_first = snapshot.table(first_commitment).unwrap();
+        let snapshot_second = snapshot.table(second_commitment).unwrap();
+
+        assert!(snapshot.table(first.commit()).is_none());
+
+        snapshot_first.assert_records((0..64).map(|i| (i, i)));
+        snapshot_second.assert_records((64..128).map(|i| (i, i)));
+
+        drop(snapshot_first);
+        drop(snapshot_second);
+
+        let held = std::iter::once(first.root())
+            .chain(snapshot.roots())
+            .collect::<Vec<_>>();
+
+        let mut store = database.store.take();
+        store.check_leaks(held.clone());
+        store.check_references(held);
+        database.store.restore(store);
+
+        drop(snapshot);
+
+        database.check([&first], []);
+        assert_eq!(database.table_count(), 1);
+    }
+
+    #[test]
+    fn transact() {
+        let database: Database<u32, u32> = Database::new();
+
+        let mut first = database.table_with_records((0..64).map(|i| (i, i)));
+        let mut second = database.table_with_records((0..64).map(|i| (i, i)));
+
+        let (staged, checkpoint) = database.transact(|context| {
+            let first = context.table(&mut first);
+            let second = context.table(&mut second);
+
+            for key in 0..32 {
+                context.remove(first, &key).unwrap();
+                context.set(second, key, key + 1).unwrap();
+            }
+
+            64
+        });
+
+        assert_eq!(staged, 64);
+
+        first.assert_records((32..64).map(|i| (i, i)));
+        second.assert_records((0..64).map(|i| (i, if i < 32 { i + 1 } else { i })));
+
+        assert_eq!(
+            checkpoint.unwrap(),
+            hash::hash(&vec![first.commit(), second.commit()]).unwrap()
+        );
+
+        database.check([&first, &second], []);
+    }
+
+    #[test]
+    fn transact_failure() {
+        let database: Database<u32, u32> = Database::new();
+
+        let mut first = database.table_with_records((0..64).map(|i| (i, i)));
+        let mut second = database.table_with_records((0..64).map(|i| (i, i)));
+
+        let before = (first.commit(), second.commit());
+
+        let (_, checkpoint) = database.transact(|context| {
+            let first = context.table(&mut first);
+            let second = context.table(&mut second);
+
+            context.set(first, 0, 1).unwrap();
+            context.set(second, 0, 1).unwrap();
+
+            // Duplicate operation on the same key
+            assert!(context.remove(second, &0).is_err());
+            assert!(context.is_aborted());
+        });
+
+        assert!(checkpoint.is_none());
+        assert_eq!((first.commit(), second.commit()), before);
+
+        first.assert_records((0..64).map(|i| (i, i)));
+        second.assert_records((0..64).map(|i| (i, i)));
+
+        database.check([&first, &second], []);
+    }
+
+    #[test]
+    fn state_round_trip() {
+        let database: Database<u32, u32> = Database::new();
+        let table = database.table_with_records((0..256).map(|i| (i, i + 1)));
+
+        let path =
+            std::env::temp_dir().join(format!("state_round_trip_{}.zstate", std::process::id()));
+        table.write_state(&path).unwrap();
+
+        // Tables are loaded into any `Database`
+        let other: Database<u32, u32> = Database::new();
+        let loaded = other.read_state(&path).unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.commit(), table.commit());
+        loaded.assert_records((0..256).map(|i| (i, i + 1)));
+
+        let empty = database.empty_table();
+        empty.write_state(&path).unwrap();
+
+        let loaded_empty = database.read_state(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded_empty.commit(), empty.commit());
+
+        database.check([&table, &empty, &loaded_empty], []);
+        other.check([&loaded], []);
+    }
+
+    #[test]
+    fn state_commitment_mismatch() {
+        let database: Database<u32, u32> = Database::new();
+        let table = database.table_with_records((0..256).map(|i| (i, i)));
+
+        let path = std::env::temp_dir().join(format!(
+            "state_commitment_mismatch_{}.zstate",
+            std::process::id()
+        ));
+
+        table.write_state(&path).unwrap();
+
+        // Tamper with the embedded commitment (past magic number and version)
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[8] ^= 1;
+        std::fs::write(&path, &bytes).unwrap();
+
+        let result = database.read_state(&path);
+
+        assert!(matches!(
+            result.map(|_| ()).unwrap_err().top(),
+            StateError::CommitmentMismatch
+        ));
+
+        // Files that are not `.zstate` files are rejected upfront
+        std::fs::write(&path, b"not a state file").unwrap();
+        let result = database.read_state(&path);
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result.map(|_| ()).unwrap_err().top(),
+            StateError::MalformedHeader
+        ));
+
+        database.check([&table], []);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let database: Database<u32, u32> = Database::new();
+        let table = database.table_with_records((0..256).map(|i| (i, i + 1)));
+
+        let bytes = table.to_bytes().unwrap();
+
+        let other: Database<u32, u32> = Database::new();
+        let loaded = other.table_from_bytes(&bytes).unwrap();
+
+        assert_eq!(loaded.commit(), table.commit());
+        loaded.assert_records((0..256).map(|i| (i, i + 1)));
+
+        let empty = database.empty_table();
+        let loaded_empty = database
+            .table_from_bytes(&empty.to_bytes().unwrap())
+            .unwrap();
+
+        assert_eq!(loaded_empty.commit(), empty.commit());
+
+        database.check([&table, &empty, &loaded_empty], []);
+        other.check([&loaded], []);
+    }
+
+    #[test]
+    fn bytes_tampered() {
+        let database: Database<u32, u32> = Database::new();
+        let table = database.table_with_records((0..256).map(|i| (i, i)));
+
+        let bytes = table.to_bytes().unwrap();
+
+        // Tamper with the last record's value
+        let mut tampered = bytes.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+
+        assert!(matches!(
+            database
+                .table_from_bytes(&tampered)
+                .map(|_| ())
+                .unwrap_err()
+                .top(),
+            StateError::CommitmentMismatch
+        ));
+
+        // Tamper with the version
+        let mut tampered = bytes.clone();
+        tampered[6] ^= 1;
+
+        assert!(matches!(
+            database
+                .table_from_bytes(&tampered)
+                .map(|_| ())
+                .unwrap_err()
+                .top(),
+            StateError::UnsupportedVersion
+        ));
+
+        // Truncate the records
+        assert!(matches!(
+            database
+                .table_from_bytes(&bytes[..bytes.len() - 1])
+                .map(|_| ())
+                .unwrap_err()
+                .top(),
+            StateError::MalformedRecords
+        ));
+
+        database.check([&table], []);
+    }
+
+    #[test]
+    fn open_close() {
+        let path = std::env::temp_dir().join(format!("open_close_{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let (database, tables) =
+            Database::<u32, u32>::open(LogBackend::open(&path).unwrap()).unwrap();
+
+        assert!(tables.is_empty());
+
+        let first = database.table_with_records((0..256).map(|i| (i, i)));
+        let second = database.table_with_records((128..384).map(|i| (i, i)));
+        let dropped = database.table_with_records((0..64).map(|i| (i, i + 1)));
+
+        drop(dropped);
+
+        let (first_commit, second_commit) = (first.commit(), second.commit());
+
+        database.close().unwrap();
+
+        drop(first);
+        drop(second);
+        drop(database);
+
+        // Only the `Table`s alive upon closing are recovered
+        let (database, mut tables) =
+            Database::<u32, u32>::open(LogBackend::open(&path).unwrap()).unwrap();
+
+        assert_eq!(tables.len(), 2);
+
+        if tables[0].commit() != first_commit {
+            tables.swap(0, 1);
+        }
+
+        let second = tables.pop().unwrap();
+        let first = tables.pop().unwrap();
+
+        assert_eq!(first.commit(), first_commit);
+        assert_eq!(second.commit(), second_commit);
+
+        first.assert_records((0..256).map(|i| (i, i)));
+        second.assert_records((128..384).map(|i| (i, i)));
+
+        database.check([&first, &second], []);
+
+        // Changes after reopening are persisted as well
+        drop(first);
+        database.close().unwrap();
+        drop(second);
+        drop(database);
+
+        let (database, tables) =
+            Database::<u32, u32>::open(LogBackend::open(&path).unwrap()).unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(tables.len(), 1);
+        tables[0].assert_records((128..384).map(|i| (i, i)));
+
+        database.check(tables.iter(), []);
+    }
+
+    #[test]
+    fn open_truncated() {
+        let path = std::env::temp_dir().join(format!("open_truncated_{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let (database, _) = Database::<u32, u32>::open(LogBackend::open(&path).unwrap()).unwrap();
+
+        let mut table = database.table_with_records((0..256).map(|i| (i, i)));
+        database.flush().unwrap();
+
+        let commit = table.commit();
+
+        let mut transaction = TableTransaction::new();
+
+        for key in 0..128 {
+            transaction.remove(&key).unwrap();
+        }
+
+        table.execute(transaction);
+        database.flush().unwrap();
+
+        // Simulate a crash in the middle of writing the last batch
+        let file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        let length = file.metadata().unwrap().len();
+        file.set_len(length - 8).unwrap();
+        drop(file);
+
+        // The log reopens to the last committed state
+        let (recovered, tables) =
+            Database::<u32, u32>::open(LogBackend::open(&path).unwrap()).unwrap();
+
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].commit(), commit);
+        tables[0].assert_records((0..256).map(|i| (i, i)));
+
+        recovered.check(tables.iter(), []);
+
+        drop(tables);
+        drop(recovered);
+        drop(table);
+        drop(database);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn write_failed() {
+        // Accepts the first `capacity` batches, fails all later ones
+        struct Faulty {
+            writes: std::sync::atomic::AtomicUsize,
+            capacity: usize,
+        }
+
+        impl Backend for Faulty {
+            fn write(&self, _: Vec<(Vec<u8>, Option<Vec<u8>>)>) -> Result<(), Top<BackendError>> {
+                use std::sync::atomic::Ordering;
+
+                if self.writes.fetch_add(1, Ordering::Relaxed) < self.capacity {
+                    Ok(())
+                } else {
+                    BackendError::WriteFailed.fail().spot(here!())
+                }
+            }
+
+            fn flush(&self) -> std::io::Result<()> {
+                Ok(())
+            }
+
+            fn load(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Top<BackendError>> {
+                Ok(Vec::new())
+            }
+        }
+
+        let (database, _) = Database::<u32, u32>::open(Faulty {
+            writes: Default::default(),
+            capacity: 2,
+        })
+        .unwrap();
+
+        let first = database.table_with_records((0..16).map(|i| (i, i)));
+        database.flush().unwrap();
+
+        let mut second = database.table_with_records((0..256).map(|i| (i, i)));
+
+        assert!(matches!(
+            database.flush().unwrap_err().top(),
+            BackendError::WriteFailed
+        ));
+
+        // The `Database` keeps working in memory
+        let mut transaction = TableTransaction::new();
+        transaction.set(0, 1).unwrap();
+        second.execute(transaction);
+
+        first.assert_records((0..16).map(|i| (i, i)));
+        second.assert_records((0..256).map(|i| (i, if i == 0 { 1 } else { i })));
+
+        assert!(matches!(
+            database.close().unwrap_err().top(),
+            BackendError::WriteFailed
+        ));
+    }
+
+    #[test]
+    fn table_with_records_large() {
+        let database: Database<u32, u32> = Database::builder().threads(4).build().unwrap();
+        let table = database.table_with_records((0..100_000).map(|i| (i, i)));
+
+        let mut map: Map<u32, u32> = Map::new();
+
+        for key in 0..100_000 {
+            map.insert(key, key).unwrap();
+        }
+
+        assert_eq!(table.commit(), map.commit());
+    }
+
+    #[test]
+    fn table_with_records_duplicates() {
+        let database: Database<u32, u32> = Database::new();
+
+        let records = (0..256)
+            .map(|i| (i, i))
+            .chain((0..256).step_by(2).map(|i| (i, i + 1)))
+            .chain([(7, 0), (7, 1)]);
+
+        let table = database.table_with_records(records);
+
+        table.assert_records((0..256).map(|i| match i {
+            7 => (7, 1),
+            i if i % 2 == 0 => (i, i + 1),
+            i => (i, i),
+        }));
+
+        database.check([&table], []);
+    }
+
+    #[test]
+    fn table_with_records_empty() {
+        let database: Database<u32, u32> = Database::new();
+        let table = database.table_with_records([]);
+
+        assert_eq!(table.commit(), database.empty_table().commit());
+        table.assert_records([]);
+    }
+
+    #[test]
+    fn merge_tables_disjoint() {
+        let database: Database<u32, u32> = Database::new();
+
+        let lho = database.table_with_records((0..256).map(|i| (i, i)));
+
+        let rho = database.table_with_records((256..512).map(|i| (i, i)));
+
+        let merged = database.merge_tables(&lho, &rho, Prefer::Left);
+        merged.assert_records((0..512).map(|i| (i, i)));
+
+        let reference = database.table_with_records((0..512).map(|i| (i, i)));
+
+        assert_eq!(merged.commit(), reference.commit());
+        database.check([&lho, &rho, &merged, &reference], []);
+
+        drop(lho);
+        drop(rho);
+        drop(reference);
+
+        database.check([&merged], []);
+    }
+
+    #[test]
+    fn merge_tables_conflict() {
+        let database: Database<u32, u32> = Database::new();
+
+        let lho = database.table_with_records((0..384).map(|i| (i, i)));
+
+        let rho = database.table_with_records((128..512).map(|i| (i, i + (i % 2))));
+
+        let left = database.merge_tables(&lho, &rho, Prefer::Left);
+        let right = database.merge_tables(&lho, &rho, Prefer::Right);
+
+        left.assert_records((0..512).map(|i| (i, if i < 384 { i } else { i + (i % 2) })));
+        right.assert_records((0..512).map(|i| (i, if i < 128 { i } else { i + (i % 2) })));
+
+        database.check([&lho, &rho, &left, &right], []);
+
+        drop(lho);
+        drop(right);
+
+        database.check([&rho, &left], []);
+    }
+
+    #[test]
+    fn merge_tables_identical() {
+        let database: Database<u32, u32> = Database::new();
+
+        let lho = database.table_with_records((0..256).map(|i| (i, i)));
+
+        let rho = lho.clone();
+
+        let merged = database.merge_tables(&lho, &rho, Prefer::Right);
+        assert_eq!(merged.commit(), lho.commit());
+
+        let empty = database.empty_table();
+        let other = database.merge_tables(&empty, &lho, Prefer::Left);
+        assert_eq!(other.commit(), lho.commit());
+
+        database.check([&lho, &rho, &merged, &empty, &other], []);
+
+        drop(lho);
+        drop(rho);
+
+        database.check([&merged, &empty, &other], []);
+    }
+
+    #[test]
+    #[should_panic]
+    fn merge_tables_foreign() {
+        let database: Database<u32, u32> = Database::new();
+        let other: Database<u32, u32> = Database::new();
+
+        let lho = database.empty_table();
+        let rho = other.empty_table();
+
+        database.merge_tables(&lho, &rho, Prefer::Left);
+    }
+
+    #[test]
+    fn table_from_map() {
+        let database: Database<u32, u32> = Database::new();
+
+        let mut map: Map<u32, u32> = Map::new();
+
+        for key in 0..256 {
+            map.insert(key, key).unwrap();
+        }
+
+        let table = database.table_from_map(map.clone()).unwrap();
+
+        assert_eq!(table.commit(), map.commit());
+        table.assert_records((0..256).map(|i| (i, i)));
+
+        database.check([&table], []);
+
+        // Nodes already in the `Database` are shared
+        let reference = database.table_with_records((0..128).map(|i| (i, i)));
+
+        for key in 256..512 {
+            map.insert(key, key).unwrap();
+        }
+
+        let mut other = database.table_from_map(map.clone()).unwrap();
+        assert_eq!(other.commit(), map.commit());
+
+        database.check([&table, &reference, &other], []);
+
+        let mut transaction = TableTransaction::new();
+
+        for key in 0..256 {
+            transaction.remove(&key).unwrap();
+        }
+
+        other.execute(transaction);
+        other.assert_records((256..512).map(|i| (i, i)));
+
+        database.check([&table, &reference, &other], []);
+
+        drop(table);
+        drop(other);
+
+        database.check([&reference], []);
+    }
+
+    #[test]
+    fn table_from_map_empty() {
+        let database: Database<u32, u32> = Database::new();
+        let table = database.table_from_map(Map::new()).unwrap();
+
+        assert_eq!(table.commit(), database.empty_table().commit());
+        database.check([&table], []);
+    }
+
+    #[test]
+    fn table_from_map_incomplete() {
+        let database: Database<u32, u32> = Database::new();
+
+        let mut map: Map<u32, u32> = Map::new();
+
+        for key in 0..256 {
+            map.insert(key, key).unwrap();
+        }
+
+        let export = map.export([0]).unwrap();
+
+        assert!(matches!(
+            database.table_from_map(export).err().unwrap().top(),
+            ImportError::MapIncomplete
+        ));
+
+        database.check([], []);
+    }
+
+    #[test]
+    fn flush() {
+        let path = std::env::temp_dir().join(format!("flush_{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let (database, _) = Database::<u32, u32>::open(LogBackend::open(&path).unwrap()).unwrap();
+
+        let mut table = database.table_with_records((0..256).map(|i| (i, i)));
+        let commit = table.commit();
+
+        database.flush().unwrap();
+
+        // Simulate a crash: nothing is written after `flush`
+        let mut transaction = TableTransaction::new();
+        transaction.set(0, 1).unwrap();
+        table.execute(transaction);
+
+        std::mem::forget(table);
+        std::mem::forget(database);
+
+        let (database, tables) =
+            Database::<u32, u32>::open(LogBackend::open(&path).unwrap()).unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].commit(), commit);
+
+        tables[0].assert_records((0..256).map(|i| (i, i)));
+        database.check(tables.iter(), []);
     }
 }
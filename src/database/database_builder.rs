@@ -0,0 +1,391 @@
+use crate::{
+    common::store::Field,
+    database::{
+        errors::BuildError,
+        store::{Cell, Store, DEFAULT_DEPTH, MAX_DEPTH},
+        Database, Global, StoreAllocator,
+    },
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+use rayon::ThreadPoolBuilder;
+
+use std::marker::PhantomData;
+
+use talk::sync::lenders::AtomicLender;
+
+/// A builder for [`Database`]s, gathering the tuning knobs of a `Database`
+/// in one place (see [`Database::builder`]).
+///
+/// Tuning knobs only affect performance: a `Database` built by a
+/// `DatabaseBuilder` stores, commits to and transfers [`Table`]s exactly
+/// like one created by [`Database::new`]. In particular, unlike [`Map`]s,
+/// `Database`s always hash with [`Blake3Hasher`].
+///
+/// [`Database`]: crate::database::Database
+/// [`Database::builder`]: crate::database::Database::builder
+/// [`Database::new`]: crate::database::Database::new
+/// [`Table`]: crate::database::Table
+/// [`Map`]: crate::map::Map
+/// [`Blake3Hasher`]: crate::hashing::Blake3Hasher
+///
+/// # Examples
+///
+/// ```
+/// use zebra::database::{Database, TableTransaction};
+///
+/// let database: Database<u32, u32> = Database::builder()
+///     .threads(2)
+///     .with_capacity(1024)
+///     .parallel_threshold(16)
+///     .build()
+///     .unwrap();
+///
+/// let mut table = database.empty_table();
+///
+/// let mut transaction = TableTransaction::new();
+/// transaction.set(0, 0).unwrap();
+/// table.execute(transaction);
+/// ```
+pub struct DatabaseBuilder<Key: Field, Value: Field, A: StoreAllocator = Global> {
+    parallel_threshold: Option<usize>,
+    threads: Option<usize>,
+    capacity: usize,
+    depth: Option<u8>,
+    allocator: A,
+    _phantom: PhantomData<(Key, Value)>,
+}
+
+impl<Key, Value> DatabaseBuilder<Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    pub(crate) fn new() -> Self {
+        DatabaseBuilder {
+            parallel_threshold: None,
+            threads: None,
+            capacity: 0,
+            depth: None,
+            allocator: Global,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<Key, Value, A> DatabaseBuilder<Key, Value, A>
+where
+    Key: Field,
+    Value: Field,
+    A: StoreAllocator,
+{
+    /// Sets the number of operations below which batches are processed
+    /// sequentially (see [`Database::with_parallel_threshold`]).
+    ///
+    /// [`Database::with_parallel_threshold`]: crate::database::Database::with_parallel_threshold
+    pub fn parallel_threshold(mut self, threshold: usize) -> Self {
+        self.parallel_threshold = Some(threshold);
+        self
+    }
+
+    /// Processes batches on a dedicated pool of `threads` threads, rather
+    /// than on `rayon`'s global pool.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Reserves room for (approximately) `capacity` tree nodes upfront.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Shards the nodes of the `Database` into `1 << depth` maps, along the
+    /// first `depth` levels of their tree (`depth` is 8 by default). Deeper
+    /// sharding allows more batches to be split across threads, but costs
+    /// memory: shallow sharding suits `Database`s of small [`Table`]s.
+    ///
+    /// [`Table`]: crate::database::Table
+    pub fn depth(mut self, depth: u8) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    /// Allocates the maps holding the nodes of the `Database` through
+    /// `allocator`, rather than through the [`Global`] allocator. Nodes
+    /// themselves are reference-counted, and allocated globally.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::{Database, Global};
+    ///
+    /// let database: Database<u32, u32> = Database::builder()
+    ///     .allocator(Global)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [`Global`]: crate::database::Global
+    pub fn allocator<B>(self, allocator: B) -> DatabaseBuilder<Key, Value, B>
+    where
+        B: StoreAllocator,
+    {
+        DatabaseBuilder {
+            parallel_threshold: self.parallel_threshold,
+            threads: self.threads,
+            capacity: self.capacity,
+            depth: self.depth,
+            allocator,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Builds the [`Database`].
+    ///
+    /// # Errors
+    ///
+    /// If the thread pool requested by [`threads`] cannot be built,
+    /// [`ThreadPoolFailed`] is returned.
+    ///
+    /// If the [`depth`] exceeds 16, [`DepthTooLarge`] is returned.
+    ///
+    /// [`Database`]: crate::database::Database
+    /// [`threads`]: crate::database::DatabaseBuilder::threads
+    /// [`ThreadPoolFailed`]: crate::database::errors::BuildError::ThreadPoolFailed
+    /// [`depth`]: crate::database::DatabaseBuilder::depth
+    /// [`DepthTooLarge`]: crate::database::errors::BuildError::DepthTooLarge
+    pub fn build(self) -> Result<Database<Key, Value, A>, Top<BuildError>> {
+        let mut store = match self.depth {
+            Some(depth) if depth > MAX_DEPTH => {
+                return BuildError::DepthTooLarge.fail().spot(here!());
+            }
+            Some(depth) => Store::with_depth_and_capacity_in(depth, self.capacity, self.allocator),
+            None => Store::with_depth_and_capacity_in(DEFAULT_DEPTH, self.capacity, self.allocator),
+        };
+
+        if let Some(threshold) = self.parallel_threshold {
+            store.set_parallel_threshold(threshold);
+        }
+
+        if let Some(threads) = self.threads {
+            let pool = ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .map_err(|_| BuildError::ThreadPoolFailed.into_top())
+                .spot(here!())?;
+
+            store.set_pool(pool);
+        }
+
+        Ok(Database {
+            store: Cell::new(AtomicLender::new(store)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::database::{Allocator, TableTransaction};
+
+    use allocator_api2::alloc::AllocError;
+
+    use std::{
+        alloc::Layout,
+        ptr::NonNull,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    // Counts the allocations routed through (any clone of) it
+    #[derive(Clone, Default)]
+    struct CountingAllocator(Arc<AtomicUsize>);
+
+    impl CountingAllocator {
+        fn allocations(&self) -> usize {
+            self.0.load(Ordering::Relaxed)
+        }
+    }
+
+    unsafe impl Allocator for CountingAllocator {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn default() {
+        let database: Database<u32, u32> = Database::builder().build().unwrap();
+        let reference: Database<u32, u32> = Database::new();
+
+        {
+            let store = database.store.take();
+            let reference_store = reference.store.take();
+
+            assert_eq!(
+                store.parallel_threshold(),
+                reference_store.parallel_threshold()
+            );
+
+            assert!(store.pool().is_none());
+
+            database.store.restore(store);
+            reference.store.restore(reference_store);
+        }
+
+        let table = database.table_with_records((0..256).map(|i| (i, i)));
+        let reference_table = reference.table_with_records((0..256).map(|i| (i, i)));
+
+        assert_eq!(table.commit(), reference_table.commit());
+    }
+
+    #[test]
+    fn configured() {
+        let database: Database<u32, u32> = Database::builder()
+            .threads(2)
+            .with_capacity(4096)
+            .parallel_threshold(4)
+            .build()
+            .unwrap();
+
+        let store = database.store.take();
+        assert_eq!(store.parallel_threshold(), 4);
+        assert_eq!(store.pool().unwrap().current_num_threads(), 2);
+        database.store.restore(store);
+
+        let reference: Database<u32, u32> = Database::new();
+
+        let mut table = database.empty_table();
+        let mut reference_table = reference.empty_table();
+
+        for round in 0..4 {
+            let mut transaction = TableTransaction::new();
+            let mut reference_transaction = TableTransaction::new();
+
+            for key in (0..1024).filter(|key| key % 4 != round) {
+                transaction.set(key, key + round).unwrap();
+                reference_transaction.set(key, key + round).unwrap();
+            }
+
+            for key in (0..1024).filter(|key| key % 4 == round) {
+                transaction.remove(&key).unwrap();
+                reference_transaction.remove(&key).unwrap();
+            }
+
+            table.execute(transaction);
+            reference_table.execute(reference_transaction);
+
+            assert_eq!(table.commit(), reference_table.commit());
+        }
+
+        table.check_tree();
+        table.assert_records(
+            (0..1024)
+                .filter(|key| key % 4 != 3)
+                .map(|key| (key, key + 3)),
+        );
+
+        database.check([&table], []);
+    }
+
+    #[test]
+    fn depths() {
+        let reference: Database<u32, u32> = Database::new();
+        let mut reference_table = reference.table_with_records((0..1024).map(|i| (i, i)));
+
+        for depth in [0, 4, 12, 16] {
+            let database: Database<u32, u32> = Database::builder()
+                .depth(depth)
+                .threads(2)
+                .with_capacity(1024)
+                .parallel_threshold(1)
+                .build()
+                .unwrap();
+
+            let mut table = database.table_with_records((0..1024).map(|i| (i, i)));
+            assert_eq!(table.commit(), reference_table.commit());
+
+            let mut transaction = TableTransaction::new();
+            let mut reference_transaction = TableTransaction::new();
+
+            for key in 0..512 {
+                transaction.remove(&key).unwrap();
+                reference_transaction.remove(&key).unwrap();
+            }
+
+            table.execute(transaction);
+            let mut clone = reference_table.clone();
+            clone.execute(reference_transaction);
+
+            assert_eq!(table.commit(), clone.commit());
+
+            table.check_tree();
+            table.assert_records((512..1024).map(|i| (i, i)));
+
+            database.check([&table], []);
+            reference_table = reference.table_with_records((0..1024).map(|i| (i, i)));
+        }
+    }
+
+    #[test]
+    fn rejected() {
+        assert!(matches!(
+            Database::<u32, u32>::builder()
+                .depth(17)
+                .build()
+                .map(|_| ())
+                .unwrap_err()
+                .top(),
+            BuildError::DepthTooLarge
+        ));
+    }
+
+    #[test]
+    fn allocator() {
+        let allocator = CountingAllocator::default();
+
+        let database = Database::builder()
+            .with_capacity(1024)
+            .allocator(allocator.clone())
+            .build()
+            .unwrap();
+
+        // Every shard reserves its capacity upfront
+        let reserved = allocator.allocations();
+        assert!(reserved > 0);
+
+        let mut table = database.empty_table();
+
+        let mut transaction = TableTransaction::new();
+
+        for key in 0..16384u32 {
+            transaction.set(key, key).unwrap();
+        }
+
+        table.execute(transaction);
+
+        // Outgrowing the reserved capacity reallocates the shards
+        assert!(allocator.allocations() > reserved);
+
+        let reference: Database<u32, u32> = Database::new();
+        let reference_table = reference.table_with_records((0..16384).map(|i| (i, i)));
+
+        assert_eq!(table.commit(), reference_table.commit());
+
+        table.check_tree();
+        table.assert_records((0..16384).map(|i| (i, i)));
+
+        database.check([&table], []);
+    }
+}
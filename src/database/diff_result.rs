@@ -0,0 +1,25 @@
+use crate::common::tree::Prefix;
+
+use std::collections::HashMap;
+
+/// The outcome of diffing a [`Table`] against a partially known [`Map`]
+/// (see [`Table::diff_partial`]).
+///
+/// [`Table`]: crate::database::Table
+/// [`Map`]: crate::map::Map
+/// [`Table::diff_partial`]: crate::database::Table::diff_partial
+#[derive(Debug, Clone)]
+pub struct DiffResult<Key, Value> {
+    /// The records that differ between the [`Table`] and the [`Map`], as
+    /// `(table_value, map_value)` pairs (`None` for a missing record).
+    ///
+    /// [`Table`]: crate::database::Table
+    /// [`Map`]: crate::map::Map
+    pub changes: HashMap<Key, (Option<Value>, Option<Value>)>,
+    /// The locations of the stubs of the [`Map`] at which the [`Table`]
+    /// differs: records under them can be neither compared nor reported.
+    ///
+    /// [`Table`]: crate::database::Table
+    /// [`Map`]: crate::map::Map
+    pub unknown_prefixes: Vec<Prefix>,
+}
@@ -1,9 +1,25 @@
-use doomstack::Doom;
+// `QueryError::KeyCollision` is deprecated, but still referenced by the
+// implementation derived for `QueryError`
+#![allow(deprecated)]
+
+use crate::common::tree::Path;
+
+use doomstack::{Doom, Top};
+
+use talk::crypto::primitives::hash::HashError;
 
 #[derive(Doom)]
 pub enum QueryError {
     #[doom(description("Failed to hash field"))]
     HashError,
+    #[doom(description("Failed to hash key at index {}: {:?}", index, source))]
+    KeyHashError {
+        index: usize,
+        source: Top<HashError>,
+    },
+    #[doom(description("Duplicate operation within transaction on path {:?}", path))]
+    DuplicateOperation { path: Path },
+    #[deprecated(note = "duplicate operations are reported as `DuplicateOperation`")]
     #[doom(description("Key collision within transaction"))]
     KeyCollision,
 }
@@ -14,4 +30,66 @@ pub enum SyncError {
     MalformedQuestion,
     #[doom(description("Malformed `Answer`"))]
     MalformedAnswer,
+    #[doom(description("Transfer stalled"))]
+    TransferStalled,
+    #[doom(description("Malformed `ReceiverState`"))]
+    MalformedState,
+}
+
+#[derive(Doom)]
+pub enum BuildError {
+    #[doom(description("Failed to build thread pool"))]
+    ThreadPoolFailed,
+    #[doom(description("Store depth exceeds maximum"))]
+    DepthTooLarge,
+}
+
+#[derive(Doom)]
+pub enum StateError {
+    #[doom(description("Failed to open state file"))]
+    OpenFailed,
+    #[doom(description("Failed to read state file"))]
+    ReadFailed,
+    #[doom(description("Failed to write state file"))]
+    WriteFailed,
+    #[doom(description("Failed to serialize record"))]
+    SerializeFailed,
+    #[doom(description("Malformed state file header"))]
+    MalformedHeader,
+    #[doom(description("Unsupported state file version"))]
+    UnsupportedVersion,
+    #[doom(description("Malformed state file records"))]
+    MalformedRecords,
+    #[doom(description("Commitment mismatch"))]
+    CommitmentMismatch,
+}
+
+#[derive(Doom)]
+pub enum BackendError {
+    #[doom(description("Failed to open backend"))]
+    OpenFailed,
+    #[doom(description("Malformed log"))]
+    MalformedLog,
+    #[doom(description("Malformed backend record"))]
+    MalformedRecord,
+    #[doom(description("Failed to write to backend"))]
+    WriteFailed,
+    #[doom(description("Failed to flush backend"))]
+    FlushFailed,
+}
+
+#[derive(Doom)]
+pub enum TransitionError {
+    #[doom(description("Pre-commitment mismatch"))]
+    PreMismatch,
+    #[doom(description("Witness does not cover the transaction"))]
+    WitnessIncomplete,
+    #[doom(description("Post-commitment mismatch"))]
+    PostMismatch,
+}
+
+#[derive(Doom)]
+pub enum ImportError {
+    #[doom(description("`Map` contains unknown branches"))]
+    MapIncomplete,
 }
@@ -0,0 +1,54 @@
+use crate::common::tree::Path;
+
+use std::collections::{BTreeSet, HashMap};
+
+/// Expiry epochs of the records of a [`Table`], kept aside from the
+/// `Table`'s tree so that its commitment depends on live records only.
+///
+/// [`Table`]: crate::database::Table
+#[derive(Clone, Default)]
+pub(crate) struct Expiries {
+    epochs: HashMap<Path, u64>,
+    schedule: BTreeSet<(u64, Path)>,
+}
+
+impl Expiries {
+    pub fn new() -> Self {
+        Expiries::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.epochs.is_empty()
+    }
+
+    /// Sets (`Some`) or clears (`None`) the expiry epoch of `path`.
+    pub fn set(&mut self, path: Path, epoch: Option<u64>) {
+        if let Some(previous) = self.epochs.remove(&path) {
+            self.schedule.remove(&(previous, path));
+        }
+
+        if let Some(epoch) = epoch {
+            self.epochs.insert(path, epoch);
+            self.schedule.insert((epoch, path));
+        }
+    }
+
+    /// Removes and returns all paths whose expiry epoch is `now` or earlier.
+    pub fn expire(&mut self, now: u64) -> Vec<Path> {
+        let expired = match now.checked_add(1) {
+            Some(next) => {
+                let rest = self.schedule.split_off(&(next, Path::empty()));
+                std::mem::replace(&mut self.schedule, rest)
+            }
+            None => std::mem::take(&mut self.schedule),
+        };
+
+        expired
+            .into_iter()
+            .map(|(_, path)| {
+                self.epochs.remove(&path);
+                path
+            })
+            .collect()
+    }
+}
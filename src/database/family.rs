@@ -1,10 +1,10 @@
 use crate::{
     common::store::Field,
-    database::{Collection, CollectionReceiver, Database},
+    database::{Collection, CollectionReceiver, Database, Global, StoreAllocator},
 };
 
 #[derive(Clone)]
-pub struct Family<Item: Field>(pub(crate) Database<Item, ()>);
+pub struct Family<Item: Field, A: StoreAllocator = Global>(pub(crate) Database<Item, (), A>);
 
 impl<Item> Family<Item>
 where
@@ -13,12 +13,18 @@ where
     pub fn new() -> Self {
         Family(Database::new())
     }
+}
 
-    pub fn empty_collection(&self) -> Collection<Item> {
+impl<Item, A> Family<Item, A>
+where
+    Item: Field,
+    A: StoreAllocator,
+{
+    pub fn empty_collection(&self) -> Collection<Item, A> {
         Collection(self.0.empty_table())
     }
 
-    pub fn receive(&self) -> CollectionReceiver<Item> {
+    pub fn receive(&self) -> CollectionReceiver<Item, A> {
         CollectionReceiver(self.0.receive())
     }
 }
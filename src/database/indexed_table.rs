@@ -0,0 +1,299 @@
+use crate::{
+    common::{store::Field, tree::Path},
+    database::{
+        errors::QueryError,
+        interact::{Action, Operation},
+        Global, StoreAllocator, Table, TableResponse, TableTransaction,
+    },
+    map::{errors::MapError, Map, Set},
+};
+
+use doomstack::{here, ResultExt, Top};
+
+use std::collections::HashMap;
+
+use talk::crypto::primitives::hash;
+
+/// A [`Table`] paired with a secondary index, obtained through
+/// [`Database::with_index`].
+///
+/// The index maps every `IndexKey` to the [`Set`] of keys whose records
+/// are indexed under it, as determined by the index function: a record
+/// `(key, value)` is indexed under `index_fn(&key, &value)`, or not at
+/// all if `index_fn` returns `None`. Executing a [`TableTransaction`]
+/// through [`execute`] updates the [`Table`] and its index together.
+///
+/// [`Table`]: crate::database::Table
+/// [`Database::with_index`]: crate::database::Database::with_index
+/// [`Set`]: crate::map::Set
+/// [`TableTransaction`]: crate::database::TableTransaction
+/// [`execute`]: crate::database::IndexedTable::execute
+pub struct IndexedTable<Key, Value, IndexKey, F, A = Global>
+where
+    Key: Field,
+    Value: Field,
+    IndexKey: Field,
+    A: StoreAllocator,
+{
+    table: Table<Key, Value, A>,
+    index: Map<IndexKey, Set<Key>>,
+    indexed: HashMap<Path, (Key, IndexKey)>,
+    index_fn: F,
+}
+
+// New index entry (if any) for the record at a path
+type Change<Key, IndexKey> = (Path, Option<(Key, IndexKey)>);
+
+impl<Key, Value, IndexKey, F, A> IndexedTable<Key, Value, IndexKey, F, A>
+where
+    Key: Field + Clone,
+    Value: Field,
+    IndexKey: Field + Clone,
+    F: Fn(&Key, &Value) -> Option<IndexKey>,
+    A: StoreAllocator,
+{
+    pub(crate) fn new(table: Table<Key, Value, A>, index_fn: F) -> Result<Self, Top<QueryError>> {
+        let changes = table
+            .records()
+            .into_iter()
+            .map(|(key, value)| {
+                let entry = index_fn(key.inner(), value.inner())
+                    .map(|index_key| ((**key.inner()).clone(), index_key));
+
+                (Path::from(key.digest()), entry)
+            })
+            .collect::<Vec<_>>();
+
+        IndexedTable::<Key, Value, IndexKey, F>::validate(&changes)?;
+
+        let mut indexed_table = IndexedTable {
+            table,
+            index: Map::new(),
+            indexed: HashMap::new(),
+            index_fn,
+        };
+
+        indexed_table.update(changes);
+        Ok(indexed_table)
+    }
+
+    /// Returns the underlying [`Table`].
+    ///
+    /// [`Table`]: crate::database::Table
+    pub fn table(&self) -> &Table<Key, Value, A> {
+        &self.table
+    }
+
+    /// Returns the index, mapping every `IndexKey` to the keys indexed under it.
+    pub fn index(&self) -> &Map<IndexKey, Set<Key>> {
+        &self.index
+    }
+
+    /// Returns the [`Set`] of keys indexed under `index_key`, or `None`
+    /// if no key is indexed under `index_key`.
+    ///
+    /// # Errors
+    ///
+    /// If `index_key` cannot be hashed, [`KeyHashError`] is returned.
+    ///
+    /// [`Set`]: crate::map::Set
+    /// [`KeyHashError`]: crate::map::errors::MapError::KeyHashError
+    pub fn lookup(&self, index_key: &IndexKey) -> Result<Option<&Set<Key>>, Top<MapError>> {
+        self.index.get(index_key)
+    }
+
+    /// Executes `transaction` on the [`Table`] (see [`Table::execute`]),
+    /// updating the index accordingly.
+    ///
+    /// # Errors
+    ///
+    /// If any newly indexed key or `IndexKey` cannot be hashed, [`HashError`]
+    /// is returned, and neither the [`Table`] nor its index are modified.
+    ///
+    /// [`Table`]: crate::database::Table
+    /// [`Table::execute`]: crate::database::Table::execute
+    /// [`HashError`]: crate::database::errors::QueryError::HashError
+    pub fn execute(
+        &mut self,
+        transaction: TableTransaction<Key, Value>,
+    ) -> Result<TableResponse<Key, Value>, Top<QueryError>> {
+        let changes = transaction
+            .operations()
+            .iter()
+            .filter_map(|Operation { path, action }| match action {
+                Action::Get(..) => None,
+                Action::Set(key, value) => {
+                    let entry = (self.index_fn)(key.inner(), value.inner())
+                        .map(|index_key| ((**key.inner()).clone(), index_key));
+
+                    Some((*path, entry))
+                }
+                Action::Remove(..) => Some((*path, None)),
+            })
+            .collect::<Vec<_>>();
+
+        IndexedTable::<Key, Value, IndexKey, F>::validate(&changes)?;
+
+        let response = self.table.execute(transaction);
+        self.update(changes);
+
+        Ok(response)
+    }
+
+    /// Returns the underlying [`Table`], dropping the index.
+    ///
+    /// [`Table`]: crate::database::Table
+    pub fn into_table(self) -> Table<Key, Value, A> {
+        self.table
+    }
+
+    // Once `changes` are validated, updating the index cannot fail
+    fn validate(changes: &[Change<Key, IndexKey>]) -> Result<(), Top<QueryError>> {
+        for (_, entry) in changes {
+            if let Some((key, index_key)) = entry {
+                hash::hash(key).pot(QueryError::HashError, here!())?;
+                hash::hash(index_key).pot(QueryError::HashError, here!())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update(&mut self, changes: Vec<Change<Key, IndexKey>>) {
+        for (path, entry) in changes {
+            if let Some((key, index_key)) = self.indexed.remove(&path) {
+                let mut keys = self.index.remove(&index_key).unwrap().unwrap();
+                keys.remove(&key).unwrap();
+
+                if !keys.is_empty() {
+                    self.index.insert(index_key, keys).unwrap();
+                }
+            }
+
+            if let Some((key, index_key)) = entry {
+                let mut keys = self
+                    .index
+                    .remove(&index_key)
+                    .unwrap()
+                    .unwrap_or_else(Set::new);
+                keys.insert(key.clone()).unwrap();
+
+                self.index.insert(index_key.clone(), keys).unwrap();
+                self.indexed.insert(path, (key, index_key));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::database::Database;
+
+    fn parity(_: &u32, value: &u32) -> Option<u32> {
+        if *value < 1000 {
+            Some(value % 2)
+        } else {
+            None
+        }
+    }
+
+    impl<Key, Value, IndexKey, F> IndexedTable<Key, Value, IndexKey, F>
+    where
+        Key: Field + Clone + Eq,
+        Value: Field,
+        IndexKey: Field + Clone,
+        F: Fn(&Key, &Value) -> Option<IndexKey>,
+    {
+        fn assert_indexed<I>(&self, index_key: &IndexKey, keys: I)
+        where
+            I: IntoIterator<Item = Key>,
+        {
+            let keys = keys.into_iter().collect::<Vec<_>>();
+
+            match self.lookup(index_key).unwrap() {
+                Some(set) => {
+                    assert_eq!(set.len(), keys.len());
+
+                    for key in keys {
+                        assert!(set.contains(&key).unwrap());
+                    }
+                }
+                None => assert!(keys.is_empty()),
+            }
+        }
+    }
+
+    #[test]
+    fn build() {
+        let database: Database<u32, u32> = Database::new();
+        let table = database.table_with_records((0..64).map(|i| (i, i)));
+
+        let table = database.with_index(table, parity).unwrap();
+
+        table.assert_indexed(&0, (0..64).filter(|i| i % 2 == 0));
+        table.assert_indexed(&1, (0..64).filter(|i| i % 2 == 1));
+    }
+
+    #[test]
+    fn execute() {
+        let database: Database<u32, u32> = Database::new();
+        let table = database.empty_table();
+
+        let mut table = database.with_index(table, parity).unwrap();
+
+        let mut transaction = TableTransaction::new();
+
+        for i in 0..64 {
+            transaction.set(i, i).unwrap();
+        }
+
+        table.execute(transaction).unwrap();
+
+        table.assert_indexed(&0, (0..64).filter(|i| i % 2 == 0));
+        table.assert_indexed(&1, (0..64).filter(|i| i % 2 == 1));
+
+        // Move even keys to `1`, unindex keys below 8, remove keys below 4
+        let mut transaction = TableTransaction::new();
+
+        for i in (8..64).filter(|i| i % 2 == 0) {
+            transaction.set(i, i + 1).unwrap();
+        }
+
+        for i in 4..8 {
+            transaction.set(i, 1000).unwrap();
+        }
+
+        for i in 0..4 {
+            transaction.remove(&i).unwrap();
+        }
+
+        // Gets leave the index untouched
+        let get = transaction.get(&9).unwrap();
+
+        let response = table.execute(transaction).unwrap();
+        assert_eq!(response.get(&get), Some(&9));
+
+        table.assert_indexed(&0, []);
+        table.assert_indexed(&1, 8..64);
+
+        assert_eq!(table.index().len(), 1);
+        table
+            .table()
+            .assert_records((4..64).map(|i| (i, if i < 8 { 1000 } else { i + (i + 1) % 2 })));
+
+        // Emptying the table empties the index
+        let mut transaction = TableTransaction::new();
+
+        for i in 4..64 {
+            transaction.remove(&i).unwrap();
+        }
+
+        table.execute(transaction).unwrap();
+        assert!(table.index().is_empty());
+
+        let table = table.into_table();
+        database.check([&table], []);
+    }
+}
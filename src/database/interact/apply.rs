@@ -1,15 +1,16 @@
 use crate::{
     common::{
         store::Field,
-        tree::{Direction, Path},
+        tree::{Direction, Path, Prefix},
     },
     database::{
         interact::{Action, Batch, Chunk, Operation, Task},
         store::{Label, Node, Split, Store},
+        StoreAllocator,
     },
 };
 
-use std::collections::hash_map::Entry::{Occupied, Vacant};
+use hashbrown::hash_map::Entry::{Occupied, Vacant};
 
 #[derive(Eq, PartialEq)]
 enum References {
@@ -46,10 +47,11 @@ where
     }
 }
 
-fn get<Key, Value>(store: &mut Store<Key, Value>, label: Label) -> Entry<Key, Value>
+fn get<Key, Value, A>(store: &mut Store<Key, Value, A>, label: Label) -> Entry<Key, Value>
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
     if !label.is_empty() {
         match store.entry(label) {
@@ -68,19 +70,27 @@ where
     }
 }
 
-fn branch<Key, Value>(
-    store: Store<Key, Value>,
+// Counts the tasks forked by `branch` from each thread, so that tests can
+// tell whether `apply` ran in parallel
+#[cfg(test)]
+thread_local! {
+    static FORKS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+fn branch<Key, Value, A>(
+    store: Store<Key, Value, A>,
     original: Option<&Entry<Key, Value>>,
     preserve: bool,
-    depth: u8,
+    location: Prefix,
     batch: Batch<Key, Value>,
     chunk: Chunk,
     left: Entry<Key, Value>,
     right: Entry<Key, Value>,
-) -> (Store<Key, Value>, Batch<Key, Value>, Label)
+) -> (Store<Key, Value, A>, Batch<Key, Value>, Label)
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
     let preserve_branches = preserve
         || if let Some(original) = original {
@@ -89,33 +99,46 @@ where
             false
         };
 
+    // Small chunks are not worth spawning a task for: below the
+    // threshold, `left` and `right` are processed sequentially
+    // (the store is still split to preserve its sharding)
+    let parallel = chunk.len() >= store.parallel_threshold();
+
     let (mut store, batch, new_left, new_right) = match store.split() {
         Split::Split(left_store, right_store) => {
             let (left_batch, left_chunk, right_batch, right_chunk) = chunk.snap(batch);
 
+            let left_task = move || {
+                recur(
+                    left_store,
+                    left,
+                    preserve_branches,
+                    location.left(),
+                    left_batch,
+                    left_chunk,
+                )
+            };
+
+            let right_task = move || {
+                recur(
+                    right_store,
+                    right,
+                    preserve_branches,
+                    location.right(),
+                    right_batch,
+                    right_chunk,
+                )
+            };
+
             let ((left_store, left_batch, left_label), (right_store, right_batch, right_label)) =
-                rayon::join(
-                    move || {
-                        recur(
-                            left_store,
-                            left,
-                            preserve_branches,
-                            depth + 1,
-                            left_batch,
-                            left_chunk,
-                        )
-                    },
-                    move || {
-                        recur(
-                            right_store,
-                            right,
-                            preserve_branches,
-                            depth + 1,
-                            right_batch,
-                            right_chunk,
-                        )
-                    },
-                );
+                if parallel {
+                    #[cfg(test)]
+                    FORKS.with(|forks| forks.set(forks.get() + 1));
+
+                    rayon::join(left_task, right_task)
+                } else {
+                    (left_task(), right_task())
+                };
 
             let store = Store::merge(left_store, right_store);
             let batch = Batch::merge(left_batch, right_batch);
@@ -125,14 +148,20 @@ where
         Split::Unsplittable(store) => {
             let (left_chunk, right_chunk) = chunk.split(&batch);
 
-            let (store, batch, left_label) =
-                recur(store, left, preserve_branches, depth + 1, batch, left_chunk);
+            let (store, batch, left_label) = recur(
+                store,
+                left,
+                preserve_branches,
+                location.left(),
+                batch,
+                left_chunk,
+            );
 
             let (store, batch, right_label) = recur(
                 store,
                 right,
                 preserve_branches,
-                depth + 1,
+                location.right(),
                 batch,
                 right_chunk,
             );
@@ -148,7 +177,7 @@ where
         }
         (new_left, new_right) => {
             let node = Node::<Key, Value>::Internal(new_left, new_right);
-            let label = store.label(&node);
+            let label = store.label(&node, location);
             let adopt = store.populate(label, node);
 
             (label, adopt)
@@ -190,17 +219,18 @@ where
     (store, batch, new_label)
 }
 
-fn recur<Key, Value>(
-    mut store: Store<Key, Value>,
+fn recur<Key, Value, A>(
+    mut store: Store<Key, Value, A>,
     target: Entry<Key, Value>,
     preserve: bool,
-    depth: u8,
+    location: Prefix,
     mut batch: Batch<Key, Value>,
     chunk: Chunk,
-) -> (Store<Key, Value>, Batch<Key, Value>, Label)
+) -> (Store<Key, Value, A>, Batch<Key, Value>, Label)
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
     match (&target.node, chunk.task(&mut batch)) {
         (_, Task::Pass) => (store, batch, target.label),
@@ -209,18 +239,18 @@ where
             Action::Get(..) => (store, batch, Label::Empty),
             Action::Set(key, value) => {
                 let node = Node::Leaf(key.clone(), value.clone());
-                let label = store.label(&node);
+                let label = store.label(&node, location);
 
                 store.populate(label, node);
                 (store, batch, label)
             }
-            Action::Remove => (store, batch, Label::Empty),
+            Action::Remove(..) => (store, batch, Label::Empty),
         },
         (Node::Empty, Task::Split) => branch(
             store,
             None,
             preserve,
-            depth,
+            location,
             batch,
             chunk,
             Entry::empty(),
@@ -237,13 +267,16 @@ where
                 }
                 Action::Set(_, new_value) if new_value != original_value => {
                     let node = Node::Leaf(key.clone(), new_value.clone());
-                    let label = store.label(&node);
+                    let label = store.label(&node, location);
                     store.populate(label, node);
 
                     (store, batch, label)
                 }
                 Action::Set(..) => (store, batch, target.label),
-                Action::Remove => (store, batch, Label::Empty),
+                Action::Remove(removed) => {
+                    *removed = true;
+                    (store, batch, Label::Empty)
+                }
             }
         }
         (
@@ -254,13 +287,13 @@ where
             }),
         ) => (store, batch, target.label),
         (Node::Leaf(key, _), _) => {
-            let (left, right) = if Path::from(key.digest())[depth] == Direction::Left {
+            let (left, right) = if Path::from(key.digest())[location.depth()] == Direction::Left {
                 (target, Entry::empty())
             } else {
                 (Entry::empty(), target)
             };
 
-            branch(store, None, preserve, depth, batch, chunk, left, right)
+            branch(store, None, preserve, location, batch, chunk, left, right)
         }
 
         (Node::Internal(left, right), _) => {
@@ -271,7 +304,7 @@ where
                 store,
                 Some(&target),
                 preserve,
-                depth,
+                location,
                 batch,
                 chunk,
                 left,
@@ -281,19 +314,21 @@ where
     }
 }
 
-pub(crate) fn apply<Key, Value>(
-    mut store: Store<Key, Value>,
+pub(crate) fn apply<Key, Value, A>(
+    mut store: Store<Key, Value, A>,
     root: Label,
     batch: Batch<Key, Value>,
-) -> (Store<Key, Value>, Label, Batch<Key, Value>)
+) -> (Store<Key, Value, A>, Label, Batch<Key, Value>)
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
     let root_node = get(&mut store, root);
     let root_chunk = Chunk::root(&batch);
 
-    let (mut store, batch, new_root) = recur(store, root_node, false, 0, batch, root_chunk);
+    let (mut store, batch, new_root) =
+        recur(store, root_node, false, Prefix::root(), batch, root_chunk);
 
     let old_root = root;
     if new_root != old_root {
@@ -308,11 +343,188 @@ where
 mod tests {
     use super::*;
 
-    use crate::database::interact::Operation;
+    use crate::database::interact::{drop, Operation};
+
+    use proptest::{collection::vec, prelude::*};
 
     use rand::{seq::IteratorRandom, Rng};
 
-    use std::collections::HashMap;
+    use serde::Serialize;
+
+    use std::{
+        collections::HashMap,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    // Applies `batch` to `root`, also returning whether `apply` forked
+    // any task (the root is always branched from the calling thread)
+    fn traced_apply(
+        store: Store<u32, u32>,
+        root: Label,
+        batch: Batch<u32, u32>,
+    ) -> (Store<u32, u32>, Label, bool) {
+        let before = FORKS.with(|forks| forks.get());
+        let (store, root, _) = apply(store, root, batch);
+        let forked = FORKS.with(|forks| forks.get()) > before;
+
+        (store, root, forked)
+    }
+
+    #[derive(Debug, Clone)]
+    enum Step {
+        Get(u32),
+        Set(u32, u32),
+        Remove(u32),
+    }
+
+    #[derive(Debug, Clone)]
+    enum Round {
+        Apply(usize, Vec<Step>),
+        Fork(usize, usize),
+    }
+
+    fn step() -> impl Strategy<Value = Step> {
+        prop_oneof![
+            (0..64u32).prop_map(Step::Get),
+            (0..64u32, 0..4u32).prop_map(|(key, value)| Step::Set(key, value)),
+            (0..64u32).prop_map(Step::Remove),
+        ]
+    }
+
+    fn round() -> impl Strategy<Value = Round> {
+        prop_oneof![
+            4 => (0..2usize, vec(step(), 0..32)).prop_map(|(root, steps)| Round::Apply(root, steps)),
+            1 => (0..2usize, 0..2usize).prop_map(|(from, to)| Round::Fork(from, to)),
+        ]
+    }
+
+    #[test]
+    fn parallel_threshold() {
+        let small = || Batch::new((0..16).map(|i| set!(i, i)).collect());
+        let large = || Batch::new((0..1024).map(|i| set!(i, i)).collect());
+
+        let mut eager = Store::<u32, u32>::new();
+        eager.set_parallel_threshold(1);
+
+        let (mut eager, eager_root, forked) = traced_apply(eager, Label::Empty, small());
+        assert!(forked);
+
+        let (mut lazy, lazy_root, forked) =
+            traced_apply(Store::<u32, u32>::new(), Label::Empty, small());
+        assert!(!forked);
+
+        assert_eq!(eager_root, lazy_root);
+
+        eager.check_tree(eager_root);
+        eager.check_leaks([eager_root]);
+        lazy.check_tree(lazy_root);
+        lazy.check_leaks([lazy_root]);
+
+        let (_, _, forked) = traced_apply(Store::<u32, u32>::new(), Label::Empty, large());
+        assert!(forked);
+    }
+
+    #[test]
+    fn sequential_matches_parallel() {
+        fn references(store: &mut Store<u32, u32>, roots: &[Label]) -> HashMap<Label, usize> {
+            let labels = roots
+                .iter()
+                .flat_map(|root| store.collect_tree(*root))
+                .collect::<Vec<_>>();
+
+            labels
+                .into_iter()
+                .map(|label| match store.entry(label) {
+                    Occupied(entry) => (label, entry.get().references),
+                    Vacant(..) => panic!("`sequential_matches_parallel`: label not found"),
+                })
+                .collect()
+        }
+
+        let mut parallel = Store::<u32, u32>::new();
+        parallel.set_parallel_threshold(1);
+
+        let mut sequential = Store::<u32, u32>::new();
+        sequential.set_parallel_threshold(usize::MAX);
+
+        let mut parallel_roots = [Label::Empty, Label::Empty];
+        let mut sequential_roots = [Label::Empty, Label::Empty];
+
+        // Returns the root to update and the batch to apply at each round
+        let round = |index| -> (usize, Batch<u32, u32>) {
+            match index {
+                0 => (0, Batch::new((0..1024).map(|i| set!(i, i)).collect())),
+                1 => (
+                    0,
+                    Batch::new((0..1024).step_by(3).map(|i| remove!(i)).collect()),
+                ),
+                2 => (1, Batch::new((512..1536).map(|i| set!(i, i + 1)).collect())),
+                _ => (
+                    1,
+                    Batch::new((0..2048).step_by(5).map(|i| remove!(i)).collect()),
+                ),
+            }
+        };
+
+        for index in 0..4 {
+            // Fork the first root into the second, mirroring `Table::clone`
+            if index == 2 {
+                parallel.incref(parallel_roots[0]);
+                sequential.incref(sequential_roots[0]);
+
+                parallel_roots[1] = parallel_roots[0];
+                sequential_roots[1] = sequential_roots[0];
+            }
+
+            let (root, batch) = round(index);
+            let (store, new_root, forked) = traced_apply(parallel, parallel_roots[root], batch);
+            assert!(forked);
+
+            parallel = store;
+            parallel_roots[root] = new_root;
+
+            let (root, batch) = round(index);
+            let (store, new_root, forked) = traced_apply(sequential, sequential_roots[root], batch);
+            assert!(!forked);
+
+            sequential = store;
+            sequential_roots[root] = new_root;
+
+            assert_eq!(parallel_roots, sequential_roots);
+
+            for root in parallel_roots {
+                let mut parallel_records = Vec::new();
+                let mut sequential_records = Vec::new();
+
+                parallel.records(root, &mut parallel_records);
+                sequential.records(root, &mut sequential_records);
+
+                let parallel_records = parallel_records
+                    .iter()
+                    .map(|(key, value)| (**key.inner(), **value.inner()))
+                    .collect::<HashMap<_, _>>();
+
+                let sequential_records = sequential_records
+                    .iter()
+                    .map(|(key, value)| (**key.inner(), **value.inner()))
+                    .collect::<HashMap<_, _>>();
+
+                assert_eq!(parallel_records, sequential_records);
+            }
+
+            assert_eq!(
+                references(&mut parallel, &parallel_roots),
+                references(&mut sequential, &sequential_roots)
+            );
+
+            assert_eq!(parallel.size(), sequential.size());
+
+            parallel.check_leaks(parallel_roots);
+            parallel.check_references(parallel_roots);
+            sequential.check_leaks(sequential_roots);
+            sequential.check_references(sequential_roots);
+        }
+    }
 
     #[test]
     fn single_static_tree() {
@@ -545,6 +757,37 @@ mod tests {
         batch.assert_gets((0..192).map(|i| (i, Some(i))));
     }
 
+    #[test]
+    fn single_modify_same_large_value() {
+        static COMPARISONS: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Debug, Clone, Serialize)]
+        struct Large(Vec<u8>);
+
+        impl PartialEq for Large {
+            fn eq(&self, rho: &Self) -> bool {
+                COMPARISONS.fetch_add(1, Ordering::Relaxed);
+                self.0 == rho.0
+            }
+        }
+
+        let large = |i: u32| Large(vec![i as u8; 1 << 14]);
+
+        let store = Store::<u32, Large>::new();
+
+        let batch = Batch::new((0..128).map(|i| set!(i, large(i))).collect());
+        let (store, root, _) = apply(store, Label::Empty, batch);
+
+        let batch = Batch::new((0..128).map(|i| set!(i, large(i))).collect());
+        let (mut store, same_root, _) = apply(store, root, batch);
+
+        // No-op sets are detected by comparing digests, never values
+        assert_eq!(COMPARISONS.load(Ordering::Relaxed), 0);
+        assert_eq!(same_root, root);
+
+        store.check_tree(root);
+    }
+
     #[test]
     fn single_insert_hybrid_read_set() {
         let store = Store::<u32, u32>::new();
@@ -927,4 +1170,75 @@ mod tests {
             store.check_leaks([first_root, second_root]);
         }
     }
+
+    proptest! {
+        #[test]
+        fn invariants(rounds in vec(round(), 1..16)) {
+            let mut store = Store::<u32, u32>::new();
+
+            let mut roots = [Label::Empty; 2];
+            let mut record_references = [HashMap::new(), HashMap::new()];
+
+            for round in rounds {
+                match round {
+                    Round::Apply(target, steps) => {
+                        // Operations within a `Batch` must have distinct keys: only
+                        // the last `Step` on each key is kept
+                        let steps: HashMap<u32, Step> = steps
+                            .into_iter()
+                            .map(|step| match step {
+                                Step::Get(key) | Step::Set(key, _) | Step::Remove(key) => {
+                                    (key, step)
+                                }
+                            })
+                            .collect();
+
+                        let record_reference = &mut record_references[target];
+                        let mut get_reference = HashMap::new();
+
+                        let operations: Vec<Operation<u32, u32>> = steps
+                            .into_values()
+                            .map(|step| match step {
+                                Step::Get(key) => {
+                                    get_reference.insert(key, record_reference.get(&key).cloned());
+                                    get!(key)
+                                }
+                                Step::Set(key, value) => {
+                                    record_reference.insert(key, value);
+                                    set!(key, value)
+                                }
+                                Step::Remove(key) => {
+                                    record_reference.remove(&key);
+                                    remove!(key)
+                                }
+                            })
+                            .collect();
+
+                        let batch = Batch::new(operations);
+                        let next = apply(store, roots[target], batch);
+
+                        store = next.0;
+                        roots[target] = next.1;
+                        let batch = next.2;
+
+                        store.check_tree(roots[target]);
+                        store.assert_records(roots[target], record_reference.clone());
+
+                        batch.assert_gets(get_reference);
+                    }
+                    Round::Fork(from, to) => {
+                        // Mirrors `Table::clone` followed by dropping the overwritten `Table`
+                        store.incref(roots[from]);
+                        drop::drop(&mut store, roots[to]);
+
+                        roots[to] = roots[from];
+                        record_references[to] = record_references[from].clone();
+                    }
+                }
+
+                store.check_leaks(roots);
+                store.check_references(roots);
+            }
+        }
+    }
 }
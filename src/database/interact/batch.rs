@@ -33,6 +33,13 @@ where
         }
     }
 
+    pub fn unwrap(self) -> Vec<Operation<Key, Value>> {
+        match self.operations.try_unwrap() {
+            Ok(operations) => operations,
+            Err(_) => panic!("called `Batch::unwrap` on an incomplete `Batch`"),
+        }
+    }
+
     pub fn operations(&self) -> &[Operation<Key, Value>] {
         &self.operations
     }
@@ -23,6 +23,10 @@ impl Chunk {
         }
     }
 
+    pub fn len(&self) -> usize {
+        self.range.len()
+    }
+
     fn operations<'a, Key, Value>(
         &self,
         batch: &'a Batch<Key, Value>,
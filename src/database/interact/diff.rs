@@ -1,17 +1,21 @@
 use crate::{
-    common::store::Field,
-    database::store::{Label, Node, Split, Store, Wrap},
+    common::{store::Field, tree::Prefix},
+    database::{
+        store::{Label, Node, Split, Store, Wrap},
+        StoreAllocator,
+    },
+    map::store::Node as MapNode,
 };
 
-use std::collections::{
-    hash_map::Entry::{Occupied, Vacant},
-    LinkedList,
-};
+use hashbrown::hash_map::Entry::{Occupied, Vacant};
+
+use std::collections::LinkedList;
 
-fn get<Key, Value>(store: &mut Store<Key, Value>, label: Label) -> Node<Key, Value>
+fn get<Key, Value, A>(store: &mut Store<Key, Value, A>, label: Label) -> Node<Key, Value>
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
     if !label.is_empty() {
         match store.entry(label) {
@@ -26,18 +30,19 @@ where
     }
 }
 
-pub(crate) fn branch<Key, Value>(
-    store: Store<Key, Value>,
+pub(crate) fn branch<Key, Value, A>(
+    store: Store<Key, Value, A>,
     lho_recursion: Option<(Label, Label)>,
     rho_recursion: Option<(Label, Label)>,
 ) -> (
-    Store<Key, Value>,
+    Store<Key, Value, A>,
     LinkedList<(Wrap<Key>, Wrap<Value>)>,
     LinkedList<(Wrap<Key>, Wrap<Value>)>,
 )
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
     let (lho_left, lho_right) = match lho_recursion {
         Some((lho_left, lho_right)) => (Some(lho_left), Some(lho_right)),
@@ -103,18 +108,19 @@ where
     (store, lho_candidates, rho_candidates)
 }
 
-pub(crate) fn recur<Key, Value>(
-    mut store: Store<Key, Value>,
+pub(crate) fn recur<Key, Value, A>(
+    mut store: Store<Key, Value, A>,
     lho_node: Option<Label>,
     rho_node: Option<Label>,
 ) -> (
-    Store<Key, Value>,
+    Store<Key, Value, A>,
     LinkedList<(Wrap<Key>, Wrap<Value>)>,
     LinkedList<(Wrap<Key>, Wrap<Value>)>,
 )
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
     if lho_node != rho_node {
         let mut lho_collector = LinkedList::new();
@@ -156,18 +162,113 @@ where
     }
 }
 
-pub(crate) fn diff<Key, Value>(
-    store: Store<Key, Value>,
+pub(crate) fn diff<Key, Value, A>(
+    store: Store<Key, Value, A>,
     lho_root: Label,
     rho_root: Label,
 ) -> (
-    Store<Key, Value>,
+    Store<Key, Value, A>,
     LinkedList<(Wrap<Key>, Wrap<Value>)>,
     LinkedList<(Wrap<Key>, Wrap<Value>)>,
 )
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
     recur(store, Some(lho_root), Some(rho_root))
 }
+
+// Candidates collected by `diff_partial`: `lho` from the `Store`, `rho` from
+// the `MapNode`, along with the locations of the stubs of the `MapNode`
+pub(crate) struct Partial<Key: Field, Value: Field> {
+    pub lho: Vec<(Wrap<Key>, Wrap<Value>)>,
+    pub rho: Vec<(Key, Value)>,
+    pub unknown: Vec<Prefix>,
+}
+
+fn recur_partial<Key, Value, A>(
+    store: &mut Store<Key, Value, A>,
+    lho_node: Label,
+    rho_node: Option<&MapNode<Key, Value>>,
+    location: Prefix,
+    partial: &mut Partial<Key, Value>,
+) where
+    Key: Field + Clone,
+    Value: Field + Clone,
+    A: StoreAllocator,
+{
+    let rho_hash = rho_node.map(MapNode::hash).unwrap_or(Label::Empty.hash());
+
+    if lho_node.hash() == rho_hash {
+        return;
+    }
+
+    if let Some(MapNode::Stub(_)) = rho_node {
+        partial.unknown.push(location);
+        return;
+    }
+
+    let lho_recursion = match get(store, lho_node) {
+        Node::Internal(left, right) => Some((left, right)),
+        Node::Leaf(key, value) => {
+            partial.lho.push((key, value));
+            None
+        }
+        Node::Empty => None,
+    };
+
+    let rho_recursion = match rho_node {
+        Some(MapNode::Internal(internal)) => Some((internal.left(), internal.right())),
+        Some(MapNode::Leaf(leaf)) => {
+            partial
+                .rho
+                .push((leaf.key().inner().clone(), leaf.value().inner().clone()));
+            None
+        }
+        _ => None,
+    };
+
+    if lho_recursion.is_some() || rho_recursion.is_some() {
+        let (lho_left, lho_right) = lho_recursion.unwrap_or((Label::Empty, Label::Empty));
+
+        let (rho_left, rho_right) = match rho_recursion {
+            Some((rho_left, rho_right)) => (Some(rho_left), Some(rho_right)),
+            None => (None, None),
+        };
+
+        recur_partial(store, lho_left, rho_left, location.left(), partial);
+        recur_partial(store, lho_right, rho_right, location.right(), partial);
+    }
+}
+
+// Like `diff`, but against a `MapNode` that might contain stubs. Candidates
+// lying under a stub cannot be compared, and are discarded.
+pub(crate) fn diff_partial<Key, Value, A>(
+    store: &mut Store<Key, Value, A>,
+    root: Label,
+    map: &MapNode<Key, Value>,
+) -> Partial<Key, Value>
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+    A: StoreAllocator,
+{
+    let mut partial = Partial {
+        lho: Vec::new(),
+        rho: Vec::new(),
+        unknown: Vec::new(),
+    };
+
+    recur_partial(store, root, Some(map), Prefix::root(), &mut partial);
+
+    // A leaf of the `Store` might lie under a stub deeper than itself
+    let unknown = partial.unknown.clone();
+
+    partial.lho.retain(|(key, _)| {
+        let path = key.digest().into();
+        !unknown.iter().any(|location| location.contains(&path))
+    });
+
+    partial
+}
@@ -1,12 +1,14 @@
 use crate::{
     common::store::Field,
     database::store::{Label, Node, Store},
+    database::StoreAllocator,
 };
 
-pub(crate) fn drop<Key, Value>(store: &mut Store<Key, Value>, label: Label)
+pub(crate) fn drop<Key, Value, A>(store: &mut Store<Key, Value, A>, label: Label)
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
     match store.decref(label, false) {
         Some(Node::Internal(left, right)) => {
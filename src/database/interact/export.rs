@@ -3,18 +3,29 @@ use crate::{
         store::Field,
         tree::{Direction, Path},
     },
-    database::store::{Label, Node, Split, Store},
+    database::{
+        store::{Label, Node, Split, Store},
+        StoreAllocator,
+    },
     map::store::{Internal as MapInternal, Leaf as MapLeaf, Node as MapNode, Wrap as MapWrap},
 };
 
+use hashbrown::hash_map::Entry::{Occupied, Vacant};
+
 use oh_snap::Snap;
 
-use std::collections::hash_map::Entry::{Occupied, Vacant};
+// A `Store`, along with the exported left and right children of a node
+type Branches<Key, Value, A> = (
+    Store<Key, Value, A>,
+    MapNode<Key, Value>,
+    MapNode<Key, Value>,
+);
 
-fn get<Key, Value>(store: &mut Store<Key, Value>, label: Label) -> Node<Key, Value>
+fn get<Key, Value, A>(store: &mut Store<Key, Value, A>, label: Label) -> Node<Key, Value>
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
     if !label.is_empty() {
         match store.entry(label) {
@@ -36,80 +47,119 @@ fn split(paths: Snap<Path>, depth: u8) -> (Snap<Path>, Snap<Path>) {
     (left, right)
 }
 
-fn branch<Key, Value>(
-    store: Store<Key, Value>,
+fn branch<Key, Value, A>(
+    store: Store<Key, Value, A>,
     depth: u8,
     paths: Snap<Path>,
+    siblings: bool,
     left: Label,
     right: Label,
-) -> (Store<Key, Value>, MapNode<Key, Value>, MapNode<Key, Value>)
+) -> Branches<Key, Value, A>
 where
     Key: Field + Clone,
     Value: Field + Clone,
+    A: StoreAllocator,
 {
     let (left_paths, right_paths) = split(paths, depth);
 
     match store.split() {
         Split::Split(left_store, right_store) => {
             let ((left_store, left), (right_store, right)) = rayon::join(
-                move || recur(left_store, left, depth + 1, left_paths),
-                move || recur(right_store, right, depth + 1, right_paths),
+                move || recur(left_store, left, depth + 1, left_paths, siblings),
+                move || recur(right_store, right, depth + 1, right_paths, siblings),
             );
 
             let store = Store::merge(left_store, right_store);
             (store, left, right)
         }
         Split::Unsplittable(store) => {
-            let (store, left) = recur(store, left, depth + 1, left_paths);
-            let (store, right) = recur(store, right, depth + 1, right_paths);
+            let (store, left) = recur(store, left, depth + 1, left_paths, siblings);
+            let (store, right) = recur(store, right, depth + 1, right_paths, siblings);
 
             (store, left, right)
         }
     }
 }
 
-fn recur<Key, Value>(
-    mut store: Store<Key, Value>,
+fn stub<Key, Value>(label: Label) -> MapNode<Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    if label.is_empty() {
+        MapNode::Empty
+    } else {
+        MapNode::stub(label.hash())
+    }
+}
+
+fn recur<Key, Value, A>(
+    mut store: Store<Key, Value, A>,
     node: Label,
     depth: u8,
     paths: Snap<Path>,
-) -> (Store<Key, Value>, MapNode<Key, Value>)
+    siblings: bool,
+) -> (Store<Key, Value, A>, MapNode<Key, Value>)
 where
     Key: Field + Clone,
     Value: Field + Clone,
+    A: StoreAllocator,
 {
     let hash = node.hash();
 
     match get(&mut store, node) {
         Node::Internal(left, right) if !paths.is_empty() => {
-            let (store, left, right) = branch(store, depth, paths, left, right);
+            let (store, left, right) = branch(store, depth, paths, siblings, left, right);
 
             (
                 store,
                 MapNode::Internal(MapInternal::raw(hash, left, right)),
             )
         }
-        Node::Leaf(key, value) if !paths.is_empty() => {
+        Node::Leaf(key, value) if !paths.is_empty() || siblings => {
             let key = MapWrap::raw(key.digest(), (**key.inner()).clone());
             let value = MapWrap::raw(value.digest(), (**value.inner()).clone());
 
             (store, MapNode::Leaf(MapLeaf::raw(hash, key, value)))
         }
 
+        Node::Internal(left, right) if siblings => (
+            store,
+            MapNode::Internal(MapInternal::raw(hash, stub(left), stub(right))),
+        ),
+
         Node::Empty => (store, MapNode::Empty),
 
         node => (store, MapNode::stub(node.hash())),
     }
 }
 
-pub(crate) fn export<Key, Value>(
-    store: Store<Key, Value>,
+pub(crate) fn export<Key, Value, A>(
+    store: Store<Key, Value, A>,
+    root: Label,
+    paths: Snap<Path>,
+) -> (Store<Key, Value, A>, MapNode<Key, Value>)
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+    A: StoreAllocator,
+{
+    recur(store, root, 0, paths, false)
+}
+
+// Like `export`, but also reveals the siblings of all nodes along `paths`
+// one level deep: sibling leaves in full, sibling internal nodes with their
+// children stubbed. This allows to tell leaves from internal nodes when
+// compacting the exported tree after removals (see `TransitionProof`).
+pub(crate) fn export_with_siblings<Key, Value, A>(
+    store: Store<Key, Value, A>,
     root: Label,
     paths: Snap<Path>,
-) -> (Store<Key, Value>, MapNode<Key, Value>)
+) -> (Store<Key, Value, A>, MapNode<Key, Value>)
 where
     Key: Field + Clone,
     Value: Field + Clone,
+    A: StoreAllocator,
 {
-    recur(store, root, 0, paths)
+    recur(store, root, 0, paths, true)
 }
@@ -0,0 +1,67 @@
+use crate::{
+    common::{store::Field, tree::Prefix},
+    database::{
+        store::{Label, Node, Store, Wrap},
+        StoreAllocator,
+    },
+    map::store::Node as MapNode,
+};
+
+fn recur<Key, Value, A>(
+    store: &mut Store<Key, Value, A>,
+    node: &MapNode<Key, Value>,
+    location: Prefix,
+) -> Label
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+    A: StoreAllocator,
+{
+    let node = match node {
+        MapNode::Empty => return Label::Empty,
+        MapNode::Internal(internal) => {
+            let left = recur(store, internal.left(), location.left());
+            let right = recur(store, internal.right(), location.right());
+
+            Node::Internal(left, right)
+        }
+        MapNode::Leaf(leaf) => {
+            let key = Wrap::raw(leaf.key().digest(), leaf.key().inner().clone());
+            let value = Wrap::raw(leaf.value().digest(), leaf.value().inner().clone());
+
+            Node::Leaf(key, value)
+        }
+        MapNode::Stub(_) => unreachable!("called `import` on an incomplete `Map`"),
+    };
+
+    let label = store.label(&node, location);
+    let children = match node {
+        Node::Internal(left, right) => Some((left, right)),
+        _ => None,
+    };
+
+    // As in `apply`: if `node` was already in `store`, so were its children
+    // (along with their references), otherwise `node` adopts its children
+    if store.populate(label, node) {
+        if let Some((left, right)) = children {
+            store.incref(left);
+            store.incref(right);
+        }
+    }
+
+    label
+}
+
+// Populates `store` with the nodes of `root`, which must not contain stubs,
+// and returns the label of `root`. The returned label is not `incref`-ed.
+pub(crate) fn import<Key, Value, A>(
+    store: &mut Store<Key, Value, A>,
+    root: &MapNode<Key, Value>,
+) -> Label
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+    A: StoreAllocator,
+{
+    recur(store, root, Prefix::root())
+}
@@ -0,0 +1,231 @@
+use crate::{
+    common::{
+        store::Field,
+        tree::{Direction, Path, Prefix},
+    },
+    database::{
+        store::{Label, Node, Store},
+        Prefer, StoreAllocator,
+    },
+};
+
+use hashbrown::hash_map::Entry::{Occupied, Vacant};
+
+fn get<Key, Value, A>(store: &mut Store<Key, Value, A>, label: Label) -> Node<Key, Value>
+where
+    Key: Field,
+    Value: Field,
+    A: StoreAllocator,
+{
+    if !label.is_empty() {
+        match store.entry(label) {
+            Occupied(entry) => entry.get().node.clone(),
+            Vacant(..) => unreachable!(),
+        }
+    } else {
+        Node::Empty
+    }
+}
+
+// Returns the children of `node`, lying at `location`. A `Leaf` is pushed
+// one level down, next to an `Empty` sibling.
+fn children<Key, Value>(label: Label, node: Node<Key, Value>, location: Prefix) -> (Label, Label)
+where
+    Key: Field,
+    Value: Field,
+{
+    match node {
+        Node::Internal(left, right) => (left, right),
+        Node::Leaf(key, _) => {
+            if Path::from(key.digest())[location.depth()] == Direction::Left {
+                (label, Label::Empty)
+            } else {
+                (Label::Empty, label)
+            }
+        }
+        Node::Empty => (Label::Empty, Label::Empty),
+    }
+}
+
+fn recur<Key, Value, A>(
+    store: &mut Store<Key, Value, A>,
+    lho: Label,
+    rho: Label,
+    location: Prefix,
+    prefer: Prefer,
+) -> Label
+where
+    Key: Field,
+    Value: Field,
+    A: StoreAllocator,
+{
+    // Identical subtrees (including identical `Leaf`s) are shared as they are
+    if lho == rho {
+        return lho;
+    }
+
+    if lho.is_empty() {
+        return rho;
+    }
+
+    if rho.is_empty() {
+        return lho;
+    }
+
+    let lho_node = get(store, lho);
+    let rho_node = get(store, rho);
+
+    if let (Node::Leaf(lho_key, _), Node::Leaf(rho_key, _)) = (&lho_node, &rho_node) {
+        if lho_key == rho_key {
+            return match prefer {
+                Prefer::Left => lho,
+                Prefer::Right => rho,
+            };
+        }
+    }
+
+    let (lho_left, lho_right) = children(lho, lho_node, location);
+    let (rho_left, rho_right) = children(rho, rho_node, location);
+
+    let left = recur(store, lho_left, rho_left, location.left(), prefer);
+    let right = recur(store, lho_right, rho_right, location.right(), prefer);
+
+    match (left, right) {
+        (Label::Empty, Label::Empty) => Label::Empty,
+        (Label::Empty, Label::Leaf(map, hash)) | (Label::Leaf(map, hash), Label::Empty) => {
+            Label::Leaf(map, hash)
+        }
+        (left, right) => {
+            let node = Node::<Key, Value>::Internal(left, right);
+            let label = store.label(&node, location);
+
+            // If `node` is already in `store`, then so are its children,
+            // which already count it among their references
+            if store.populate(label, node) {
+                store.incref(left);
+                store.incref(right);
+            }
+
+            label
+        }
+    }
+}
+
+// Returns the root of the union of the trees rooted at `lho` and `rho`,
+// resolving conflicting records according to `prefer`. The root is not
+// `incref`-ed: as in `import`, it is up to the caller to adopt it.
+pub(crate) fn merge<Key, Value, A>(
+    store: &mut Store<Key, Value, A>,
+    lho: Label,
+    rho: Label,
+    prefer: Prefer,
+) -> Label
+where
+    Key: Field,
+    Value: Field,
+    A: StoreAllocator,
+{
+    recur(store, lho, rho, Prefix::root(), prefer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::database::interact::{apply, drop, Batch};
+
+    use std::collections::HashMap;
+
+    fn build(store: Store<u32, u32>, records: &[(u32, u32)]) -> (Store<u32, u32>, Label) {
+        let batch = Batch::new(
+            records
+                .iter()
+                .map(|(key, value)| set!(*key, *value))
+                .collect(),
+        );
+
+        let (store, root, _) = apply::apply(store, Label::Empty, batch);
+        (store, root)
+    }
+
+    fn check(lho: &[(u32, u32)], rho: &[(u32, u32)], prefer: Prefer) {
+        let store = Store::<u32, u32>::new();
+
+        let (store, lho_root) = build(store, lho);
+        let (store, rho_root) = build(store, rho);
+
+        let mut expected: HashMap<u32, u32> = HashMap::new();
+
+        let (first, second) = match prefer {
+            Prefer::Left => (rho, lho),
+            Prefer::Right => (lho, rho),
+        };
+
+        for (key, value) in first.iter().chain(second.iter()) {
+            expected.insert(*key, *value);
+        }
+
+        let expected = expected.into_iter().collect::<Vec<_>>();
+        let (mut store, reference_root) = build(store, &expected);
+
+        let merged_root = merge(&mut store, lho_root, rho_root, prefer);
+        store.incref(merged_root);
+
+        assert_eq!(merged_root, reference_root);
+
+        store.check_tree(merged_root);
+        store.assert_records(merged_root, expected);
+        store.check_leaks([lho_root, rho_root, reference_root, merged_root]);
+        store.check_references([lho_root, rho_root, reference_root, merged_root]);
+
+        drop::drop(&mut store, lho_root);
+        drop::drop(&mut store, rho_root);
+        drop::drop(&mut store, reference_root);
+        store.check_leaks([merged_root]);
+
+        drop::drop(&mut store, merged_root);
+        store.check_leaks([]);
+    }
+
+    #[test]
+    fn empty() {
+        let records = (0..128).map(|i| (i, i)).collect::<Vec<_>>();
+
+        check(&[], &[], Prefer::Left);
+        check(&records, &[], Prefer::Left);
+        check(&[], &records, Prefer::Right);
+    }
+
+    #[test]
+    fn disjoint() {
+        let lho = (0..128).map(|i| (i, i)).collect::<Vec<_>>();
+        let rho = (128..256).map(|i| (i, i)).collect::<Vec<_>>();
+
+        check(&lho, &rho, Prefer::Left);
+        check(&lho, &rho, Prefer::Right);
+    }
+
+    #[test]
+    fn overlapping() {
+        let lho = (0..192).map(|i| (i, i)).collect::<Vec<_>>();
+        let rho = (64..256).map(|i| (i, i + 1)).collect::<Vec<_>>();
+
+        check(&lho, &rho, Prefer::Left);
+        check(&lho, &rho, Prefer::Right);
+    }
+
+    #[test]
+    fn identical() {
+        let records = (0..256).map(|i| (i, i)).collect::<Vec<_>>();
+
+        check(&records, &records, Prefer::Left);
+        check(&records, &records, Prefer::Right);
+    }
+
+    #[test]
+    fn single() {
+        check(&[(0, 0)], &[(1, 1)], Prefer::Left);
+        check(&[(0, 0)], &[(0, 1)], Prefer::Left);
+        check(&[(0, 0)], &[(0, 1)], Prefer::Right);
+    }
+}
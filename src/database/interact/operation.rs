@@ -42,9 +42,16 @@ where
 
         Ok(Operation {
             path: Path::from(hash),
-            action: Action::Remove,
+            action: Action::Remove(false),
         })
     }
+
+    pub fn remove_path(path: Path) -> Self {
+        Operation {
+            path,
+            action: Action::Remove(false),
+        }
+    }
 }
 
 impl<Key, Value> PartialEq for Operation<Key, Value>
@@ -85,6 +92,6 @@ mod tests {
 
         let remove = remove!(0u32);
         assert_eq!(remove.path, set.path);
-        assert_eq!(remove.action, Action::<u32, u32>::Remove);
+        assert_eq!(remove.action, Action::<u32, u32>::Remove(false));
     }
 }
@@ -5,44 +5,79 @@ mod interact;
 mod store;
 mod sync;
 
+mod backend;
 mod collection;
 mod collection_answer;
+mod collection_diff;
 mod collection_receiver;
 mod collection_response;
 mod collection_sender;
 mod collection_status;
 mod collection_transaction;
 mod database;
+mod database_builder;
+mod diff_result;
+mod expiries;
 mod family;
+mod indexed_table;
+mod prefer;
 mod query;
+mod query_outcome;
 mod question;
+mod read_snapshot;
+mod receiver_state;
+mod store_allocator;
+mod store_stats;
+mod sync_format;
 mod table;
 mod table_answer;
+mod table_batch;
 mod table_receiver;
 mod table_response;
 mod table_sender;
+mod table_state;
 mod table_status;
 mod table_transaction;
+mod transition_proof;
+mod txn_context;
 
+use expiries::Expiries;
 use table_transaction::Tid;
 
 pub mod errors;
 
+pub use allocator_api2::alloc::{Allocator, Global};
+
+pub use backend::{Backend, LogBackend};
 pub use collection::Collection;
 pub use collection_answer::CollectionAnswer;
+pub use collection_diff::CollectionDiff;
 pub use collection_receiver::CollectionReceiver;
 pub use collection_response::CollectionResponse;
 pub use collection_sender::CollectionSender;
 pub use collection_status::CollectionStatus;
 pub use collection_transaction::CollectionTransaction;
 pub use database::Database;
+pub use database_builder::DatabaseBuilder;
+pub use diff_result::DiffResult;
 pub use family::Family;
+pub use indexed_table::IndexedTable;
+pub use prefer::Prefer;
 pub use query::Query;
+pub use query_outcome::QueryOutcome;
 pub use question::Question;
+pub use read_snapshot::ReadSnapshot;
+pub use receiver_state::ReceiverState;
+pub use store_allocator::StoreAllocator;
+pub use store_stats::StoreStats;
+pub use sync_format::SyncFormat;
 pub use table::Table;
 pub use table_answer::TableAnswer;
-pub use table_receiver::TableReceiver;
+pub use table_batch::TableBatch;
+pub use table_receiver::{Settings, SyncBudget, TableReceiver};
 pub use table_response::TableResponse;
 pub use table_sender::TableSender;
 pub use table_status::TableStatus;
 pub use table_transaction::TableTransaction;
+pub use transition_proof::TransitionProof;
+pub use txn_context::{StagedTable, TxnContext};
@@ -0,0 +1,16 @@
+/// Which side wins when [`Database::merge_tables`] finds the same key
+/// in both [`Table`]s, with different values.
+///
+/// [`Database::merge_tables`]: crate::database::Database::merge_tables
+/// [`Table`]: crate::database::Table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prefer {
+    /// Keep the value of the left-hand [`Table`].
+    ///
+    /// [`Table`]: crate::database::Table
+    Left,
+    /// Keep the value of the right-hand [`Table`].
+    ///
+    /// [`Table`]: crate::database::Table
+    Right,
+}
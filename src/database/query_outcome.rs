@@ -0,0 +1,20 @@
+/// The outcome of a [`Query`] against a [`TableResponse`], obtained
+/// through [`TableResponse::outcome`].
+///
+/// [`Query`]: crate::database::Query
+/// [`TableResponse`]: crate::database::TableResponse
+/// [`TableResponse::outcome`]: crate::database::TableResponse::outcome
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryOutcome<'r, Value> {
+    /// The key was read, and is held with the enclosed value.
+    Found(&'r Value),
+    /// The key was read, and is not held.
+    Absent,
+    /// The [`Query`] was not issued by a read of the [`TableTransaction`]
+    /// that produced the [`TableResponse`].
+    ///
+    /// [`Query`]: crate::database::Query
+    /// [`TableTransaction`]: crate::database::TableTransaction
+    /// [`TableResponse`]: crate::database::TableResponse
+    NotQueried,
+}
@@ -1,4 +1,4 @@
-use crate::database::store::Label;
+use crate::database::{store::Label, sync::wire};
 
 use serde::{Deserialize, Serialize};
 
@@ -17,4 +17,4 @@ use crate::database::{TableAnswer, TableReceiver, TableSender};
 /// [`Answer`]: crate::database::Question
 
 #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
-pub struct Question(pub(crate) Vec<Label>);
+pub struct Question(#[serde(with = "wire::labels")] pub(crate) Vec<Label>);
@@ -0,0 +1,115 @@
+use crate::{
+    common::{data::Bytes, store::Field},
+    database::{
+        interact::drop,
+        store::{Cell, Handle, Label},
+        Global, StoreAllocator, Table,
+    },
+};
+
+use std::collections::HashMap;
+
+use talk::crypto::primitives::hash::Hash;
+
+// Documentation links
+#[allow(unused_imports)]
+use crate::database::Database;
+
+/// A consistent, read-only view of the [`Table`]s alive in a [`Database`]
+/// at the time [`Database::read_snapshot`] was called.
+///
+/// A `ReadSnapshot` keeps the contents of every [`Table`] it captured:
+/// writers can keep modifying or dropping their own [`Table`]s without
+/// affecting the snapshot. The captured contents are released when the
+/// `ReadSnapshot` is dropped.
+///
+/// Until retrieved by [`table`], captured [`Table`]s are not alive: they
+/// do not affect [`Database::checkpoint`] or [`Database::table_count`],
+/// and are not recovered by [`Database::open`].
+///
+/// [`Table`]: crate::database::Table
+/// [`Database`]: crate::database::Database
+/// [`Database::read_snapshot`]: crate::database::Database::read_snapshot
+/// [`table`]: crate::database::ReadSnapshot::table
+/// [`Database::checkpoint`]: crate::database::Database::checkpoint
+/// [`Database::table_count`]: crate::database::Database::table_count
+/// [`Database::open`]: crate::database::Database::open
+pub struct ReadSnapshot<Key: Field, Value: Field, A: StoreAllocator = Global> {
+    cell: Cell<Key, Value, A>,
+    roots: HashMap<Bytes, Label>,
+}
+
+impl<Key, Value, A> ReadSnapshot<Key, Value, A>
+where
+    Key: Field,
+    Value: Field,
+    A: StoreAllocator,
+{
+    // Assumes that each of `roots` is already referenced on behalf of the snapshot
+    pub(crate) fn new(cell: Cell<Key, Value, A>, roots: HashMap<Bytes, Label>) -> Self {
+        ReadSnapshot { cell, roots }
+    }
+
+    /// Returns a copy of the captured [`Table`] with commitment `commitment`,
+    /// or `None` if no such [`Table`] was alive when the snapshot was taken.
+    ///
+    /// [`Table`]: crate::database::Table
+    pub fn table(&self, commitment: Hash) -> Option<Table<Key, Value, A>> {
+        let root = *self.roots.get(&commitment.into())?;
+
+        let mut store = self.cell.take();
+        store.incref(root);
+        store.hold(root);
+        store.persist();
+        self.cell.restore(store);
+
+        Some(Table::from_handle(Handle::held(self.cell.clone(), root)))
+    }
+
+    /// Returns the number of distinct [`Table`]s captured by the snapshot.
+    ///
+    /// [`Table`]: crate::database::Table
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// Returns `true` if the snapshot captured no [`Table`].
+    ///
+    /// [`Table`]: crate::database::Table
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+}
+
+impl<Key, Value, A> Drop for ReadSnapshot<Key, Value, A>
+where
+    Key: Field,
+    Value: Field,
+    A: StoreAllocator,
+{
+    fn drop(&mut self) {
+        let mut store = self.cell.take();
+
+        for root in self.roots.values() {
+            drop::drop(&mut store, *root);
+        }
+
+        store.persist();
+        self.cell.restore(store);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl<Key, Value> ReadSnapshot<Key, Value>
+    where
+        Key: Field,
+        Value: Field,
+    {
+        pub(crate) fn roots(&self) -> impl Iterator<Item = Label> + '_ {
+            self.roots.values().copied()
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::{
+    common::store::Field,
+    database::store::{Label, Node},
+};
+
+use serde::{Deserialize, Serialize};
+
+// Documentation links
+#[allow(unused_imports)]
+use crate::database::{Database, TableReceiver};
+
+/// A serializable snapshot of an ongoing [`TableReceiver`] transfer,
+/// obtained through [`TableReceiver::checkpoint`].
+///
+/// A transfer interrupted (e.g., by a process restart) can be resumed from
+/// its latest `ReceiverState` through [`Database::resume_receive`], without
+/// asking again for the nodes acquired before the checkpoint.
+///
+/// Subtrees that the [`TableReceiver`] found in its [`Database`] are not
+/// included: upon resuming, they are looked up again, and asked for if
+/// no longer available.
+///
+/// [`TableReceiver`]: crate::database::TableReceiver
+/// [`TableReceiver::checkpoint`]: crate::database::TableReceiver::checkpoint
+/// [`Database`]: crate::database::Database
+/// [`Database::resume_receive`]: crate::database::Database::resume_receive
+#[derive(Serialize, Deserialize)]
+pub struct ReceiverState<Key: Field, Value: Field> {
+    pub(crate) root: Option<Label>,
+    pub(crate) acquired: Vec<Node<Key, Value>>,
+    pub(crate) received: usize,
+    pub(crate) steps: usize,
+}
@@ -1,7 +1,7 @@
-use crate::database::store::Store;
+use crate::database::{store::Store, Global};
 
 use std::sync::Arc;
 
 use talk::sync::lenders::AtomicLender;
 
-pub(crate) type Cell<Key, Value> = Arc<AtomicLender<Store<Key, Value>>>;
+pub(crate) type Cell<Key, Value, A = Global> = Arc<AtomicLender<Store<Key, Value, A>>>;
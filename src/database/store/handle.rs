@@ -1,8 +1,12 @@
 use crate::{
-    common::{store::Field, tree::Path},
+    common::{
+        store::Field,
+        tree::{Direction, Path, Prefix},
+    },
     database::{
-        interact::{apply, diff, drop, export, Batch},
-        store::{Cell, Label},
+        interact::{apply, diff, drop, export, merge, Batch},
+        store::{Cell, Label, Wrap},
+        DiffResult, Global, Prefer, StoreAllocator,
     },
     map::store::Node as MapNode,
 };
@@ -17,27 +21,45 @@ use std::{
 
 use talk::crypto::primitives::hash::Hash;
 
-pub(crate) struct Handle<Key: Field, Value: Field> {
-    pub cell: Cell<Key, Value>,
+#[cfg(feature = "tokio")]
+use tokio::sync::watch;
+
+pub(crate) struct Handle<Key: Field, Value: Field, A: StoreAllocator = Global> {
+    pub cell: Cell<Key, Value, A>,
     pub root: Label,
+    #[cfg(feature = "tokio")]
+    pub watch: watch::Sender<Hash>,
 }
 
-impl<Key, Value> Handle<Key, Value>
+impl<Key, Value, A> Handle<Key, Value, A>
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
-    pub fn empty(cell: Cell<Key, Value>) -> Self {
+    pub fn empty(cell: Cell<Key, Value, A>) -> Self {
+        Handle::new(cell, Label::Empty)
+    }
+
+    pub fn new(cell: Cell<Key, Value, A>, root: Label) -> Self {
+        let mut store = cell.take();
+        store.hold(root);
+        store.persist();
+        cell.restore(store);
+
+        Handle::held(cell, root)
+    }
+
+    // Unlike `new`, assumes that `root` is already held
+    pub fn held(cell: Cell<Key, Value, A>, root: Label) -> Self {
         Handle {
             cell,
-            root: Label::Empty,
+            root,
+            #[cfg(feature = "tokio")]
+            watch: watch::channel(root.hash().into()).0,
         }
     }
 
-    pub fn new(cell: Cell<Key, Value>, root: Label) -> Self {
-        Handle { cell, root }
-    }
-
     pub fn commit(&self) -> Hash {
         self.root.hash().into()
     }
@@ -46,14 +68,91 @@ where
         let root = self.root;
         let store = self.cell.take();
 
-        let (store, root, batch) = apply::apply(store, root, batch);
+        let (mut store, root, batch) = match store.pool().cloned() {
+            Some(pool) => pool.install(|| apply::apply(store, root, batch)),
+            None => apply::apply(store, root, batch),
+        };
+
+        store.release(self.root);
+        store.hold(root);
+        store.persist();
 
         self.cell.restore(store);
         self.root = root;
 
+        self.notify();
         batch
     }
 
+    pub fn clear(&mut self) {
+        let mut store = self.cell.take();
+
+        store.release(self.root);
+        drop::drop(&mut store, self.root);
+        store.hold(Label::Empty);
+        store.persist();
+
+        self.cell.restore(store);
+        self.root = Label::Empty;
+
+        self.notify();
+    }
+
+    // Like `clear`, but resets the store outright (retaining the capacity
+    // of its maps) if `self.root` holds the only external reference to its
+    // entries, rather than dropping entries one by one. Stores backed by a
+    // `Backend` always drop entries, so that their removal is written through.
+    pub fn clear_keep_capacity(&mut self) {
+        let mut store = self.cell.take();
+
+        store.release(self.root);
+
+        if store.backend().is_none() && store.external_references() == 1 {
+            store.reset();
+        } else {
+            drop::drop(&mut store, self.root);
+        }
+
+        store.hold(Label::Empty);
+        store.persist();
+
+        self.cell.restore(store);
+        self.root = Label::Empty;
+
+        self.notify();
+    }
+
+    fn notify(&self) {
+        #[cfg(feature = "tokio")]
+        {
+            let commitment = self.commit();
+
+            self.watch.send_if_modified(|current| {
+                let modified = *current != commitment;
+                *current = commitment;
+                modified
+            });
+        }
+    }
+
+    pub fn records(&self) -> Vec<(Wrap<Key>, Wrap<Value>)> {
+        let mut store = self.cell.take();
+        let mut records = Vec::new();
+
+        store.records(self.root, &mut records);
+        self.cell.restore(store);
+
+        records
+    }
+
+    pub fn serialized_size(&self) -> usize {
+        let mut store = self.cell.take();
+        let size = store.serialized_size(self.root);
+        self.cell.restore(store);
+
+        size
+    }
+
     pub fn export(&mut self, paths: Snap<Path>) -> MapNode<Key, Value>
     where
         Key: Clone,
@@ -66,9 +165,134 @@ where
         root
     }
 
+    // Like `export`, but runs on the thread pool of the `Store`, if any
+    pub fn export_parallel(&mut self, paths: Snap<Path>) -> MapNode<Key, Value>
+    where
+        Key: Clone,
+        Value: Clone,
+    {
+        let store = self.cell.take();
+
+        let (store, root) = match store.pool().cloned() {
+            Some(pool) => pool.install(|| export::export(store, self.root, paths)),
+            None => export::export(store, self.root, paths),
+        };
+
+        self.cell.restore(store);
+
+        root
+    }
+
+    pub fn export_with_siblings(&mut self, paths: Snap<Path>) -> MapNode<Key, Value>
+    where
+        Key: Clone,
+        Value: Clone,
+    {
+        let store = self.cell.take();
+        let (store, root) = export::export_with_siblings(store, self.root, paths);
+        self.cell.restore(store);
+
+        root
+    }
+
+    pub fn commitment_at(&self, location: Prefix) -> Option<Hash> {
+        let mut store = self.cell.take();
+        let mut label = self.root;
+
+        for direction in location {
+            label = match store.children(label) {
+                Some((left, right)) => match direction {
+                    Direction::Left => left,
+                    Direction::Right => right,
+                },
+                None => {
+                    self.cell.restore(store);
+                    return None;
+                }
+            };
+        }
+
+        self.cell.restore(store);
+        Some(label.hash().into())
+    }
+
+    pub fn diff_remote<F>(&self, remote: F) -> Vec<Prefix>
+    where
+        F: Fn(Prefix) -> Option<Hash>,
+    {
+        fn recur<Key, Value, F, A>(
+            handle: &Handle<Key, Value, A>,
+            remote: &F,
+            location: Prefix,
+            label: Label,
+            remote_commitment: Option<Hash>,
+            divergent: &mut Vec<Prefix>,
+        ) where
+            Key: Field,
+            Value: Field,
+            F: Fn(Prefix) -> Option<Hash>,
+            A: StoreAllocator,
+        {
+            if remote_commitment == Some(label.hash().into()) {
+                return;
+            }
+
+            // The store is not held while `remote` is invoked, as `remote`
+            // might be backed by a table of the same `Database`
+            let children = if remote_commitment.is_some() {
+                let mut store = handle.cell.take();
+                let children = store.children(label);
+                handle.cell.restore(store);
+
+                children
+            } else {
+                None
+            };
+
+            if let Some((left, right)) = children {
+                let left_location = location.left();
+                let right_location = location.right();
+
+                let remote_left = remote(left_location);
+                let remote_right = remote(right_location);
+
+                // If the remote node at `location` has no children, it is
+                // either a leaf or empty, and no further descent is possible
+                if remote_left.is_some() || remote_right.is_some() {
+                    recur(handle, remote, left_location, left, remote_left, divergent);
+                    recur(
+                        handle,
+                        remote,
+                        right_location,
+                        right,
+                        remote_right,
+                        divergent,
+                    );
+                    return;
+                }
+            }
+
+            divergent.push(location);
+        }
+
+        let mut divergent = Vec::new();
+        let root_commitment = remote(Prefix::root());
+
+        recur(
+            self,
+            &remote,
+            Prefix::root(),
+            self.root,
+            root_commitment,
+            &mut divergent,
+        );
+
+        divergent
+    }
+
     pub fn diff(
-        lho: &mut Handle<Key, Value>,
-        rho: &mut Handle<Key, Value>,
+        lho: &mut Handle<Key, Value, A>,
+        rho: &mut Handle<Key, Value, A>,
     ) -> HashMap<Key, (Option<Value>, Option<Value>)>
     where
         Key: Clone + Eq + StdHash,
@@ -84,62 +308,121 @@ where
 
         lho.cell.restore(store);
 
-        let mut diff: HashMap<Key, (Option<Value>, Option<Value>)> = HashMap::new();
+        let lho_candidates = lho_candidates
+            .into_iter()
+            .map(|(key, value)| ((**key.inner()).clone(), (**value.inner()).clone()));
+
+        let rho_candidates = rho_candidates
+            .into_iter()
+            .map(|(key, value)| ((**key.inner()).clone(), (**value.inner()).clone()));
 
-        for (key, value) in lho_candidates {
-            let key = (**key.inner()).clone();
-            let value = (**value.inner()).clone();
+        pair(lho_candidates, rho_candidates)
+    }
 
-            diff.insert(key, (Some(value), None));
+    pub fn merge(
+        lho: &Handle<Key, Value, A>,
+        rho: &Handle<Key, Value, A>,
+        prefer: Prefer,
+    ) -> Handle<Key, Value, A> {
+        if !ptr::eq(lho.cell.as_ref(), rho.cell.as_ref()) {
+            panic!("called `Handle::merge` on two `Handle`s for different `Store`s (most likely, `Database::merge_tables` was called on `Table`s belonging to different `Database`s)");
         }
 
-        for (key, value) in rho_candidates {
-            let key = (**key.inner()).clone();
-            let value = (**value.inner()).clone();
-
-            match diff.entry(key) {
-                Entry::Occupied(mut entry) => {
-                    if entry.get().0.as_ref().unwrap() == &value {
-                        entry.remove_entry();
-                    } else {
-                        entry.get_mut().1 = Some(value);
-                    }
-                }
-                Entry::Vacant(entry) => {
-                    entry.insert((None, Some(value)));
+        let mut store = lho.cell.take();
+
+        let root = merge::merge(&mut store, lho.root, rho.root, prefer);
+        store.incref(root);
+
+        lho.cell.restore(store);
+
+        Handle::new(lho.cell.clone(), root)
+    }
+
+    pub fn diff_partial(&self, map: &MapNode<Key, Value>) -> DiffResult<Key, Value>
+    where
+        Key: Clone + Eq + StdHash,
+        Value: Clone + Eq,
+    {
+        let mut store = self.cell.take();
+        let partial = diff::diff_partial(&mut store, self.root, map);
+        self.cell.restore(store);
+
+        let lho_candidates = partial
+            .lho
+            .into_iter()
+            .map(|(key, value)| ((**key.inner()).clone(), (**value.inner()).clone()));
+
+        DiffResult {
+            changes: pair(lho_candidates, partial.rho),
+            unknown_prefixes: partial.unknown,
+        }
+    }
+}
+
+// Pairs the candidates collected on either side of a diff, dropping records
+// that appear, unchanged, on both sides
+fn pair<Key, Value, L, R>(
+    lho_candidates: L,
+    rho_candidates: R,
+) -> HashMap<Key, (Option<Value>, Option<Value>)>
+where
+    Key: Eq + StdHash,
+    Value: Eq,
+    L: IntoIterator<Item = (Key, Value)>,
+    R: IntoIterator<Item = (Key, Value)>,
+{
+    let mut diff: HashMap<Key, (Option<Value>, Option<Value>)> = HashMap::new();
+
+    for (key, value) in lho_candidates {
+        diff.insert(key, (Some(value), None));
+    }
+
+    for (key, value) in rho_candidates {
+        match diff.entry(key) {
+            Entry::Occupied(mut entry) => {
+                if entry.get().0.as_ref().unwrap() == &value {
+                    entry.remove_entry();
+                } else {
+                    entry.get_mut().1 = Some(value);
                 }
             }
+            Entry::Vacant(entry) => {
+                entry.insert((None, Some(value)));
+            }
         }
-
-        diff
     }
+
+    diff
 }
 
-impl<Key, Value> Clone for Handle<Key, Value>
+impl<Key, Value, A> Clone for Handle<Key, Value, A>
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
     fn clone(&self) -> Self {
         let mut store = self.cell.take();
         store.incref(self.root);
+        store.hold(self.root);
+        store.persist();
         self.cell.restore(store);
 
-        Handle {
-            cell: self.cell.clone(),
-            root: self.root,
-        }
+        Handle::held(self.cell.clone(), self.root)
     }
 }
 
-impl<Key, Value> Drop for Handle<Key, Value>
+impl<Key, Value, A> Drop for Handle<Key, Value, A>
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
     fn drop(&mut self) {
         let mut store = self.cell.take();
+        store.release(self.root);
         drop::drop(&mut store, self.root);
+        store.persist();
         self.cell.restore(store);
     }
 }
@@ -3,23 +3,26 @@ use crate::{
         data::Bytes,
         tree::{Direction, Prefix},
     },
-    database::store::DEPTH,
+    database::store::MAX_DEPTH,
 };
 
 use serde::{Deserialize, Serialize};
 
 use std::fmt::{Debug, Error, Formatter, LowerHex};
 
+// A `MapId` holds the first `MAX_DEPTH` directions of a node's position,
+// regardless of the depth of the `Store` holding it: labels are exchanged
+// between `Store`s of different depths, and must match across them.
 #[derive(Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
-pub(crate) struct MapId(u8);
+pub(crate) struct MapId(u16);
 
 impl MapId {
     pub fn internal(position: Prefix) -> Self {
         let mut id = 0;
 
-        for (bit, direction) in (0..DEPTH).zip(position) {
+        for (bit, direction) in (0..MAX_DEPTH).zip(position) {
             if direction == Direction::Left {
-                id |= 1 << (7 - bit);
+                id |= 1 << (15 - bit);
             }
         }
 
@@ -27,12 +30,31 @@ impl MapId {
     }
 
     pub fn leaf(key_hash: &Bytes) -> Self {
-        MapId(key_hash.0[0])
+        MapId(u16::from_be_bytes([key_hash.0[0], key_hash.0[1]]))
     }
 
-    pub fn id(&self) -> usize {
-        if DEPTH > 0 {
-            (self.0 >> (8 - DEPTH)) as usize
+    pub fn raw(bytes: [u8; 2]) -> Self {
+        MapId(u16::from_be_bytes(bytes))
+    }
+
+    pub fn bytes(&self) -> [u8; 2] {
+        self.0.to_be_bytes()
+    }
+
+    // The first 8 bits of the `MapId`, as sent over the wire (see `sync::wire`)
+    pub fn narrow(&self) -> u8 {
+        (self.0 >> 8) as u8
+    }
+
+    // Inverse of `narrow`, up to the last 8 bits (which are left empty)
+    pub fn widen(byte: u8) -> Self {
+        MapId(u16::from(byte) << 8)
+    }
+
+    // Index of the map holding the node in a `Store` of depth `depth`
+    pub fn id(&self, depth: u8) -> usize {
+        if depth > 0 {
+            (self.0 >> (MAX_DEPTH - depth)) as usize
         } else {
             0
         }
@@ -8,8 +8,6 @@ mod split;
 mod store;
 mod wrap;
 
-use store::DEPTH;
-
 pub(crate) use cell::Cell;
 pub(crate) use entry::Entry;
 pub(crate) use handle::Handle;
@@ -17,5 +15,5 @@ pub(crate) use label::Label;
 pub(crate) use map_id::MapId;
 pub(crate) use node::Node;
 pub(crate) use split::Split;
-pub(crate) use store::Store;
+pub(crate) use store::{Store, DEFAULT_DEPTH, MAX_DEPTH};
 pub(crate) use wrap::Wrap;
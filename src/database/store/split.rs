@@ -1,6 +1,8 @@
-use crate::{common::store::Field, database::store::Store};
+use crate::{
+    common::store::Field, database::store::Store, database::Global, database::StoreAllocator,
+};
 
-pub(crate) enum Split<Key: Field, Value: Field> {
-    Split(Store<Key, Value>, Store<Key, Value>),
-    Unsplittable(Store<Key, Value>),
+pub(crate) enum Split<Key: Field, Value: Field, A: StoreAllocator = Global> {
+    Split(Store<Key, Value, A>, Store<Key, Value, A>),
+    Unsplittable(Store<Key, Value, A>),
 }
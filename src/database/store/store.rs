@@ -1,29 +1,67 @@
 use crate::{
     common::{data::Bytes, store::Field, tree::Prefix},
-    database::store::{Entry, Label, MapId, Node, Split},
+    database::{
+        errors::BackendError,
+        store::{Entry, Label, MapId, Node, Split, Wrap},
+        Backend, Global, StoreAllocator, StoreStats,
+    },
 };
 
+use doomstack::{here, Doom, ResultExt, Top};
+
+use hashbrown::hash_map::{Entry as HashMapEntry, HashMap as HashbrownMap};
+
 use oh_snap::Snap;
 
+use rayon::ThreadPool;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
 use std::{
     collections::{
         hash_map::{
-            Entry as HashMapEntry,
             Entry::{Occupied, Vacant},
+            RandomState,
         },
-        HashMap,
+        HashMap, HashSet,
     },
-    iter,
+    iter, mem,
+    sync::Arc,
 };
 
-pub(crate) type EntryMap<Key, Value> = HashMap<Bytes, Entry<Key, Value>>;
-pub(crate) type EntryMapEntry<'a, Key, Value> = HashMapEntry<'a, Bytes, Entry<Key, Value>>;
+pub(crate) type EntryMap<Key, Value, A> = HashbrownMap<Bytes, Entry<Key, Value>, RandomState, A>;
+pub(crate) type EntryMapEntry<'a, Key, Value, A> =
+    HashMapEntry<'a, Bytes, Entry<Key, Value>, RandomState, A>;
+
+// The keys under which a `Store` writes its entries and live roots
+// through to its `Backend`. A node is written once, when populated: its
+// references are written separately, whenever they change. Changed records
+// are staged, then written together by `persist` (see `Backend::write`).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+enum Record {
+    Node(Label),
+    References(Label),
+    Root(Label),
+}
 
-pub(crate) const DEPTH: u8 = 8;
+// The depth of a `Store` is the number of levels of the tree it is sharded
+// along: a `Store` of depth `depth` holds `1 << depth` maps, and splits
+// (see `split`) until its scope is `depth` levels deep.
+pub(crate) const DEFAULT_DEPTH: u8 = 8;
+pub(crate) const MAX_DEPTH: u8 = 16;
+pub(crate) const DEFAULT_PARALLEL_THRESHOLD: usize = 64;
 
-pub(crate) struct Store<Key: Field, Value: Field> {
-    maps: Snap<EntryMap<Key, Value>>,
+pub(crate) struct Store<Key: Field, Value: Field, A: StoreAllocator = Global> {
+    maps: Snap<EntryMap<Key, Value, A>>,
+    depth: u8,
     scope: Prefix,
+    roots: HashMap<Label, usize>,
+    shared: HashMap<Bytes, (Node<Key, Value>, usize)>,
+    parallel_threshold: usize,
+    pool: Option<Arc<ThreadPool>>,
+    backend: Option<Arc<dyn Backend>>,
+    staged: HashSet<Record>,
+    poisoned: bool,
 }
 
 impl<Key, Value> Store<Key, Value>
@@ -32,37 +70,109 @@ where
     Value: Field,
 {
     pub fn new() -> Self {
+        Store::with_capacity(0)
+    }
+
+    /// Creates a `Store` with room for (approximately) `capacity` entries,
+    /// evenly reserved across its maps.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Store::with_depth_and_capacity(DEFAULT_DEPTH, capacity)
+    }
+
+    pub fn with_depth_and_capacity(depth: u8, capacity: usize) -> Self {
+        Store::with_depth_and_capacity_in(depth, capacity, Global)
+    }
+}
+
+impl<Key, Value, A> Store<Key, Value, A>
+where
+    Key: Field,
+    Value: Field,
+    A: StoreAllocator,
+{
+    // Each map of the `Store` allocates through its own clone of `allocator`
+    pub fn with_depth_and_capacity_in(depth: u8, capacity: usize, allocator: A) -> Self {
+        assert!(depth <= MAX_DEPTH, "`depth` must be at most {}", MAX_DEPTH);
+
+        let shards = 1 << depth;
+        let shard_capacity = capacity.div_ceil(shards);
+
         Store {
             maps: Snap::new(
-                iter::repeat_with(|| EntryMap::new())
-                    .take(1 << DEPTH)
-                    .collect(),
+                iter::repeat_with(|| {
+                    EntryMap::with_capacity_and_hasher_in(
+                        shard_capacity,
+                        RandomState::new(),
+                        allocator.clone(),
+                    )
+                })
+                .take(shards)
+                .collect(),
             ),
+            depth,
             scope: Prefix::root(),
+            roots: HashMap::new(),
+            shared: HashMap::new(),
+            parallel_threshold: DEFAULT_PARALLEL_THRESHOLD,
+            pool: None,
+            backend: None,
+            staged: HashSet::new(),
+            poisoned: false,
         }
     }
 
-    pub fn merge(left: Self, right: Self) -> Self {
+    pub fn merge(mut left: Self, right: Self) -> Self {
+        left.roots.extend(right.roots);
+        left.shared.extend(right.shared);
+        left.staged.extend(right.staged);
+
         Store {
             maps: Snap::merge(right.maps, left.maps),
+            depth: left.depth,
             scope: left.scope.ancestor(1),
+            roots: left.roots,
+            shared: left.shared,
+            parallel_threshold: left.parallel_threshold,
+            pool: left.pool,
+            backend: left.backend,
+            staged: left.staged,
+            poisoned: left.poisoned || right.poisoned,
         }
     }
 
-    pub fn split(self) -> Split<Key, Value> {
-        if self.scope.depth() < DEPTH {
-            let mid = 1 << (DEPTH - self.scope.depth() - 1);
+    pub fn split(self) -> Split<Key, Value, A> {
+        if self.scope.depth() < self.depth {
+            let mid = 1 << (self.depth - self.scope.depth() - 1);
 
             let (right_maps, left_maps) = self.maps.snap(mid); // `oh-snap` stores the lowest-index elements in `left`, while `zebra` stores them in `right`, hence the swap
 
+            // Live roots, shared nodes, staged records and the thread pool are not
+            // sharded: they follow the `left` half. Both halves stage their records
+            // for the same `Backend`.
             let left = Store {
                 maps: left_maps,
+                depth: self.depth,
                 scope: self.scope.left(),
+                roots: self.roots,
+                shared: self.shared,
+                parallel_threshold: self.parallel_threshold,
+                pool: self.pool,
+                backend: self.backend.clone(),
+                staged: self.staged,
+                poisoned: self.poisoned,
             };
 
             let right = Store {
                 maps: right_maps,
+                depth: self.depth,
                 scope: self.scope.right(),
+                roots: HashMap::new(),
+                shared: HashMap::new(),
+                parallel_threshold: self.parallel_threshold,
+                pool: None,
+                backend: self.backend,
+                staged: HashSet::new(),
+                poisoned: self.poisoned,
             };
 
             Split::Split(left, right)
@@ -71,25 +181,193 @@ where
         }
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "test-util"))]
     pub fn size(&self) -> usize {
         debug_assert!(self.maps.is_complete());
         self.maps.iter().map(|map| map.len()).sum()
     }
 
-    pub fn entry(&mut self, label: Label) -> EntryMapEntry<Key, Value> {
-        let map = label.map().id() - self.maps.range().start;
+    // Returns the number of entries the maps of the `Store` can hold
+    // without reallocating (see `compact`)
+    pub fn capacity(&self) -> usize {
+        debug_assert!(self.maps.is_complete());
+        self.maps.iter().map(|map| map.capacity()).sum()
+    }
+
+    // Estimates the heap held by the store, in bytes. Each slot of a map
+    // counts its `(key, value)` pair plus one control byte (as in `std`'s
+    // `HashMap`), whether occupied or not. Each `Leaf` additionally counts
+    // the `Arc` allocations behind its `Wrap`s, sized after `Key` and `Value`
+    // alone: payloads owned by `Key` or `Value` (e.g., a `String`'s buffer)
+    // are not accounted for (see `memory_usage_with`).
+    pub fn memory_usage(&self) -> usize {
+        self.memory_usage_with(|_| 0, |_| 0)
+    }
+
+    // Same as `memory_usage`, additionally counting `key_heap(key)` and
+    // `value_heap(value)` bytes for the key and value of each `Leaf`
+    pub fn memory_usage_with<KeyHeap, ValueHeap>(
+        &self,
+        key_heap: KeyHeap,
+        value_heap: ValueHeap,
+    ) -> usize
+    where
+        KeyHeap: Fn(&Key) -> usize,
+        ValueHeap: Fn(&Value) -> usize,
+    {
+        debug_assert!(self.maps.is_complete());
+
+        fn slots<K, V>(map: &HashMap<K, V>) -> usize {
+            mem::size_of::<HashMap<K, V>>() + map.capacity() * (mem::size_of::<(K, V)>() + 1)
+        }
+
+        fn shard_slots<Key, Value, A>(map: &EntryMap<Key, Value, A>) -> usize
+        where
+            Key: Field,
+            Value: Field,
+            A: StoreAllocator,
+        {
+            mem::size_of::<EntryMap<Key, Value, A>>()
+                + map.capacity() * (mem::size_of::<(Bytes, Entry<Key, Value>)>() + 1)
+        }
+
+        // `Arc`s allocate their strong and weak counts next to their payload
+        let payload = 4 * mem::size_of::<usize>() + mem::size_of::<Key>() + mem::size_of::<Value>();
+
+        let leaves = self
+            .maps
+            .iter()
+            .flat_map(|map| map.values())
+            .filter_map(|entry| match &entry.node {
+                Node::Leaf(key, value) => {
+                    Some(payload + key_heap(key.inner()) + value_heap(value.inner()))
+                }
+                _ => None,
+            })
+            .sum::<usize>();
+
+        self.maps.iter().map(shard_slots).sum::<usize>()
+            + leaves
+            + slots(&self.roots)
+            + slots(&self.shared)
+    }
+
+    // Shrinks each map to fit its entries, releasing the capacity left over
+    // by removed entries. Shrinking rehashes each map, dropping tombstones
+    // along the way. Entries (and their references) are left untouched:
+    // nothing is written through to the `Backend`.
+    pub fn compact(&mut self) {
+        debug_assert!(self.maps.is_complete());
+
+        for map in self.maps.iter_mut() {
+            map.shrink_to_fit();
+        }
+
+        self.roots.shrink_to_fit();
+        self.shared.shrink_to_fit();
+    }
+
+    // Counts the references to entries that are not accounted for by the
+    // children of other entries, i.e., those held by `Table`s, receivers
+    // and snapshots
+    pub fn external_references(&self) -> usize {
+        debug_assert!(self.maps.is_complete());
+
+        let (references, children) = self.maps.iter().flat_map(|map| map.values()).fold(
+            (0, 0),
+            |(references, children), entry| {
+                let count = match &entry.node {
+                    Node::Internal(left, right) => [left, right]
+                        .iter()
+                        .filter(|child| !child.is_empty())
+                        .count(),
+                    _ => 0,
+                };
+
+                (references + entry.references, children + count)
+            },
+        );
+
+        references - children
+    }
+
+    // Removes every entry, retaining the capacity of each map (unlike
+    // `compact`). Only to be called when no entry is referenced from
+    // outside the store: nothing is written through to the `Backend`.
+    pub fn reset(&mut self) {
+        debug_assert!(self.maps.is_complete());
+
+        for map in self.maps.iter_mut() {
+            map.clear();
+        }
+    }
+
+    pub fn stats(&self) -> StoreStats {
+        debug_assert!(self.maps.is_complete());
+
+        let mut stats = StoreStats::default();
+
+        for entry in self.maps.iter().flat_map(|map| map.values()) {
+            stats.record(matches!(entry.node, Node::Leaf(..)), entry.references);
+        }
+
+        stats
+    }
+
+    // Counts the entries with no references, i.e., those left on the store
+    // by `decref`-ing with `preserve` (leaves being pulled up during `apply`).
+    // Once a batch is applied, pulled-up leaves are adopted: outside of
+    // `apply`, any such entry is a leak.
+    pub fn gc_candidates(&self) -> usize {
+        debug_assert!(self.maps.is_complete());
+
+        self.maps
+            .iter()
+            .flat_map(|map| map.values())
+            .filter(|entry| entry.references == 0)
+            .count()
+    }
+
+    pub fn entry(&mut self, label: Label) -> EntryMapEntry<Key, Value, A> {
+        let map = label.map().id(self.depth) - self.maps.range().start;
         let hash = label.hash();
         self.maps[map].entry(hash)
     }
 
-    pub fn label(&self, node: &Node<Key, Value>) -> Label {
+    // Looks up the node labeled `label`, which may have been widened (see
+    // `sync::wire`). In a `Store` sharded along more than 8 levels, a widened
+    // label only tells the first 8 levels of its map: the node is searched
+    // among all the maps sharing them.
+    pub fn lookup(&self, label: Label) -> Option<&Node<Key, Value>> {
+        let range = self.maps.range();
+        let hash = label.hash();
+
+        let exact = label.map().id(self.depth);
+
+        let candidates = if self.depth > 8 {
+            let spread = 1 << (self.depth - 8);
+            let first = label.map().narrow() as usize * spread;
+            first..first + spread
+        } else {
+            exact..exact + 1
+        };
+
+        iter::once(exact)
+            .chain(candidates.filter(|map| *map != exact))
+            .filter(|map| range.contains(map))
+            .find_map(|map| self.maps[map - range.start].get(&hash))
+            .map(|entry| &entry.node)
+    }
+
+    // Labels `node`, lying at `location`. Labels do not depend on the
+    // depth of the `Store` (see `MapId`).
+    pub fn label(&self, node: &Node<Key, Value>, location: Prefix) -> Label {
         let hash = node.hash();
 
         match node {
             Node::Empty => Label::Empty,
             Node::Internal(..) => {
-                let map = MapId::internal(self.scope);
+                let map = MapId::internal(location);
                 Label::Internal(map, hash)
             }
             Node::Leaf(key, _) => {
@@ -106,15 +384,16 @@ where
     {
         if !label.is_empty() {
             match self.entry(label) {
-                Vacant(entry) => {
+                EntryMapEntry::Vacant(entry) => {
                     entry.insert(Entry {
                         node,
                         references: 0,
                     });
 
+                    self.stage(Record::Node(label));
                     true
                 }
-                Occupied(..) => false,
+                EntryMapEntry::Occupied(..) => false,
             }
         } else {
             false
@@ -128,11 +407,13 @@ where
     {
         if !label.is_empty() {
             match self.entry(label) {
-                Occupied(mut entry) => {
+                EntryMapEntry::Occupied(mut entry) => {
                     entry.get_mut().references += 1;
                 }
-                Vacant(..) => panic!("called `incref` on non-existing node"),
+                EntryMapEntry::Vacant(..) => panic!("called `incref` on non-existing node"),
             }
+
+            self.stage(Record::References(label));
         }
     }
 
@@ -143,79 +424,353 @@ where
     {
         if !label.is_empty() {
             match self.entry(label) {
-                Occupied(mut entry) => {
+                EntryMapEntry::Occupied(mut entry) => {
                     let value = entry.get_mut();
                     value.references -= 1;
 
-                    if value.references == 0 && !preserve {
+                    let removed = if value.references == 0 && !preserve {
                         let (_, entry) = entry.remove_entry();
                         Some(entry.node)
                     } else {
                         None
+                    };
+
+                    self.stage(Record::References(label));
+
+                    if removed.is_some() {
+                        self.stage(Record::Node(label));
                     }
+
+                    removed
                 }
-                Vacant(..) => panic!("called `decref` on non-existing node"),
+                EntryMapEntry::Vacant(..) => panic!("called `decref` on non-existing node"),
             }
         } else {
             None
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    pub fn parallel_threshold(&self) -> usize {
+        self.parallel_threshold
+    }
 
-    use crate::{
-        common::tree::{Direction, Path},
-        database::store::{Entry, Node, Wrap},
-    };
+    pub fn set_parallel_threshold(&mut self, threshold: usize) {
+        self.parallel_threshold = threshold;
+    }
 
-    use std::{collections::HashSet, fmt::Debug, hash::Hash};
+    pub fn pool(&self) -> Option<&Arc<ThreadPool>> {
+        self.pool.as_ref()
+    }
 
-    impl<Key, Value> Store<Key, Value>
+    pub fn set_pool(&mut self, pool: ThreadPool) {
+        self.pool = Some(Arc::new(pool));
+    }
+
+    // Returns the previous `Backend`, if any. Entries are not written
+    // through to a newly set `Backend`: see `load`.
+    pub fn backend(&self) -> Option<&Arc<dyn Backend>> {
+        self.backend.as_ref()
+    }
+
+    pub fn set_backend(&mut self, backend: Option<Arc<dyn Backend>>) -> Option<Arc<dyn Backend>> {
+        self.staged.clear();
+        std::mem::replace(&mut self.backend, backend)
+    }
+
+    // Returns `true` if a write through to the `Backend` failed. A poisoned
+    // `Store` no longer writes through: later writes would build upon the
+    // failed one, leaving the `Backend` inconsistent.
+    pub fn poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    fn stage(&mut self, record: Record) {
+        if self.backend.is_some() && !self.poisoned {
+            self.staged.insert(record);
+        }
+    }
+
+    // Writes all records changed since the last call through to the `Backend`,
+    // in one batch. `persist` is to be called once all `Table`s are consistent
+    // (e.g., once a batch is applied and its root held): should the process
+    // crash, the `Backend` reverts to the last persisted state.
+    pub fn persist(&mut self) {
+        if self.staged.is_empty() {
+            return;
+        }
+
+        debug_assert!(self.maps.is_complete());
+
+        let staged = mem::take(&mut self.staged);
+
+        let writes = staged
+            .into_iter()
+            .map(|record| {
+                let value = match record {
+                    Record::Node(label) => self.maps[label.map().id(self.depth)]
+                        .get(&label.hash())
+                        // `Node`s were serialized to be hashed upon creation, and can be serialized again
+                        .map(|entry| bincode::serialize(&entry.node).unwrap()),
+                    Record::References(label) => self.maps[label.map().id(self.depth)]
+                        .get(&label.hash())
+                        .map(|entry| bincode::serialize(&entry.references).unwrap()),
+                    Record::Root(root) => self
+                        .roots
+                        .get(&root)
+                        .map(|count| bincode::serialize(count).unwrap()),
+                };
+
+                (bincode::serialize(&record).unwrap(), value)
+            })
+            .collect();
+
+        if let Some(backend) = self.backend.as_ref() {
+            if backend.write(writes).is_err() {
+                self.poisoned = true;
+            }
+        }
+    }
+
+    // Loads the entries and live roots written through to `backend`,
+    // checking that every entry matches its label, and that every live
+    // root and child is present
+    pub fn load(&mut self, backend: &dyn Backend) -> Result<(), Top<BackendError>>
     where
-        Key: Field,
-        Value: Field,
+        Key: DeserializeOwned,
+        Value: DeserializeOwned,
     {
-        pub fn raw_leaves<I>(leaves: I) -> (Self, Vec<Label>)
-        where
-            I: IntoIterator<Item = (Key, Value)>,
-        {
-            let mut store = Store::new();
+        debug_assert!(self.maps.is_complete());
 
-            let labels = leaves
-                .into_iter()
-                .map(|(key, value)| {
-                    let key = wrap!(key);
-                    let value = wrap!(value);
+        let mut references = Vec::new();
 
-                    let node = Node::Leaf(key, value);
-                    let label = store.label(&node);
+        for (key, value) in backend.load()? {
+            let record = bincode::deserialize(&key)
+                .map_err(|_| BackendError::MalformedRecord.into_top())
+                .spot(here!())?;
+
+            match record {
+                Record::Node(label) if !label.is_empty() => {
+                    let node: Node<Key, Value> = bincode::deserialize(&value)
+                        .map_err(|_| BackendError::MalformedRecord.into_top())
+                        .spot(here!())?;
+
+                    if node.hash() != label.hash() {
+                        return BackendError::MalformedRecord.fail().spot(here!());
+                    }
 
+                    // References are set once all records are loaded
                     let entry = Entry {
                         node,
-                        references: 1,
+                        references: 0,
                     };
 
-                    match store.entry(label) {
-                        EntryMapEntry::Vacant(entrymapentry) => {
-                            entrymapentry.insert(entry);
-                        }
-                        _ => unreachable!(),
-                    }
+                    self.maps[label.map().id(self.depth)].insert(label.hash(), entry);
+                }
+                Record::References(label) if !label.is_empty() => {
+                    let count = bincode::deserialize(&value)
+                        .map_err(|_| BackendError::MalformedRecord.into_top())
+                        .spot(here!())?;
 
-                    label
-                })
-                .collect();
+                    references.push((label, count));
+                }
+                Record::Root(label) => {
+                    let count = bincode::deserialize(&value)
+                        .map_err(|_| BackendError::MalformedRecord.into_top())
+                        .spot(here!())?;
 
-            (store, labels)
+                    self.roots.insert(label, count);
+                }
+                _ => return BackendError::MalformedRecord.fail().spot(here!()),
+            }
+        }
+
+        for (label, count) in references {
+            match self.maps[label.map().id(self.depth)].get_mut(&label.hash()) {
+                Some(entry) => entry.references = count,
+                None => return BackendError::MalformedRecord.fail().spot(here!()),
+            }
+        }
+
+        let present = |store: &Self, label: &Label| {
+            label.is_empty() || store.maps[label.map().id(store.depth)].contains_key(&label.hash())
+        };
+
+        let children = self.maps.iter().flat_map(|map| {
+            map.values().flat_map(|entry| match entry.node {
+                Node::Internal(left, right) => vec![left, right],
+                _ => Vec::new(),
+            })
+        });
+
+        if !children
+            .chain(self.roots.keys().copied())
+            .all(|label| present(self, &label))
+        {
+            return BackendError::MalformedRecord.fail().spot(here!());
+        }
+
+        Ok(())
+    }
+
+    pub fn hold(&mut self, root: Label) {
+        *self.roots.entry(root).or_insert(0) += 1;
+        self.stage(Record::Root(root));
+    }
+
+    pub fn release(&mut self, root: Label) {
+        match self.roots.entry(root) {
+            Occupied(mut entry) => {
+                *entry.get_mut() -= 1;
+
+                if *entry.get() == 0 {
+                    entry.remove_entry();
+                }
+            }
+            Vacant(..) => panic!("called `release` on a root that is not held"),
+        }
+
+        self.stage(Record::Root(root));
+    }
+
+    pub fn roots(&self) -> impl Iterator<Item = Label> + '_ {
+        self.roots
+            .iter()
+            .flat_map(|(root, count)| iter::repeat_n(*root, *count))
+    }
+
+    // Nodes acquired by a `TableReceiver` are shared until it is dropped, so that
+    // concurrent `TableReceiver`s of the same `Database` need not re-request them
+    pub fn share(&mut self, hash: Bytes, node: Node<Key, Value>) {
+        self.shared.entry(hash).or_insert((node, 0)).1 += 1;
+    }
+
+    pub fn unshare(&mut self, hash: Bytes) {
+        match self.shared.entry(hash) {
+            Occupied(mut entry) => {
+                entry.get_mut().1 -= 1;
+
+                if entry.get().1 == 0 {
+                    entry.remove_entry();
+                }
+            }
+            Vacant(..) => panic!("called `unshare` on a node that is not shared"),
+        }
+    }
+
+    pub fn shared(&self, hash: &Bytes) -> Option<&Node<Key, Value>> {
+        self.shared.get(hash).map(|(node, _)| node)
+    }
+
+    // Returns the children of `label`, or `None` if `label` is not `Internal`.
+    pub fn children(&mut self, label: Label) -> Option<(Label, Label)> {
+        match label {
+            Label::Internal(..) => match self.entry(label) {
+                EntryMapEntry::Occupied(entry) => match entry.get().node {
+                    Node::Internal(left, right) => Some((left, right)),
+                    _ => panic!("`children`: node not `Internal`"),
+                },
+                EntryMapEntry::Vacant(..) => panic!("`children`: node not found"),
+            },
+            _ => None,
+        }
+    }
+
+    // Returns the number of records in the tree rooted at `label`. Record
+    // counts of internal nodes are memoized in `cache`, so that subtrees
+    // shared across calls are only traversed once.
+    pub fn count(&mut self, label: Label, cache: &mut HashMap<Label, usize>) -> usize {
+        match label {
+            Label::Empty => 0,
+            Label::Leaf(..) => 1,
+            Label::Internal(..) => {
+                if let Some(count) = cache.get(&label) {
+                    return *count;
+                }
+
+                let (left, right) = match self.entry(label) {
+                    EntryMapEntry::Occupied(entry) => match entry.get().node {
+                        Node::Internal(left, right) => (left, right),
+                        _ => panic!("`count`: node not `Internal`"),
+                    },
+                    EntryMapEntry::Vacant(..) => panic!("`count`: node not found"),
+                };
+
+                let count = self.count(left, cache) + self.count(right, cache);
+                cache.insert(label, count);
+
+                count
+            }
+        }
+    }
+
+    pub fn records(&mut self, label: Label, collector: &mut Vec<(Wrap<Key>, Wrap<Value>)>) {
+        match label {
+            Label::Empty => {}
+            Label::Internal(..) | Label::Leaf(..) => {
+                let node = match self.entry(label) {
+                    EntryMapEntry::Occupied(entry) => entry.get().node.clone(),
+                    EntryMapEntry::Vacant(..) => panic!("`records`: node not found"),
+                };
+
+                match node {
+                    Node::Internal(left, right) => {
+                        self.records(left, collector);
+                        self.records(right, collector);
+                    }
+                    Node::Leaf(key, value) => collector.push((key, value)),
+                    Node::Empty => {}
+                }
+            }
         }
+    }
+
+    // Returns the number of bytes taken by the `bincode` serialization of the
+    // tree rooted at `label`, once exported to a `Map` (no node is serialized).
+    pub fn serialized_size(&mut self, label: Label) -> usize {
+        // `bincode` prefixes every `Node` with a 4-byte variant tag
+        const TAG: usize = 4;
+
+        let (left, right) = match label {
+            Label::Empty => return TAG,
+            Label::Internal(..) | Label::Leaf(..) => match self.entry(label) {
+                EntryMapEntry::Occupied(entry) => match &entry.get().node {
+                    Node::Internal(left, right) => (*left, *right),
+                    Node::Leaf(key, value) => {
+                        // `key` and `value` were serialized to be hashed
+                        // upon insertion, and can be serialized again
+                        let size = bincode::serialized_size(key.inner()).unwrap()
+                            + bincode::serialized_size(value.inner()).unwrap();
+
+                        return TAG + size as usize;
+                    }
+                    Node::Empty => return TAG,
+                },
+                EntryMapEntry::Vacant(..) => panic!("`serialized_size`: node not found"),
+            },
+        };
+
+        TAG + self.serialized_size(left) + self.serialized_size(right)
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+mod checks {
+    use super::*;
+
+    use crate::{common::tree::Path, database::store::Wrap};
+
+    use std::{collections::HashSet, fmt::Debug, hash::Hash};
 
+    impl<Key, Value, A> Store<Key, Value, A>
+    where
+        Key: Field,
+        Value: Field,
+        A: StoreAllocator,
+    {
         pub fn fetch_node(&mut self, label: Label) -> Node<Key, Value> {
             match self.entry(label) {
-                Occupied(entry) => entry.get().node.clone(),
-                Vacant(..) => panic!("`fetch_node`: node not found"),
+                EntryMapEntry::Occupied(entry) => entry.get().node.clone(),
+                EntryMapEntry::Vacant(..) => panic!("`fetch_node`: node not found"),
             }
         }
 
@@ -233,20 +788,6 @@ mod tests {
             }
         }
 
-        pub fn fetch_label_at(&mut self, root: Label, location: Prefix) -> Label {
-            let mut next = root;
-
-            for direction in location {
-                next = match (self.fetch_node(next), direction) {
-                    (Node::Internal(next, _), Direction::Left)
-                    | (Node::Internal(_, next), Direction::Right) => next,
-                    _ => panic!("`label_at`: reached a dead end"),
-                };
-            }
-
-            next
-        }
-
         pub fn check_internal(&mut self, label: Label) {
             let (left, right) = self.fetch_internal(label);
 
@@ -261,7 +802,7 @@ mod tests {
 
             for child in [left, right] {
                 if child != Label::Empty {
-                    if let Vacant(..) = self.entry(child) {
+                    if let EntryMapEntry::Vacant(..) = self.entry(child) {
                         panic!("`check_internal`: child not found");
                     }
                 }
@@ -276,10 +817,14 @@ mod tests {
         }
 
         pub fn check_tree(&mut self, root: Label) {
-            fn recursion<Key, Value>(store: &mut Store<Key, Value>, label: Label, location: Prefix)
-            where
+            fn recursion<Key, Value, A>(
+                store: &mut Store<Key, Value, A>,
+                label: Label,
+                location: Prefix,
+            ) where
                 Key: Field,
                 Value: Field,
+                A: StoreAllocator,
             {
                 match label {
                     Label::Internal(..) => {
@@ -302,13 +847,14 @@ mod tests {
         pub fn collect_tree(&mut self, root: Label) -> HashSet<Label> {
             let mut collector = HashSet::new();
 
-            fn recursion<Key, Value>(
-                store: &mut Store<Key, Value>,
+            fn recursion<Key, Value, A>(
+                store: &mut Store<Key, Value, A>,
                 label: Label,
                 collector: &mut HashSet<Label>,
             ) where
                 Key: Field,
                 Value: Field,
+                A: StoreAllocator,
             {
                 if !label.is_empty() {
                     collector.insert(label);
@@ -350,13 +896,14 @@ mod tests {
                 External(usize),
             }
 
-            fn recursion<Key, Value>(
-                store: &mut Store<Key, Value>,
+            fn recursion<Key, Value, A>(
+                store: &mut Store<Key, Value, A>,
                 label: Label,
                 references: &mut HashMap<Label, HashSet<Reference>>,
             ) where
                 Key: Field,
                 Value: Field,
+                A: StoreAllocator,
             {
                 if let Label::Internal(..) = label {
                     let (left, right) = store.fetch_internal(label);
@@ -386,10 +933,10 @@ mod tests {
             for (label, references) in references {
                 if !label.is_empty() {
                     match self.entry(label) {
-                        Occupied(entry) => {
+                        EntryMapEntry::Occupied(entry) => {
                             assert_eq!(entry.get().references, references.len());
                         }
-                        Vacant(..) => unreachable!(),
+                        EntryMapEntry::Vacant(..) => unreachable!(),
                     }
                 }
             }
@@ -400,13 +947,14 @@ mod tests {
             Key: Clone + Eq + Hash,
             Value: Clone,
         {
-            fn recursion<Key, Value>(
-                store: &mut Store<Key, Value>,
+            fn recursion<Key, Value, A>(
+                store: &mut Store<Key, Value, A>,
                 label: Label,
                 collector: &mut HashMap<Key, Value>,
             ) where
                 Key: Field + Clone + Eq + Hash,
                 Value: Field + Clone,
+                A: StoreAllocator,
             {
                 match label {
                     Label::Internal(..) => {
@@ -445,44 +993,117 @@ mod tests {
             assert_eq!(differences, HashSet::new());
         }
     }
+}
 
-    #[test]
-    fn split() {
-        let (mut store, labels) = Store::raw_leaves([(0u32, 1u32)]);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let path = Path::from(wrap!(0u32).digest());
-        let label = labels[0];
+    use crate::{
+        common::tree::{Direction, Path},
+        database::store::{Entry, Node},
+    };
 
-        for splits in 0..DEPTH {
-            store = match store.split() {
-                Split::Split(left, right) => {
-                    if path[splits] == Direction::Left {
-                        left
-                    } else {
-                        right
+    impl<Key, Value> Store<Key, Value>
+    where
+        Key: Field,
+        Value: Field,
+    {
+        pub fn raw_leaves<I>(leaves: I) -> (Self, Vec<Label>)
+        where
+            I: IntoIterator<Item = (Key, Value)>,
+        {
+            Store::raw_leaves_at(DEFAULT_DEPTH, leaves)
+        }
+
+        pub fn raw_leaves_at<I>(depth: u8, leaves: I) -> (Self, Vec<Label>)
+        where
+            I: IntoIterator<Item = (Key, Value)>,
+        {
+            let mut store = Store::with_depth_and_capacity(depth, 0);
+
+            let labels = leaves
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = wrap!(key);
+                    let value = wrap!(value);
+
+                    let node = Node::Leaf(key, value);
+                    let label = store.label(&node, Prefix::root());
+
+                    let entry = Entry {
+                        node,
+                        references: 1,
+                    };
+
+                    match store.entry(label) {
+                        EntryMapEntry::Vacant(entrymapentry) => {
+                            entrymapentry.insert(entry);
+                        }
+                        _ => unreachable!(),
                     }
-                }
-                Split::Unsplittable(_) => unreachable!(),
-            };
 
-            match store.entry(label) {
-                EntryMapEntry::Occupied(..) => {}
-                _ => {
-                    unreachable!();
-                }
+                    label
+                })
+                .collect();
+
+            (store, labels)
+        }
+
+        pub fn fetch_label_at(&mut self, root: Label, location: Prefix) -> Label {
+            let mut next = root;
+
+            for direction in location {
+                next = match (self.fetch_node(next), direction) {
+                    (Node::Internal(next, _), Direction::Left)
+                    | (Node::Internal(_, next), Direction::Right) => next,
+                    _ => panic!("`label_at`: reached a dead end"),
+                };
             }
+
+            next
         }
+    }
 
-        for _ in DEPTH..=255 {
-            store = match store.split() {
-                Split::Split(_, _) => unreachable!(),
-                Split::Unsplittable(store) => store,
-            };
+    #[test]
+    fn split() {
+        for depth in [0, 4, DEFAULT_DEPTH, 12] {
+            let (mut store, labels) = Store::raw_leaves_at(depth, [(0u32, 1u32)]);
+
+            let path = Path::from(wrap!(0u32).digest());
+            let label = labels[0];
+
+            for splits in 0..depth {
+                store = match store.split() {
+                    Split::Split(left, right) => {
+                        if path[splits] == Direction::Left {
+                            left
+                        } else {
+                            right
+                        }
+                    }
+                    Split::Unsplittable(_) => unreachable!(),
+                };
 
-            match store.entry(label) {
-                EntryMapEntry::Occupied(..) => {}
-                _ => {
-                    unreachable!();
+                match store.entry(label) {
+                    EntryMapEntry::Occupied(..) => {}
+                    _ => {
+                        unreachable!();
+                    }
+                }
+            }
+
+            for _ in depth..=255 {
+                store = match store.split() {
+                    Split::Split(_, _) => unreachable!(),
+                    Split::Unsplittable(store) => store,
+                };
+
+                match store.entry(label) {
+                    EntryMapEntry::Occupied(..) => {}
+                    _ => {
+                        unreachable!();
+                    }
                 }
             }
         }
@@ -529,6 +1150,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn split_merge_depths() {
+        // Splits `store` all the way down, checking that every entry
+        // lies in the map its label resolves to, then merges it back
+        fn round_trip(store: Store<u32, u32>) -> Store<u32, u32> {
+            match store.split() {
+                Split::Split(left, right) => Store::merge(round_trip(left), round_trip(right)),
+                Split::Unsplittable(store) => {
+                    assert_eq!(store.maps.len(), 1);
+
+                    for entry in store.maps[0].values() {
+                        let label = store.label(&entry.node, Prefix::root());
+                        assert_eq!(label.map().id(store.depth), store.maps.range().start);
+                    }
+
+                    store
+                }
+            }
+        }
+
+        let reference = Store::raw_leaves((0..1024).map(|i| (i, i))).1;
+
+        for depth in [0, 4, 12] {
+            let (store, labels) = Store::raw_leaves_at(depth, (0..1024).map(|i| (i, i)));
+
+            // Labels do not depend on the depth of the `Store`
+            assert_eq!(labels, reference);
+
+            let mut store = round_trip(store);
+
+            assert_eq!(store.maps.len(), 1 << depth);
+            assert_eq!(store.size(), 1024);
+
+            for label in labels {
+                assert!(matches!(store.entry(label), EntryMapEntry::Occupied(..)));
+            }
+        }
+    }
+
     #[test]
     fn size() {
         let store = Store::<u32, u32>::new();
@@ -539,4 +1199,174 @@ mod tests {
 
         assert_eq!(store.size(), 9);
     }
+
+    #[test]
+    fn compact() {
+        use crate::database::interact::{apply, drop, Batch};
+
+        let store = Store::<u32, u32>::new();
+
+        let batch = Batch::new((0..4096).map(|i| set!(i, i)).collect());
+        let (store, first, _) = apply::apply(store, Label::Empty, batch);
+
+        let batch = Batch::new((0..64).map(|i| set!(i, i)).collect());
+        let (mut store, second, _) = apply::apply(store, Label::Empty, batch);
+
+        drop::drop(&mut store, first);
+
+        let size = store.size();
+        let capacity = store.capacity();
+
+        store.compact();
+
+        assert_eq!(store.size(), size);
+        assert!(store.capacity() < capacity);
+
+        store.check_tree(second);
+        store.assert_records(second, (0..64).map(|i| (i, i)));
+        store.check_leaks([second]);
+        store.check_references([second]);
+    }
+
+    type Batches = Arc<std::sync::Mutex<Vec<HashMap<Record, Option<Vec<u8>>>>>>;
+
+    // Records the batches written to it, failing all batches after `capacity`
+    struct Recorder {
+        batches: Batches,
+        capacity: usize,
+    }
+
+    impl Recorder {
+        fn attach(store: &mut Store<u32, u32>, capacity: usize) -> Batches {
+            let batches = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+            store.set_backend(Some(Arc::new(Recorder {
+                batches: batches.clone(),
+                capacity,
+            })));
+
+            batches
+        }
+    }
+
+    impl Backend for Recorder {
+        fn write(&self, writes: Vec<(Vec<u8>, Option<Vec<u8>>)>) -> Result<(), Top<BackendError>> {
+            let mut batches = self.batches.lock().unwrap();
+
+            if batches.len() == self.capacity {
+                return BackendError::WriteFailed.fail().spot(here!());
+            }
+
+            batches.push(
+                writes
+                    .into_iter()
+                    .map(|(key, value)| (bincode::deserialize(&key).unwrap(), value))
+                    .collect(),
+            );
+
+            Ok(())
+        }
+
+        fn flush(&self) -> std::io::Result<()> {
+            Ok(())
+        }
+
+        fn load(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Top<BackendError>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn persist() {
+        let (mut store, labels) = Store::raw_leaves([(0u32, 1u32)]);
+        let batches = Recorder::attach(&mut store, usize::MAX);
+
+        let node = Node::Leaf(wrap!(2u32), wrap!(3u32));
+        let label = store.label(&node, Prefix::root());
+
+        assert!(store.populate(label, node.clone()));
+
+        for _ in 0..8 {
+            store.incref(label);
+        }
+
+        for _ in 0..8 {
+            store.decref(label, true);
+        }
+
+        store.incref(labels[0]);
+
+        // Nothing is written until `persist`
+        assert!(batches.lock().unwrap().is_empty());
+
+        store.persist();
+        store.persist();
+
+        // Changes are written in one batch, each record once
+        {
+            let batches = batches.lock().unwrap();
+            assert_eq!(batches.len(), 1);
+
+            let batch = &batches[0];
+            assert_eq!(batch.len(), 3);
+
+            assert_eq!(
+                batch[&Record::Node(label)],
+                Some(bincode::serialize(&node).unwrap())
+            );
+
+            assert_eq!(
+                batch[&Record::References(label)],
+                Some(bincode::serialize(&0usize).unwrap())
+            );
+
+            assert_eq!(
+                batch[&Record::References(labels[0])],
+                Some(bincode::serialize(&2usize).unwrap())
+            );
+        }
+
+        store.incref(label);
+        store.decref(label, false);
+        store.persist();
+
+        let batches = batches.lock().unwrap();
+        assert_eq!(batches.len(), 2);
+
+        // Removed nodes are deleted along with their references
+        let batch = &batches[1];
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch[&Record::Node(label)], None);
+        assert_eq!(batch[&Record::References(label)], None);
+    }
+
+    #[test]
+    fn poisoned() {
+        let (mut store, labels) = Store::raw_leaves([(0u32, 1u32), (2u32, 3u32)]);
+        let batches = Recorder::attach(&mut store, 1);
+
+        store.incref(labels[0]);
+        store.persist();
+        assert!(!store.poisoned());
+
+        store.incref(labels[1]);
+        store.persist();
+        assert!(store.poisoned());
+
+        // A poisoned `Store` keeps working in memory, without writing through
+        store.incref(labels[0]);
+        store.persist();
+
+        match store.entry(labels[0]) {
+            EntryMapEntry::Occupied(entry) => assert_eq!(entry.get().references, 3),
+            EntryMapEntry::Vacant(..) => unreachable!(),
+        }
+
+        assert_eq!(batches.lock().unwrap().len(), 1);
+
+        match store.split() {
+            Split::Split(left, right) => assert!(left.poisoned() && right.poisoned()),
+            Split::Unsplittable(..) => unreachable!(),
+        }
+    }
 }
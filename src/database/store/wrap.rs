@@ -25,6 +25,13 @@ where
         })
     }
 
+    pub fn raw(digest: Bytes, inner: Inner) -> Self {
+        Wrap {
+            digest,
+            inner: Arc::new(inner),
+        }
+    }
+
     pub fn digest(&self) -> Bytes {
         self.digest
     }
@@ -51,6 +58,8 @@ where
     Inner: Field,
 {
     fn eq(&self, rho: &Wrap<Inner>) -> bool {
+        // Digests are collision-resistant: comparing them takes constant
+        // time, however large `Inner` is
         self.digest == rho.digest
     }
 }
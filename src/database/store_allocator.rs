@@ -0,0 +1,17 @@
+use allocator_api2::alloc::Allocator;
+
+/// An [`Allocator`] backing the maps of a [`Database`]'s store.
+///
+/// Every `'static`, cloneable, thread-safe [`Allocator`] is a
+/// `StoreAllocator`: each shard of the store holds its own clone of the
+/// allocator, and shards are processed concurrently. Allocators are
+/// provided through [`DatabaseBuilder::allocator`], and default to
+/// [`Global`].
+///
+/// [`Allocator`]: crate::database::Allocator
+/// [`Database`]: crate::database::Database
+/// [`DatabaseBuilder::allocator`]: crate::database::DatabaseBuilder::allocator
+/// [`Global`]: crate::database::Global
+pub trait StoreAllocator: 'static + Allocator + Clone + Send + Sync {}
+
+impl<A> StoreAllocator for A where A: 'static + Allocator + Clone + Send + Sync {}
@@ -0,0 +1,51 @@
+use std::collections::BTreeMap;
+
+/// Statistics on the nodes retained by a [`Database`], obtained through
+/// [`Database::stats`].
+///
+/// Nodes are shared among all the [`Table`]s of a [`Database`]: a node's
+/// reference count is the number of internal nodes pointing to it, plus
+/// the number of [`Table`]s rooted at it.
+///
+/// [`Database`]: crate::database::Database
+/// [`Database::stats`]: crate::database::Database::stats
+/// [`Table`]: crate::database::Table
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StoreStats {
+    leaves: usize,
+    internals: usize,
+    references: BTreeMap<usize, usize>,
+}
+
+impl StoreStats {
+    pub(crate) fn record(&mut self, leaf: bool, references: usize) {
+        if leaf {
+            self.leaves += 1;
+        } else {
+            self.internals += 1;
+        }
+
+        *self.references.entry(references).or_insert(0) += 1;
+    }
+
+    /// Returns the total number of nodes retained.
+    pub fn nodes(&self) -> usize {
+        self.leaves + self.internals
+    }
+
+    /// Returns the number of leaves (i.e., distinct key-value pairs) retained.
+    pub fn leaves(&self) -> usize {
+        self.leaves
+    }
+
+    /// Returns the number of internal nodes retained.
+    pub fn internals(&self) -> usize {
+        self.internals
+    }
+
+    /// Returns, for every reference count, the number of nodes with
+    /// that many references, sorted by reference count.
+    pub fn references(&self) -> &BTreeMap<usize, usize> {
+        &self.references
+    }
+}
@@ -0,0 +1,159 @@
+// Compact encoding of `TableAnswer`s. Unlike `bincode`'s default encoding:
+// - Tags are one byte long, and integers are varint-encoded.
+// - `Leaf` keys and values are sent without their digests, which the
+//   receiving end recomputes.
+// - `MapId`s are sent in full (see `sync::wire` for their `Standard` layout).
+
+use crate::{
+    common::{data::Bytes, store::Field},
+    database::{
+        errors::SyncError,
+        store::{Label, MapId, Node, Wrap},
+    },
+};
+
+use bincode::Options;
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+use serde::de::DeserializeOwned;
+
+use std::io::Read;
+
+use talk::crypto::primitives::hash::HASH_LENGTH;
+
+const EMPTY: u8 = 0;
+const INTERNAL: u8 = 1;
+const LEAF: u8 = 2;
+
+fn options() -> impl Options + Copy {
+    bincode::DefaultOptions::new()
+}
+
+pub(crate) fn encode<Key, Value>(nodes: &[Node<Key, Value>]) -> Vec<u8>
+where
+    Key: Field,
+    Value: Field,
+{
+    let mut buffer = Vec::new();
+    options().serialize_into(&mut buffer, &nodes.len()).unwrap();
+
+    for node in nodes {
+        match node {
+            Node::Empty => buffer.push(EMPTY),
+            Node::Internal(left, right) => {
+                buffer.push(INTERNAL);
+                encode_label(&mut buffer, left);
+                encode_label(&mut buffer, right);
+            }
+            Node::Leaf(key, value) => {
+                buffer.push(LEAF);
+
+                options()
+                    .serialize_into(&mut buffer, key.inner().as_ref())
+                    .unwrap();
+
+                options()
+                    .serialize_into(&mut buffer, value.inner().as_ref())
+                    .unwrap();
+            }
+        }
+    }
+
+    buffer
+}
+
+fn encode_label(buffer: &mut Vec<u8>, label: &Label) {
+    match label {
+        Label::Empty => buffer.push(EMPTY),
+        Label::Internal(map, hash) => {
+            buffer.push(INTERNAL);
+            buffer.extend_from_slice(&map.bytes());
+            buffer.extend_from_slice(&hash.0);
+        }
+        Label::Leaf(map, hash) => {
+            buffer.push(LEAF);
+            buffer.extend_from_slice(&map.bytes());
+            buffer.extend_from_slice(&hash.0);
+        }
+    }
+}
+
+pub(crate) fn decode<Key, Value>(mut bytes: &[u8]) -> Result<Vec<Node<Key, Value>>, Top<SyncError>>
+where
+    Key: Field + DeserializeOwned,
+    Value: Field + DeserializeOwned,
+{
+    // Bounding reads to the length of `bytes` prevents oversized allocations
+    let options = options().with_limit(bytes.len() as u64);
+    let reader = &mut bytes;
+
+    let len: usize = options
+        .deserialize_from(&mut *reader)
+        .map_err(|_| SyncError::MalformedAnswer.into_top())
+        .spot(here!())?;
+    let mut nodes = Vec::with_capacity(len.min(reader.len()));
+
+    for _ in 0..len {
+        let node = match read_byte(reader)? {
+            EMPTY => Node::Empty,
+            INTERNAL => Node::Internal(decode_label(reader)?, decode_label(reader)?),
+            LEAF => {
+                let key: Key = options
+                    .deserialize_from(&mut *reader)
+                    .map_err(|_| SyncError::MalformedAnswer.into_top())
+                    .spot(here!())?;
+                let value: Value = options
+                    .deserialize_from(&mut *reader)
+                    .map_err(|_| SyncError::MalformedAnswer.into_top())
+                    .spot(here!())?;
+
+                Node::Leaf(
+                    Wrap::new(key).pot(SyncError::MalformedAnswer, here!())?,
+                    Wrap::new(value).pot(SyncError::MalformedAnswer, here!())?,
+                )
+            }
+            _ => return SyncError::MalformedAnswer.fail().spot(here!()),
+        };
+
+        nodes.push(node);
+    }
+
+    if !reader.is_empty() {
+        return SyncError::MalformedAnswer.fail().spot(here!());
+    }
+
+    Ok(nodes)
+}
+
+fn decode_label(reader: &mut &[u8]) -> Result<Label, Top<SyncError>> {
+    let kind = read_byte(reader)?;
+
+    if kind == EMPTY {
+        return Ok(Label::Empty);
+    }
+
+    let map = MapId::raw([read_byte(reader)?, read_byte(reader)?]);
+
+    let mut hash = [0u8; HASH_LENGTH];
+    reader
+        .read_exact(&mut hash)
+        .map_err(|_| SyncError::MalformedAnswer.into_top())
+        .spot(here!())?;
+    let hash = Bytes(hash);
+
+    match kind {
+        INTERNAL => Ok(Label::Internal(map, hash)),
+        LEAF => Ok(Label::Leaf(map, hash)),
+        _ => SyncError::MalformedAnswer.fail().spot(here!()),
+    }
+}
+
+fn read_byte(reader: &mut &[u8]) -> Result<u8, Top<SyncError>> {
+    let mut byte = [0u8; 1];
+    reader
+        .read_exact(&mut byte)
+        .map_err(|_| SyncError::MalformedAnswer.into_top())
+        .spot(here!())?;
+    Ok(byte[0])
+}
@@ -3,20 +3,27 @@ use crate::{
         store::Field,
         tree::{Path, Prefix},
     },
-    database::store::{Label, Node, Store},
+    database::{
+        store::{Label, Node, Store},
+        StoreAllocator,
+    },
 };
 
-use std::collections::hash_map::Entry::{Occupied, Vacant};
+use hashbrown::hash_map::Entry::{Occupied, Vacant};
 
 enum Recursion {
     Recur(Label),
     Stop(Label, Label),
 }
 
-fn get_siblings<Key, Value>(store: &mut Store<Key, Value>, label: Label) -> (u8, (Label, Label))
+fn get_siblings<Key, Value, A>(
+    store: &mut Store<Key, Value, A>,
+    label: Label,
+) -> (u8, (Label, Label))
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
     let recursion = match store.entry(label) {
         Occupied(entry) => match entry.get().node {
@@ -39,10 +46,11 @@ where
     }
 }
 
-fn leaf_path<Key, Value>(store: &mut Store<Key, Value>, label: Label) -> Path
+fn leaf_path<Key, Value, A>(store: &mut Store<Key, Value, A>, label: Label) -> Path
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
     match store.entry(label) {
         Occupied(entry) => match &entry.get().node {
@@ -53,13 +61,14 @@ where
     }
 }
 
-pub(crate) fn locate<Key, Value>(store: &mut Store<Key, Value>, label: Label) -> Prefix
+pub(crate) fn locate<Key, Value, A>(store: &mut Store<Key, Value, A>, label: Label) -> Prefix
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
     let (dive, (left, right)) = get_siblings(store, label);
-    let common = Prefix::common(leaf_path(store, left), leaf_path(store, right));
+    let common = Prefix::common(&leaf_path(store, left), &leaf_path(store, right));
     common.ancestor(dive)
 }
 
@@ -2,6 +2,8 @@ mod severity;
 
 pub(crate) const ANSWER_DEPTH: u8 = 2;
 
+pub(crate) mod compact;
 pub(crate) mod locate;
+pub(crate) mod wire;
 
 pub(crate) use severity::Severity;
@@ -1,5 +1,3 @@
-use crate::database::sync::ANSWER_DEPTH;
-
 use std::ops::Add;
 
 #[derive(Debug)]
@@ -34,6 +32,14 @@ impl Severity {
             Severity::Malicious => true,
         }
     }
+
+    // Escalates to `Malicious` past `max_benign_faults` (accumulated) benign faults
+    pub(crate) fn tolerate(self, max_benign_faults: usize) -> Self {
+        match self {
+            Severity::Benign(recidivity) if recidivity > max_benign_faults => Severity::Malicious,
+            severity => severity,
+        }
+    }
 }
 
 impl Add for Severity {
@@ -41,14 +47,7 @@ impl Add for Severity {
 
     fn add(self, rho: Self) -> Self {
         match (self, rho) {
-            (Severity::Benign(left), Severity::Benign(right)) => {
-                let recidivity = left + right;
-                if recidivity > (1 << (ANSWER_DEPTH + 1)) - 2 {
-                    Severity::Malicious
-                } else {
-                    Severity::Benign(left + right)
-                }
-            }
+            (Severity::Benign(left), Severity::Benign(right)) => Severity::Benign(left + right),
             _ => Severity::Malicious,
         }
     }
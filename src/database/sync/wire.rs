@@ -0,0 +1,220 @@
+// Wire layout of the `Label`s exchanged by `TableSender`s and `TableReceiver`s,
+// through `Question`s and (`Standard`-encoded) `TableAnswer`s.
+//
+// `MapId`s used to be 8 bits wide, before `Store`s could be sharded along more
+// than 8 levels. To remain compatible with existing peers, only the first 8
+// bits of each `MapId` are sent: the receiving end widens them back with zeros.
+// Widened labels are never stored: `TableReceiver`s recompute the labels they
+// store, and `Store::lookup` looks widened labels up across all the maps
+// sharing their first 8 bits.
+
+use crate::{
+    common::{data::Bytes, store::Field},
+    database::store::{Label, MapId, Node, Wrap},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Serialize, Deserialize)]
+enum WireLabel {
+    Internal(u8, Bytes),
+    Leaf(u8, Bytes),
+    Empty,
+}
+
+#[derive(Serialize)]
+enum WireNodeRef<'n, Key: Field, Value: Field> {
+    Empty,
+    Internal(WireLabel, WireLabel),
+    Leaf(&'n Wrap<Key>, &'n Wrap<Value>),
+}
+
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "Key: Field + DeserializeOwned, Value: Field + DeserializeOwned"))]
+enum WireNode<Key: Field, Value: Field> {
+    Empty,
+    Internal(WireLabel, WireLabel),
+    Leaf(Wrap<Key>, Wrap<Value>),
+}
+
+impl From<&Label> for WireLabel {
+    fn from(label: &Label) -> Self {
+        match label {
+            Label::Internal(map, hash) => WireLabel::Internal(map.narrow(), *hash),
+            Label::Leaf(map, hash) => WireLabel::Leaf(map.narrow(), *hash),
+            Label::Empty => WireLabel::Empty,
+        }
+    }
+}
+
+impl From<WireLabel> for Label {
+    fn from(label: WireLabel) -> Self {
+        match label {
+            WireLabel::Internal(map, hash) => Label::Internal(MapId::widen(map), hash),
+            WireLabel::Leaf(map, hash) => Label::Leaf(MapId::widen(map), hash),
+            WireLabel::Empty => Label::Empty,
+        }
+    }
+}
+
+// To be used with `#[serde(with = "...")]` on a `Vec<Label>`
+pub(crate) mod labels {
+    use super::*;
+
+    pub(crate) fn serialize<S>(labels: &[Label], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(labels.iter().map(WireLabel::from))
+    }
+
+    pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Label>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let labels = Vec::<WireLabel>::deserialize(deserializer)?;
+        Ok(labels.into_iter().map(Label::from).collect())
+    }
+}
+
+// To be used with `#[serde(with = "...")]` on a `Vec<Node<Key, Value>>`
+pub(crate) mod nodes {
+    use super::*;
+
+    pub(crate) fn serialize<Key, Value, S>(
+        nodes: &[Node<Key, Value>],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        Key: Field,
+        Value: Field,
+        S: Serializer,
+    {
+        serializer.collect_seq(nodes.iter().map(|node| match node {
+            Node::Empty => WireNodeRef::Empty,
+            Node::Internal(left, right) => WireNodeRef::Internal(left.into(), right.into()),
+            Node::Leaf(key, value) => WireNodeRef::Leaf(key, value),
+        }))
+    }
+
+    pub(crate) fn deserialize<'de, Key, Value, D>(
+        deserializer: D,
+    ) -> Result<Vec<Node<Key, Value>>, D::Error>
+    where
+        Key: Field + DeserializeOwned,
+        Value: Field + DeserializeOwned,
+        D: Deserializer<'de>,
+    {
+        let nodes = Vec::<WireNode<Key, Value>>::deserialize(deserializer)?;
+
+        Ok(nodes
+            .into_iter()
+            .map(|node| match node {
+                WireNode::Empty => Node::Empty,
+                WireNode::Internal(left, right) => Node::Internal(left.into(), right.into()),
+                WireNode::Leaf(key, value) => Node::Leaf(key, value),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        common::tree::Prefix,
+        database::{Question, TableAnswer},
+    };
+
+    // `Label` and `Node`, as laid out before `MapId`s were widened
+    #[derive(Serialize)]
+    enum LegacyLabel {
+        Internal(u8, Bytes),
+        Leaf(u8, Bytes),
+        Empty,
+    }
+
+    #[derive(Serialize)]
+    enum LegacyNode {
+        Empty,
+        Internal(LegacyLabel, LegacyLabel),
+        Leaf(Wrap<u32>, Wrap<u32>),
+    }
+
+    fn internal() -> Node<u32, u32> {
+        let leaf = Node::<u32, u32>::Leaf(wrap!(0), wrap!(1));
+        let map = MapId::internal(Prefix::root().left().right());
+
+        Node::Internal(
+            Label::Internal(map, leaf.hash()),
+            Label::Leaf(MapId::leaf(&wrap!(0u32).digest()), leaf.hash()),
+        )
+    }
+
+    #[test]
+    fn legacy_layout() {
+        let node = internal();
+
+        let (left, right) = match &node {
+            Node::Internal(left, right) => (*left, *right),
+            _ => unreachable!(),
+        };
+
+        let answer = TableAnswer(vec![
+            Node::Empty,
+            node,
+            Node::Leaf(wrap!(2u32), wrap!(3u32)),
+        ]);
+
+        let legacy = vec![
+            LegacyNode::Empty,
+            LegacyNode::Internal(
+                LegacyLabel::Internal(left.map().narrow(), left.hash()),
+                LegacyLabel::Leaf(right.map().narrow(), right.hash()),
+            ),
+            LegacyNode::Leaf(wrap!(2u32), wrap!(3u32)),
+        ];
+
+        assert_eq!(
+            bincode::serialize(&answer).unwrap(),
+            bincode::serialize(&legacy).unwrap()
+        );
+
+        let question = Question(vec![left, Label::Empty, right]);
+
+        let legacy = vec![
+            LegacyLabel::Internal(left.map().narrow(), left.hash()),
+            LegacyLabel::Empty,
+            LegacyLabel::Leaf(right.map().narrow(), right.hash()),
+        ];
+
+        assert_eq!(
+            bincode::serialize(&question).unwrap(),
+            bincode::serialize(&legacy).unwrap()
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let answer = TableAnswer(vec![internal()]);
+
+        let bytes = bincode::serialize(&answer).unwrap();
+        let decoded: TableAnswer<u32, u32> = bincode::deserialize(&bytes).unwrap();
+
+        match (&answer.0[0], &decoded.0[0]) {
+            (Node::Internal(left, right), Node::Internal(decoded_left, decoded_right)) => {
+                assert_eq!(decoded_left.hash(), left.hash());
+                assert_eq!(decoded_right.hash(), right.hash());
+
+                assert_eq!(decoded_left.map().narrow(), left.map().narrow());
+                assert_eq!(decoded_right.map().narrow(), right.map().narrow());
+
+                // Only the first 8 bits of each `MapId` go on the wire
+                assert_eq!(decoded_left.map().id(8), left.map().id(8));
+                assert_eq!(decoded_left.map().id(16) & 0xff, 0);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
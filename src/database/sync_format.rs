@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+// Documentation links
+#[allow(unused_imports)]
+use crate::database::{TableAnswer, TableReceiver, TableSender};
+
+/// The wire encoding of a [`TableAnswer`], agreed upon by a [`TableSender`]
+/// and a [`TableReceiver`] before the transfer starts.
+///
+/// The [`TableReceiver`] lists the formats it understands by preference
+/// (see [`TableReceiver::formats`]), the [`TableSender`] picks one
+/// (see [`TableSender::negotiate`]). [`TableAnswer`]s are then encoded with
+/// [`TableAnswer::encode`] and fed to [`TableReceiver::learn_encoded`].
+///
+/// [`TableSender`]: crate::database::TableSender
+/// [`TableReceiver`]: crate::database::TableReceiver
+/// [`TableAnswer`]: crate::database::TableAnswer
+/// [`TableReceiver::formats`]: crate::database::TableReceiver::formats
+/// [`TableSender::negotiate`]: crate::database::TableSender::negotiate
+/// [`TableAnswer::encode`]: crate::database::TableAnswer::encode
+/// [`TableReceiver::learn_encoded`]: crate::database::TableReceiver::learn_encoded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyncFormat {
+    /// `bincode`'s default encoding of the [`TableAnswer`], as obtained by
+    /// serializing it directly.
+    ///
+    /// The layout of serialized [`TableAnswer`]s (and [`Question`]s) does not
+    /// depend on the [`depth`] of either end, and is unchanged since before
+    /// depths were configurable: only the first 8 levels of a node's shard
+    /// are sent, the receiving end looks up the remaining ones.
+    ///
+    /// [`TableAnswer`]: crate::database::TableAnswer
+    /// [`Question`]: crate::database::Question
+    /// [`depth`]: crate::database::DatabaseBuilder::depth
+    #[default]
+    Standard,
+    /// A fixed-layout encoding with one-byte tags and varint-encoded integers.
+    /// Keys and values are sent without their digests, which are recomputed
+    /// upon decoding. Nodes are sent along with their full (16-level) shard.
+    Compact,
+}
+
+impl SyncFormat {
+    /// All formats, by decreasing preference.
+    pub(crate) const SUPPORTED: [SyncFormat; 2] = [SyncFormat::Compact, SyncFormat::Standard];
+}
@@ -1,24 +1,40 @@
 use crate::{
-    common::{data::Bytes, store::Field, tree::Path},
+    common::{
+        data::Bytes,
+        store::Field,
+        tree::{Path, Prefix},
+    },
     database::{
-        errors::QueryError,
-        store::{Cell, Handle, Label},
-        TableResponse, TableSender, TableTransaction,
+        errors::{QueryError, StateError},
+        interact::{Action, Batch, Operation},
+        store::{Cell, Handle, Label, Wrap},
+        table_state,
+        table_transaction::Expiry,
+        Database, DiffResult, Expiries, Global, Prefer, StoreAllocator, TableBatch, TableResponse,
+        TableSender, TableTransaction, TransitionProof,
     },
     map::Map,
 };
 
-use doomstack::{here, ResultExt, Top};
+use doomstack::{here, Doom, ResultExt, Top};
 
 use oh_snap::Snap;
 
-use std::{borrow::Borrow, collections::HashMap, hash::Hash as StdHash};
+use rayon::prelude::*;
+
+use std::{
+    borrow::Borrow, collections::HashMap, fs::File, hash::Hash as StdHash, io::BufWriter,
+    path::Path as FsPath, sync::Arc,
+};
 
 use talk::crypto::primitives::{hash, hash::Hash};
 
+#[cfg(feature = "tokio")]
+use tokio_stream::{wrappers::WatchStream, Stream};
+
 // Documentation links
 #[allow(unused_imports)]
-use crate::database::{Database, TableReceiver};
+use crate::database::TableReceiver;
 
 /// A map implemented using Merkle Patricia Trees.
 ///
@@ -35,28 +51,140 @@ use crate::database::{Database, TableReceiver};
 /// [`TableSender`]: crate::database::TableSender
 /// [`TableReceiver`]: crate::database::TableReceiver
 
-pub struct Table<Key: Field, Value: Field>(Handle<Key, Value>);
+pub struct Table<Key: Field, Value: Field, A: StoreAllocator = Global> {
+    handle: Handle<Key, Value, A>,
+    expiries: Arc<Expiries>,
+}
 
-impl<Key, Value> Table<Key, Value>
+impl<Key, Value, A> Table<Key, Value, A>
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
-    pub(crate) fn empty(cell: Cell<Key, Value>) -> Self {
-        Table(Handle::empty(cell))
+    pub(crate) fn empty(cell: Cell<Key, Value, A>) -> Self {
+        Table::from_handle(Handle::empty(cell))
     }
 
-    pub(crate) fn new(cell: Cell<Key, Value>, root: Label) -> Self {
-        Table(Handle::new(cell, root))
+    pub(crate) fn new(cell: Cell<Key, Value, A>, root: Label) -> Self {
+        Table::from_handle(Handle::new(cell, root))
     }
 
-    pub(crate) fn from_handle(handle: Handle<Key, Value>) -> Self {
-        Table(handle)
+    pub(crate) fn from_handle(handle: Handle<Key, Value, A>) -> Self {
+        Table {
+            handle,
+            expiries: Arc::new(Expiries::new()),
+        }
     }
 
     /// Returns a cryptographic commitment to the contents of the `Table`.
     pub fn commit(&self) -> Hash {
-        self.0.commit()
+        self.handle.commit()
+    }
+
+    /// Returns a [`Stream`] of the `Table`'s commitments, yielding the new
+    /// commitment (see [`commit`]) every time the `Table` is modified.
+    ///
+    /// The [`Stream`] only yields the latest commitment: if the `Table` is
+    /// modified several times before the [`Stream`] is polled, intermediate
+    /// commitments are skipped. The [`Stream`] ends when the `Table` is
+    /// dropped (or sent).
+    ///
+    /// [`Stream`]: tokio_stream::Stream
+    /// [`commit`]: crate::database::Table::commit
+    #[cfg(feature = "tokio")]
+    pub fn watch(&self) -> impl Stream<Item = Hash> {
+        WatchStream::from_changes(self.handle.watch.subscribe())
+    }
+
+    /// Returns the number of bytes the `Table` would take once fully
+    /// exported (see [`export`]) and serialized with `bincode`, without
+    /// exporting or serializing it. This allows to decide, e.g., whether
+    /// to send the `Table` at once or to paginate it.
+    ///
+    /// The size of each record is computed with `bincode::serialized_size`.
+    ///
+    /// [`export`]: crate::database::Table::export
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::{Database, TableTransaction};
+    ///
+    /// let database: Database<u32, u32> = Database::new();
+    /// let mut table = database.empty_table();
+    ///
+    /// let mut transaction = TableTransaction::new();
+    /// transaction.set(0, 0).unwrap();
+    /// table.execute(transaction);
+    ///
+    /// let export = table.export([0]).unwrap();
+    /// assert_eq!(table.serialized_size(), bincode::serialize(&export).unwrap().len());
+    /// ```
+    pub fn serialized_size(&self) -> usize {
+        self.handle.serialized_size()
+    }
+
+    /// Returns an iterator over the keys of the `Table`, in no particular order.
+    ///
+    /// The `Table` is snapshotted upon calling `keys`: its store is held only
+    /// while its (reference-counted) records are collected, and each key is
+    /// cloned only when the iterator reaches it. This keeps the other `Table`s
+    /// of the [`Database`] accessible while iterating, at the cost of a pass
+    /// over the whole `Table` upfront.
+    ///
+    /// [`Database`]: crate::database::Database
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::{Database, TableTransaction};
+    ///
+    /// let database: Database<u32, u32> = Database::new();
+    /// let mut table = database.empty_table();
+    ///
+    /// let mut transaction = TableTransaction::new();
+    /// transaction.set(1, 2).unwrap();
+    /// table.execute(transaction);
+    ///
+    /// assert_eq!(table.keys().collect::<Vec<_>>(), vec![1]);
+    /// ```
+    pub fn keys(&self) -> impl Iterator<Item = Key>
+    where
+        Key: Clone,
+    {
+        self.records()
+            .into_iter()
+            .map(|(key, _)| (**key.inner()).clone())
+    }
+
+    /// Returns an iterator over the values of the `Table`, in no particular
+    /// order. Like [`keys`], the `Table` is snapshotted upon calling `values`.
+    ///
+    /// [`keys`]: crate::database::Table::keys
+    pub fn values(&self) -> impl Iterator<Item = Value>
+    where
+        Value: Clone,
+    {
+        self.records()
+            .into_iter()
+            .map(|(_, value)| (**value.inner()).clone())
+    }
+
+    pub(crate) fn records(&self) -> Vec<(Wrap<Key>, Wrap<Value>)> {
+        self.handle.records()
+    }
+
+    pub(crate) fn belongs_to(&self, cell: &Cell<Key, Value, A>) -> bool {
+        Arc::ptr_eq(&self.handle.cell, cell)
+    }
+
+    pub(crate) fn merge(
+        lho: &Table<Key, Value, A>,
+        rho: &Table<Key, Value, A>,
+        prefer: Prefer,
+    ) -> Self {
+        Table::from_handle(Handle::merge(&lho.handle, &rho.handle, prefer))
     }
 
     /// Executes a [`TableTransaction`] returning a [`TableResponse`]
@@ -96,219 +224,875 @@ where
         &mut self,
         transaction: TableTransaction<Key, Value>,
     ) -> TableResponse<Key, Value> {
-        let (tid, batch) = transaction.finalize();
-        let batch = self.0.apply(batch);
+        let (tid, batch, expiries) = transaction.finalize();
+        let batch = self.handle.apply(batch);
+
+        self.update_expiries(expiries);
+
         TableResponse::new(tid, batch)
     }
 
-    pub fn export<I, K>(&mut self, keys: I) -> Result<Map<Key, Value>, Top<QueryError>>
-    // TODO: Decide if a `QueryError` is appropriate here
+    /// Executes a [`TableTransaction`] like [`execute`], also returning a
+    /// [`TransitionProof`] that the `Table`'s commitment moved from its value
+    /// before the transaction to its value after the transaction.
+    ///
+    /// [`TableTransaction`]: crate::database::TableTransaction
+    /// [`execute`]: crate::database::Table::execute
+    /// [`TransitionProof`]: crate::database::TransitionProof
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::{Database, TableTransaction};
+    ///
+    /// let database: Database<u32, u32> = Database::new();
+    /// let mut table = database.empty_table();
+    ///
+    /// let pre = table.commit();
+    ///
+    /// let mut transaction = TableTransaction::new();
+    /// transaction.set(0, 1).unwrap();
+    ///
+    /// let mut copy = TableTransaction::new();
+    /// copy.set(0, 1).unwrap();
+    ///
+    /// let (_, proof) = table.apply_and_prove(transaction);
+    ///
+    /// assert!(proof.verify(pre, &copy).is_ok());
+    /// assert_eq!(proof.post(), table.commit());
+    /// ```
+    pub fn apply_and_prove(
+        &mut self,
+        transaction: TableTransaction<Key, Value>,
+    ) -> (TableResponse<Key, Value>, TransitionProof<Key, Value>)
     where
         Key: Clone,
         Value: Clone,
-        I: IntoIterator<Item = K>,
-        K: Borrow<Key>,
     {
-        let paths: Result<Vec<Path>, Top<QueryError>> = keys
-            .into_iter()
-            .map(|key| {
-                hash::hash(key.borrow())
-                    .pot(QueryError::HashError, here!())
-                    .map(|digest| Path::from(Bytes::from(digest)))
-            })
+        let pre = self.commit();
+
+        let mut paths: Vec<Path> = transaction
+            .operations()
+            .iter()
+            .filter(|operation| !matches!(operation.action, Action::Get(..)))
+            .map(|operation| operation.path)
             .collect();
 
-        let mut paths = paths?;
         paths.sort();
-        let paths = Snap::new(paths);
 
-        let root = self.0.export(paths);
-        Ok(Map::raw(root))
-    }
+        let witness = Map::raw(self.handle.export_with_siblings(Snap::new(paths)));
 
-    pub fn diff(
-        lho: &mut Table<Key, Value>,
-        rho: &mut Table<Key, Value>,
-    ) -> HashMap<Key, (Option<Value>, Option<Value>)>
-    where
-        Key: Clone + Eq + StdHash,
-        Value: Clone + Eq,
-    {
-        Handle::diff(&mut lho.0, &mut rho.0)
+        let response = self.execute(transaction);
+        let proof = TransitionProof::new(pre, self.commit(), witness);
+
+        (response, proof)
     }
 
-    /// Transforms the table into a [`TableSender`], preparing it for sending to
-    /// to a [`TableReceiver`] of another [`Database`]. For details on how to use
-    /// Senders and Receivers check their respective documentation.
+    /// Returns a copy of the value associated with `key`, if any.
+    ///
+    /// This is a shorthand for executing a [`TableTransaction`] with
+    /// a single [`get`]. Like [`execute`], it requires `&mut self`, as the
+    /// `Table`'s store is taken for the duration of the read.
+    ///
+    /// # Errors
+    ///
+    /// If `key` cannot be hashed, [`HashError`] is returned.
+    ///
+    /// [`TableTransaction`]: crate::database::TableTransaction
+    /// [`get`]: crate::database::TableTransaction::get
+    /// [`execute`]: crate::database::Table::execute
+    /// [`HashError`]: crate::database::errors::QueryError::HashError
+    ///
+    /// # Examples
+    ///
     /// ```
-    /// use zebra::database::Database;
+    /// use zebra::database::{Database, TableTransaction};
     ///
-    /// let mut database: Database<u32, u32> = Database::new();
-    /// let original = database.empty_table();
+    /// let database: Database<u32, u32> = Database::new();
+    /// let mut table = database.empty_table();
     ///
-    /// // Sending consumes the copy so we typically clone first, which is cheap.
-    /// let copy = original.clone();
-    /// let sender = copy.send();
+    /// let mut transaction = TableTransaction::new();
+    /// transaction.set(0, 1).unwrap();
+    /// table.execute(transaction);
     ///
-    /// // Use sender...
+    /// assert_eq!(table.get(&0).unwrap(), Some(1));
+    /// assert_eq!(table.get(&1).unwrap(), None);
     /// ```
-    pub fn send(self) -> TableSender<Key, Value> {
-        TableSender::from_handle(self.0)
-    }
-}
-
-impl<Key, Value> Clone for Table<Key, Value>
-where
-    Key: Field,
-    Value: Field,
-{
-    fn clone(&self) -> Self {
-        Table(self.0.clone())
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    use rand::seq::IteratorRandom;
-
-    use std::{fmt::Debug, hash::Hash};
-
-    impl<Key, Value> Table<Key, Value>
+    pub fn get(&mut self, key: &Key) -> Result<Option<Value>, Top<QueryError>>
     where
-        Key: Field,
-        Value: Field,
+        Value: Clone,
     {
-        pub(crate) fn root(&self) -> Label {
-            self.0.root
-        }
-
-        pub(crate) fn check_tree(&self) {
-            let mut store = self.0.cell.take();
-            store.check_tree(self.0.root);
-            self.0.cell.restore(store);
-        }
+        let operation = Operation::get(key).pot(QueryError::HashError, here!())?;
+        let batch = self.handle.apply(Batch::new(vec![operation]));
 
-        pub(crate) fn assert_records<I>(&self, reference: I)
-        where
-            Key: Debug + Clone + Eq + Hash,
-            Value: Debug + Clone + Eq + Hash,
-            I: IntoIterator<Item = (Key, Value)>,
-        {
-            let mut store = self.0.cell.take();
-            store.assert_records(self.0.root, reference);
-            self.0.cell.restore(store);
+        match batch.unwrap().pop().map(|operation| operation.action) {
+            Some(Action::Get(holder)) => Ok(holder.map(|value| (*value).clone())),
+            _ => unreachable!(),
         }
     }
 
-    #[test]
-    fn export_empty() {
-        let database: Database<u32, u32> = Database::new();
-        let mut table = database.empty_table();
-
-        let map = table.export::<[u32; 0], u32>([]).unwrap(); // Explicit type arguments are to aid type inference on an empty array
-
-        map.check_tree();
-        map.assert_records([]);
-
-        table.check_tree();
-        table.assert_records([]);
+    /// Applies a [`TableBatch`] (or a part of one, see [`TableBatch::snap`]),
+    /// returning it with its reads filled in. Merging all executed parts
+    /// of a [`TableBatch`] (see [`TableBatch::merge`]) yields the same
+    /// [`TableResponse`] as [`execute`]-ing the original [`TableTransaction`].
+    ///
+    /// The part is applied right away, and sequentially (see [`execute`] to
+    /// process a transaction in parallel): the `Table` reflects the
+    /// transaction only partially until all its parts are executed.
+    ///
+    /// [`TableBatch`]: crate::database::TableBatch
+    /// [`TableBatch::snap`]: crate::database::TableBatch::snap
+    /// [`TableBatch::merge`]: crate::database::TableBatch::merge
+    /// [`TableResponse`]: crate::database::TableResponse
+    /// [`TableTransaction`]: crate::database::TableTransaction
+    /// [`execute`]: crate::database::Table::execute
+    pub fn execute_batch(&mut self, mut batch: TableBatch<Key, Value>) -> TableBatch<Key, Value> {
+        let expiries = batch.take_expiries();
+        let (tid, batch) = batch.unwrap();
+
+        let batch = self.handle.apply(batch);
+        self.update_expiries(expiries);
+
+        TableBatch::new(tid, batch, Vec::new())
     }
 
-    #[test]
-    fn export_none() {
-        let database: Database<u32, u32> = Database::new();
-        let mut table = database.empty_table();
-
-        let mut transaction = TableTransaction::new();
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            transaction.set(key, value).unwrap();
-        }
-
-        table.execute(transaction);
-
-        let map = table.export::<[u32; 0], u32>([]).unwrap(); // Explicit type arguments are to aid type inference on an empty array
+    /// Executes a [`TableTransaction`] like [`execute`], invoking `on_each`
+    /// with the `Table`'s commitment after each of its writes (sets and
+    /// removals), in the order they were added to the transaction.
+    ///
+    /// Reads are not steps on their own, and observe the same values
+    /// as they would under [`execute`].
+    ///
+    /// Intermediate commitments are obtained by applying each write separately,
+    /// which forgoes the parallelism [`execute`] achieves on large transactions:
+    /// prefer [`execute`] unless intermediate commitments are needed.
+    ///
+    /// [`execute`]: crate::database::Table::execute
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::{Database, TableTransaction};
+    ///
+    /// let database: Database<u32, u32> = Database::new();
+    /// let mut table = database.empty_table();
+    ///
+    /// let mut transaction = TableTransaction::new();
+    /// transaction.set(0, 0).unwrap();
+    /// transaction.set(1, 1).unwrap();
+    ///
+    /// let mut commitments = Vec::new();
+    /// table.execute_observed(transaction, |commitment| commitments.push(commitment));
+    ///
+    /// assert_eq!(commitments.len(), 2);
+    /// assert_eq!(commitments[1], table.commit());
+    /// ```
+    pub fn execute_observed<F>(
+        &mut self,
+        transaction: TableTransaction<Key, Value>,
+        mut on_each: F,
+    ) -> TableResponse<Key, Value>
+    where
+        F: FnMut(Hash),
+    {
+        let (tid, operations, expiries) = transaction.unwrap();
 
-        map.check_tree();
-        map.assert_records([]);
+        let mut applied = Vec::with_capacity(operations.len());
+        let mut step = Vec::new();
 
-        table.check_tree();
-        table.assert_records((0..1024).map(|i| (i, i)));
-    }
+        for operation in operations {
+            let write = !matches!(operation.action, Action::Get(..));
+            step.push(operation);
 
-    #[test]
-    fn export_single() {
-        let database: Database<u32, u32> = Database::new();
-        let mut table = database.empty_table();
+            if write {
+                let batch = self.handle.apply(Batch::new(step));
+                applied.extend(batch.unwrap());
+                step = Vec::new();
 
-        let mut transaction = TableTransaction::new();
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            transaction.set(key, value).unwrap();
+                on_each(self.commit());
+            }
         }
 
-        table.execute(transaction);
-
-        let map = table.export([33]).unwrap();
+        if !step.is_empty() {
+            let batch = self.handle.apply(Batch::new(step));
+            applied.extend(batch.unwrap());
+        }
 
-        map.check_tree();
-        map.assert_records([(33, 33)]);
+        self.update_expiries(expiries);
 
-        table.check_tree();
-        table.assert_records((0..1024).map(|i| (i, i)));
+        TableResponse::new(tid, Batch::new(applied))
     }
 
-    #[test]
-    fn export_half() {
-        let database: Database<u32, u32> = Database::new();
-        let mut table = database.empty_table();
+    fn update_expiries(&mut self, expiries: Vec<Expiry>) {
+        if expiries.iter().any(|(_, epoch)| epoch.is_some()) || !self.expiries.is_empty() {
+            let table_expiries = Arc::make_mut(&mut self.expiries);
 
-        let mut transaction = TableTransaction::new();
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            transaction.set(key, value).unwrap();
+            for (path, epoch) in expiries {
+                table_expiries.set(path, epoch);
+            }
         }
-
-        table.execute(transaction);
-
-        let map = table.export(0..512).unwrap();
-        map.check_tree();
-        map.assert_records((0..512).map(|i| (i, i)));
-
-        table.check_tree();
-        table.assert_records((0..1024).map(|i| (i, i)));
     }
 
-    #[test]
-    fn export_all() {
-        let database: Database<u32, u32> = Database::new();
-        let mut table = database.empty_table();
-
-        let mut transaction = TableTransaction::new();
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            transaction.set(key, value).unwrap();
+    /// Removes, in a single batch, all records whose expiry epoch
+    /// (see [`TableTransaction::set_with_ttl`]) is `now` or earlier.
+    /// Returns the number of records removed.
+    ///
+    /// [`TableTransaction::set_with_ttl`]: crate::database::TableTransaction::set_with_ttl
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::{Database, TableTransaction};
+    ///
+    /// let database: Database<u32, u32> = Database::new();
+    /// let mut table = database.empty_table();
+    ///
+    /// let mut transaction = TableTransaction::new();
+    /// transaction.set_with_ttl(0, 0, 10).unwrap();
+    /// transaction.set(1, 1).unwrap();
+    /// table.execute(transaction);
+    ///
+    /// assert_eq!(table.expire(9), 0);
+    /// assert_eq!(table.expire(10), 1);
+    /// ```
+    pub fn expire(&mut self, now: u64) -> usize {
+        if self.expiries.is_empty() {
+            return 0;
         }
 
-        table.execute(transaction);
-
-        let map = table.export(0..1024).unwrap();
-        map.check_tree();
-        map.assert_records((0..1024).map(|i| (i, i)));
+        let expired = Arc::make_mut(&mut self.expiries).expire(now);
 
-        table.check_tree();
-        table.assert_records((0..1024).map(|i| (i, i)));
-    }
+        if expired.is_empty() {
+            return 0;
+        }
 
-    #[test]
-    fn diff_empty_empty() {
-        let database: Database<u32, u32> = Database::new();
+        let count = expired.len();
 
-        let mut lho = database.empty_table();
-        let mut rho = database.empty_table();
+        let operations = expired.into_iter().map(Operation::remove_path).collect();
+        self.handle.apply(Batch::new(operations));
 
-        assert_eq!(Table::diff(&mut lho, &mut rho), HashMap::new());
+        count
     }
 
-    #[test]
+    /// Removes all records from the `Table`, along with their expiry epochs.
+    ///
+    /// Unlike executing a [`TableTransaction`] that removes every record,
+    /// `clear` does not require the keys of the `Table` to be known. Nodes
+    /// no longer referenced by any `Table` are dropped from the [`Database`],
+    /// while nodes shared with other `Table`s are left untouched.
+    ///
+    /// [`TableTransaction`]: crate::database::TableTransaction
+    /// [`Database`]: crate::database::Database
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::Database;
+    ///
+    /// let database: Database<u32, u32> = Database::new();
+    ///
+    /// let mut table = database.table_with_records((0..16).map(|i| (i, i)));
+    /// let copy = table.clone();
+    ///
+    /// table.clear();
+    ///
+    /// assert_eq!(table.commit(), database.empty_table().commit());
+    /// assert_ne!(copy.commit(), table.commit());
+    /// ```
+    pub fn clear(&mut self) {
+        self.handle.clear();
+        self.expiries = Arc::new(Expiries::new());
+    }
+
+    /// Removes all records from the `Table`, along with their expiry epochs,
+    /// retaining the capacity of its [`Database`]'s maps.
+    ///
+    /// If the `Table` is the only one holding nodes in its [`Database`], the
+    /// maps of the [`Database`] are emptied at once, keeping their allocated
+    /// capacity: re-populating the `Table` with a similar number of records
+    /// does not cause the [`Database`] to reallocate. Otherwise (or if the
+    /// [`Database`] was [`open`]ed on a [`Backend`]), `clear_keep_capacity`
+    /// behaves like [`clear`]. Unlike [`Database::compact`], no capacity is
+    /// ever released.
+    ///
+    /// [`clear`]: crate::database::Table::clear
+    /// [`Database`]: crate::database::Database
+    /// [`open`]: crate::database::Database::open
+    /// [`Backend`]: crate::database::Backend
+    /// [`Database::compact`]: crate::database::Database::compact
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::{Database, TableTransaction};
+    ///
+    /// let database: Database<u32, u32> = Database::new();
+    /// let mut table = database.empty_table();
+    ///
+    /// let mut transaction = TableTransaction::new();
+    /// transaction.set(0, 0).unwrap();
+    /// table.execute(transaction);
+    ///
+    /// table.clear_keep_capacity();
+    /// assert_eq!(table.commit(), database.empty_table().commit());
+    /// ```
+    pub fn clear_keep_capacity(&mut self) {
+        self.handle.clear_keep_capacity();
+        self.expiries = Arc::new(Expiries::new());
+    }
+
+    /// Exports the branches of the `Table` along the paths of `keys` to
+    /// a [`Map`], whose other branches are replaced by their digests.
+    ///
+    /// # Errors
+    ///
+    /// If a key cannot be hashed, [`KeyHashError`] is returned, carrying the
+    /// position of the first such key in `keys` (along with the error that
+    /// prevented hashing it).
+    ///
+    /// [`Map`]: crate::map::Map
+    /// [`KeyHashError`]: crate::database::errors::QueryError::KeyHashError
+    pub fn export<I, K>(&mut self, keys: I) -> Result<Map<Key, Value>, Top<QueryError>>
+    // TODO: Decide if a `QueryError` is appropriate here
+    where
+        Key: Clone,
+        Value: Clone,
+        I: IntoIterator<Item = K>,
+        K: Borrow<Key>,
+    {
+        let paths: Result<Vec<Path>, Top<QueryError>> = keys
+            .into_iter()
+            .enumerate()
+            .map(|(index, key)| {
+                hash::hash(key.borrow())
+                    .map(|digest| Path::from(Bytes::from(digest)))
+                    .map_err(|source| QueryError::KeyHashError { index, source }.into_top())
+                    .spot(here!())
+            })
+            .collect();
+
+        let mut paths = paths?;
+        paths.sort();
+        let paths = Snap::new(paths);
+
+        let root = self.handle.export(paths);
+        Ok(Map::raw(root))
+    }
+
+    /// Like [`export`], but hashes and sorts `keys` in parallel. The
+    /// branches of the `Table` are then extracted concurrently, one task
+    /// per shard of the [`Database`] (on its thread pool, if any).
+    /// The result is identical to that of [`export`]: this is meant for
+    /// exporting large numbers of keys.
+    ///
+    /// # Errors
+    ///
+    /// If a key cannot be hashed, [`KeyHashError`] is returned, carrying the
+    /// position of the first such key in `keys` (as with [`export`]).
+    ///
+    /// [`export`]: crate::database::Table::export
+    /// [`Database`]: crate::database::Database
+    /// [`KeyHashError`]: crate::database::errors::QueryError::KeyHashError
+    pub fn export_parallel<I, K>(&mut self, keys: I) -> Result<Map<Key, Value>, Top<QueryError>>
+    where
+        Key: Clone,
+        Value: Clone,
+        I: IntoParallelIterator<Item = K>,
+        K: Borrow<Key>,
+    {
+        let digests = keys
+            .into_par_iter()
+            .map(|key| hash::hash(key.borrow()))
+            .collect::<Vec<_>>();
+
+        // `collect` preserves the order of `keys`: the first key that cannot
+        // be hashed is identified as in `export`
+        let mut paths = digests
+            .into_iter()
+            .enumerate()
+            .map(|(index, digest)| {
+                digest
+                    .map(|digest| Path::from(Bytes::from(digest)))
+                    .map_err(|source| QueryError::KeyHashError { index, source }.into_top())
+                    .spot(here!())
+            })
+            .collect::<Result<Vec<Path>, Top<QueryError>>>()?;
+
+        paths.par_sort_unstable();
+        let paths = Snap::new(paths);
+
+        let root = self.handle.export_parallel(paths);
+        Ok(Map::raw(root))
+    }
+
+    pub fn diff(
+        lho: &mut Table<Key, Value, A>,
+        rho: &mut Table<Key, Value, A>,
+    ) -> HashMap<Key, (Option<Value>, Option<Value>)>
+    where
+        Key: Clone + Eq + StdHash,
+        Value: Clone + Eq,
+    {
+        Handle::diff(&mut lho.handle, &mut rho.handle)
+    }
+
+    /// Diffs the `Table` against `map`, which might be only partially
+    /// known (e.g., an export received from a remote peer).
+    ///
+    /// Subtrees with matching commitments are skipped. Where the `Table`
+    /// differs from a stub of `map`, the location of the stub is reported
+    /// as unknown, and the records of the `Table` under it are not reported
+    /// as changes (as `map` might hold them as well).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::{Database, TableTransaction};
+    ///
+    /// let database: Database<u32, u32> = Database::new();
+    ///
+    /// let mut transaction = TableTransaction::new();
+    /// transaction.set(0, 0).unwrap();
+    /// transaction.set(1, 1).unwrap();
+    ///
+    /// let mut table = database.empty_table();
+    /// table.execute(transaction);
+    ///
+    /// let map = table.export([0]).unwrap();
+    /// let diff = table.diff_partial(&map);
+    ///
+    /// assert!(diff.changes.is_empty());
+    /// assert!(diff.unknown_prefixes.is_empty());
+    /// ```
+    pub fn diff_partial(&self, map: &Map<Key, Value>) -> DiffResult<Key, Value>
+    where
+        Key: Clone + Eq + StdHash,
+        Value: Clone + Eq,
+    {
+        self.handle.diff_partial(map.root())
+    }
+
+    /// Returns the commitment to the subtree of the `Table` at `location`,
+    /// or `None` if the `Table` has no node at `location` (i.e., `location`
+    /// lies below a leaf or an empty subtree).
+    ///
+    /// This is the counterpart of [`diff_remote`], used to answer a remote
+    /// peer's queries.
+    ///
+    /// [`diff_remote`]: crate::database::Table::diff_remote
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::{database::Database, tree::Prefix};
+    ///
+    /// let database: Database<u32, u32> = Database::new();
+    /// let table = database.empty_table();
+    ///
+    /// assert_eq!(table.commitment_at(Prefix::root()), Some(table.commit()));
+    /// assert_eq!(table.commitment_at(Prefix::root().left()), None);
+    /// ```
+    pub fn commitment_at(&self, location: Prefix) -> Option<Hash> {
+        self.handle.commitment_at(location)
+    }
+
+    /// Compares the `Table` against a remote table that is only accessible
+    /// through `remote`, which returns the commitment to the remote subtree
+    /// at a given location (see [`commitment_at`]).
+    ///
+    /// `remote` is queried prefix by prefix, descending only into subtrees
+    /// whose commitments differ. The locations where the two tables diverge
+    /// and the descent stops are returned: every record that differs
+    /// between the two tables lies under one of them.
+    ///
+    /// [`commitment_at`]: crate::database::Table::commitment_at
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::{Database, TableTransaction};
+    ///
+    /// let database: Database<u32, u32> = Database::new();
+    ///
+    /// let local = database.empty_table();
+    /// let mut remote = database.empty_table();
+    ///
+    /// assert!(local.diff_remote(|location| remote.commitment_at(location)).is_empty());
+    ///
+    /// let mut transaction = TableTransaction::new();
+    /// transaction.set(0, 0).unwrap();
+    /// remote.execute(transaction);
+    ///
+    /// assert_eq!(local.diff_remote(|location| remote.commitment_at(location)).len(), 1);
+    /// ```
+    pub fn diff_remote<F>(&self, remote: F) -> Vec<Prefix>
+    where
+        F: Fn(Prefix) -> Option<Hash>,
+    {
+        self.handle.diff_remote(remote)
+    }
+
+    /// Writes the records of the `Table`, along with its commitment, to a
+    /// `.zstate` file at `path`, which [`Database::read_state`] loads back.
+    ///
+    /// A `.zstate` file is laid out as follows (integers are little-endian):
+    ///
+    /// | Bytes | Content |
+    /// |-------|---------|
+    /// | 6     | Magic number, `ZSTATE` in ASCII |
+    /// | 2     | Format version, currently `1` |
+    /// | 32    | Commitment of the `Table` (see [`commit`]) |
+    /// | 8     | Number of records |
+    /// | ...   | Records, each a `bincode`-serialized `(Key, Value)` pair |
+    ///
+    /// Expiry epochs are not written.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be created or written, [`OpenFailed`] or
+    /// [`WriteFailed`] is returned.
+    ///
+    /// If a record cannot be serialized, [`SerializeFailed`] is returned.
+    ///
+    /// [`Database::read_state`]: crate::database::Database::read_state
+    /// [`commit`]: crate::database::Table::commit
+    /// [`OpenFailed`]: crate::database::errors::StateError::OpenFailed
+    /// [`WriteFailed`]: crate::database::errors::StateError::WriteFailed
+    /// [`SerializeFailed`]: crate::database::errors::StateError::SerializeFailed
+    pub fn write_state<P>(&self, path: P) -> Result<(), Top<StateError>>
+    where
+        P: AsRef<FsPath>,
+    {
+        let file = File::create(path)
+            .map_err(|_| StateError::OpenFailed.into_top())
+            .spot(here!())?;
+
+        table_state::write(BufWriter::new(file), self.commit(), &self.records())
+    }
+
+    /// Serializes the records of the `Table`, along with its commitment,
+    /// in the `.zstate` format (see [`write_state`]). The bytes are loaded
+    /// back by [`Database::table_from_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// If a record cannot be serialized, [`SerializeFailed`] is returned.
+    ///
+    /// [`write_state`]: crate::database::Table::write_state
+    /// [`Database::table_from_bytes`]: crate::database::Database::table_from_bytes
+    /// [`SerializeFailed`]: crate::database::errors::StateError::SerializeFailed
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Top<StateError>> {
+        let mut bytes = Vec::new();
+        table_state::write(&mut bytes, self.commit(), &self.records())?;
+
+        Ok(bytes)
+    }
+
+    /// Builds a new [`Table`] in `database`, holding the records of the
+    /// `Table` transformed by `f`. As the keys change, the new [`Table`]
+    /// shares neither structure nor commitment with the `Table`.
+    ///
+    /// Expiry epochs are not carried over.
+    ///
+    /// # Errors
+    ///
+    /// If a transformed key or value cannot be hashed, [`HashError`] is
+    /// returned.
+    ///
+    /// If `f` maps two records to the same key, [`DuplicateOperation`]
+    /// is returned.
+    ///
+    /// [`Table`]: crate::database::Table
+    /// [`HashError`]: crate::database::errors::QueryError::HashError
+    /// [`DuplicateOperation`]: crate::database::errors::QueryError::DuplicateOperation
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::{Database, TableTransaction};
+    ///
+    /// let database: Database<u32, u32> = Database::new();
+    /// let mut table = database.empty_table();
+    ///
+    /// let mut transaction = TableTransaction::new();
+    /// transaction.set(1, 2).unwrap();
+    /// table.execute(transaction);
+    ///
+    /// let target: Database<String, u64> = Database::new();
+    /// let mut remapped = table
+    ///     .remap(&target, |key, value| (key.to_string(), *value as u64))
+    ///     .unwrap();
+    ///
+    /// let mut transaction = TableTransaction::new();
+    /// let query = transaction.get(&"1".to_string()).unwrap();
+    /// let response = remapped.execute(transaction);
+    ///
+    /// assert_eq!(response.get(&query), Some(&2));
+    /// ```
+    pub fn remap<K2, V2, F>(
+        &self,
+        database: &Database<K2, V2>,
+        f: F,
+    ) -> Result<Table<K2, V2>, Top<QueryError>>
+    where
+        K2: Field,
+        V2: Field,
+        F: Fn(&Key, &Value) -> (K2, V2),
+    {
+        let mut transaction = TableTransaction::new();
+
+        for (key, value) in self.records() {
+            let (key, value) = f(key.inner(), value.inner());
+            transaction.set(key, value)?;
+        }
+
+        let mut table = database.empty_table();
+        table.execute(transaction);
+
+        Ok(table)
+    }
+
+    /// Transforms the table into a [`TableSender`], preparing it for sending to
+    /// to a [`TableReceiver`] of another [`Database`]. For details on how to use
+    /// Senders and Receivers check their respective documentation.
+    /// Expiry epochs are not sent.
+    /// ```
+    /// use zebra::database::Database;
+    ///
+    /// let mut database: Database<u32, u32> = Database::new();
+    /// let original = database.empty_table();
+    ///
+    /// // Sending consumes the copy so we typically clone first, which is cheap.
+    /// let copy = original.clone();
+    /// let sender = copy.send();
+    ///
+    /// // Use sender...
+    /// ```
+    pub fn send(self) -> TableSender<Key, Value, A> {
+        TableSender::from_handle(self.handle)
+    }
+}
+
+impl<Key, Value, A> Clone for Table<Key, Value, A>
+where
+    Key: Field,
+    Value: Field,
+    A: StoreAllocator,
+{
+    fn clone(&self) -> Self {
+        Table {
+            handle: self.handle.clone(),
+            expiries: self.expiries.clone(),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+mod checks {
+    use super::*;
+
+    use std::{fmt::Debug, hash::Hash};
+
+    impl<Key, Value, A> Table<Key, Value, A>
+    where
+        Key: Field,
+        Value: Field,
+        A: StoreAllocator,
+    {
+        pub(crate) fn root(&self) -> Label {
+            self.handle.root
+        }
+
+        /// Panics if the `Table`'s tree violates compactness, or if any
+        /// of its leaves lies outside of its key path.
+        pub fn check_tree(&self) {
+            let mut store = self.handle.cell.take();
+            store.check_tree(self.handle.root);
+            self.handle.cell.restore(store);
+        }
+
+        /// Panics if the records stored in the `Table` differ from `reference`.
+        pub fn assert_records<I>(&self, reference: I)
+        where
+            Key: Debug + Clone + Eq + Hash,
+            Value: Debug + Clone + Eq + Hash,
+            I: IntoIterator<Item = (Key, Value)>,
+        {
+            let mut store = self.handle.cell.take();
+            store.assert_records(self.handle.root, reference);
+            self.handle.cell.restore(store);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::database::{errors::TransitionError, Query, QueryOutcome};
+
+    use rand::seq::IteratorRandom;
+
+    #[cfg(feature = "tokio")]
+    use tokio_stream::StreamExt;
+
+    use std::collections::HashSet;
+
+    #[test]
+    fn export_empty() {
+        let database: Database<u32, u32> = Database::new();
+        let mut table = database.empty_table();
+
+        let map = table.export::<[u32; 0], u32>([]).unwrap(); // Explicit type arguments are to aid type inference on an empty array
+
+        map.check_tree();
+        map.assert_records([]);
+
+        table.check_tree();
+        table.assert_records([]);
+    }
+
+    #[test]
+    fn export_none() {
+        let database: Database<u32, u32> = Database::new();
+        let mut table = database.empty_table();
+
+        let mut transaction = TableTransaction::new();
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            transaction.set(key, value).unwrap();
+        }
+
+        table.execute(transaction);
+
+        let map = table.export::<[u32; 0], u32>([]).unwrap(); // Explicit type arguments are to aid type inference on an empty array
+
+        map.check_tree();
+        map.assert_records([]);
+
+        table.check_tree();
+        table.assert_records((0..1024).map(|i| (i, i)));
+    }
+
+    #[test]
+    fn export_single() {
+        let database: Database<u32, u32> = Database::new();
+        let mut table = database.empty_table();
+
+        let mut transaction = TableTransaction::new();
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            transaction.set(key, value).unwrap();
+        }
+
+        table.execute(transaction);
+
+        let map = table.export([33]).unwrap();
+
+        map.check_tree();
+        map.assert_records([(33, 33)]);
+
+        table.check_tree();
+        table.assert_records((0..1024).map(|i| (i, i)));
+    }
+
+    #[test]
+    fn export_half() {
+        let database: Database<u32, u32> = Database::new();
+        let mut table = database.empty_table();
+
+        let mut transaction = TableTransaction::new();
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            transaction.set(key, value).unwrap();
+        }
+
+        table.execute(transaction);
+
+        let map = table.export(0..512).unwrap();
+        map.check_tree();
+        map.assert_records((0..512).map(|i| (i, i)));
+
+        table.check_tree();
+        table.assert_records((0..1024).map(|i| (i, i)));
+    }
+
+    #[test]
+    fn export_all() {
+        let database: Database<u32, u32> = Database::new();
+        let mut table = database.empty_table();
+
+        let mut transaction = TableTransaction::new();
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            transaction.set(key, value).unwrap();
+        }
+
+        table.execute(transaction);
+
+        let map = table.export(0..1024).unwrap();
+        map.check_tree();
+        map.assert_records((0..1024).map(|i| (i, i)));
+
+        table.check_tree();
+        table.assert_records((0..1024).map(|i| (i, i)));
+    }
+
+    #[test]
+    fn export_hash_error() {
+        use serde::{ser::Error, Serialize, Serializer};
+
+        // A key that fails to serialize (hence, to hash) if equal to `u32::MAX`
+        #[derive(Clone)]
+        struct Faulty(u32);
+
+        impl Serialize for Faulty {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                if self.0 == u32::MAX {
+                    Err(S::Error::custom("faulty key"))
+                } else {
+                    self.0.serialize(serializer)
+                }
+            }
+        }
+
+        let database: Database<Faulty, u32> = Database::new();
+
+        let mut table = database.table_with_records((0..64).map(|i| (Faulty(i), i)));
+
+        let keys = (0..64).map(|i| Faulty(if i == 13 || i == 42 { u32::MAX } else { i }));
+
+        let error = table.export(keys.clone()).map(|_| ()).unwrap_err();
+
+        assert!(matches!(
+            error.top(),
+            QueryError::KeyHashError { index: 13, .. }
+        ));
+
+        let keys = keys.collect::<Vec<_>>();
+        let error = table
+            .export_parallel(keys.par_iter())
+            .map(|_| ())
+            .unwrap_err();
+
+        assert!(matches!(
+            error.top(),
+            QueryError::KeyHashError { index: 13, .. }
+        ));
+
+        assert!(table.export((0..64).map(Faulty)).is_ok());
+    }
+
+    #[test]
+    fn diff_empty_empty() {
+        let database: Database<u32, u32> = Database::new();
+
+        let mut lho = database.empty_table();
+        let mut rho = database.empty_table();
+
+        assert_eq!(Table::diff(&mut lho, &mut rho), HashMap::new());
+    }
+
+    #[test]
     fn diff_identity_empty() {
         let database: Database<u32, u32> = Database::new();
 
@@ -320,292 +1104,1038 @@ mod tests {
             transaction.set(key, value).unwrap();
         }
 
-        lho.execute(transaction);
+        lho.execute(transaction);
+
+        let diff = Table::diff(&mut lho, &mut rho);
+
+        for key in 0..1024 {
+            assert_eq!(diff[&key], (Some(key), None));
+        }
+
+        let (mut lho, mut rho) = (rho, lho);
+        let diff = Table::diff(&mut lho, &mut rho);
+
+        for key in 0..1024 {
+            assert_eq!(diff[&key], (None, Some(key)));
+        }
+    }
+
+    #[test]
+    fn diff_identity_match() {
+        let database: Database<u32, u32> = Database::new();
+
+        let mut lho = database.empty_table();
+        let mut rho = database.empty_table();
+
+        let mut transaction = TableTransaction::new();
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            transaction.set(key, value).unwrap();
+        }
+
+        lho.execute(transaction);
+
+        let mut transaction = TableTransaction::new();
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            transaction.set(key, value).unwrap();
+        }
+
+        rho.execute(transaction);
+
+        let diff = Table::diff(&mut lho, &mut rho);
+        assert_eq!(diff, HashMap::new());
+
+        let (mut lho, mut rho) = (rho, lho);
+        let diff = Table::diff(&mut lho, &mut rho);
+        assert_eq!(diff, HashMap::new());
+    }
+
+    #[test]
+    fn diff_identity_successor() {
+        let database: Database<u32, u32> = Database::new();
+
+        let mut lho = database.empty_table();
+        let mut rho = database.empty_table();
+
+        let mut transaction = TableTransaction::new();
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            transaction.set(key, value).unwrap();
+        }
+
+        lho.execute(transaction);
+
+        let mut transaction = TableTransaction::new();
+        for (key, value) in (0..1024).map(|i| (i, i + 1)) {
+            transaction.set(key, value).unwrap();
+        }
+
+        rho.execute(transaction);
+
+        let diff = Table::diff(&mut lho, &mut rho);
+
+        for key in 0..1024 {
+            assert_eq!(diff[&key], (Some(key), Some(key + 1)));
+        }
+
+        let (mut lho, mut rho) = (rho, lho);
+        let diff = Table::diff(&mut lho, &mut rho);
+
+        for key in 0..1024 {
+            assert_eq!(diff[&key], (Some(key + 1), Some(key)));
+        }
+    }
+
+    #[test]
+    fn export_parallel() {
+        let database: Database<u32, u32> = Database::builder().threads(4).build().unwrap();
+        let mut table = database.table_with_records((0..1 << 14).map(|i| (i, i)));
+
+        // Present and absent keys, in no particular order, with duplicates
+        let keys: Vec<u32> = (0..1 << 13)
+            .map(|i| (i * 7919) % (1 << 15))
+            .chain(0..16)
+            .collect();
+
+        let sequential = table.export(keys.iter()).unwrap();
+        let parallel = table.export_parallel(keys.par_iter()).unwrap();
+
+        assert_eq!(parallel.commit(), table.commit());
+        assert_eq!(parallel.commit(), sequential.commit());
+
+        assert_eq!(
+            bincode::serialize(&parallel).unwrap(),
+            bincode::serialize(&sequential).unwrap()
+        );
+
+        database.check([&table], []);
+    }
+
+    #[test]
+    fn remap() {
+        let source: Database<u32, u32> = Database::new();
+        let target: Database<String, u64> = Database::new();
+
+        let table = source.table_with_records((0..256).map(|i| (i, i)));
+
+        let remapped = table
+            .remap(&target, |key, value| {
+                (format!("key_{}", key), (*value as u64) << 32)
+            })
+            .unwrap();
+
+        remapped.assert_records((0..256).map(|i| (format!("key_{}", i), (i as u64) << 32)));
+
+        source.check([&table], []);
+        target.check([&remapped], []);
+
+        // Remapping two records to the same key fails
+        assert!(matches!(
+            table
+                .remap(&target, |key, value| (
+                    format!("key_{}", key / 2),
+                    *value as u64
+                ))
+                .err()
+                .unwrap()
+                .top(),
+            QueryError::DuplicateOperation { .. }
+        ));
+
+        target.check([&remapped], []);
+    }
+
+    #[test]
+    fn diff_partial() {
+        let database: Database<u32, u32> = Database::new();
+
+        let mut lho = database.table_with_records((0..256).map(|i| (i, i)));
+        let mut rho = database.table_with_records(
+            (0..200)
+                .map(|i| (i, if i < 16 { i + 1 } else { i }))
+                .chain((256..300).map(|i| (i, i))),
+        );
+
+        let full = Table::diff(&mut lho, &mut rho);
+
+        // Half-export of `rho`, proving the absence of the removed records
+        let map = rho.export((0..128).chain(200..256)).unwrap();
+        let diff = lho.diff_partial(&map);
+
+        assert!(!diff.unknown_prefixes.is_empty());
+
+        let unknown = |key: u32| {
+            let path = Path::from(Bytes::from(hash::hash(&key).unwrap()));
+
+            diff.unknown_prefixes
+                .iter()
+                .any(|location| location.contains(&path))
+        };
+
+        // Every reported change is a change, not under an unknown prefix
+        for (key, change) in diff.changes.iter() {
+            assert_eq!(full.get(key), Some(change));
+            assert!(!unknown(*key));
+        }
+
+        // Every unreported change lies under an unknown prefix
+        for key in full.keys() {
+            assert!(diff.changes.contains_key(key) || unknown(*key));
+        }
+
+        // Changes to exported keys are all known
+        for key in 0..16 {
+            assert_eq!(diff.changes[&key], (Some(key), Some(key + 1)));
+        }
+
+        for key in 200..256 {
+            assert_eq!(diff.changes[&key], (Some(key), None));
+        }
+
+        // Against a complete `Map`, the diff is the full diff
+        let map = rho.export((0..300).collect::<Vec<_>>()).unwrap();
+        let diff = lho.diff_partial(&map);
+
+        assert!(diff.unknown_prefixes.is_empty());
+        assert_eq!(diff.changes, full);
+    }
+
+    #[test]
+    fn diff_first_identity_match_rest_successor() {
+        let database: Database<u32, u32> = Database::new();
+
+        let mut lho = database.empty_table();
+        let mut rho = database.empty_table();
+
+        let mut transaction = TableTransaction::new();
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            transaction.set(key, value).unwrap();
+        }
+
+        lho.execute(transaction);
+
+        let mut transaction = TableTransaction::new();
+        transaction.set(0, 0).unwrap();
+
+        for (key, value) in (1..1024).map(|i| (i, i + 1)) {
+            transaction.set(key, value).unwrap();
+        }
+
+        rho.execute(transaction);
+
+        let diff = Table::diff(&mut lho, &mut rho);
+
+        for key in 0..1024 {
+            if key == 0 {
+                assert_eq!(diff.get(&key), None);
+            } else {
+                assert_eq!(diff[&key], (Some(key), Some(key + 1)));
+            }
+        }
+
+        let (mut lho, mut rho) = (rho, lho);
+        let diff = Table::diff(&mut lho, &mut rho);
+
+        for key in 0..1024 {
+            if key == 0 {
+                assert_eq!(diff.get(&key), None);
+            } else {
+                assert_eq!(diff[&key], (Some(key + 1), Some(key)));
+            }
+        }
+    }
+
+    #[test]
+    fn diff_half_identity_match_half_successor() {
+        let database: Database<u32, u32> = Database::new();
+
+        let mut lho = database.empty_table();
+        let mut rho = database.empty_table();
+
+        let mut transaction = TableTransaction::new();
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            transaction.set(key, value).unwrap();
+        }
+
+        lho.execute(transaction);
+
+        let mut transaction = TableTransaction::new();
+
+        for (key, value) in (0..512).map(|i| (i, i)) {
+            transaction.set(key, value).unwrap();
+        }
+
+        for (key, value) in (512..1024).map(|i| (i, i + 1)) {
+            transaction.set(key, value).unwrap();
+        }
+
+        rho.execute(transaction);
+
+        let diff = Table::diff(&mut lho, &mut rho);
+
+        for key in 0..1024 {
+            if key < 512 {
+                assert_eq!(diff.get(&key), None);
+            } else {
+                assert_eq!(diff[&key], (Some(key), Some(key + 1)));
+            }
+        }
+
+        let (mut lho, mut rho) = (rho, lho);
+        let diff = Table::diff(&mut lho, &mut rho);
+
+        for key in 0..1024 {
+            if key < 512 {
+                assert_eq!(diff.get(&key), None);
+            } else {
+                assert_eq!(diff[&key], (Some(key + 1), Some(key)));
+            }
+        }
+    }
+
+    #[test]
+    fn diff_identity_overlap() {
+        let database: Database<u32, u32> = Database::new();
+
+        let mut lho = database.empty_table();
+        let mut rho = database.empty_table();
+
+        let mut transaction = TableTransaction::new();
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            transaction.set(key, value).unwrap();
+        }
+
+        lho.execute(transaction);
+
+        let mut transaction = TableTransaction::new();
+
+        for (key, value) in (512..1536).map(|i| (i, i)) {
+            transaction.set(key, value).unwrap();
+        }
+
+        rho.execute(transaction);
+
+        let diff = Table::diff(&mut lho, &mut rho);
+
+        for key in 0..1536 {
+            if key < 512 {
+                assert_eq!(diff[&key], (Some(key), None));
+            } else if key < 1024 {
+                assert_eq!(diff.get(&key), None);
+            } else {
+                assert_eq!(diff[&key], (None, Some(key)));
+            }
+        }
+
+        let (mut lho, mut rho) = (rho, lho);
+        let diff = Table::diff(&mut lho, &mut rho);
+
+        for key in 0..1536 {
+            if key < 512 {
+                assert_eq!(diff[&key], (None, Some(key)));
+            } else if key < 1024 {
+                assert_eq!(diff.get(&key), None);
+            } else {
+                assert_eq!(diff[&key], (Some(key), None));
+            }
+        }
+    }
+
+    #[test]
+    fn diff_remote() {
+        let database: Database<u32, u32> = Database::new();
+
+        let mut lho = database.table_with_records((0..1024).map(|i| (i, i)));
+
+        let mut rho = database.table_with_records(
+            (0..1024)
+                .filter(|key| key % 100 != 7)
+                .map(|key| {
+                    if key % 50 == 3 {
+                        (key, key + 1)
+                    } else {
+                        (key, key)
+                    }
+                })
+                .chain((1024..1040).map(|i| (i, i))),
+        );
+
+        let divergent = lho.diff_remote(|location| rho.commitment_at(location));
+        let diff = Table::diff(&mut lho, &mut rho);
+
+        assert!(!divergent.is_empty());
+
+        let paths: Vec<Path> = diff
+            .keys()
+            .map(|key| Path::from(hash::hash(key).unwrap()))
+            .collect();
+
+        for path in paths.iter() {
+            assert!(divergent.iter().any(|prefix| prefix.contains(path)));
+        }
+
+        for prefix in divergent.iter() {
+            assert!(paths.iter().any(|path| prefix.contains(path)));
+        }
+
+        assert!(lho
+            .diff_remote(|location| lho.commitment_at(location))
+            .is_empty());
+    }
+
+    #[test]
+    #[ignore]
+    fn diff_stress() {
+        enum Set {
+            Identity,
+            Successor,
+            Empty,
+        }
+
+        const SETS: &[Set] = &[Set::Identity, Set::Successor, Set::Empty];
+
+        let database: Database<u32, u32> = Database::new();
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..512 {
+            let mut lho = database.empty_table();
+            let mut rho = database.empty_table();
+            let mut diff_reference = HashMap::new();
+
+            let mut lho_transaction = TableTransaction::new();
+            let mut rho_transaction = TableTransaction::new();
+
+            for key in 0..512 {
+                let lho_set = SETS.iter().choose(&mut rng).unwrap();
+                let rho_set = SETS.iter().choose(&mut rng).unwrap();
+
+                match lho_set {
+                    Set::Identity => lho_transaction.set(key, key).unwrap(),
+                    Set::Successor => lho_transaction.set(key, key + 1).unwrap(),
+                    Set::Empty => (),
+                }
+
+                match rho_set {
+                    Set::Identity => rho_transaction.set(key, key).unwrap(),
+                    Set::Successor => rho_transaction.set(key, key + 1).unwrap(),
+                    Set::Empty => (),
+                }
+
+                match (lho_set, rho_set) {
+                    (Set::Identity, Set::Successor) => {
+                        diff_reference.insert(key, (Some(key), Some(key + 1)));
+                    }
+                    (Set::Identity, Set::Empty) => {
+                        diff_reference.insert(key, (Some(key), None));
+                    }
+                    (Set::Successor, Set::Identity) => {
+                        diff_reference.insert(key, (Some(key + 1), Some(key)));
+                    }
+                    (Set::Successor, Set::Empty) => {
+                        diff_reference.insert(key, (Some(key + 1), None));
+                    }
+                    (Set::Empty, Set::Identity) => {
+                        diff_reference.insert(key, (None, Some(key)));
+                    }
+                    (Set::Empty, Set::Successor) => {
+                        diff_reference.insert(key, (None, Some(key + 1)));
+                    }
+                    _ => {}
+                }
+            }
+
+            lho.execute(lho_transaction);
+            rho.execute(rho_transaction);
+
+            assert_eq!(Table::diff(&mut lho, &mut rho), diff_reference);
+        }
+    }
+
+    #[test]
+    fn remove_expecting() {
+        let database: Database<u32, u32> = Database::new();
+        let mut table = database.table_with_records((0..256).map(|i| (i, i)));
+
+        let mut transaction = TableTransaction::new();
+
+        let queries: Vec<(u32, Query)> = (128..384)
+            .map(|key| (key, transaction.remove_expecting(&key).unwrap()))
+            .collect();
+
+        let response = table.execute(transaction);
+
+        for (key, query) in queries {
+            assert_eq!(response.removed(&query), key < 256);
+        }
+
+        table.check_tree();
+        table.assert_records((0..128).map(|i| (i, i)));
+
+        let mut transaction = TableTransaction::new();
+        let query = transaction.remove_expecting(&0).unwrap();
+        let response = table.execute(transaction);
+
+        assert!(response.removed(&query));
+
+        let mut transaction = TableTransaction::new();
+        let query = transaction.remove_expecting(&0).unwrap();
+        let response = table.execute(transaction);
+
+        assert!(!response.removed(&query));
+
+        database.check([&table], []);
+    }
+
+    #[test]
+    fn outcome() {
+        let database: Database<u32, u32> = Database::new();
+        let mut table = database.table_with_records((0..128).map(|i| (i, i + 1)));
+
+        let mut transaction = TableTransaction::new();
+
+        let queries: Vec<(u32, Query)> = (64..192)
+            .map(|key| (key, transaction.get(&key).unwrap()))
+            .collect();
+
+        let removal = transaction.remove_expecting(&0).unwrap();
+        let response = table.execute(transaction);
+
+        for (key, query) in queries.iter() {
+            if *key < 128 {
+                assert_eq!(response.outcome(query), QueryOutcome::Found(&(key + 1)));
+                assert_eq!(response.get(query), Some(&(key + 1)));
+            } else {
+                assert_eq!(response.outcome(query), QueryOutcome::Absent);
+                assert_eq!(response.get(query), None);
+            }
+        }
+
+        // A removal is not a read
+        assert_eq!(response.outcome(&removal), QueryOutcome::NotQueried);
+        assert_eq!(response.get(&removal), None);
+        assert!(response.removed(&removal));
+
+        // Neither is a `Query` from another transaction
+        let mut transaction = TableTransaction::new();
+        let foreign = transaction.get(&64).unwrap();
+
+        assert_eq!(response.outcome(&foreign), QueryOutcome::NotQueried);
+        assert_eq!(response.get(&foreign), None);
+
+        let response = table.execute(transaction);
+        assert_eq!(response.outcome(&foreign), QueryOutcome::Found(&65));
+        assert_eq!(response.outcome(&queries[0].1), QueryOutcome::NotQueried);
+    }
+
+    #[test]
+    fn clear_shared() {
+        let database: Database<u32, u32> = Database::new();
+
+        let mut lho = database.table_with_records((0..1024).map(|i| (i, i)));
+
+        let mut rho = lho.clone();
+
+        let mut transaction = TableTransaction::new();
+
+        for key in 0..16 {
+            transaction.set(key, key + 1).unwrap();
+        }
+
+        rho.execute(transaction);
+
+        lho.clear();
+
+        assert_eq!(lho.commit(), database.empty_table().commit());
+
+        lho.check_tree();
+        lho.assert_records([]);
+
+        rho.check_tree();
+        rho.assert_records((0..1024).map(|i| (i, if i < 16 { i + 1 } else { i })));
+
+        database.check([&lho, &rho], []);
+
+        rho.clear();
+
+        rho.check_tree();
+        rho.assert_records([]);
+
+        database.check([&lho, &rho], []);
+    }
+
+    #[test]
+    fn clear_keep_capacity() {
+        let database: Database<u32, u32> = Database::new();
+
+        let mut lho = database.table_with_records((0..1024).map(|i| (i, i)));
+        let rho = lho.clone();
+
+        // Nodes shared with `rho` are left untouched
+        lho.clear_keep_capacity();
+
+        lho.assert_records([]);
+        rho.assert_records((0..1024).map(|i| (i, i)));
+
+        database.check([&lho, &rho], []);
+
+        drop(rho);
+
+        let mut table = database.table_with_records((0..1024).map(|i| (i, i)));
+
+        let capacity = {
+            let store = database.store.take();
+            let capacity = store.capacity();
+            database.store.restore(store);
+            capacity
+        };
+
+        // `table` is alone: the maps of the `Database` are emptied at once
+        table.clear_keep_capacity();
+
+        assert_eq!(table.commit(), database.empty_table().commit());
+        table.assert_records([]);
+
+        let store = database.store.take();
+        assert_eq!(store.size(), 0);
+        assert_eq!(store.capacity(), capacity);
+        database.store.restore(store);
+
+        database.check([&lho, &table], []);
+    }
+
+    #[test]
+    fn execute_merged() {
+        let database: Database<u32, u32> = Database::new();
+
+        let original = database.table_with_records((0..256).map(|i| (i, i)));
+
+        let transactions = || {
+            let mut first = TableTransaction::new();
+            let mut second = TableTransaction::new();
+
+            for key in 0..128 {
+                first.set(key, key + 1).unwrap();
+            }
+
+            let query = first.get(&200).unwrap();
+
+            for key in 128..192 {
+                second.remove(&key).unwrap();
+            }
+
+            for key in 256..320 {
+                second.set(key, key).unwrap();
+            }
+
+            (first, second, query)
+        };
+
+        let mut sequential = original.clone();
+        let (first, second, _) = transactions();
+
+        sequential.execute(first);
+        sequential.execute(second);
+
+        let mut merged = original.clone();
+        let (first, second, query) = transactions();
+
+        let response = merged.execute(first.merge(second).unwrap());
+
+        assert_eq!(response.get(&query), Some(&200));
+        assert_eq!(merged.commit(), sequential.commit());
+
+        merged.check_tree();
+        merged.assert_records(
+            (0..128)
+                .map(|i| (i, i + 1))
+                .chain((192..320).map(|i| (i, i))),
+        );
+
+        database.check([&original, &sequential, &merged], []);
+    }
+
+    #[test]
+    fn execute_observed() {
+        let database: Database<u32, u32> = Database::new();
+        let mut table = database.table_with_records([(0, 0), (1, 1)]);
+
+        let mut transaction = TableTransaction::new();
+
+        transaction.set(2, 2).unwrap();
+        let read = transaction.get(&1).unwrap();
+        transaction.remove(&0).unwrap();
+        transaction.set(1, 2).unwrap_err(); // Duplicate operation on the read key
+        transaction.set(3, 3).unwrap();
+        let removed = transaction.remove_expecting(&4).unwrap();
+
+        let mut observed = Vec::new();
+        let response = table.execute_observed(transaction, |commitment| observed.push(commitment));
+
+        let mut reference: Map<u32, u32> = Map::new();
+        reference.insert(0, 0).unwrap();
+        reference.insert(1, 1).unwrap();
+
+        let mut expected = Vec::new();
+
+        reference.insert(2, 2).unwrap();
+        expected.push(reference.commit());
+
+        reference.remove(&0).unwrap();
+        expected.push(reference.commit());
+
+        reference.insert(3, 3).unwrap();
+        expected.push(reference.commit());
 
-        let diff = Table::diff(&mut lho, &mut rho);
+        reference.remove(&4).unwrap();
+        expected.push(reference.commit());
 
-        for key in 0..1024 {
-            assert_eq!(diff[&key], (Some(key), None));
-        }
+        assert_eq!(observed, expected);
+        assert_eq!(table.commit(), reference.commit());
 
-        let (mut lho, mut rho) = (rho, lho);
-        let diff = Table::diff(&mut lho, &mut rho);
+        assert_eq!(response.get(&read), Some(&1));
+        assert!(!response.removed(&removed));
 
-        for key in 0..1024 {
-            assert_eq!(diff[&key], (None, Some(key)));
-        }
+        table.check_tree();
+        table.assert_records([(1, 1), (2, 2), (3, 3)]);
+        database.check([&table], []);
     }
 
     #[test]
-    fn diff_identity_match() {
+    fn execute_batch() {
         let database: Database<u32, u32> = Database::new();
 
-        let mut lho = database.empty_table();
-        let mut rho = database.empty_table();
+        let mut split = database.table_with_records((0..128).map(|i| (i, i)));
+        let mut whole = split.clone();
 
-        let mut transaction = TableTransaction::new();
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            transaction.set(key, value).unwrap();
+        let build = || {
+            let mut transaction = TableTransaction::new();
+
+            for key in 0..64 {
+                transaction.remove(&key).unwrap();
+            }
+
+            for key in 128..192 {
+                transaction.set_with_ttl(key, key, 10).unwrap();
+            }
+
+            let reads = (64..128)
+                .chain(192..256)
+                .map(|key| (key, transaction.get(&key).unwrap()))
+                .collect::<Vec<_>>();
+
+            (transaction, reads)
+        };
+
+        let (transaction, reads) = build();
+        let response = whole.execute(transaction);
+
+        for (key, query) in reads {
+            assert_eq!(
+                response.get(&query),
+                if key < 128 { Some(&key) } else { None }
+            );
         }
 
-        lho.execute(transaction);
+        let (transaction, reads) = build();
+        let batch = transaction.into_batch();
+        assert_eq!(batch.len(), 256);
 
-        let mut transaction = TableTransaction::new();
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            transaction.set(key, value).unwrap();
+        let (left, right) = batch.snap(100);
+        let (left_left, left_right) = left.snap(50);
+
+        let left_right = split.execute_batch(left_right);
+        let right = split.execute_batch(right);
+        let left_left = split.execute_batch(left_left);
+
+        let left = TableBatch::merge(left_left, left_right);
+        let response = TableBatch::merge(left, right).into_response();
+
+        for (key, query) in reads {
+            assert_eq!(
+                response.get(&query),
+                if key < 128 { Some(&key) } else { None }
+            );
         }
 
-        rho.execute(transaction);
+        assert_eq!(split.commit(), whole.commit());
 
-        let diff = Table::diff(&mut lho, &mut rho);
-        assert_eq!(diff, HashMap::new());
+        // Expiry epochs are preserved across halves
+        assert_eq!(split.expire(10), 64);
+        assert_eq!(whole.expire(10), 64);
+        assert_eq!(split.commit(), whole.commit());
 
-        let (mut lho, mut rho) = (rho, lho);
-        let diff = Table::diff(&mut lho, &mut rho);
-        assert_eq!(diff, HashMap::new());
+        database.check([&split, &whole], []);
     }
 
     #[test]
-    fn diff_identity_successor() {
+    fn expire() {
         let database: Database<u32, u32> = Database::new();
-
-        let mut lho = database.empty_table();
-        let mut rho = database.empty_table();
+        let mut table = database.empty_table();
 
         let mut transaction = TableTransaction::new();
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            transaction.set(key, value).unwrap();
+
+        for key in 0..256 {
+            if key < 192 {
+                transaction
+                    .set_with_ttl(key, key, (key % 3) as u64)
+                    .unwrap();
+            } else {
+                transaction.set(key, key).unwrap();
+            }
         }
 
-        lho.execute(transaction);
+        table.execute(transaction);
 
+        // Setting a record without a TTL clears its expiry epoch
         let mut transaction = TableTransaction::new();
-        for (key, value) in (0..1024).map(|i| (i, i + 1)) {
-            transaction.set(key, value).unwrap();
+
+        for key in (0..192).filter(|key| key % 4 == 0) {
+            transaction.set(key, key + 1).unwrap();
         }
 
-        rho.execute(transaction);
+        table.execute(transaction);
 
-        let diff = Table::diff(&mut lho, &mut rho);
+        // Records still live after expiring up to `now` (all records if `now` is `None`)
+        let live = |now: Option<u32>| {
+            (0..256).filter_map(move |key| {
+                if key < 192 && key % 4 == 0 {
+                    Some((key, key + 1))
+                } else if key >= 192 || !matches!(now, Some(now) if key % 3 <= now) {
+                    Some((key, key))
+                } else {
+                    None
+                }
+            })
+        };
 
-        for key in 0..1024 {
-            assert_eq!(diff[&key], (Some(key), Some(key + 1)));
-        }
+        let snapshot = table.clone();
 
-        let (mut lho, mut rho) = (rho, lho);
-        let diff = Table::diff(&mut lho, &mut rho);
+        assert_eq!(table.expire(0), live(None).count() - live(Some(0)).count());
+        table.check_tree();
+        table.assert_records(live(Some(0)));
 
-        for key in 0..1024 {
-            assert_eq!(diff[&key], (Some(key + 1), Some(key)));
-        }
+        assert_eq!(table.expire(0), 0);
+
+        assert_eq!(
+            table.expire(2),
+            live(Some(0)).count() - live(Some(2)).count()
+        );
+        table.check_tree();
+        table.assert_records(live(Some(2)));
+
+        // Commitments only depend on live records
+        let reference = database.table_with_records(live(Some(2)));
+        assert_eq!(table.commit(), reference.commit());
+
+        // Clones expire independently
+        snapshot.assert_records(live(None));
+
+        let mut snapshot = snapshot;
+        snapshot.expire(1);
+        snapshot.assert_records(live(Some(1)));
+
+        database.check([&table, &snapshot, &reference], []);
     }
 
     #[test]
-    fn diff_first_identity_match_rest_successor() {
+    fn get() {
         let database: Database<u32, u32> = Database::new();
+        let mut table = database.table_with_records((0..256).map(|i| (i, i + 1)));
 
-        let mut lho = database.empty_table();
-        let mut rho = database.empty_table();
+        let before = table.commit();
 
-        let mut transaction = TableTransaction::new();
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            transaction.set(key, value).unwrap();
+        for key in 0..512 {
+            let expected = if key < 256 { Some(key + 1) } else { None };
+            assert_eq!(table.get(&key).unwrap(), expected);
         }
 
-        lho.execute(transaction);
+        assert_eq!(table.commit(), before);
 
-        let mut transaction = TableTransaction::new();
-        transaction.set(0, 0).unwrap();
+        // Reads leave reference counts untouched, including on shared trees
+        let clone = table.clone();
+        assert_eq!(table.get(&0).unwrap(), Some(1));
 
-        for (key, value) in (1..1024).map(|i| (i, i + 1)) {
-            transaction.set(key, value).unwrap();
-        }
+        database.check([&table, &clone], []);
+    }
 
-        rho.execute(transaction);
+    #[test]
+    fn serialized_size() {
+        let database: Database<u32, String> = Database::new();
 
-        let diff = Table::diff(&mut lho, &mut rho);
+        let mut table = database.empty_table();
+        let export = table.export::<[u32; 0], u32>([]).unwrap();
 
-        for key in 0..1024 {
-            if key == 0 {
-                assert_eq!(diff.get(&key), None);
-            } else {
-                assert_eq!(diff[&key], (Some(key), Some(key + 1)));
-            }
-        }
+        assert_eq!(
+            table.serialized_size(),
+            bincode::serialize(&export).unwrap().len()
+        );
 
-        let (mut lho, mut rho) = (rho, lho);
-        let diff = Table::diff(&mut lho, &mut rho);
+        let mut table =
+            database.table_with_records((0..1024).map(|i| (i, "x".repeat(i as usize % 16))));
 
-        for key in 0..1024 {
-            if key == 0 {
-                assert_eq!(diff.get(&key), None);
-            } else {
-                assert_eq!(diff[&key], (Some(key + 1), Some(key)));
-            }
-        }
+        let export = table.export(0..1024).unwrap();
+        let actual = bincode::serialize(&export).unwrap().len();
+
+        // The estimate is exact for `bincode`'s default encoding
+        assert_eq!(table.serialized_size(), actual);
+
+        database.check([&table], []);
     }
 
     #[test]
-    fn diff_half_identity_match_half_successor() {
+    fn apply_and_prove() {
         let database: Database<u32, u32> = Database::new();
+        let mut table = database.table_with_records((0..256).map(|i| (i, i)));
 
-        let mut lho = database.empty_table();
-        let mut rho = database.empty_table();
+        let transaction = || {
+            let mut transaction = TableTransaction::new();
 
-        let mut transaction = TableTransaction::new();
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            transaction.set(key, value).unwrap();
-        }
+            for key in 0..16 {
+                transaction.set(key, key + 1).unwrap();
+            }
 
-        lho.execute(transaction);
+            for key in 100..150 {
+                transaction.remove(&key).unwrap();
+            }
 
-        let mut transaction = TableTransaction::new();
+            for key in 256..288 {
+                transaction.set(key, key).unwrap();
+            }
 
-        for (key, value) in (0..512).map(|i| (i, i)) {
-            transaction.set(key, value).unwrap();
-        }
+            transaction.get(&200).unwrap();
+            transaction
+        };
 
-        for (key, value) in (512..1024).map(|i| (i, i + 1)) {
-            transaction.set(key, value).unwrap();
-        }
+        let pre = table.commit();
+        let (_, proof) = table.apply_and_prove(transaction());
 
-        rho.execute(transaction);
+        let expected = (0..16)
+            .map(|i| (i, i + 1))
+            .chain((16..100).map(|i| (i, i)))
+            .chain((150..288).map(|i| (i, i)));
 
-        let diff = Table::diff(&mut lho, &mut rho);
+        let reference = database.table_with_records(expected);
 
-        for key in 0..1024 {
-            if key < 512 {
-                assert_eq!(diff.get(&key), None);
-            } else {
-                assert_eq!(diff[&key], (Some(key), Some(key + 1)));
-            }
-        }
+        assert_eq!(proof.pre(), pre);
+        assert_eq!(proof.post(), reference.commit());
+        assert_eq!(proof.post(), table.commit());
 
-        let (mut lho, mut rho) = (rho, lho);
-        let diff = Table::diff(&mut lho, &mut rho);
+        proof.verify(pre, &transaction()).unwrap();
 
-        for key in 0..1024 {
-            if key < 512 {
-                assert_eq!(diff.get(&key), None);
-            } else {
-                assert_eq!(diff[&key], (Some(key + 1), Some(key)));
-            }
-        }
+        let serialized = bincode::serialize(&proof).unwrap();
+        let deserialized: TransitionProof<u32, u32> = bincode::deserialize(&serialized).unwrap();
+
+        deserialized.verify(pre, &transaction()).unwrap();
+
+        assert!(matches!(
+            proof
+                .verify(reference.commit(), &transaction())
+                .unwrap_err()
+                .top(),
+            TransitionError::PreMismatch
+        ));
+
+        let mut other = transaction();
+        other.set(16, 0).unwrap();
+
+        assert!(matches!(
+            proof.verify(pre, &other).unwrap_err().top(),
+            TransitionError::WitnessIncomplete
+        ));
+
+        let mut other = TableTransaction::new();
+        other.set(0, 0).unwrap();
+
+        assert!(matches!(
+            proof.verify(pre, &other).unwrap_err().top(),
+            TransitionError::PostMismatch
+        ));
+
+        database.check([&table, &reference], []);
     }
 
     #[test]
-    fn diff_identity_overlap() {
+    fn apply_and_prove_compaction() {
         let database: Database<u32, u32> = Database::new();
+        let mut table = database.table_with_records((0..8).map(|i| (i, i)));
 
-        let mut lho = database.empty_table();
-        let mut rho = database.empty_table();
+        // The last remaining leaf moves up to the root
+        let transaction = || {
+            let mut transaction = TableTransaction::new();
 
-        let mut transaction = TableTransaction::new();
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            transaction.set(key, value).unwrap();
-        }
+            for key in 0..7 {
+                transaction.remove(&key).unwrap();
+            }
 
-        lho.execute(transaction);
+            transaction
+        };
 
-        let mut transaction = TableTransaction::new();
+        let pre = table.commit();
+        let (_, proof) = table.apply_and_prove(transaction());
 
-        for (key, value) in (512..1536).map(|i| (i, i)) {
-            transaction.set(key, value).unwrap();
-        }
+        proof.verify(pre, &transaction()).unwrap();
+        assert_eq!(proof.post(), database.table_with_records([(7, 7)]).commit());
 
-        rho.execute(transaction);
+        // Were siblings stubbed, compaction could not be verified
+        let mut table = database.table_with_records((0..2).map(|i| (i, i)));
+        let pre = table.commit();
 
-        let diff = Table::diff(&mut lho, &mut rho);
+        let witness = table.export([0]).unwrap();
 
-        for key in 0..1536 {
-            if key < 512 {
-                assert_eq!(diff[&key], (Some(key), None));
-            } else if key < 1024 {
-                assert_eq!(diff.get(&key), None);
-            } else {
-                assert_eq!(diff[&key], (None, Some(key)));
-            }
-        }
+        let mut transaction = TableTransaction::new();
+        transaction.remove(&0).unwrap();
+        table.execute(transaction);
 
-        let (mut lho, mut rho) = (rho, lho);
-        let diff = Table::diff(&mut lho, &mut rho);
+        let proof = TransitionProof::new(pre, table.commit(), witness);
 
-        for key in 0..1536 {
-            if key < 512 {
-                assert_eq!(diff[&key], (None, Some(key)));
-            } else if key < 1024 {
-                assert_eq!(diff.get(&key), None);
-            } else {
-                assert_eq!(diff[&key], (Some(key), None));
-            }
-        }
+        let mut transaction = TableTransaction::new();
+        transaction.remove(&0).unwrap();
+
+        assert!(matches!(
+            proof.verify(pre, &transaction).unwrap_err().top(),
+            TransitionError::WitnessIncomplete
+        ));
     }
 
     #[test]
-    #[ignore]
-    fn diff_stress() {
-        enum Set {
-            Identity,
-            Successor,
-            Empty,
-        }
+    fn keys_values() {
+        let database: Database<u32, u32> = Database::new();
 
-        const SETS: &[Set] = &[Set::Identity, Set::Successor, Set::Empty];
+        for size in [0, 1, 2, 100, 1024] {
+            let table = database.table_with_records((0..size).map(|i| (i, i + 1)));
 
-        let database: Database<u32, u32> = Database::new();
-        let mut rng = rand::thread_rng();
+            let keys = table.keys().collect::<HashSet<_>>();
+            let values = table.values().collect::<HashSet<_>>();
 
-        for _ in 0..512 {
-            let mut lho = database.empty_table();
-            let mut rho = database.empty_table();
-            let mut diff_reference = HashMap::new();
+            assert_eq!(keys.len(), size as usize);
+            assert_eq!(keys, (0..size).collect::<HashSet<_>>());
+            assert_eq!(values, (1..size + 1).collect::<HashSet<_>>());
+        }
+    }
 
-            let mut lho_transaction = TableTransaction::new();
-            let mut rho_transaction = TableTransaction::new();
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn watch() {
+        let database: Database<u32, u32> = Database::new();
+        let mut table = database.empty_table();
 
-            for key in 0..512 {
-                let lho_set = SETS.iter().choose(&mut rng).unwrap();
-                let rho_set = SETS.iter().choose(&mut rng).unwrap();
+        let mut watch = Box::pin(table.watch());
 
-                match lho_set {
-                    Set::Identity => lho_transaction.set(key, key).unwrap(),
-                    Set::Successor => lho_transaction.set(key, key + 1).unwrap(),
-                    Set::Empty => (),
-                }
+        let mut transaction = TableTransaction::new();
+        transaction.set(0, 0).unwrap();
+        table.execute(transaction);
 
-                match rho_set {
-                    Set::Identity => rho_transaction.set(key, key).unwrap(),
-                    Set::Successor => rho_transaction.set(key, key + 1).unwrap(),
-                    Set::Empty => (),
-                }
+        let first = watch.next().await.unwrap();
+        assert_eq!(first, table.commit());
 
-                match (lho_set, rho_set) {
-                    (Set::Identity, Set::Successor) => {
-                        diff_reference.insert(key, (Some(key), Some(key + 1)));
-                    }
-                    (Set::Identity, Set::Empty) => {
-                        diff_reference.insert(key, (Some(key), None));
-                    }
-                    (Set::Successor, Set::Identity) => {
-                        diff_reference.insert(key, (Some(key + 1), Some(key)));
-                    }
-                    (Set::Successor, Set::Empty) => {
-                        diff_reference.insert(key, (Some(key + 1), None));
-                    }
-                    (Set::Empty, Set::Identity) => {
-                        diff_reference.insert(key, (None, Some(key)));
-                    }
-                    (Set::Empty, Set::Successor) => {
-                        diff_reference.insert(key, (None, Some(key + 1)));
-                    }
-                    _ => {}
-                }
-            }
+        let mut transaction = TableTransaction::new();
+        transaction.set(1, 1).unwrap();
+        table.execute(transaction);
 
-            lho.execute(lho_transaction);
-            rho.execute(rho_transaction);
+        let second = watch.next().await.unwrap();
+        assert_eq!(second, table.commit());
 
-            assert_eq!(Table::diff(&mut lho, &mut rho), diff_reference);
-        }
+        assert_ne!(first, second);
+
+        drop(table);
+        assert!(watch.next().await.is_none());
     }
 }
@@ -1,6 +1,16 @@
-use crate::{common::store::Field, database::store::Node};
+use crate::{
+    common::store::Field,
+    database::{
+        errors::SyncError,
+        store::Node,
+        sync::{compact, wire},
+        SyncFormat,
+    },
+};
 
-use serde::{Deserialize, Serialize};
+use doomstack::{here, Doom, ResultExt, Top};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use std::vec::Vec;
 
@@ -17,4 +27,44 @@ use crate::database::{Question, TableReceiver, TableSender};
 /// [`Question`]: crate::database::Question
 
 #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
-pub struct TableAnswer<Key: Field, Value: Field>(pub(crate) Vec<Node<Key, Value>>);
+#[serde(bound(deserialize = "Key: DeserializeOwned, Value: DeserializeOwned"))]
+pub struct TableAnswer<Key: Field, Value: Field>(
+    #[serde(with = "wire::nodes")] pub(crate) Vec<Node<Key, Value>>,
+);
+
+impl<Key, Value> TableAnswer<Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    /// Encodes the answer according to `format`.
+    pub fn encode(&self, format: SyncFormat) -> Vec<u8> {
+        match format {
+            SyncFormat::Standard => bincode::serialize(self).unwrap(),
+            SyncFormat::Compact => compact::encode(&self.0),
+        }
+    }
+}
+
+impl<Key, Value> TableAnswer<Key, Value>
+where
+    Key: Field + DeserializeOwned,
+    Value: Field + DeserializeOwned,
+{
+    /// Decodes an answer encoded with [`encode`] according to `format`.
+    ///
+    /// # Errors
+    ///
+    /// If `bytes` is not a valid encoding, [`MalformedAnswer`] is returned.
+    ///
+    /// [`encode`]: crate::database::TableAnswer::encode
+    /// [`MalformedAnswer`]: crate::database::errors::SyncError::MalformedAnswer
+    pub fn decode(bytes: &[u8], format: SyncFormat) -> Result<Self, Top<SyncError>> {
+        match format {
+            SyncFormat::Standard => bincode::deserialize(bytes)
+                .map_err(|_| SyncError::MalformedAnswer.into_top())
+                .spot(here!()),
+            SyncFormat::Compact => Ok(TableAnswer(compact::decode(bytes)?)),
+        }
+    }
+}
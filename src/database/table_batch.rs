@@ -0,0 +1,146 @@
+use crate::{
+    common::store::Field,
+    database::{interact::Batch, table_transaction::Expiry, TableResponse, Tid},
+};
+
+// Documentation links
+#[allow(unused_imports)]
+use crate::database::{Table, TableTransaction};
+
+/// A finalized [`TableTransaction`], which can be split and merged to
+/// execute its operations in parts.
+///
+/// A `TableBatch`'s operations are sorted by path (i.e., by the hash of
+/// their keys). [`snap`] splits a `TableBatch` in two halves spanning
+/// disjoint, contiguous ranges of paths; [`merge`] reunites them. Each
+/// half can be applied on its own by [`Table::execute_batch`].
+///
+/// As [`Table::execute_batch`] borrows its [`Table`] mutably, the parts of
+/// a `TableBatch` are executed one after the other, never concurrently:
+/// splitting a `TableBatch` allows, e.g., to bound the time spent in each
+/// call, or to interleave other work between parts. Use [`Table::execute`]
+/// to have a whole transaction processed in parallel.
+///
+/// Parts are not atomic: each part is applied to the [`Table`] as soon as
+/// it is executed. Until all parts are executed, the [`Table`] reflects only
+/// some of the transaction's writes. A part that is dropped without being
+/// executed is lost for good, leaving the transaction half-applied.
+///
+/// [`TableTransaction`]: crate::database::TableTransaction
+/// [`Table`]: crate::database::Table
+/// [`Table::execute`]: crate::database::Table::execute
+/// [`Table::execute_batch`]: crate::database::Table::execute_batch
+/// [`snap`]: crate::database::TableBatch::snap
+/// [`merge`]: crate::database::TableBatch::merge
+///
+/// # Examples
+///
+/// ```
+/// use zebra::database::{Database, TableBatch, TableTransaction};
+///
+/// let database: Database<u32, u32> = Database::new();
+/// let mut table = database.empty_table();
+///
+/// let mut transaction = TableTransaction::new();
+///
+/// for key in 0..16 {
+///     transaction.set(key, key).unwrap();
+/// }
+///
+/// let (left, right) = transaction.into_batch().snap(8);
+///
+/// let left = table.execute_batch(left);
+/// let right = table.execute_batch(right);
+///
+/// let _response = TableBatch::merge(left, right).into_response();
+/// ```
+pub struct TableBatch<Key: Field, Value: Field> {
+    tid: Tid,
+    batch: Batch<Key, Value>,
+    expiries: Vec<Expiry>,
+}
+
+impl<Key, Value> TableBatch<Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    pub(crate) fn new(tid: Tid, batch: Batch<Key, Value>, expiries: Vec<Expiry>) -> Self {
+        TableBatch {
+            tid,
+            batch,
+            expiries,
+        }
+    }
+
+    pub(crate) fn take_expiries(&mut self) -> Vec<Expiry> {
+        std::mem::take(&mut self.expiries)
+    }
+
+    pub(crate) fn unwrap(self) -> (Tid, Batch<Key, Value>) {
+        (self.tid, self.batch)
+    }
+
+    /// Returns the number of operations in the `TableBatch`.
+    pub fn len(&self) -> usize {
+        self.batch.operations().len()
+    }
+
+    /// Returns `true` if the `TableBatch` contains no operation.
+    pub fn is_empty(&self) -> bool {
+        self.batch.operations().is_empty()
+    }
+
+    /// Splits the `TableBatch` in two: the first half holds the `at`
+    /// operations with lowest paths, the second half holds the rest.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is greater than [`len`].
+    ///
+    /// [`len`]: crate::database::TableBatch::len
+    pub fn snap(self, at: usize) -> (Self, Self) {
+        let (left, right) = self.batch.snap(at);
+
+        let (left_expiries, right_expiries) = match left.operations().last() {
+            Some(boundary) => self
+                .expiries
+                .into_iter()
+                .partition(|(path, _)| *path <= boundary.path),
+            None => (Vec::new(), self.expiries),
+        };
+
+        (
+            TableBatch::new(self.tid, left, left_expiries),
+            TableBatch::new(self.tid, right, right_expiries),
+        )
+    }
+
+    /// Reunites two halves obtained by [`snap`]ping the same `TableBatch`,
+    /// `left` being the first half and `right` the second.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `left` and `right` are not contiguous halves of the same
+    /// `TableBatch`.
+    ///
+    /// [`snap`]: crate::database::TableBatch::snap
+    pub fn merge(left: Self, right: Self) -> Self {
+        assert_eq!(
+            left.tid, right.tid,
+            "called `TableBatch::merge` on halves of different transactions"
+        );
+
+        let mut expiries = left.expiries;
+        expiries.extend(right.expiries);
+
+        TableBatch::new(left.tid, Batch::merge(left.batch, right.batch), expiries)
+    }
+
+    /// Converts an executed `TableBatch` into a [`TableResponse`].
+    ///
+    /// [`TableResponse`]: crate::database::TableResponse
+    pub fn into_response(self) -> TableResponse<Key, Value> {
+        TableResponse::new(self.tid, self.batch)
+    }
+}
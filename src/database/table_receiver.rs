@@ -3,32 +3,100 @@ use crate::{
     database::{
         errors::SyncError,
         interact::drop,
-        store::{Cell, Label, MapId, Node, Store},
-        sync::{locate, Severity},
-        Question, Table, TableAnswer, TableStatus,
+        store::{Cell, Label, MapId, Node, Split, Store},
+        sync::{locate, Severity, ANSWER_DEPTH},
+        Global, Question, ReceiverState, StoreAllocator, SyncFormat, Table, TableAnswer,
+        TableStatus,
     },
 };
 
 use doomstack::{here, Doom, ResultExt, Top};
 
-use std::collections::{
-    hash_map::Entry::{Occupied, Vacant},
-    HashMap, HashSet,
+use hashbrown::hash_map::Entry::{Occupied, Vacant};
+
+use serde::de::DeserializeOwned;
+
+use std::{
+    collections::{hash_map::Entry as HashMapEntry, HashMap, HashSet},
+    time::{Duration, Instant},
 };
 
 const DEFAULT_WINDOW: usize = 128;
 
-pub struct TableReceiver<Key: Field, Value: Field> {
-    cell: Cell<Key, Value>,
+// As many nodes as an answer to a single label holds, besides the answered node
+const DEFAULT_MAX_BENIGN_FAULTS: usize = (1 << (ANSWER_DEPTH + 1)) - 2;
+
+pub struct TableReceiver<Key: Field, Value: Field, A: StoreAllocator = Global> {
+    cell: Cell<Key, Value, A>,
     root: Option<Label>,
     held: HashSet<Label>,
     frontier: HashMap<Bytes, Context>,
     acquired: HashMap<Bytes, Node<Key, Value>>,
+    received: usize,
+    steps: usize,
+    started: Instant,
     pub settings: Settings,
 }
 
+/// Tuning of a [`TableReceiver`] (or [`CollectionReceiver`]) transfer,
+/// adjustable between calls to `learn`.
+///
+/// [`TableReceiver`]: crate::database::TableReceiver
+/// [`CollectionReceiver`]: crate::database::CollectionReceiver
 pub struct Settings {
+    /// Maximum number of labels asked for by each [`Question`]. Changes
+    /// take effect from the next [`Question`] on.
+    ///
+    /// [`Question`]: crate::database::Question
     pub window: usize,
+    /// Resources the transfer is allowed to spend (see [`SyncBudget`]),
+    /// unlimited if `None`.
+    ///
+    /// [`SyncBudget`]: crate::database::SyncBudget
+    pub budget: Option<SyncBudget>,
+    /// Maximum number of nodes in a [`TableAnswer`]: if exceeded,
+    /// [`TableReceiver::learn`] fails with [`SyncError::MalformedAnswer`]
+    /// without processing the [`TableAnswer`].
+    ///
+    /// [`TableAnswer`]: crate::database::TableAnswer
+    /// [`TableReceiver::learn`]: crate::database::TableReceiver::learn
+    /// [`SyncError::MalformedAnswer`]: crate::database::errors::SyncError::MalformedAnswer
+    pub max_answer_nodes: Option<usize>,
+    /// Maximum number of calls to [`TableReceiver::learn`] in a transfer:
+    /// if exceeded before the transfer completes, [`TableReceiver::learn`]
+    /// fails with [`SyncError::TransferStalled`].
+    ///
+    /// [`TableReceiver::learn`]: crate::database::TableReceiver::learn
+    /// [`SyncError::TransferStalled`]: crate::database::errors::SyncError::TransferStalled
+    pub max_steps: Option<usize>,
+    /// Maximum number of consecutive benign faults (e.g., nodes that were
+    /// not asked for) tolerated in a [`TableAnswer`]: if exceeded,
+    /// [`TableReceiver::learn`] fails with [`SyncError::MalformedAnswer`].
+    /// Set to 0 to abort upon the first fault.
+    ///
+    /// [`TableAnswer`]: crate::database::TableAnswer
+    /// [`TableReceiver::learn`]: crate::database::TableReceiver::learn
+    /// [`SyncError::MalformedAnswer`]: crate::database::errors::SyncError::MalformedAnswer
+    pub max_benign_faults: usize,
+}
+
+/// Limits on the resources a [`TableReceiver`] is allowed to spend on a
+/// transfer. Once either limit is exceeded, [`TableReceiver::learn`] returns
+/// [`TableStatus::BudgetExceeded`], handing back the receiver for checkpointing.
+///
+/// [`TableReceiver`]: crate::database::TableReceiver
+/// [`TableReceiver::learn`]: crate::database::TableReceiver::learn
+/// [`TableStatus::BudgetExceeded`]: crate::database::TableStatus::BudgetExceeded
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SyncBudget {
+    /// Maximum number of (serialized) bytes received over all [`TableAnswer`]s.
+    ///
+    /// [`TableAnswer`]: crate::database::TableAnswer
+    pub max_bytes: Option<usize>,
+    /// Maximum wall-clock time elapsed since the creation of the [`TableReceiver`].
+    ///
+    /// [`TableReceiver`]: crate::database::TableReceiver
+    pub max_duration: Option<Duration>,
 }
 
 struct Context {
@@ -36,35 +104,188 @@ struct Context {
     remote_label: Label,
 }
 
-impl<Key, Value> TableReceiver<Key, Value>
+impl<Key, Value, A> TableReceiver<Key, Value, A>
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
-    pub(crate) fn new(cell: Cell<Key, Value>) -> Self {
+    pub(crate) fn new(cell: Cell<Key, Value, A>) -> Self {
         TableReceiver {
             cell,
             root: None,
             held: HashSet::new(),
             frontier: HashMap::new(),
             acquired: HashMap::new(),
+            received: 0,
+            steps: 0,
+            started: Instant::now(),
             settings: Settings {
                 window: DEFAULT_WINDOW,
+                budget: None,
+                max_answer_nodes: None,
+                max_steps: None,
+                max_benign_faults: DEFAULT_MAX_BENIGN_FAULTS,
             },
         }
     }
 
+    // Rebuilds a receiver from `state`: acquired nodes are walked from the root
+    // to recover the frontier, then the frontier is resolved against `cell`,
+    // holding again the subtrees that are (still) available locally
+    pub(crate) fn resume(
+        cell: Cell<Key, Value, A>,
+        state: ReceiverState<Key, Value>,
+    ) -> Result<Self, Top<SyncError>> {
+        let mut receiver = TableReceiver::new(cell);
+
+        receiver.received = state.received;
+        receiver.steps = state.steps;
+
+        let root = match state.root {
+            Some(root) => root,
+            None => return Ok(receiver),
+        };
+
+        receiver.root = Some(root);
+
+        let mut nodes: HashMap<Bytes, Node<Key, Value>> = state
+            .acquired
+            .into_iter()
+            .map(|node| (node.hash(), node))
+            .collect();
+
+        let mut store = receiver.cell.take();
+        let mut stack = vec![(root, Prefix::root())];
+        let mut malformed = false;
+
+        while let Some((label, location)) = stack.pop() {
+            if label.is_empty() {
+                continue;
+            }
+
+            let hash = label.hash();
+
+            if let Some(node) = nodes.remove(&hash) {
+                if let Node::Internal(left, right) = node {
+                    stack.push((left, location.left()));
+                    stack.push((right, location.right()));
+                }
+
+                store.share(hash, node.clone());
+                receiver.acquired.insert(hash, node);
+            } else if receiver.acquired.contains_key(&hash) || receiver.frontier.contains_key(&hash)
+            {
+                // As in `sight`, a node cannot lie at two locations of the same tree
+                malformed = true;
+                break;
+            } else {
+                receiver.frontier.insert(
+                    hash,
+                    Context {
+                        location,
+                        remote_label: label,
+                    },
+                );
+            }
+        }
+
+        if !malformed {
+            malformed = receiver.resolve(&mut store, Severity::ok()).is_malicious();
+        }
+
+        receiver.cell.restore(store);
+
+        if malformed {
+            SyncError::MalformedState.fail().spot(here!())
+        } else {
+            Ok(receiver)
+        }
+    }
+
+    /// Takes a serializable snapshot of the transfer, from which it can be
+    /// resumed through [`Database::resume_receive`] (see [`ReceiverState`]).
+    ///
+    /// Settings are not included, and the duration budget (if any) restarts
+    /// upon resuming. The byte budget and the step count carry over.
+    ///
+    /// [`Database::resume_receive`]: crate::database::Database::resume_receive
+    /// [`ReceiverState`]: crate::database::ReceiverState
+    pub fn checkpoint(&self) -> ReceiverState<Key, Value> {
+        ReceiverState {
+            root: self.root,
+            acquired: self.acquired.values().cloned().collect(),
+            received: self.received,
+            steps: self.steps,
+        }
+    }
+
+    /// Returns the [`Question`] to carry on the transfer with, as returned
+    /// alongside the receiver by the latest call to [`learn`].
+    ///
+    /// This is meant for resuming a transfer (see [`Database::resume_receive`]).
+    /// If no node was received before the checkpoint, the [`Question`] is
+    /// empty, and the transfer restarts from [`TableSender::hello`].
+    ///
+    /// [`Question`]: crate::database::Question
+    /// [`learn`]: crate::database::TableReceiver::learn
+    /// [`Database::resume_receive`]: crate::database::Database::resume_receive
+    /// [`TableSender::hello`]: crate::database::TableSender::hello
+    pub fn question(&self) -> Question {
+        self.ask()
+    }
+
+    /// Lists the [`SyncFormat`]s this receiver can decode, by decreasing
+    /// preference, for the [`TableSender`] to pick from (see
+    /// [`TableSender::negotiate`]).
+    ///
+    /// [`SyncFormat`]: crate::database::SyncFormat
+    /// [`TableSender`]: crate::database::TableSender
+    /// [`TableSender::negotiate`]: crate::database::TableSender::negotiate
+    pub fn formats(&self) -> Vec<SyncFormat> {
+        SyncFormat::SUPPORTED.to_vec()
+    }
+
     pub fn learn(
         mut self,
         answer: TableAnswer<Key, Value>,
-    ) -> Result<TableStatus<Key, Value>, Top<SyncError>> {
+    ) -> Result<TableStatus<Key, Value, A>, Top<SyncError>> {
+        if !self.spend(bincode::serialized_size(&answer).unwrap() as usize) {
+            return Ok(TableStatus::BudgetExceeded(self));
+        }
+
+        self.process(answer)
+    }
+
+    fn process(
+        mut self,
+        answer: TableAnswer<Key, Value>,
+    ) -> Result<TableStatus<Key, Value, A>, Top<SyncError>> {
+        self.steps += 1;
+
+        if self
+            .settings
+            .max_steps
+            .is_some_and(|max_steps| self.steps > max_steps)
+        {
+            return SyncError::TransferStalled.fail().spot(here!());
+        }
+
+        if self
+            .settings
+            .max_answer_nodes
+            .is_some_and(|max_answer_nodes| answer.0.len() > max_answer_nodes)
+        {
+            return SyncError::MalformedAnswer.fail().spot(here!());
+        }
+
         let mut store = self.cell.take();
         let mut severity = Severity::ok();
 
         for node in answer.0 {
             severity = match self.update(&mut store, node) {
                 Ok(()) => Severity::ok(),
-                Err(offence) => severity + offence,
+                Err(offence) => (severity + offence).tolerate(self.settings.max_benign_faults),
             };
 
             if severity.is_malicious() {
@@ -72,13 +293,17 @@ where
             }
         }
 
+        if severity.is_benign() {
+            severity = self.resolve(&mut store, severity);
+        }
+
         if severity.is_benign() {
             if self.frontier.is_empty() {
                 // Receive complete, flush if necessary
                 match self.root {
                     Some(root) => {
                         // At least one node was received: flush
-                        self.flush(&mut store, root);
+                        let store = self.flush(store, root);
                         self.cell.restore(store);
 
                         Ok(TableStatus::Complete(Table::new(self.cell.clone(), root)))
@@ -105,9 +330,30 @@ where
         }
     }
 
+    // Charges `bytes` to the budget, returning `false` if the budget is exceeded
+    fn spend(&mut self, bytes: usize) -> bool {
+        self.received += bytes;
+
+        if let Some(budget) = self.settings.budget {
+            let bytes_exceeded = budget
+                .max_bytes
+                .is_some_and(|max_bytes| self.received > max_bytes);
+
+            let duration_exceeded = budget
+                .max_duration
+                .is_some_and(|max_duration| self.started.elapsed() > max_duration);
+
+            if bytes_exceeded || duration_exceeded {
+                return false;
+            }
+        }
+
+        true
+    }
+
     fn update(
         &mut self,
-        store: &mut Store<Key, Value>,
+        store: &mut Store<Key, Value, A>,
         node: Node<Key, Value>,
     ) -> Result<(), Severity> {
         let hash = node.hash();
@@ -121,10 +367,14 @@ where
         }?;
 
         // Check if `node` preserves topology invariants:
-        // - If `node` is `Internal`, its children must preserve compactness.
+        // - If `node` is `Internal`, its children must preserve compactness,
+        //   and lie within the length of a path.
         // - If `node` is `Leaf`, it must lie along its `key` path.
         // If so, compute `node`'s `label`.
         let label = match node {
+            Node::Internal(..) if location.depth() == Prefix::MAX_DEPTH => {
+                Err(Severity::malicious())
+            }
             Node::Internal(left, right) => match (left, right) {
                 (Label::Empty, Label::Empty)
                 | (Label::Empty, Label::Leaf(..))
@@ -169,27 +419,76 @@ where
             self.held.insert(label);
         } else {
             if let Node::Internal(ref left, ref right) = node {
-                self.sight(left, location.left());
-                self.sight(right, location.right());
+                self.sight(left, location.left())?;
+                self.sight(right, location.right())?;
             }
 
-            self.acquired.insert(label.hash(), node);
+            if let HashMapEntry::Vacant(entry) = self.acquired.entry(label.hash()) {
+                store.share(label.hash(), node.clone());
+                entry.insert(node);
+            }
         }
 
         self.frontier.remove(&hash);
         Ok(())
     }
 
-    fn sight(&mut self, label: &Label, location: Prefix) {
-        if !label.is_empty() {
-            self.frontier.insert(
-                label.hash(),
-                Context {
-                    location,
-                    remote_label: *label,
-                },
-            );
+    // Feeds to `update` all nodes in `frontier` that are available locally, either
+    // because a concurrent `TableReceiver` acquired them, or because they were
+    // stored after being sighted. Available nodes are never asked for.
+    fn resolve(&mut self, store: &mut Store<Key, Value, A>, mut severity: Severity) -> Severity {
+        loop {
+            let available = self
+                .frontier
+                .values()
+                .filter_map(|context| {
+                    let label = context.remote_label;
+
+                    if let Some(node) = store.shared(&label.hash()) {
+                        return Some(node.clone());
+                    }
+
+                    store.lookup(label).cloned()
+                })
+                .collect::<Vec<_>>();
+
+            if available.is_empty() {
+                return severity;
+            }
+
+            for node in available {
+                severity = match self.update(store, node) {
+                    Ok(()) => Severity::ok(),
+                    Err(offence) => (severity + offence).tolerate(self.settings.max_benign_faults),
+                };
+
+                if severity.is_malicious() {
+                    return severity;
+                }
+            }
+        }
+    }
+
+    fn sight(&mut self, label: &Label, location: Prefix) -> Result<(), Severity> {
+        if label.is_empty() {
+            return Ok(());
+        }
+
+        // A node cannot lie at two locations of the same tree: otherwise, `flush`
+        // would descend its subtree at a location where it was never checked
+        if self.frontier.contains_key(&label.hash()) || self.acquired.contains_key(&label.hash()) {
+            return Err(Severity::malicious());
         }
+
+        self.frontier.insert(
+            label.hash(),
+            Context {
+                location,
+                remote_label: *label,
+            },
+        );
+
+        Ok(())
     }
 
     fn ask(&self) -> Question {
@@ -202,43 +501,148 @@ where
         )
     }
 
-    fn flush(&mut self, store: &mut Store<Key, Value>, label: Label) {
-        if !label.is_empty() {
-            let stored = match store.entry(label) {
-                Occupied(..) => true,
-                Vacant(..) => false,
-            };
+    fn flush(&mut self, store: Store<Key, Value, A>, root: Label) -> Store<Key, Value, A> {
+        let held: HashMap<Bytes, Label> = self
+            .held
+            .iter()
+            .map(|label| (label.hash(), *label))
+            .collect();
 
-            let recursion = if stored {
-                None
-            } else {
-                let node = self.acquired.get(&label.hash()).unwrap();
-                store.populate(label, node.clone());
+        let (store, _, consumed) = match store.pool().cloned() {
+            Some(pool) => {
+                pool.install(|| flush(store, &self.acquired, &held, root, Prefix::root()))
+            }
+            None => flush(store, &self.acquired, &held, root, Prefix::root()),
+        };
+
+        for label in consumed {
+            self.held.remove(&label);
+        }
+
+        store
+    }
+}
+
+// Populates `store` with the acquired nodes of the tree rooted at `label`
+// (lying at `location`), and `incref`s them, unless they are `held` already.
+// Independent subtrees are flushed concurrently, as long as `store` can be
+// split. Returns the label under which the root of the tree is stored,
+// along with the `held` labels consumed by the tree.
+//
+// Labels are recomputed rather than taken from the acquired nodes, as the
+// `MapId`s of their children are chosen by the remote end: a misplaced
+// label would fall outside the range of a split `store`.
+fn flush<Key, Value, A>(
+    mut store: Store<Key, Value, A>,
+    acquired: &HashMap<Bytes, Node<Key, Value>>,
+    held: &HashMap<Bytes, Label>,
+    label: Label,
+    location: Prefix,
+) -> (Store<Key, Value, A>, Label, Vec<Label>)
+where
+    Key: Field,
+    Value: Field,
+    A: StoreAllocator,
+{
+    if label.is_empty() {
+        return (store, Label::Empty, Vec::new());
+    }
+
+    if let Some(held) = held.get(&label.hash()) {
+        return (store, *held, vec![*held]);
+    }
 
-                match node {
-                    Node::Internal(left, right) => Some((*left, *right)),
-                    _ => None,
+    let node = acquired.get(&label.hash()).unwrap();
+    let label = store.label(node, location);
+
+    let stored = match store.entry(label) {
+        Occupied(..) => true,
+        Vacant(..) => false,
+    };
+
+    let (store, consumed) = match node {
+        Node::Internal(left, right) if !stored => {
+            let (left, right) = (*left, *right);
+
+            let (mut store, left, right, consumed) = match store.split() {
+                Split::Split(left_store, right_store) => {
+                    let (
+                        (left_store, left, mut left_consumed),
+                        (right_store, right, mut right_consumed),
+                    ) = rayon::join(
+                        move || flush(left_store, acquired, held, left, location.left()),
+                        move || flush(right_store, acquired, held, right, location.right()),
+                    );
+
+                    let store = Store::merge(left_store, right_store);
+                    left_consumed.append(&mut right_consumed);
+
+                    (store, left, right, left_consumed)
+                }
+                Split::Unsplittable(store) => {
+                    let (store, left, mut left_consumed) =
+                        flush(store, acquired, held, left, location.left());
+
+                    let (store, right, mut right_consumed) =
+                        flush(store, acquired, held, right, location.right());
+
+                    left_consumed.append(&mut right_consumed);
+
+                    (store, left, right, left_consumed)
                 }
             };
 
-            if self.held.contains(&label) {
-                self.held.remove(&label);
-            } else {
-                store.incref(label);
-            }
+            // Children `incref`-ed themselves upon flushing
+            store.populate(label, Node::Internal(left, right));
+            store.incref(label);
 
-            if let Some((left, right)) = recursion {
-                self.flush(store, left);
-                self.flush(store, right);
+            (store, consumed)
+        }
+        node => {
+            if !stored {
+                store.populate(label, node.clone());
             }
+
+            store.incref(label);
+            (store, Vec::new())
         }
+    };
+
+    (store, label, consumed)
+}
+
+impl<Key, Value, A> TableReceiver<Key, Value, A>
+where
+    Key: Field + DeserializeOwned,
+    Value: Field + DeserializeOwned,
+    A: StoreAllocator,
+{
+    /// Like [`learn`], but takes a [`TableAnswer`] encoded according to the
+    /// negotiated `format` (see [`SyncFormat`]). The budget is charged for
+    /// the length of `bytes`.
+    ///
+    /// [`learn`]: crate::database::TableReceiver::learn
+    /// [`TableAnswer`]: crate::database::TableAnswer
+    /// [`SyncFormat`]: crate::database::SyncFormat
+    pub fn learn_encoded(
+        mut self,
+        format: SyncFormat,
+        bytes: &[u8],
+    ) -> Result<TableStatus<Key, Value, A>, Top<SyncError>> {
+        if !self.spend(bytes.len()) {
+            return Ok(TableStatus::BudgetExceeded(self));
+        }
+
+        let answer = TableAnswer::decode(bytes, format)?;
+        self.process(answer)
     }
 }
 
-impl<Key, Value> Drop for TableReceiver<Key, Value>
+impl<Key, Value, A> Drop for TableReceiver<Key, Value, A>
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
     fn drop(&mut self) {
         let mut store = self.cell.take();
@@ -247,15 +651,35 @@ where
             drop::drop(&mut store, *label);
         }
 
+        for hash in self.acquired.keys() {
+            store.unshare(*hash);
+        }
+
         self.cell.restore(store);
     }
 }
 
+#[cfg(any(test, feature = "test-util"))]
+mod checks {
+    use super::*;
+
+    impl<Key, Value, A> TableReceiver<Key, Value, A>
+    where
+        Key: Field,
+        Value: Field,
+        A: StoreAllocator,
+    {
+        pub(crate) fn held(&self) -> Vec<Label> {
+            self.held.iter().map(|label| *label).collect()
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    use crate::database::{sync::ANSWER_DEPTH, Database, TableSender};
+    use crate::database::{Database, TableSender};
 
     enum Transfer<'a, Key, Value>
     where
@@ -291,22 +715,13 @@ mod tests {
                     answer = sender.answer(&question).unwrap();
                     receiver = receiver_t;
                 }
+                TableStatus::BudgetExceeded(..) => unreachable!(),
             };
         }
 
         Transfer::Incomplete(sender, receiver, answer)
     }
 
-    impl<Key, Value> TableReceiver<Key, Value>
-    where
-        Key: Field,
-        Value: Field,
-    {
-        pub(crate) fn held(&self) -> Vec<Label> {
-            self.held.iter().map(|label| *label).collect()
-        }
-    }
-
     fn run<'a, Key, Value, I, const N: usize>(
         database: &Database<Key, Value>,
         tables: I,
@@ -437,6 +852,62 @@ mod tests {
         received.assert_records((0..256).map(|i| (i, i)));
     }
 
+    #[test]
+    fn different_depths() {
+        let alice: Database<u32, u32> = Database::builder().depth(4).build().unwrap();
+        let bob: Database<u32, u32> = Database::builder().depth(12).build().unwrap();
+
+        let shared = bob.table_with_records((0..128).map(|i| (i, i)));
+
+        let original = alice.table_with_records((0..256).map(|i| (i, i)));
+        let commit = original.commit();
+        let mut sender = original.send();
+
+        // Labels match across depths: nodes held by `bob` are not asked for
+        let receiver = bob.receive();
+        let ([received], _) = run(&bob, [&shared], [(&mut sender, receiver)]);
+
+        assert_eq!(received.commit(), commit);
+        received.assert_records((0..256).map(|i| (i, i)));
+    }
+
+    #[test]
+    fn different_depths_serialized() {
+        // Serialized labels only carry the first 8 bits of their `MapId`s
+        let alice: Database<u32, u32> = Database::builder().depth(12).build().unwrap();
+        let bob: Database<u32, u32> = Database::builder().depth(16).build().unwrap();
+
+        let _shared = bob.table_with_records((0..128).map(|i| (i, i)));
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let commit = original.commit();
+        let mut sender = original.send();
+
+        let mut receiver = bob.receive();
+        let mut answer = sender.hello();
+
+        let received = loop {
+            let bytes = bincode::serialize(&answer).unwrap();
+            let decoded: TableAnswer<u32, u32> = bincode::deserialize(&bytes).unwrap();
+
+            match receiver.learn(decoded).unwrap() {
+                TableStatus::Complete(table) => break table,
+                TableStatus::Incomplete(receiver_t, question) => {
+                    let bytes = bincode::serialize(&question).unwrap();
+                    let question: Question = bincode::deserialize(&bytes).unwrap();
+
+                    answer = sender.answer(&question).unwrap();
+                    receiver = receiver_t;
+                }
+                TableStatus::BudgetExceeded(..) => unreachable!(),
+            }
+        };
+
+        assert_eq!(received.commit(), commit);
+        received.check_tree();
+        received.assert_records((0..1024).map(|i| (i, i)));
+    }
+
     #[test]
     fn single_then_single() {
         let alice: Database<u32, u32> = Database::new();
@@ -723,6 +1194,88 @@ mod tests {
         second.assert_records((128..384).map(|i| (i, i)));
     }
 
+    #[test]
+    fn multiple_interleave_overlap_shared() {
+        let alice: Database<u32, u32> = Database::new();
+
+        let first_original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let second_original = alice.table_with_records((0..1040).map(|i| (i, i)));
+
+        // Transfer each table to a distinct database
+        let independent: usize = vec![first_original.clone(), second_original.clone()]
+            .into_iter()
+            .map(|original| {
+                let bob: Database<u32, u32> = Database::new();
+
+                let mut sender = original.send();
+                let mut receiver = bob.receive();
+                let mut answer = sender.hello();
+
+                let mut transferred = 0;
+
+                loop {
+                    transferred += answer.0.len();
+
+                    match receiver.learn(answer).unwrap() {
+                        TableStatus::Complete(_) => break transferred,
+                        TableStatus::BudgetExceeded(..) => unreachable!(),
+                        TableStatus::Incomplete(receiver_t, question) => {
+                            answer = sender.answer(&question).unwrap();
+                            receiver = receiver_t;
+                        }
+                    }
+                }
+            })
+            .sum();
+
+        // Transfer both tables, interleaved, to the same database
+        let bob: Database<u32, u32> = Database::new();
+
+        let mut first_sender = first_original.clone().send();
+        let mut second_sender = second_original.clone().send();
+
+        let first_hello = first_sender.hello();
+        let second_hello = second_sender.hello();
+
+        let mut transferred = 0;
+
+        let mut advance = |transfer| match transfer {
+            Transfer::Incomplete(sender, receiver, answer) => {
+                transferred += answer.0.len();
+                run_for(receiver, sender, answer, 1)
+            }
+            complete => complete,
+        };
+
+        let mut first = Transfer::Incomplete(&mut first_sender, bob.receive(), first_hello);
+
+        // Let the first transfer get ahead
+        for _ in 0..2 {
+            first = advance(first);
+        }
+
+        let mut second = Transfer::Incomplete(&mut second_sender, bob.receive(), second_hello);
+
+        while matches!(first, Transfer::Incomplete(..))
+            || matches!(second, Transfer::Incomplete(..))
+        {
+            first = advance(first);
+            second = advance(second);
+        }
+
+        let (first, second) = match (first, second) {
+            (Transfer::Complete(first), Transfer::Complete(second)) => (first, second),
+            _ => unreachable!(),
+        };
+
+        first.assert_records((0..1024).map(|i| (i, i)));
+        second.assert_records((0..1040).map(|i| (i, i)));
+
+        bob.check([&first, &second], []);
+
+        assert!(transferred < independent);
+    }
+
     #[test]
     fn multiple_then_double_overlap() {
         let alice: Database<u32, u32> = Database::new();
@@ -809,7 +1362,7 @@ mod tests {
 
         let mut answer = sender.hello();
 
-        let max_benign = (1 << (ANSWER_DEPTH + 1)) - 2;
+        let max_benign = DEFAULT_MAX_BENIGN_FAULTS;
 
         answer = TableAnswer(
             (0..max_benign + 1)
@@ -840,7 +1393,7 @@ mod tests {
 
         let mut answer = sender.hello();
 
-        let max_benign = (1 << (ANSWER_DEPTH + 1)) - 2;
+        let max_benign = DEFAULT_MAX_BENIGN_FAULTS;
 
         answer = TableAnswer(
             (0..max_benign + 2)
@@ -858,66 +1411,144 @@ mod tests {
     }
 
     #[test]
-    fn multiple_malicious_internal_topology_empty_leaf() {
+    fn zero_benign() {
         let alice: Database<u32, u32> = Database::new();
         let bob: Database<u32, u32> = Database::new();
 
-        let original = alice.table_with_records((0..100).map(|i| (i, i)));
+        let original = alice.table_with_records((0..256).map(|i| (i, i)));
         let mut sender = original.send();
 
-        let receiver = bob.receive();
-
-        let mut answer = sender.hello();
+        let mut receiver = bob.receive();
+        receiver.settings.max_benign_faults = 0;
 
-        // Malicious tampering of Internal node's right child label ((empty, leaf) -> bad topology)
-        let fake_leaf = Node::Leaf(wrap!(u32::MAX), wrap!(u32::MAX - 4));
-        let fake_internal = Node::Internal(
-            Label::Empty,
-            Label::Leaf(MapId::leaf(&wrap!(u32::MAX).digest()), fake_leaf.hash()),
-        );
-        let fake_internal_label =
-            Label::Internal(MapId::internal(Prefix::root().left()), fake_internal.hash());
-        if let Node::<_, _>::Internal(_, r) = answer.0[0].clone() {
-            answer.0[0] = Node::Internal(fake_internal_label, r);
-        }
-        answer.0[1] = fake_internal;
+        let answer = sender.hello();
+        let answer = TableAnswer(vec![answer.0[0].clone(), answer.0[0].clone()]);
 
         match receiver.learn(answer) {
             Err(e) if *e.top() == SyncError::MalformedAnswer => (),
             Err(x) => {
                 panic!("Expected `SyncError::MalformedAnswer` but got {:?}", x)
             }
-            _ => panic!("Receiver accepts too many benign faults from sender"),
+            _ => panic!("Receiver accepts a benign fault from sender"),
         }
+
+        // Without faults, the transfer completes
+        let mut receiver = bob.receive();
+        receiver.settings.max_benign_faults = 0;
+
+        let answer = sender.hello();
+
+        let first = match run_for(receiver, &mut sender, answer, 100) {
+            Transfer::Incomplete(..) => {
+                panic!("Transfer does not complete")
+            }
+            Transfer::Complete(table) => table,
+        };
+
+        first.assert_records((0..256).map(|i| (i, i)));
     }
 
     #[test]
-    fn multiple_malicious_internal_topology_leaf_empty() {
+    fn raised_benign() {
         let alice: Database<u32, u32> = Database::new();
         let bob: Database<u32, u32> = Database::new();
 
-        let original = alice.table_with_records((0..100).map(|i| (i, i)));
+        let original = alice.table_with_records((0..256).map(|i| (i, i)));
         let mut sender = original.send();
 
-        let receiver = bob.receive();
+        let max_benign = DEFAULT_MAX_BENIGN_FAULTS + 10;
 
-        let mut answer = sender.hello();
+        let mut receiver = bob.receive();
+        receiver.settings.max_benign_faults = max_benign;
 
-        // Malicious tampering of Internal node's right child label ((leaf, empty) -> bad topology)
-        let fake_leaf = Node::Leaf(wrap!(u32::MAX), wrap!(u32::MAX - 10));
-        let fake_internal = Node::Internal(
-            Label::Leaf(MapId::leaf(&wrap!(u32::MAX).digest()), fake_leaf.hash()),
-            Label::Empty,
-        );
-        let fake_internal_label =
-            Label::Internal(MapId::internal(Prefix::root().left()), fake_internal.hash());
-        if let Node::<_, _>::Internal(_, r) = answer.0[0].clone() {
-            answer.0[0] = Node::Internal(fake_internal_label, r);
-        }
-        answer.0[1] = fake_internal;
+        let answer = sender.hello();
+        let nodes = (0..max_benign + 1)
+            .map(|_| answer.0[0].clone())
+            .collect::<Vec<Node<_, _>>>();
 
-        match receiver.learn(answer) {
-            Err(e) if *e.top() == SyncError::MalformedAnswer => (),
+        let first = match run_for(receiver, &mut sender, TableAnswer(nodes.clone()), 100) {
+            Transfer::Incomplete(..) => {
+                panic!("Transfer does not complete")
+            }
+            Transfer::Complete(table) => table,
+        };
+
+        bob.check([&first], []);
+        first.assert_records((0..256).map(|i| (i, i)));
+
+        let mut receiver = bob.receive();
+        receiver.settings.max_benign_faults = max_benign - 1;
+
+        match receiver.learn(TableAnswer(nodes)) {
+            Err(e) if *e.top() == SyncError::MalformedAnswer => (),
+            Err(x) => {
+                panic!("Expected `SyncError::MalformedAnswer` but got {:?}", x)
+            }
+            _ => panic!("Receiver accepts too many benign faults from sender"),
+        }
+    }
+
+    #[test]
+    fn multiple_malicious_internal_topology_empty_leaf() {
+        let alice: Database<u32, u32> = Database::new();
+        let bob: Database<u32, u32> = Database::new();
+
+        let original = alice.table_with_records((0..100).map(|i| (i, i)));
+        let mut sender = original.send();
+
+        let receiver = bob.receive();
+
+        let mut answer = sender.hello();
+
+        // Malicious tampering of Internal node's right child label ((empty, leaf) -> bad topology)
+        let fake_leaf = Node::Leaf(wrap!(u32::MAX), wrap!(u32::MAX - 4));
+        let fake_internal = Node::Internal(
+            Label::Empty,
+            Label::Leaf(MapId::leaf(&wrap!(u32::MAX).digest()), fake_leaf.hash()),
+        );
+        let fake_internal_label =
+            Label::Internal(MapId::internal(Prefix::root().left()), fake_internal.hash());
+        if let Node::<_, _>::Internal(_, r) = answer.0[0].clone() {
+            answer.0[0] = Node::Internal(fake_internal_label, r);
+        }
+        answer.0[1] = fake_internal;
+
+        match receiver.learn(answer) {
+            Err(e) if *e.top() == SyncError::MalformedAnswer => (),
+            Err(x) => {
+                panic!("Expected `SyncError::MalformedAnswer` but got {:?}", x)
+            }
+            _ => panic!("Receiver accepts too many benign faults from sender"),
+        }
+    }
+
+    #[test]
+    fn multiple_malicious_internal_topology_leaf_empty() {
+        let alice: Database<u32, u32> = Database::new();
+        let bob: Database<u32, u32> = Database::new();
+
+        let original = alice.table_with_records((0..100).map(|i| (i, i)));
+        let mut sender = original.send();
+
+        let receiver = bob.receive();
+
+        let mut answer = sender.hello();
+
+        // Malicious tampering of Internal node's right child label ((leaf, empty) -> bad topology)
+        let fake_leaf = Node::Leaf(wrap!(u32::MAX), wrap!(u32::MAX - 10));
+        let fake_internal = Node::Internal(
+            Label::Leaf(MapId::leaf(&wrap!(u32::MAX).digest()), fake_leaf.hash()),
+            Label::Empty,
+        );
+        let fake_internal_label =
+            Label::Internal(MapId::internal(Prefix::root().left()), fake_internal.hash());
+        if let Node::<_, _>::Internal(_, r) = answer.0[0].clone() {
+            answer.0[0] = Node::Internal(fake_internal_label, r);
+        }
+        answer.0[1] = fake_internal;
+
+        match receiver.learn(answer) {
+            Err(e) if *e.top() == SyncError::MalformedAnswer => (),
             Err(x) => {
                 panic!("Expected `SyncError::MalformedAnswer` but got {:?}", x)
             }
@@ -1124,6 +1755,63 @@ mod tests {
         first.assert_records((0..256).map(|i| (i, i)));
     }
 
+    #[test]
+    fn malicious_chain_past_max_depth() {
+        let bob: Database<u32, u32> = Database::new();
+        let receiver = bob.receive();
+
+        let left = leaf!(0u32, 0u32);
+        let right = leaf!(1u32, 1u32);
+
+        let left = Label::Leaf(MapId::leaf(&wrap!(0u32).digest()), left.hash());
+        let right = Label::Leaf(MapId::leaf(&wrap!(1u32).digest()), right.hash());
+
+        // A chain of `Internal` nodes, whose deepest node lies at `MAX_DEPTH`
+        // and would have its children lie past the length of a path
+        let mut nodes = vec![Node::Internal(left, right)];
+
+        for _ in 0..Prefix::MAX_DEPTH {
+            let child = nodes.last().unwrap();
+            let child = Label::Internal(MapId::internal(Prefix::root()), child.hash());
+
+            nodes.push(Node::Internal(child, Label::Empty));
+        }
+
+        nodes.reverse();
+
+        match receiver.learn(TableAnswer(nodes)) {
+            Err(e) if *e.top() == SyncError::MalformedAnswer => (),
+            Err(x) => panic!("Expected `SyncError::MalformedAnswer` but got {:?}", x),
+            Ok(..) => panic!("Expected `SyncError::MalformedAnswer` but got a valid status"),
+        }
+    }
+
+    #[test]
+    fn malicious_internal_repeated_child() {
+        let alice: Database<u32, u32> = Database::new();
+        let bob: Database<u32, u32> = Database::new();
+
+        let original = alice.table_with_records((0..256).map(|i| (i, i)));
+        let mut sender = original.send();
+
+        let receiver = bob.receive();
+        let mut answer = sender.hello();
+
+        // The same subtree, at both children of the root
+        match answer.0[0] {
+            Node::Internal(l, _) => {
+                answer.0[0] = Node::Internal(l, l);
+            }
+            _ => unreachable!(),
+        };
+
+        match receiver.learn(answer) {
+            Err(e) if *e.top() == SyncError::MalformedAnswer => (),
+            Err(x) => panic!("Expected `SyncError::MalformedAnswer` but got {:?}", x),
+            Ok(..) => panic!("Expected `SyncError::MalformedAnswer` but got a valid status"),
+        }
+    }
+
     #[test]
     fn malicious_internal_swap_location_deep() {
         let alice: Database<u32, u32> = Database::new();
@@ -1178,4 +1866,443 @@ mod tests {
         bob.check([&first], []);
         first.assert_records((0..256).map(|i| (i, i)));
     }
+
+    #[test]
+    fn budget_bytes_exceeded() {
+        let alice: Database<u32, u32> = Database::new();
+        let bob: Database<u32, u32> = Database::new();
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let mut sender = original.send();
+
+        let mut receiver = bob.receive();
+        receiver.settings.budget = Some(SyncBudget {
+            max_bytes: Some(4096),
+            max_duration: None,
+        });
+
+        let mut answer = sender.hello();
+
+        let receiver = loop {
+            match receiver.learn(answer).unwrap() {
+                TableStatus::Incomplete(receiver_t, question) => {
+                    answer = sender.answer(&question).unwrap();
+                    receiver = receiver_t;
+                }
+                TableStatus::Complete(..) => {
+                    panic!("Receiver completes a transfer beyond its budget")
+                }
+                TableStatus::BudgetExceeded(receiver) => break receiver,
+            }
+        };
+
+        // Progress made within the budget is kept
+        assert!(!receiver.question().0.is_empty());
+        bob.check([], [&receiver]);
+
+        let state = receiver.checkpoint();
+
+        drop(receiver);
+        bob.check([], []);
+
+        // Budgets are not checkpointed: the resumed transfer is unbounded
+        let receiver = bob.resume_receive(state).unwrap();
+        let answer = sender.answer(&receiver.question()).unwrap();
+
+        let received = match run_for(receiver, &mut sender, answer, 1000) {
+            Transfer::Incomplete(..) => panic!("Transfer does not complete"),
+            Transfer::Complete(table) => table,
+        };
+
+        received.assert_records((0..1024).map(|i| (i, i)));
+        bob.check([&received], []);
+    }
+
+    #[test]
+    fn budget_bytes_sufficient() {
+        let alice: Database<u32, u32> = Database::new();
+        let bob: Database<u32, u32> = Database::new();
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let mut sender = original.send();
+
+        let mut receiver = bob.receive();
+        receiver.settings.budget = Some(SyncBudget {
+            max_bytes: Some(1 << 20),
+            max_duration: None,
+        });
+
+        let ([received], _) = run(&bob, [], [(&mut sender, receiver)]);
+        received.assert_records((0..1024).map(|i| (i, i)));
+    }
+
+    #[test]
+    fn budget_duration_exceeded() {
+        let alice: Database<u32, u32> = Database::new();
+        let bob: Database<u32, u32> = Database::new();
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let mut sender = original.send();
+
+        let mut receiver = bob.receive();
+        receiver.settings.budget = Some(SyncBudget {
+            max_bytes: None,
+            max_duration: Some(Duration::ZERO),
+        });
+
+        std::thread::sleep(Duration::from_millis(1));
+
+        match receiver.learn(sender.hello()).unwrap() {
+            TableStatus::BudgetExceeded(receiver) => bob.check([], [&receiver]),
+            _ => panic!("Receiver exceeds its time budget"),
+        }
+
+        bob.check([], []);
+    }
+
+    #[test]
+    fn flush_stress() {
+        use rand::{seq::IteratorRandom, thread_rng};
+
+        let alice: Database<u32, u32> = Database::new();
+
+        let parallel: Database<u32, u32> = Database::builder().threads(4).build().unwrap();
+
+        // A `Store` of depth 0 cannot be split: it is flushed serially
+        let serial: Database<u32, u32> = Database::builder().depth(0).build().unwrap();
+
+        let mut parallel_tables = Vec::new();
+        let mut serial_tables = Vec::new();
+
+        let mut rng = thread_rng();
+
+        for round in 0..6 {
+            // Overlapping records, so that later transfers find nodes held locally
+            let records = (0..1024)
+                .choose_multiple(&mut rng, 512)
+                .into_iter()
+                .map(|key| (key, if key % 6 == round { key + 1 } else { key }))
+                .collect::<Vec<_>>();
+
+            let original = alice.table_with_records(records.clone());
+            let mut parallel_sender = original.clone().send();
+            let mut serial_sender = original.clone().send();
+
+            let ([parallel_table], _) = run(
+                &parallel,
+                parallel_tables.iter(),
+                [(&mut parallel_sender, parallel.receive())],
+            );
+
+            let ([serial_table], _) = run(
+                &serial,
+                serial_tables.iter(),
+                [(&mut serial_sender, serial.receive())],
+            );
+
+            assert_eq!(parallel_table.commit(), original.commit());
+            assert_eq!(serial_table.commit(), original.commit());
+
+            parallel_table.assert_records(records.clone());
+            serial_table.assert_records(records);
+
+            parallel_tables.push(parallel_table);
+            serial_tables.push(serial_table);
+
+            parallel.check(parallel_tables.iter(), []);
+            serial.check(serial_tables.iter(), []);
+
+            assert_eq!(parallel.stats().references(), serial.stats().references());
+        }
+    }
+
+    #[test]
+    fn tiny_window() {
+        let alice: Database<u32, u32> = Database::new();
+        let bob: Database<u32, u32> = Database::new();
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let mut sender = original.send();
+
+        let mut receiver = bob.receive();
+        receiver.settings.window = 1;
+
+        let mut answer = sender.hello();
+        let mut steps = 0;
+
+        let received = loop {
+            steps += 1;
+
+            match receiver.learn(answer).unwrap() {
+                TableStatus::Complete(table) => break table,
+                TableStatus::BudgetExceeded(..) => unreachable!(),
+                TableStatus::Incomplete(mut receiver_t, question) => {
+                    assert!(question.0.len() <= receiver_t.settings.window);
+
+                    // Alternate windows: changes apply to the next `Question`
+                    receiver_t.settings.window = if steps % 2 == 0 { 1 } else { 2 };
+
+                    answer = sender.answer(&question).unwrap();
+                    receiver = receiver_t;
+                }
+            }
+        };
+
+        assert!(steps > 64);
+
+        received.assert_records((0..1024).map(|i| (i, i)));
+        bob.check([&received], []);
+    }
+
+    #[test]
+    fn oversized_answer() {
+        let alice: Database<u32, u32> = Database::new();
+        let bob: Database<u32, u32> = Database::new();
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let mut sender = original.send();
+
+        let mut receiver = bob.receive();
+        receiver.settings.max_answer_nodes = Some(4);
+
+        let answer = sender.hello();
+        assert!(answer.0.len() > 4);
+
+        match receiver.learn(answer) {
+            Err(e) if *e.top() == SyncError::MalformedAnswer => (),
+            Err(x) => {
+                panic!("Expected `SyncError::MalformedAnswer` but got {:?}", x)
+            }
+            _ => panic!("Receiver processes an oversized answer"),
+        }
+
+        bob.check([], []);
+    }
+
+    #[test]
+    fn transfer_stalled() {
+        let alice: Database<u32, u32> = Database::new();
+        let bob: Database<u32, u32> = Database::new();
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let mut sender = original.send();
+
+        let mut receiver = bob.receive();
+        receiver.settings.window = 4;
+        receiver.settings.max_steps = Some(8);
+
+        let mut answer = sender.hello();
+        let mut steps = 0;
+
+        loop {
+            steps += 1;
+
+            match receiver.learn(answer) {
+                Ok(TableStatus::Incomplete(receiver_t, question)) => {
+                    answer = sender.answer(&question).unwrap();
+                    receiver = receiver_t;
+                }
+                Ok(TableStatus::Complete(..)) => {
+                    panic!("Receiver completes a transfer beyond its steps")
+                }
+                Ok(TableStatus::BudgetExceeded(..)) => unreachable!(),
+                Err(e) if *e.top() == SyncError::TransferStalled => break,
+                Err(x) => {
+                    panic!("Expected `SyncError::TransferStalled` but got {:?}", x)
+                }
+            }
+        }
+
+        assert_eq!(steps, 9);
+
+        bob.check([], []);
+    }
+
+    #[test]
+    fn compact_format() {
+        let alice: Database<u32, u32> = Database::new();
+        let bob: Database<u32, u32> = Database::new();
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let mut sender = original.send();
+
+        let mut receiver = bob.receive();
+        let format = sender.negotiate(&receiver.formats());
+        assert_eq!(format, SyncFormat::Compact);
+
+        let mut answer = sender.hello();
+
+        let mut compact = 0;
+        let mut standard = 0;
+
+        let received = loop {
+            let bytes = answer.encode(format);
+
+            compact += bytes.len();
+            standard += answer.encode(SyncFormat::Standard).len();
+
+            match receiver.learn_encoded(format, &bytes).unwrap() {
+                TableStatus::Complete(table) => break table,
+                TableStatus::BudgetExceeded(..) => unreachable!(),
+                TableStatus::Incomplete(receiver_t, question) => {
+                    answer = sender.answer(&question).unwrap();
+                    receiver = receiver_t;
+                }
+            }
+        };
+
+        bob.check([&received], []);
+        received.assert_records((0..1024).map(|i| (i, i)));
+
+        assert!(compact < standard);
+    }
+
+    #[test]
+    fn compact_format_malformed() {
+        let alice: Database<u32, u32> = Database::new();
+        let bob: Database<u32, u32> = Database::new();
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let mut sender = original.send();
+
+        let mut bytes = sender.hello().encode(SyncFormat::Compact);
+        bytes.pop();
+
+        match bob.receive().learn_encoded(SyncFormat::Compact, &bytes) {
+            Err(e) if *e.top() == SyncError::MalformedAnswer => (),
+            Err(x) => panic!("Expected `SyncError::MalformedAnswer` but got {:?}", x),
+            _ => panic!("Receiver accepts a truncated answer"),
+        }
+
+        bob.check([], []);
+    }
+
+    // Interrupts the transfer from `sender` to `receiver` after a few steps, returning
+    // its serialized checkpoint. The receiving end must already hold some subtrees
+    fn interrupted(
+        receiver: TableReceiver<u32, u32>,
+        sender: &mut TableSender<u32, u32>,
+    ) -> Vec<u8> {
+        let mut receiver = receiver;
+        receiver.settings.window = 4;
+
+        let answer = sender.hello();
+
+        let receiver = match run_for(receiver, sender, answer, 4) {
+            Transfer::Incomplete(_, receiver, _) => receiver,
+            Transfer::Complete(_) => panic!("Should take longer than 4 steps"),
+        };
+
+        assert!(!receiver.held().is_empty());
+
+        bincode::serialize(&receiver.checkpoint()).unwrap()
+    }
+
+    #[test]
+    fn resume_same_database() {
+        let alice: Database<u32, u32> = Database::new();
+        let bob: Database<u32, u32> = Database::new();
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let commit = original.commit();
+        let mut sender = original.send();
+
+        let existing =
+            bob.table_with_records((0..1024).map(|i| (i, if i < 32 { i + 1 } else { i })));
+
+        let state = interrupted(bob.receive(), &mut sender);
+        bob.check([&existing], []);
+
+        let state = bincode::deserialize(&state).unwrap();
+        let receiver = bob.resume_receive(state).unwrap();
+
+        let answer = sender.answer(&receiver.question()).unwrap();
+
+        let received = match run_for(receiver, &mut sender, answer, 1000) {
+            Transfer::Incomplete(..) => panic!("Transfer does not complete"),
+            Transfer::Complete(table) => table,
+        };
+
+        received.assert_records((0..1024).map(|i| (i, i)));
+        assert_eq!(received.commit(), commit);
+
+        bob.check([&existing, &received], []);
+    }
+
+    #[test]
+    fn resume_fresh_database() {
+        let alice: Database<u32, u32> = Database::new();
+        let bob: Database<u32, u32> = Database::new();
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let mut sender = original.send();
+
+        let existing =
+            bob.table_with_records((0..1024).map(|i| (i, if i < 32 { i + 1 } else { i })));
+
+        let state = interrupted(bob.receive(), &mut sender);
+
+        // Subtrees held in `bob` are missing from `carol`, and are asked for again
+        let carol: Database<u32, u32> = Database::new();
+
+        let state = bincode::deserialize(&state).unwrap();
+        let receiver = carol.resume_receive(state).unwrap();
+
+        let answer = sender.answer(&receiver.question()).unwrap();
+
+        let received = match run_for(receiver, &mut sender, answer, 1000) {
+            Transfer::Incomplete(..) => panic!("Transfer does not complete"),
+            Transfer::Complete(table) => table,
+        };
+
+        received.assert_records((0..1024).map(|i| (i, i)));
+
+        bob.check([&existing], []);
+        carol.check([&received], []);
+    }
+
+    #[test]
+    fn resume_abandoned() {
+        let alice: Database<u32, u32> = Database::new();
+        let bob: Database<u32, u32> = Database::new();
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let mut sender = original.send();
+
+        let existing =
+            bob.table_with_records((0..1024).map(|i| (i, if i < 32 { i + 1 } else { i })));
+
+        let state = interrupted(bob.receive(), &mut sender);
+
+        let state = bincode::deserialize(&state).unwrap();
+        let receiver = bob.resume_receive(state).unwrap();
+
+        // Subtrees of `existing` are held again upon resuming
+        assert!(!receiver.held().is_empty());
+        bob.check([&existing], [&receiver]);
+
+        drop(receiver);
+        bob.check([&existing], []);
+    }
+
+    #[test]
+    fn resume_not_started() {
+        let alice: Database<u32, u32> = Database::new();
+        let bob: Database<u32, u32> = Database::new();
+
+        let original = alice.table_with_records((0..256).map(|i| (i, i)));
+        let mut sender = original.send();
+
+        let state = bincode::serialize(&bob.receive().checkpoint()).unwrap();
+        let receiver = bob
+            .resume_receive(bincode::deserialize(&state).unwrap())
+            .unwrap();
+
+        assert!(receiver.question().0.is_empty());
+
+        let ([received], _) = run(&bob, [], [(&mut sender, receiver)]);
+
+        received.assert_records((0..256).map(|i| (i, i)));
+        bob.check([&received], []);
+    }
 }
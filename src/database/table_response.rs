@@ -1,8 +1,8 @@
 use crate::{
     common::store::Field,
     database::{
-        interact::{Action, Batch},
-        Query, Tid,
+        interact::{Action, Batch, Operation},
+        Query, QueryOutcome, Tid,
     },
 };
 
@@ -20,21 +20,92 @@ where
         TableResponse { tid, batch }
     }
 
+    /// Returns the value read by the `Query`'s operation (see
+    /// [`TableTransaction::get`]), if the key was held by the [`Table`].
+    ///
+    /// `None` is also returned if the `Query` was not issued by a read
+    /// of the transaction that produced this `TableResponse`: use
+    /// [`outcome`] to tell the two cases apart.
+    ///
+    /// [`TableTransaction::get`]: crate::database::TableTransaction::get
+    /// [`Table`]: crate::database::Table
+    /// [`outcome`]: crate::database::TableResponse::outcome
     pub fn get(&self, query: &Query) -> Option<&Value> {
+        match self.outcome(query) {
+            QueryOutcome::Found(value) => Some(value),
+            QueryOutcome::Absent | QueryOutcome::NotQueried => None,
+        }
+    }
+
+    /// Returns the outcome of the read issued by `query` (see
+    /// [`TableTransaction::get`]), distinguishing keys that were read and
+    /// found absent from `Query`s that were not part of the transaction
+    /// (e.g., `Query`s from another transaction, or returned by
+    /// [`TableTransaction::remove_expecting`]).
+    ///
+    /// [`TableTransaction::get`]: crate::database::TableTransaction::get
+    /// [`TableTransaction::remove_expecting`]: crate::database::TableTransaction::remove_expecting
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::{Database, QueryOutcome, TableTransaction};
+    ///
+    /// let database: Database<u32, u32> = Database::new();
+    /// let mut table = database.table_with_records([(0, 1)]);
+    ///
+    /// let mut transaction = TableTransaction::new();
+    /// let found = transaction.get(&0).unwrap();
+    /// let absent = transaction.get(&1).unwrap();
+    /// let response = table.execute(transaction);
+    ///
+    /// assert_eq!(response.outcome(&found), QueryOutcome::Found(&1));
+    /// assert_eq!(response.outcome(&absent), QueryOutcome::Absent);
+    ///
+    /// let mut transaction: TableTransaction<u32, u32> = TableTransaction::new();
+    /// let foreign = transaction.get(&0).unwrap();
+    ///
+    /// assert_eq!(response.outcome(&foreign), QueryOutcome::NotQueried);
+    /// ```
+    pub fn outcome(&self, query: &Query) -> QueryOutcome<'_, Value> {
+        if query.tid != self.tid {
+            return QueryOutcome::NotQueried;
+        }
+
+        match self.search(query).map(|operation| &operation.action) {
+            Some(Action::Get(Some(holder))) => QueryOutcome::Found(holder),
+            Some(Action::Get(None)) => QueryOutcome::Absent,
+            _ => QueryOutcome::NotQueried,
+        }
+    }
+
+    /// Returns `true` if the key removed by the `Query`'s operation (see
+    /// [`TableTransaction::remove_expecting`]) was present in the [`Table`].
+    ///
+    /// [`TableTransaction::remove_expecting`]: crate::database::TableTransaction::remove_expecting
+    /// [`Table`]: crate::database::Table
+    pub fn removed(&self, query: &Query) -> bool {
+        match &self.operation(query).action {
+            Action::Remove(removed) => *removed,
+            _ => unreachable!(),
+        }
+    }
+
+    fn operation(&self, query: &Query) -> &Operation<Key, Value> {
         assert_eq!(
             query.tid, self.tid,
-            "called `Response::get` with a foreign `Query`"
+            "called `Response::removed` with a foreign `Query`"
         );
 
-        let index = self
-            .batch
-            .operations()
+        self.search(query).unwrap()
+    }
+
+    fn search(&self, query: &Query) -> Option<&Operation<Key, Value>> {
+        let operations = self.batch.operations();
+
+        operations
             .binary_search_by_key(&query.path, |operation| operation.path)
-            .unwrap();
-        match &self.batch.operations()[index].action {
-            Action::Get(Some(holder)) => Some(holder),
-            Action::Get(None) => None,
-            _ => unreachable!(),
-        }
+            .ok()
+            .map(|index| &operations[index])
     }
 }
@@ -4,22 +4,21 @@ use crate::{
         errors::SyncError,
         store::{Handle, Label, Node, Store},
         sync::ANSWER_DEPTH,
-        Question, Table, TableAnswer,
+        Global, Question, StoreAllocator, SyncFormat, Table, TableAnswer,
     },
 };
 
 use doomstack::{here, Doom, ResultExt, Top};
 
-use std::collections::hash_map::Entry::{Occupied, Vacant};
+pub struct TableSender<Key: Field, Value: Field, A: StoreAllocator = Global>(Handle<Key, Value, A>);
 
-pub struct TableSender<Key: Field, Value: Field>(Handle<Key, Value>);
-
-impl<Key, Value> TableSender<Key, Value>
+impl<Key, Value, A> TableSender<Key, Value, A>
 where
     Key: Field,
     Value: Field,
+    A: StoreAllocator,
 {
-    pub(crate) fn from_handle(handle: Handle<Key, Value>) -> Self {
+    pub(crate) fn from_handle(handle: Handle<Key, Value, A>) -> Self {
         TableSender(handle)
     }
 
@@ -27,6 +26,20 @@ where
         self.answer(&Question(vec![self.0.root])).unwrap()
     }
 
+    /// Picks the first of `formats` (listed by the receiving end by decreasing
+    /// preference, see [`TableReceiver::formats`]) that is supported, falling
+    /// back to [`SyncFormat::Standard`].
+    ///
+    /// [`TableReceiver::formats`]: crate::database::TableReceiver::formats
+    /// [`SyncFormat::Standard`]: crate::database::SyncFormat::Standard
+    pub fn negotiate(&self, formats: &[SyncFormat]) -> SyncFormat {
+        formats
+            .iter()
+            .copied()
+            .find(|format| SyncFormat::SUPPORTED.contains(format))
+            .unwrap_or_default()
+    }
+
     pub fn answer(
         &mut self,
         question: &Question,
@@ -45,23 +58,42 @@ where
         Ok(TableAnswer(collector))
     }
 
-    pub fn end(self) -> Table<Key, Value> {
+    /// Answers each of `questions` in turn, as [`answer`] would.
+    ///
+    /// Questions are pulled lazily, one per answer: `questions` can be fed
+    /// (e.g., through a channel) with the [`Question`]s a [`TableReceiver`]
+    /// issues upon learning the answers previously yielded.
+    ///
+    /// [`answer`]: crate::database::TableSender::answer
+    /// [`Question`]: crate::database::Question
+    /// [`TableReceiver`]: crate::database::TableReceiver
+    pub fn stream<'s, I>(
+        &'s mut self,
+        questions: I,
+    ) -> impl Iterator<Item = Result<TableAnswer<Key, Value>, Top<SyncError>>> + 's
+    where
+        I: IntoIterator<Item = Question>,
+        I::IntoIter: 's,
+    {
+        questions
+            .into_iter()
+            .map(move |question| self.answer(&question))
+    }
+
+    pub fn end(self) -> Table<Key, Value, A> {
         Table::from_handle(self.0)
     }
 
     fn grab(
-        store: &mut Store<Key, Value>,
+        store: &mut Store<Key, Value, A>,
         collector: &mut Vec<Node<Key, Value>>,
         label: Label,
         ttl: u8,
     ) -> Result<(), Top<SyncError>> {
         if !label.is_empty() {
-            let node = match store.entry(label) {
-                Occupied(entry) => {
-                    let node = entry.get().node.clone();
-                    Ok(node)
-                }
-                Vacant(..) => SyncError::MalformedQuestion.fail().spot(here!()),
+            let node = match store.lookup(label) {
+                Some(node) => Ok(node.clone()),
+                None => SyncError::MalformedQuestion.fail().spot(here!()),
             }?;
 
             let recur = match node {
@@ -87,9 +119,11 @@ where
 mod tests {
     use super::*;
 
-    use crate::database::{store::MapId, Database};
+    use crate::database::{store::MapId, Database, TableStatus};
+
+    use hashbrown::hash_map::Entry::Occupied;
 
-    use std::collections::hash_map::Entry::Occupied;
+    use std::sync::mpsc;
 
     #[test]
     fn answer_empty() {
@@ -175,4 +209,55 @@ mod tests {
 
         assert_eq!(answer, TableAnswer(vec!(n0, n1, n2)));
     }
+
+    #[test]
+    fn negotiate() {
+        let database: Database<u32, u32> = Database::new();
+        let send = database.empty_table().send();
+
+        assert_eq!(
+            send.negotiate(&database.receive().formats()),
+            SyncFormat::Compact
+        );
+
+        assert_eq!(
+            send.negotiate(&[SyncFormat::Standard, SyncFormat::Compact]),
+            SyncFormat::Standard
+        );
+
+        assert_eq!(send.negotiate(&[]), SyncFormat::Standard);
+    }
+
+    #[test]
+    fn stream() {
+        let alice: Database<u32, u32> = Database::new();
+        let bob: Database<u32, u32> = Database::new();
+
+        let table = alice.table_with_records((0..256).map(|i| (i, i)));
+        let commit = table.commit();
+
+        let mut send = table.send();
+
+        let (questions, inbox) = mpsc::channel();
+        questions.send(Question(vec![send.0.root])).unwrap();
+
+        let mut answers = send.stream(inbox.iter());
+        let mut receiver = bob.receive();
+
+        let received = loop {
+            let answer = answers.next().unwrap().unwrap();
+
+            match receiver.learn(answer).unwrap() {
+                TableStatus::Complete(table) => break table,
+                TableStatus::BudgetExceeded(..) => unreachable!(),
+                TableStatus::Incomplete(receiver_t, question) => {
+                    receiver = receiver_t;
+                    questions.send(question).unwrap();
+                }
+            }
+        };
+
+        assert_eq!(received.commit(), commit);
+        received.assert_records((0..256).map(|i| (i, i)));
+    }
 }
@@ -0,0 +1,129 @@
+use crate::{
+    common::store::Field,
+    database::{errors::StateError, store::Wrap},
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+use serde::de::DeserializeOwned;
+
+use std::io::{Read, Write};
+
+use talk::crypto::primitives::hash::{Hash, HASH_LENGTH};
+
+// The layout of a `.zstate` file (all integers are little-endian):
+//
+//  - `MAGIC` (6 bytes);
+//  - `VERSION` (2 bytes);
+//  - the commitment of the `Table` (`HASH_LENGTH` bytes);
+//  - the number of records (8 bytes);
+//  - the records, each a `bincode`-serialized `(Key, Value)` pair.
+//
+// Records are not sorted: their order does not affect the commitment.
+
+pub(crate) const MAGIC: [u8; 6] = *b"ZSTATE";
+pub(crate) const VERSION: u16 = 1;
+
+// Bound on the records preallocated upon reading, so that a corrupted
+// record count cannot trigger a huge allocation
+const PREALLOCATE: u64 = 1 << 16;
+
+pub(crate) fn write<Key, Value, W>(
+    mut writer: W,
+    commitment: Hash,
+    records: &[(Wrap<Key>, Wrap<Value>)],
+) -> Result<(), Top<StateError>>
+where
+    Key: Field,
+    Value: Field,
+    W: Write,
+{
+    let mut header = Vec::with_capacity(MAGIC.len() + 2 + HASH_LENGTH + 8);
+
+    header.extend_from_slice(&MAGIC);
+    header.extend_from_slice(&VERSION.to_le_bytes());
+    header.extend_from_slice(&commitment.to_bytes());
+    header.extend_from_slice(&(records.len() as u64).to_le_bytes());
+
+    writer
+        .write_all(&header)
+        .map_err(|_| StateError::WriteFailed.into_top())
+        .spot(here!())?;
+
+    for (key, value) in records {
+        bincode::serialize_into(&mut writer, &(key.inner(), value.inner()))
+            .map_err(|_| StateError::SerializeFailed.into_top())
+            .spot(here!())?;
+    }
+
+    writer
+        .flush()
+        .map_err(|_| StateError::WriteFailed.into_top())
+        .spot(here!())
+}
+
+// The commitment and records of a `.zstate` file
+pub(crate) type State<Key, Value> = (Hash, Vec<(Key, Value)>);
+
+pub(crate) fn read<Key, Value, R>(mut reader: R) -> Result<State<Key, Value>, Top<StateError>>
+where
+    Key: Field + DeserializeOwned,
+    Value: Field + DeserializeOwned,
+    R: Read,
+{
+    let mut magic = [0u8; MAGIC.len()];
+
+    read_header(&mut reader, &mut magic)?;
+
+    // Reject foreign files before reading any further
+    if magic != MAGIC {
+        return StateError::MalformedHeader.fail().spot(here!());
+    }
+
+    let mut version = [0u8; 2];
+    let mut commitment = [0u8; HASH_LENGTH];
+    let mut count = [0u8; 8];
+
+    read_header(&mut reader, &mut version)?;
+    read_header(&mut reader, &mut commitment)?;
+    read_header(&mut reader, &mut count)?;
+
+    if u16::from_le_bytes(version) != VERSION {
+        return StateError::UnsupportedVersion.fail().spot(here!());
+    }
+
+    let commitment = Hash::from_bytes(commitment);
+    let count = u64::from_le_bytes(count);
+
+    let mut records = Vec::with_capacity(count.min(PREALLOCATE) as usize);
+
+    for _ in 0..count {
+        let record = bincode::deserialize_from(&mut reader)
+            .map_err(|_| StateError::MalformedRecords.into_top())
+            .spot(here!())?;
+
+        records.push(record);
+    }
+
+    // The file must end with the last record
+    let trailing = reader
+        .read(&mut [0u8])
+        .map_err(|_| StateError::ReadFailed.into_top())
+        .spot(here!())?;
+
+    if trailing > 0 {
+        return StateError::MalformedRecords.fail().spot(here!());
+    }
+
+    Ok((commitment, records))
+}
+
+fn read_header<R>(reader: &mut R, field: &mut [u8]) -> Result<(), Top<StateError>>
+where
+    R: Read,
+{
+    reader
+        .read_exact(field)
+        .map_err(|_| StateError::MalformedHeader.into_top())
+        .spot(here!())
+}
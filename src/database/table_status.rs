@@ -1,9 +1,19 @@
 use crate::{
     common::store::Field,
-    database::{Question, Table, TableReceiver},
+    database::{Global, Question, StoreAllocator, Table, TableReceiver},
 };
 
-pub enum TableStatus<Key: Field, Value: Field> {
-    Complete(Table<Key, Value>),
-    Incomplete(TableReceiver<Key, Value>, Question),
+pub enum TableStatus<Key: Field, Value: Field, A: StoreAllocator = Global> {
+    Complete(Table<Key, Value, A>),
+    Incomplete(TableReceiver<Key, Value, A>, Question),
+    /// The budget of the [`TableReceiver`] (see [`SyncBudget`]) was exceeded
+    /// before the transfer completed. The receiver is handed back without
+    /// processing the latest [`TableAnswer`], so that the transfer can be
+    /// checkpointed (see [`TableReceiver::checkpoint`]) and resumed later.
+    ///
+    /// [`TableReceiver`]: crate::database::TableReceiver
+    /// [`SyncBudget`]: crate::database::SyncBudget
+    /// [`TableAnswer`]: crate::database::TableAnswer
+    /// [`TableReceiver::checkpoint`]: crate::database::TableReceiver::checkpoint
+    BudgetExceeded(TableReceiver<Key, Value, A>),
 }
@@ -2,8 +2,8 @@ use crate::{
     common::{store::Field, tree::Path},
     database::{
         errors::QueryError,
-        interact::{Batch, Operation},
-        Query,
+        interact::{Action, Batch, Operation},
+        Query, TableBatch,
     },
 };
 
@@ -17,12 +17,16 @@ use std::{
 
 pub(crate) type Tid = usize;
 
+// Expiry epoch set (`Some`) or cleared (`None`) for a path
+pub(crate) type Expiry = (Path, Option<u64>);
+
 static TID: AtomicUsize = AtomicUsize::new(0);
 
 pub struct TableTransaction<Key: Field, Value: Field> {
     tid: Tid,
     operations: Vec<Operation<Key, Value>>,
     paths: HashSet<Path>,
+    expiries: Vec<Expiry>,
 }
 
 impl<Key, Value> TableTransaction<Key, Value>
@@ -35,48 +39,292 @@ where
             tid: TID.fetch_add(1, Ordering::Relaxed),
             operations: Vec::new(),
             paths: HashSet::new(),
+            expiries: Vec::new(),
         }
     }
 
     pub fn get(&mut self, key: &Key) -> Result<Query, Top<QueryError>> {
         let operation = Operation::<Key, Value>::get(key).pot(QueryError::HashError, here!())?;
+        self.push(operation, None)
+    }
 
-        if self.paths.insert(operation.path) {
-            let query = Query {
-                tid: self.tid,
-                path: operation.path,
-            };
+    pub fn set(&mut self, key: Key, value: Value) -> Result<(), Top<QueryError>> {
+        self.set_expiring(key, value, None)
+    }
 
-            self.operations.push(operation);
-            Ok(query)
-        } else {
-            QueryError::KeyCollision.fail().spot(here!())
-        }
+    /// Sets `key` to `value`, scheduling the record to be removed by
+    /// [`Table::expire`] once `epoch` is reached.
+    ///
+    /// Expiry epochs are kept aside from the [`Table`]'s records: they
+    /// do not affect its commitment, and are not transferred by [`Table::send`].
+    /// Setting or removing `key` again without a TTL clears its expiry epoch.
+    ///
+    /// [`Table`]: crate::database::Table
+    /// [`Table::expire`]: crate::database::Table::expire
+    /// [`Table::send`]: crate::database::Table::send
+    pub fn set_with_ttl(
+        &mut self,
+        key: Key,
+        value: Value,
+        epoch: u64,
+    ) -> Result<(), Top<QueryError>> {
+        self.set_expiring(key, value, Some(epoch))
     }
 
-    pub fn set(&mut self, key: Key, value: Value) -> Result<(), Top<QueryError>> {
+    fn set_expiring(
+        &mut self,
+        key: Key,
+        value: Value,
+        epoch: Option<u64>,
+    ) -> Result<(), Top<QueryError>> {
         let operation = Operation::set(key, value).pot(QueryError::HashError, here!())?;
-
-        if self.paths.insert(operation.path) {
-            self.operations.push(operation);
-            Ok(())
-        } else {
-            QueryError::KeyCollision.fail().spot(here!())
-        }
+        self.push(operation, epoch).map(|_| ())
     }
 
     pub fn remove(&mut self, key: &Key) -> Result<(), Top<QueryError>> {
+        self.remove_expecting(key).map(|_| ())
+    }
+
+    /// Removes `key`, like [`remove`], returning a [`Query`] to learn from
+    /// the [`TableResponse`] whether `key` was present in the [`Table`]
+    /// (see [`TableResponse::removed`]).
+    ///
+    /// # Errors
+    ///
+    /// If `key` cannot be hashed, [`HashError`] is returned. If the
+    /// transaction already operates on `key`, [`DuplicateOperation`] is
+    /// returned.
+    ///
+    /// [`remove`]: crate::database::TableTransaction::remove
+    /// [`Query`]: crate::database::Query
+    /// [`TableResponse`]: crate::database::TableResponse
+    /// [`Table`]: crate::database::Table
+    /// [`TableResponse::removed`]: crate::database::TableResponse::removed
+    /// [`HashError`]: crate::database::errors::QueryError::HashError
+    /// [`DuplicateOperation`]: crate::database::errors::QueryError::DuplicateOperation
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::{Database, TableTransaction};
+    ///
+    /// let database: Database<u32, u32> = Database::new();
+    /// let mut table = database.table_with_records([(0, 0)]);
+    ///
+    /// let mut transaction = TableTransaction::new();
+    /// let present = transaction.remove_expecting(&0).unwrap();
+    /// let absent = transaction.remove_expecting(&1).unwrap();
+    ///
+    /// let response = table.execute(transaction);
+    ///
+    /// assert!(response.removed(&present));
+    /// assert!(!response.removed(&absent));
+    /// ```
+    pub fn remove_expecting(&mut self, key: &Key) -> Result<Query, Top<QueryError>> {
         let operation = Operation::remove(key).pot(QueryError::HashError, here!())?;
+        self.push(operation, None)
+    }
+
+    // Adds `operation` to the transaction, which operates at most once on
+    // each path. Writes set the expiry epoch of their path to `epoch`.
+    fn push(
+        &mut self,
+        operation: Operation<Key, Value>,
+        epoch: Option<u64>,
+    ) -> Result<Query, Top<QueryError>> {
+        if !self.paths.insert(operation.path) {
+            return QueryError::DuplicateOperation {
+                path: operation.path,
+            }
+            .fail()
+            .spot(here!());
+        }
 
-        if self.paths.insert(operation.path) {
-            self.operations.push(operation);
-            Ok(())
-        } else {
-            QueryError::KeyCollision.fail().spot(here!())
+        if !matches!(operation.action, Action::Get(..)) {
+            self.expiries.push((operation.path, epoch));
         }
+
+        let query = Query {
+            tid: self.tid,
+            path: operation.path,
+        };
+
+        self.operations.push(operation);
+        Ok(query)
+    }
+
+    /// Appends the operations of `other` to the transaction, so that both
+    /// can be executed at once.
+    ///
+    /// The merged transaction keeps the identity of `self`: [`Query`]s
+    /// obtained from `self` remain usable with its [`TableResponse`], while
+    /// [`Query`]s obtained from `other` do not (see [`TableResponse::outcome`]).
+    ///
+    /// # Errors
+    ///
+    /// If `self` and `other` operate on a common key, [`DuplicateOperation`]
+    /// is returned, and both transactions are dropped.
+    ///
+    /// [`Query`]: crate::database::Query
+    /// [`TableResponse`]: crate::database::TableResponse
+    /// [`TableResponse::outcome`]: crate::database::TableResponse::outcome
+    /// [`DuplicateOperation`]: crate::database::errors::QueryError::DuplicateOperation
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::database::{Database, TableTransaction};
+    ///
+    /// let database: Database<&str, i32> = Database::new();
+    /// let mut table = database.empty_table();
+    ///
+    /// let mut first = TableTransaction::new();
+    /// first.set("alice", 31).unwrap();
+    ///
+    /// let mut second = TableTransaction::new();
+    /// second.set("bob", 44).unwrap();
+    ///
+    /// table.execute(first.merge(second).unwrap());
+    ///
+    /// assert_eq!(table.get(&"bob").unwrap(), Some(44));
+    /// ```
+    pub fn merge(mut self, other: TableTransaction<Key, Value>) -> Result<Self, Top<QueryError>> {
+        if let Some(path) = other
+            .operations
+            .iter()
+            .map(|operation| operation.path)
+            .find(|path| self.paths.contains(path))
+        {
+            return QueryError::DuplicateOperation { path }.fail().spot(here!());
+        }
+
+        self.paths.extend(other.paths);
+        self.operations.extend(other.operations);
+        self.expiries.extend(other.expiries);
+
+        Ok(self)
+    }
+
+    /// Finalizes the transaction into a [`TableBatch`], to be executed in
+    /// parts (see its documentation for more details).
+    ///
+    /// [`TableBatch`]: crate::database::TableBatch
+    pub fn into_batch(self) -> TableBatch<Key, Value> {
+        let (tid, batch, expiries) = self.finalize();
+        TableBatch::new(tid, batch, expiries)
+    }
+
+    pub(crate) fn finalize(self) -> (Tid, Batch<Key, Value>, Vec<Expiry>) {
+        let (tid, operations, expiries) = self.unwrap();
+        (tid, Batch::new(operations), expiries)
+    }
+
+    pub(crate) fn operations(&self) -> &[Operation<Key, Value>] {
+        &self.operations
+    }
+
+    // Unlike `finalize`, preserves the order in which operations were added
+    pub(crate) fn unwrap(self) -> (Tid, Vec<Operation<Key, Value>>, Vec<Expiry>) {
+        (self.tid, self.operations, self.expiries)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path<Key>(key: &Key) -> Path
+    where
+        Key: Field,
+    {
+        Operation::<Key, ()>::get(key).unwrap().path
+    }
+
+    fn assert_duplicate(result: Result<(), Top<QueryError>>, expected: Path) {
+        match result.unwrap_err().top() {
+            QueryError::DuplicateOperation { path } => assert_eq!(*path, expected),
+            _ => panic!("unexpected error"),
+        }
+    }
+
+    #[test]
+    fn duplicate_set_set() {
+        let mut transaction: TableTransaction<u32, u32> = TableTransaction::new();
+
+        transaction.set(5, 0).unwrap();
+        assert_duplicate(transaction.set(5, 1), path(&5));
+        assert_duplicate(transaction.set_with_ttl(5, 1, 10), path(&5));
+
+        // Other keys are unaffected
+        transaction.set(6, 0).unwrap();
+        assert_eq!(transaction.operations().len(), 2);
+    }
+
+    #[test]
+    fn duplicate_set_remove() {
+        let mut transaction: TableTransaction<u32, u32> = TableTransaction::new();
+
+        transaction.set(5, 0).unwrap();
+        assert_duplicate(transaction.remove(&5), path(&5));
+        assert_duplicate(transaction.remove_expecting(&5).map(|_| ()), path(&5));
+
+        transaction.remove(&6).unwrap();
+        assert_duplicate(transaction.set(6, 0), path(&6));
+    }
+
+    #[test]
+    fn duplicate_get_set() {
+        let mut transaction: TableTransaction<u32, u32> = TableTransaction::new();
+
+        transaction.get(&5).unwrap();
+        assert_duplicate(transaction.set(5, 0), path(&5));
+        assert_duplicate(transaction.get(&5).map(|_| ()), path(&5));
+
+        transaction.set(6, 0).unwrap();
+        assert_duplicate(transaction.get(&6).map(|_| ()), path(&6));
+    }
+
+    #[test]
+    fn merge_disjoint() {
+        let mut first: TableTransaction<u32, u32> = TableTransaction::new();
+        let query = first.get(&0).unwrap();
+        first.set(1, 1).unwrap();
+
+        let mut second: TableTransaction<u32, u32> = TableTransaction::new();
+        second.set_with_ttl(2, 2, 10).unwrap();
+        second.remove(&3).unwrap();
+
+        let tid = first.tid;
+        let merged = first.merge(second).unwrap();
+
+        assert_eq!(merged.tid, tid);
+        assert_eq!(query.tid, tid);
+
+        let paths = merged
+            .operations()
+            .iter()
+            .map(|operation| operation.path)
+            .collect::<Vec<_>>();
+
+        assert_eq!(paths, vec![path(&0), path(&1), path(&2), path(&3)]);
+        assert_eq!(merged.paths.len(), 4);
+        assert_eq!(merged.expiries.len(), 3);
+
+        // Merging an empty transaction changes nothing
+        let merged = merged.merge(TableTransaction::new()).unwrap();
+        assert_eq!(merged.operations().len(), 4);
+    }
+
+    #[test]
+    fn merge_colliding() {
+        let mut first: TableTransaction<u32, u32> = TableTransaction::new();
+        first.set(0, 0).unwrap();
+        first.get(&5).unwrap();
+
+        let mut second: TableTransaction<u32, u32> = TableTransaction::new();
+        second.set(1, 1).unwrap();
+        second.remove(&5).unwrap();
 
-    pub(crate) fn finalize(self) -> (Tid, Batch<Key, Value>) {
-        (self.tid, Batch::new(self.operations))
+        assert_duplicate(first.merge(second).map(|_| ()), path(&5));
     }
 }
@@ -0,0 +1,120 @@
+use crate::{
+    common::{store::Field, tree::Path},
+    database::{errors::TransitionError, interact::Action, TableTransaction},
+    map::Map,
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+use serde::{Deserialize, Serialize};
+
+use talk::crypto::primitives::hash::Hash;
+
+/// A proof that executing a [`TableTransaction`] on a [`Table`] moves its
+/// commitment from a pre-commitment to a post-commitment, obtained through
+/// [`Table::apply_and_prove`].
+///
+/// The proof carries the branches of the [`Table`] (prior to the transaction)
+/// along the paths of all keys set or removed by the transaction. Every
+/// sibling along those paths is revealed one level deep, so that the verifier
+/// can tell leaves from internal nodes when compacting the tree after removals.
+/// All other branches are replaced by their digests.
+///
+/// A verifier holding only the pre-commitment and the transaction can
+/// [`verify`] the proof, thus learning the post-commitment.
+///
+/// [`TableTransaction`]: crate::database::TableTransaction
+/// [`Table`]: crate::database::Table
+/// [`Table::apply_and_prove`]: crate::database::Table::apply_and_prove
+/// [`verify`]: crate::database::TransitionProof::verify
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TransitionProof<Key: Field, Value: Field> {
+    pre: Hash,
+    post: Hash,
+    witness: Map<Key, Value>,
+}
+
+impl<Key, Value> TransitionProof<Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    pub(crate) fn new(pre: Hash, post: Hash, witness: Map<Key, Value>) -> Self {
+        TransitionProof { pre, post, witness }
+    }
+
+    /// Returns the commitment of the [`Table`] before the transaction.
+    ///
+    /// [`Table`]: crate::database::Table
+    pub fn pre(&self) -> Hash {
+        self.pre
+    }
+
+    /// Returns the commitment of the [`Table`] after the transaction.
+    ///
+    /// [`Table`]: crate::database::Table
+    pub fn post(&self) -> Hash {
+        self.post
+    }
+
+    /// Verifies that executing `transaction` on a [`Table`] committed to
+    /// by `pre` yields a [`Table`] committed to by [`post`].
+    ///
+    /// Only the sets and removals of `transaction` are checked: its reads
+    /// do not affect the commitment.
+    ///
+    /// # Errors
+    ///
+    /// If `pre` does not match the pre-commitment of the proof, or the
+    /// branches carried by the proof, [`PreMismatch`] is returned.
+    ///
+    /// If the branches carried by the proof do not cover `transaction`,
+    /// [`WitnessIncomplete`] is returned.
+    ///
+    /// If executing `transaction` does not yield the post-commitment
+    /// of the proof, [`PostMismatch`] is returned.
+    ///
+    /// [`Table`]: crate::database::Table
+    /// [`post`]: crate::database::TransitionProof::post
+    /// [`PreMismatch`]: crate::database::errors::TransitionError::PreMismatch
+    /// [`WitnessIncomplete`]: crate::database::errors::TransitionError::WitnessIncomplete
+    /// [`PostMismatch`]: crate::database::errors::TransitionError::PostMismatch
+    pub fn verify(
+        &self,
+        pre: Hash,
+        transaction: &TableTransaction<Key, Value>,
+    ) -> Result<(), Top<TransitionError>>
+    where
+        Key: Clone,
+        Value: Clone,
+    {
+        if self.pre != pre || self.witness.commit() != pre {
+            return TransitionError::PreMismatch.fail().spot(here!());
+        }
+
+        let writes: Vec<(Path, Option<(Key, Value)>)> = transaction
+            .operations()
+            .iter()
+            .filter_map(|operation| match &operation.action {
+                Action::Get(..) => None,
+                Action::Set(key, value) => Some((
+                    operation.path,
+                    Some(((**key.inner()).clone(), (**value.inner()).clone())),
+                )),
+                Action::Remove(..) => Some((operation.path, None)),
+            })
+            .collect();
+
+        let mut witness = self.witness.clone();
+
+        witness
+            .update_strict(writes)
+            .pot(TransitionError::WitnessIncomplete, here!())?;
+
+        if witness.commit() != self.post {
+            return TransitionError::PostMismatch.fail().spot(here!());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{
+    common::store::Field,
+    database::{errors::QueryError, store::Cell, Global, StoreAllocator, Table, TableTransaction},
+};
+
+use doomstack::Top;
+
+use talk::crypto::primitives::{hash, hash::Hash};
+
+type Staged<'t, Key, Value, A> = (&'t mut Table<Key, Value, A>, TableTransaction<Key, Value>);
+
+// Documentation links
+#[allow(unused_imports)]
+use crate::database::Database;
+
+/// Stages writes to several [`Table`]s of a [`Database`], to be applied
+/// all together or not at all by [`Database::transact`].
+///
+/// A [`Table`] is staged by [`table`], which returns a [`StagedTable`] to
+/// address it in subsequent writes. If any write fails to stage (or
+/// [`abort`] is called), the `TxnContext` is aborted, and none of its
+/// writes is applied.
+///
+/// [`Table`]: crate::database::Table
+/// [`Database`]: crate::database::Database
+/// [`Database::transact`]: crate::database::Database::transact
+/// [`StagedTable`]: crate::database::StagedTable
+/// [`table`]: crate::database::TxnContext::table
+/// [`abort`]: crate::database::TxnContext::abort
+pub struct TxnContext<'t, Key: Field, Value: Field, A: StoreAllocator = Global> {
+    cell: Cell<Key, Value, A>,
+    staged: Vec<Staged<'t, Key, Value, A>>,
+    aborted: bool,
+}
+
+/// A [`Table`] staged in a [`TxnContext`].
+///
+/// [`Table`]: crate::database::Table
+/// [`TxnContext`]: crate::database::TxnContext
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StagedTable(usize);
+
+impl<'t, Key, Value, A> TxnContext<'t, Key, Value, A>
+where
+    Key: Field,
+    Value: Field,
+    A: StoreAllocator,
+{
+    pub(crate) fn new(cell: Cell<Key, Value, A>) -> Self {
+        TxnContext {
+            cell,
+            staged: Vec::new(),
+            aborted: false,
+        }
+    }
+
+    /// Stages `table`, returning a [`StagedTable`] to address its writes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `table` does not belong to the [`Database`] the
+    /// `TxnContext` was created by.
+    ///
+    /// [`StagedTable`]: crate::database::StagedTable
+    /// [`Database`]: crate::database::Database
+    pub fn table(&mut self, table: &'t mut Table<Key, Value, A>) -> StagedTable {
+        assert!(
+            table.belongs_to(&self.cell),
+            "called `TxnContext::table` with a `Table` from another `Database`"
+        );
+
+        self.staged.push((table, TableTransaction::new()));
+        StagedTable(self.staged.len() - 1)
+    }
+
+    /// Stages the setting of `key` to `value` in `table`.
+    ///
+    /// # Errors
+    ///
+    /// See [`TableTransaction::set`]. On error, the `TxnContext` is aborted.
+    ///
+    /// [`TableTransaction::set`]: crate::database::TableTransaction::set
+    pub fn set(
+        &mut self,
+        table: StagedTable,
+        key: Key,
+        value: Value,
+    ) -> Result<(), Top<QueryError>> {
+        let result = self.staged[table.0].1.set(key, value);
+        self.aborted |= result.is_err();
+        result
+    }
+
+    /// Stages the removal of `key` from `table`.
+    ///
+    /// # Errors
+    ///
+    /// See [`TableTransaction::remove`]. On error, the `TxnContext` is aborted.
+    ///
+    /// [`TableTransaction::remove`]: crate::database::TableTransaction::remove
+    pub fn remove(&mut self, table: StagedTable, key: &Key) -> Result<(), Top<QueryError>> {
+        let result = self.staged[table.0].1.remove(key);
+        self.aborted |= result.is_err();
+        result
+    }
+
+    /// Aborts the `TxnContext`: none of its writes will be applied.
+    pub fn abort(&mut self) {
+        self.aborted = true;
+    }
+
+    /// Returns `true` if the `TxnContext` was aborted.
+    pub fn is_aborted(&self) -> bool {
+        self.aborted
+    }
+
+    // Applies all staged writes, returning a commitment to the commitments of
+    // the staged `Table`s (in staging order), or `None` if aborted
+    pub(crate) fn commit(self) -> Option<Hash> {
+        if self.aborted {
+            return None;
+        }
+
+        let commitments = self
+            .staged
+            .into_iter()
+            .map(|(table, transaction)| {
+                table.execute(transaction);
+                table.commit()
+            })
+            .collect::<Vec<_>>();
+
+        Some(hash::hash(&commitments).unwrap())
+    }
+}
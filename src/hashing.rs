@@ -0,0 +1,283 @@
+//! Node hashing, as used to compute the commitments of [`Map`]s and
+//! [`Table`]s.
+//!
+//! These functions allow third parties (e.g., verifiers reimplementing
+//! the tree in another language) to produce digests that are byte-identical
+//! to those computed by `zebra`. Keys and values enter the tree through
+//! their digests, i.e., `talk::crypto::primitives::hash::hash(&key)`.
+//!
+//! [`Map`]s can also use a different hash function altogether (see
+//! [`Hasher`]), in which case these functions do not apply.
+//!
+//! [`Map`]: crate::map::Map
+//! [`Table`]: crate::database::Table
+//! [`Hasher`]: crate::hashing::Hasher
+//!
+//! # Examples
+//!
+//! ```
+//! use talk::crypto::primitives::hash;
+//! use zebra::{hashing, map::Map};
+//!
+//! let mut map = Map::new();
+//! map.insert(1u32, 2u32).unwrap();
+//!
+//! let key = hash::hash(&1u32).unwrap();
+//! let value = hash::hash(&2u32).unwrap();
+//!
+//! assert_eq!(map.commit(), hashing::hash_leaf(key, value));
+//! ```
+
+use crate::common::{data::Bytes, store::hash};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+use serde::Serialize;
+
+#[cfg(feature = "sha256")]
+use sha2::{Digest, Sha256};
+
+use std::fmt::Debug;
+
+use talk::crypto::primitives::{
+    hash as blake3,
+    hash::{Hash, HASH_LENGTH},
+};
+
+#[derive(Doom)]
+pub enum HasherError {
+    #[doom(description("Failed to serialize value"))]
+    SerializeFailed,
+}
+
+/// A hash function for the nodes (and the keys and values) of a [`Map`].
+///
+/// Commitments to [`Map`]s storing the same records differ across
+/// `Hasher`s. The node hashing functions in this module (e.g., [`hash_leaf`])
+/// use [`Blake3Hasher`], the default `Hasher` of a [`Map`].
+///
+/// Proofs ([`MapProof`]s, [`AbsenceProof`]s and [`RegionEqualityProof`]s),
+/// [`BloomSummary`]s and [`Set`]s are verified or built with Blake3 alone.
+/// Hence, the methods of [`Map`] producing them ([`prove`], [`prove_absent`],
+/// [`prove_region_unchanged`], [`bloom_summary`] and [`filter_to`]) are only
+/// available on [`Map`]s using [`Blake3Hasher`].
+///
+/// [`Map`]: crate::map::Map
+/// [`hash_leaf`]: crate::hashing::hash_leaf
+/// [`Blake3Hasher`]: crate::hashing::Blake3Hasher
+/// [`MapProof`]: crate::map::MapProof
+/// [`AbsenceProof`]: crate::map::AbsenceProof
+/// [`RegionEqualityProof`]: crate::map::RegionEqualityProof
+/// [`BloomSummary`]: crate::map::BloomSummary
+/// [`Set`]: crate::map::Set
+/// [`prove`]: crate::map::Map::prove
+/// [`prove_absent`]: crate::map::Map::prove_absent
+/// [`prove_region_unchanged`]: crate::map::Map::prove_region_unchanged
+/// [`bloom_summary`]: crate::map::Map::bloom_summary
+/// [`filter_to`]: crate::map::Map::filter_to
+pub trait Hasher: 'static + Debug + Clone + Send + Sync {
+    /// Hashes the serialization of `value`.
+    fn hash<T>(value: &T) -> Result<Hash, Top<HasherError>>
+    where
+        T: Serialize + ?Sized;
+
+    /// Returns the digest of an empty subtree.
+    fn empty() -> Hash {
+        Bytes([0; HASH_LENGTH]).into()
+    }
+}
+
+/// The default [`Hasher`], using Blake3 (through
+/// `talk::crypto::primitives::hash`).
+///
+/// [`Hasher`]: crate::hashing::Hasher
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Blake3Hasher;
+
+/// A [`Hasher`] using SHA-256, to match commitments computed by
+/// systems standardized on SHA-256. Requires the `sha256` feature.
+///
+/// [`Hasher`]: crate::hashing::Hasher
+#[cfg(feature = "sha256")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256Hasher;
+
+impl Hasher for Blake3Hasher {
+    fn hash<T>(value: &T) -> Result<Hash, Top<HasherError>>
+    where
+        T: Serialize + ?Sized,
+    {
+        blake3::hash(value).pot(HasherError::SerializeFailed, here!())
+    }
+}
+
+#[cfg(feature = "sha256")]
+impl Hasher for Sha256Hasher {
+    fn hash<T>(value: &T) -> Result<Hash, Top<HasherError>>
+    where
+        T: Serialize + ?Sized,
+    {
+        let serialization = bincode::serialize(value)
+            .map_err(|_| HasherError::SerializeFailed.into_top())
+            .spot(here!())?;
+
+        let mut digest = [0; HASH_LENGTH];
+        digest.copy_from_slice(&Sha256::digest(&serialization));
+
+        Ok(Bytes(digest).into())
+    }
+}
+
+/// Returns the digest of an empty subtree.
+pub fn empty_node() -> Hash {
+    hash::empty().into()
+}
+
+/// Returns the digest of a leaf storing a key with digest `key_digest`
+/// and a value with digest `value_digest`.
+pub fn hash_leaf(key_digest: Hash, value_digest: Hash) -> Hash {
+    hash::leaf(key_digest.into(), value_digest.into()).into()
+}
+
+/// Returns the digest of an internal node whose children have
+/// digests `left` and `right`.
+pub fn hash_internal(left: Hash, right: Hash) -> Hash {
+    hash::internal(left.into(), right.into()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        common::tree::{Direction, Path},
+        database::{Database, TableTransaction},
+        map::Map,
+    };
+
+    use talk::crypto::primitives::hash;
+
+    #[test]
+    fn empty() {
+        let map: Map<u32, u32> = Map::new();
+        assert_eq!(map.commit(), empty_node());
+
+        let database: Database<u32, u32> = Database::new();
+        assert_eq!(database.empty_table().commit(), empty_node());
+    }
+
+    #[test]
+    fn leaf() {
+        let mut map: Map<u32, u32> = Map::new();
+        map.insert(0, 1).unwrap();
+
+        let expected = hash_leaf(hash::hash(&0u32).unwrap(), hash::hash(&1u32).unwrap());
+        assert_eq!(map.commit(), expected);
+    }
+
+    #[test]
+    fn internal() {
+        // Find two keys whose paths diverge at the first bit
+        let (left, right) = {
+            let mut keys = (0u32..).map(|key| (key, Path::from(hash::hash(&key).unwrap())));
+
+            let (first, first_path) = keys.next().unwrap();
+
+            let (second, _) = keys.find(|(_, path)| path[0] != first_path[0]).unwrap();
+
+            if first_path[0] == Direction::Left {
+                (first, second)
+            } else {
+                (second, first)
+            }
+        };
+
+        let leaf = |key: u32| hash_leaf(hash::hash(&key).unwrap(), hash::hash(&key).unwrap());
+        let expected = hash_internal(leaf(left), leaf(right));
+
+        let mut map: Map<u32, u32> = Map::new();
+        map.insert(left, left).unwrap();
+        map.insert(right, right).unwrap();
+
+        assert_eq!(map.commit(), expected);
+
+        let database: Database<u32, u32> = Database::new();
+        let mut table = database.empty_table();
+
+        let mut transaction = TableTransaction::new();
+        transaction.set(left, left).unwrap();
+        transaction.set(right, right).unwrap();
+        table.execute(transaction);
+
+        assert_eq!(table.commit(), expected);
+    }
+
+    #[cfg(feature = "sha256")]
+    #[test]
+    fn sha256() {
+        let mut blake3: Map<u32, u32> = Map::new();
+        let mut sha256: Map<u32, u32, Sha256Hasher> = Map::with_hasher();
+
+        for (key, value) in (0..256).map(|i| (i, i + 1)) {
+            blake3.insert(key, value).unwrap();
+            sha256.insert(key, value).unwrap();
+        }
+
+        assert_ne!(blake3.commit(), sha256.commit());
+
+        for key in 0..512 {
+            assert_eq!(sha256.get(&key).unwrap(), blake3.get(&key).unwrap());
+        }
+
+        for key in 0..128 {
+            assert_eq!(sha256.remove(&key).unwrap(), Some(key + 1));
+            blake3.remove(&key).unwrap();
+        }
+
+        // Rebuilding with the same `Hasher` yields the same commitment
+        let mut other: Map<u32, u32, Sha256Hasher> = Map::with_hasher();
+
+        for (key, value) in (128..256).map(|i| (i, i + 1)) {
+            other.insert(key, value).unwrap();
+        }
+
+        assert_eq!(sha256.commit(), other.commit());
+
+        // Deserialization recomputes digests with the same `Hasher`
+        let serialized = bincode::serialize(&sha256).unwrap();
+
+        let deserialized: Map<u32, u32, Sha256Hasher> = bincode::deserialize(&serialized).unwrap();
+        assert_eq!(deserialized.commit(), sha256.commit());
+
+        // Exports follow the paths of `Sha256Hasher`, and import back together
+        let mut export = sha256.export(128..192).unwrap();
+        export.import(sha256.export(192..256).unwrap()).unwrap();
+
+        assert_eq!(export.commit(), sha256.commit());
+        assert!(export.is_complete());
+
+        for key in 128..256 {
+            assert_eq!(export.get(&key).unwrap(), Some(&(key + 1)));
+        }
+
+        // Exports import into a stub of the commitment as well
+        let mut stub: Map<u32, u32, Sha256Hasher> = Map::root_stub(sha256.commit());
+        stub.import(sha256.export(128..144).unwrap()).unwrap();
+
+        assert_eq!(stub.commit(), sha256.commit());
+        assert_eq!(stub.get(&128).unwrap(), Some(&129));
+    }
+
+    #[cfg(feature = "sha256")]
+    #[test]
+    fn sha256_empty() {
+        let map: Map<u32, u32, Sha256Hasher> = Map::with_hasher();
+        assert_eq!(map.commit(), empty_node());
+
+        // Only the empty subtree digest is shared across `Hasher`s
+        assert_ne!(
+            Sha256Hasher::hash(&0u32).unwrap(),
+            Blake3Hasher::hash(&0u32).unwrap()
+        );
+    }
+}
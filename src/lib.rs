@@ -1,5 +1,33 @@
 mod common;
 
+pub use common::tree;
+
+pub mod auth_path;
 pub mod database;
+pub mod hashing;
 pub mod map;
 pub mod vector;
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        database::{Collection, Database, Family, Table, TableReceiver, TableSender},
+        map::{Map, Set},
+        vector::Vector,
+    };
+
+    fn _assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn send_sync() {
+        _assert_send_sync::<Map<u32, u32>>();
+        _assert_send_sync::<Set<u32>>();
+        _assert_send_sync::<Vector<u32>>();
+        _assert_send_sync::<Database<u32, u32>>();
+        _assert_send_sync::<Table<u32, u32>>();
+        _assert_send_sync::<TableSender<u32, u32>>();
+        _assert_send_sync::<TableReceiver<u32, u32>>();
+        _assert_send_sync::<Family<u32>>();
+        _assert_send_sync::<Collection<u32>>();
+    }
+}
@@ -0,0 +1,301 @@
+use crate::{
+    auth_path::AuthPath,
+    common::{
+        store::{hash as node_hash, Field},
+        tree::{Direction, Path},
+    },
+    map::{
+        errors::{MapError, ProofError},
+        store::Node,
+    },
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+use serde::{Deserialize, Serialize};
+
+use talk::crypto::primitives::{
+    hash,
+    hash::{Hash, HASH_LENGTH},
+};
+
+/// A proof that a key is not in a [`Map`] (or an item is not in a
+/// [`Set`]), obtained through [`Map::prove_absent`] or [`Set::prove_absent`].
+///
+/// The proof carries the [`AuthPath`] along the key's path from where
+/// the path ends, i.e., either an empty subtree or a leaf storing
+/// a different key, up to the root.
+///
+/// [`AuthPath`]: crate::auth_path::AuthPath
+///
+/// [`Map`]: crate::map::Map
+/// [`Set`]: crate::map::Set
+/// [`Map::prove_absent`]: crate::map::Map::prove_absent
+/// [`Set::prove_absent`]: crate::map::Set::prove_absent
+///
+/// # Examples
+///
+/// ```
+/// use zebra::map::Set;
+///
+/// let mut set = Set::new();
+/// set.insert(1).unwrap();
+/// set.insert(2).unwrap();
+///
+/// let proof = set.prove_absent(&3).unwrap();
+///
+/// assert!(proof.verify(set.commit(), &3).is_ok());
+/// assert!(proof.verify(set.commit(), &4).is_err());
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbsenceProof {
+    auth_path: AuthPath,
+    terminal: Terminal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Terminal {
+    Empty,
+    Leaf { key: Hash, value: Hash },
+}
+
+impl AbsenceProof {
+    pub(crate) fn prove<Key, Value>(
+        root: &Node<Key, Value>,
+        path: Path,
+    ) -> Result<AbsenceProof, Top<MapError>>
+    where
+        Key: Field,
+        Value: Field,
+    {
+        let mut siblings = Vec::new();
+        let mut node = root;
+
+        let proof = |siblings: Vec<Hash>, terminal| {
+            let directions = path.into_iter().take(siblings.len()).collect::<Vec<_>>();
+
+            let directions = directions.into_iter().rev();
+            let siblings = siblings.into_iter().rev().collect();
+
+            AbsenceProof {
+                auth_path: AuthPath::new(directions, siblings),
+                terminal,
+            }
+        };
+
+        loop {
+            match node {
+                Node::Empty => return Ok(proof(siblings, Terminal::Empty)),
+                Node::Internal(internal) => {
+                    node = if path[siblings.len() as u8] == Direction::Left {
+                        siblings.push(internal.right().hash().into());
+                        internal.left()
+                    } else {
+                        siblings.push(internal.left().hash().into());
+                        internal.right()
+                    };
+                }
+                Node::Leaf(leaf) => {
+                    if path.reaches(leaf.key().digest()) {
+                        return MapError::KeyPresent.fail().spot(here!());
+                    }
+
+                    return Ok(proof(
+                        siblings,
+                        Terminal::Leaf {
+                            key: leaf.key().digest().into(),
+                            value: leaf.value().digest().into(),
+                        },
+                    ));
+                }
+                Node::Stub(_) => return MapError::BranchUnknown.fail().spot(here!()),
+            }
+        }
+    }
+
+    /// Returns the [`AuthPath`] from the end of the proven key's path
+    /// (see [`terminal_digest`]) to the root.
+    ///
+    /// [`AuthPath`]: crate::auth_path::AuthPath
+    /// [`terminal_digest`]: crate::map::AbsenceProof::terminal_digest
+    pub fn auth_path(&self) -> &AuthPath {
+        &self.auth_path
+    }
+
+    /// Returns the digest of the node where the proven key's path ends,
+    /// i.e., either an empty subtree or a leaf storing a different key.
+    pub fn terminal_digest(&self) -> Hash {
+        match self.terminal {
+            Terminal::Empty => node_hash::empty().into(),
+            Terminal::Leaf { key, value } => node_hash::leaf(key.into(), value.into()).into(),
+        }
+    }
+
+    /// Verifies that `item` is absent from the [`Map`] or [`Set`]
+    /// with commitment `commitment`.
+    ///
+    /// # Errors
+    ///
+    /// If `item` cannot be hashed, [`HashError`] is returned.
+    ///
+    /// If the proof shows that `item` is present, [`ItemPresent`] is returned.
+    ///
+    /// If the proof ends at a leaf that does not lie on the path of `item`,
+    /// [`OutOfPath`] is returned.
+    ///
+    /// If the proof does not match `commitment`, [`RootMismatch`] is returned.
+    ///
+    /// [`Map`]: crate::map::Map
+    /// [`Set`]: crate::map::Set
+    /// [`HashError`]: crate::map::errors::ProofError::HashError
+    /// [`ItemPresent`]: crate::map::errors::ProofError::ItemPresent
+    /// [`OutOfPath`]: crate::map::errors::ProofError::OutOfPath
+    /// [`RootMismatch`]: crate::map::errors::ProofError::RootMismatch
+    pub fn verify<Item>(&self, commitment: Hash, item: &Item) -> Result<(), Top<ProofError>>
+    where
+        Item: Serialize,
+    {
+        let digest = hash::hash(item).pot(ProofError::HashError, here!())?;
+        let path = Path::from(digest);
+
+        if self.auth_path.len() >= 8 * HASH_LENGTH {
+            return ProofError::OutOfPath.fail().spot(here!());
+        }
+
+        let depth = self.auth_path.len() as u8;
+
+        // The proof must follow the path of `item`
+        if !self.auth_path.directions().eq(path
+            .into_iter()
+            .take(depth as usize)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev())
+        {
+            return ProofError::OutOfPath.fail().spot(here!());
+        }
+
+        if let Terminal::Leaf { key, .. } = self.terminal {
+            if key == digest {
+                return ProofError::ItemPresent.fail().spot(here!());
+            }
+
+            if !Path::deepeq(&path, &Path::from(key), depth) {
+                return ProofError::OutOfPath.fail().spot(here!());
+            }
+        }
+
+        self.auth_path
+            .verify(commitment, self.terminal_digest(), |left, right| {
+                node_hash::internal(left.into(), right.into()).into()
+            })
+            .pot(ProofError::RootMismatch, here!())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::map::{Map, Set};
+
+    impl Terminal {
+        fn leaf_key(&self) -> Option<Hash> {
+            match self {
+                Terminal::Leaf { key, .. } => Some(*key),
+                Terminal::Empty => None,
+            }
+        }
+    }
+
+    #[test]
+    fn absent_at_leaf() {
+        let mut set: Set<u32> = Set::new();
+
+        for item in 0..64 {
+            set.insert(item).unwrap();
+        }
+
+        // Find an absent item whose path ends at a leaf
+        let (item, proof) = (64..)
+            .map(|item| (item, set.prove_absent(&item).unwrap()))
+            .find(|(_, proof)| proof.terminal.leaf_key().is_some())
+            .unwrap();
+
+        proof.verify(set.commit(), &item).unwrap();
+
+        // The leaf holds an item that is present: the proof shows its presence
+        let present = (0..64)
+            .find(|present| Some(hash::hash(present).unwrap()) == proof.terminal.leaf_key())
+            .unwrap();
+
+        assert!(matches!(
+            proof.verify(set.commit(), &present).unwrap_err().top(),
+            ProofError::ItemPresent
+        ));
+    }
+
+    #[test]
+    fn absent_at_empty() {
+        let mut set: Set<u32> = Set::new();
+
+        for item in 0..64 {
+            set.insert(item).unwrap();
+        }
+
+        // Find an absent item whose path ends at an empty subtree
+        let (item, proof) = (64..)
+            .map(|item| (item, set.prove_absent(&item).unwrap()))
+            .find(|(_, proof)| proof.terminal.leaf_key().is_none())
+            .unwrap();
+
+        proof.verify(set.commit(), &item).unwrap();
+
+        let mut other = set.clone();
+        other.insert(item).unwrap();
+
+        assert!(matches!(
+            proof.verify(other.commit(), &item).unwrap_err().top(),
+            ProofError::RootMismatch
+        ));
+    }
+
+    #[test]
+    fn empty() {
+        let set: Set<u32> = Set::new();
+
+        let proof = set.prove_absent(&0).unwrap();
+        assert!(proof.auth_path.is_empty());
+
+        proof.verify(set.commit(), &0).unwrap();
+    }
+
+    #[test]
+    fn present() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..64).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        assert!(matches!(
+            map.prove_absent(&0).unwrap_err().top(),
+            MapError::KeyPresent
+        ));
+    }
+
+    #[test]
+    fn stub() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..64).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let export = map.export([0]).unwrap();
+
+        assert!(export.prove_absent(&64).is_err());
+    }
+}
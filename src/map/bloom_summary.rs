@@ -0,0 +1,232 @@
+use bit_vec::BitVec;
+
+use crate::{
+    common::{data::Bytes, store::Field},
+    map::{errors::MapError, store::Node},
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+use serde::{Deserialize, Serialize};
+
+use std::f64::consts::LN_2;
+
+use talk::crypto::primitives::hash;
+
+// Bound on the number of bit positions set per key
+const MAX_HASHES: u32 = 16;
+
+/// A Bloom filter over the keys of a [`Map`] (or the items of a [`Set`]),
+/// obtained through [`Map::bloom_summary`].
+///
+/// A `BloomSummary` answers membership queries with no false negatives:
+/// if [`might_contain`] returns `false`, the key is definitely absent from
+/// the [`Map`]. A peer can send a (small) `BloomSummary` so that the other
+/// side avoids requesting proofs for keys that are definitely absent.
+///
+/// [`Map`]: crate::map::Map
+/// [`Set`]: crate::map::Set
+/// [`Map::bloom_summary`]: crate::map::Map::bloom_summary
+/// [`might_contain`]: crate::map::BloomSummary::might_contain
+///
+/// # Examples
+///
+/// ```
+/// use zebra::map::Map;
+///
+/// let mut map = Map::new();
+/// map.insert(1, "a").unwrap();
+/// map.insert(2, "b").unwrap();
+///
+/// let summary = map.bloom_summary(1024).unwrap();
+///
+/// assert!(summary.might_contain(&1));
+/// assert!(summary.might_contain(&2));
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BloomSummary {
+    bits: BitVec,
+    hashes: u32,
+}
+
+impl BloomSummary {
+    pub(crate) fn new<Key, Value>(
+        root: &Node<Key, Value>,
+        bits: usize,
+    ) -> Result<Self, Top<MapError>>
+    where
+        Key: Field,
+        Value: Field,
+    {
+        assert!(bits > 0, "`bits` must be positive");
+
+        let mut digests = Vec::new();
+        collect(root, &mut digests)?;
+
+        // The number of hashes minimizing the rate of false positives
+        let hashes = (bits as f64 / digests.len().max(1) as f64 * LN_2).round() as u32;
+        let hashes = hashes.clamp(1, MAX_HASHES);
+
+        let mut summary = BloomSummary {
+            bits: BitVec::from_elem(bits, false),
+            hashes,
+        };
+
+        for digest in digests {
+            for index in summary.indices(digest) {
+                summary.bits.set(index, true);
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Returns the number of bits in the `BloomSummary`.
+    pub fn bits(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Returns `false` if `key` is definitely absent from the [`Map`],
+    /// `true` if it might be present.
+    ///
+    /// A key that cannot be hashed cannot be in the [`Map`]: `false`
+    /// is returned.
+    ///
+    /// [`Map`]: crate::map::Map
+    pub fn might_contain<Key>(&self, key: &Key) -> bool
+    where
+        Key: Serialize,
+    {
+        // Only reachable by deserializing a malformed `BloomSummary`
+        if self.bits.is_empty() {
+            return true;
+        }
+
+        match hash::hash(key) {
+            Ok(digest) => self
+                .indices(digest.into())
+                .all(|index| self.bits.get(index) == Some(true)),
+            Err(_) => false,
+        }
+    }
+
+    // Derives `self.hashes` bit positions from `digest` by double hashing
+    fn indices(&self, digest: Bytes) -> impl Iterator<Item = usize> {
+        let mut first = [0u8; 8];
+        let mut second = [0u8; 8];
+
+        first.copy_from_slice(&digest.0[..8]);
+        second.copy_from_slice(&digest.0[8..16]);
+
+        let first = u64::from_le_bytes(first);
+        let second = u64::from_le_bytes(second) | 1; // Odd, so that positions do not collapse
+
+        let len = self.bits.len() as u64;
+
+        (0..self.hashes as u64)
+            .map(move |index| (first.wrapping_add(index.wrapping_mul(second)) % len) as usize)
+    }
+}
+
+fn collect<Key, Value>(
+    node: &Node<Key, Value>,
+    digests: &mut Vec<Bytes>,
+) -> Result<(), Top<MapError>>
+where
+    Key: Field,
+    Value: Field,
+{
+    match node {
+        Node::Empty => Ok(()),
+        Node::Internal(internal) => {
+            collect(internal.left(), digests)?;
+            collect(internal.right(), digests)
+        }
+        Node::Leaf(leaf) => {
+            digests.push(leaf.key().digest());
+            Ok(())
+        }
+        Node::Stub(_) => MapError::BranchUnknown.fail().spot(here!()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::map::{Map, Set};
+
+    #[test]
+    fn no_false_negatives() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for key in 0..1024 {
+            map.insert(key, key).unwrap();
+        }
+
+        // Even with a filter saturated by far
+        for bits in [1, 64, 1024, 16384] {
+            let summary = map.bloom_summary(bits).unwrap();
+            assert!((0..1024).all(|key| summary.might_contain(&key)));
+        }
+    }
+
+    #[test]
+    fn false_positives() {
+        let mut set: Set<u32> = Set::new();
+
+        for item in 0..1024 {
+            set.insert(item).unwrap();
+        }
+
+        // With 8 bits per key, the rate of false positives is about 2%
+        let summary = set.bloom_summary(8192).unwrap();
+
+        let false_positives = (1024..11264)
+            .filter(|item| summary.might_contain(item))
+            .count();
+
+        assert!(false_positives < 500);
+    }
+
+    #[test]
+    fn empty() {
+        let map: Map<u32, u32> = Map::new();
+        let summary = map.bloom_summary(64).unwrap();
+
+        assert_eq!(summary.bits(), 64);
+        assert!((0..1024).all(|key| !summary.might_contain(&key)));
+    }
+
+    #[test]
+    fn serialize() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for key in 0..256 {
+            map.insert(key, key).unwrap();
+        }
+
+        let summary = map.bloom_summary(2048).unwrap();
+
+        let serialized = bincode::serialize(&summary).unwrap();
+        let deserialized: BloomSummary = bincode::deserialize(&serialized).unwrap();
+
+        assert!((0..256).all(|key| deserialized.might_contain(&key)));
+    }
+
+    #[test]
+    fn stub() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for key in 0..256 {
+            map.insert(key, key).unwrap();
+        }
+
+        let export = map.export([0]).unwrap();
+
+        assert!(matches!(
+            export.bloom_summary(1024).unwrap_err().top(),
+            MapError::BranchUnknown
+        ));
+    }
+}
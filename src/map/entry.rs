@@ -0,0 +1,393 @@
+use crate::{
+    common::{store::Field, tree::Path},
+    hashing::{Blake3Hasher, Hasher},
+    map::{
+        errors::MapError,
+        interact::{self, Action, Query, Update},
+        store::Wrap,
+        Map,
+    },
+};
+
+use doomstack::{here, ResultExt, Top};
+
+/// A view into a single record of a [`Map`], which may be present
+/// (`Occupied`) or absent (`Vacant`), obtained through [`Map::entry`].
+///
+/// Whether the record is present is determined by a single descent of the
+/// [`Map`] when the `Entry` is created. Every write descends the [`Map`]
+/// once more, as the digests along the path of the key must be recomputed.
+///
+/// [`Map`]: crate::map::Map
+/// [`Map::entry`]: crate::map::Map::entry
+pub enum Entry<'m, Key: Field, Value: Field, H: Hasher = Blake3Hasher> {
+    Occupied(OccupiedEntry<'m, Key, Value, H>),
+    Vacant(VacantEntry<'m, Key, Value, H>),
+}
+
+/// A view into a record present in a [`Map`] (see [`Entry`]).
+///
+/// [`Map`]: crate::map::Map
+/// [`Entry`]: crate::map::Entry
+pub struct OccupiedEntry<'m, Key: Field, Value: Field, H: Hasher = Blake3Hasher> {
+    map: &'m mut Map<Key, Value, H>,
+    key: Wrap<Key, H>,
+}
+
+/// A view into a record absent from a [`Map`] (see [`Entry`]).
+///
+/// [`Map`]: crate::map::Map
+/// [`Entry`]: crate::map::Entry
+pub struct VacantEntry<'m, Key: Field, Value: Field, H: Hasher = Blake3Hasher> {
+    map: &'m mut Map<Key, Value, H>,
+    key: Wrap<Key, H>,
+}
+
+impl<'m, Key, Value, H> Entry<'m, Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    pub(crate) fn new(map: &'m mut Map<Key, Value, H>, key: Key) -> Result<Self, Top<MapError>> {
+        let key = Wrap::new(key).pot(MapError::HashError, here!())?;
+
+        let occupied = interact::get(map.root(), query(&key))?.is_some();
+
+        if occupied {
+            Ok(Entry::Occupied(OccupiedEntry { map, key }))
+        } else {
+            Ok(Entry::Vacant(VacantEntry { map, key }))
+        }
+    }
+
+    /// Returns the key of the `Entry`.
+    pub fn key(&self) -> &Key {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Applies `f` to the value of an `Occupied` entry, writing the
+    /// result back to the [`Map`]. A `Vacant` entry is left untouched.
+    ///
+    /// # Errors
+    ///
+    /// If the modified value cannot be hashed, [`HashError`] is returned
+    /// and the [`Map`] is left untouched.
+    ///
+    /// [`Map`]: crate::map::Map
+    /// [`HashError`]: crate::map::errors::MapError::HashError
+    pub fn and_modify<F>(self, f: F) -> Result<Self, Top<MapError>>
+    where
+        Key: Clone,
+        Value: Clone,
+        F: FnOnce(&mut Value),
+    {
+        match self {
+            Entry::Occupied(mut entry) => {
+                let mut value = entry.get().clone();
+                f(&mut value);
+
+                entry.write(value)?;
+                Ok(Entry::Occupied(entry))
+            }
+            entry => Ok(entry),
+        }
+    }
+
+    /// Returns the value of an `Occupied` entry, or inserts `default`
+    /// into a `Vacant` entry and returns it.
+    ///
+    /// # Errors
+    ///
+    /// If `default` cannot be hashed, [`HashError`] is returned.
+    ///
+    /// [`HashError`]: crate::map::errors::MapError::HashError
+    pub fn or_insert(self, default: Value) -> Result<&'m Value, Top<MapError>> {
+        self.or_insert_with(|| default)
+    }
+
+    /// Like [`or_insert`], but computes the value to insert only if the
+    /// entry is `Vacant`.
+    ///
+    /// [`or_insert`]: crate::map::Entry::or_insert
+    pub fn or_insert_with<F>(self, default: F) -> Result<&'m Value, Top<MapError>>
+    where
+        F: FnOnce() -> Value,
+    {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_ref()),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Like [`or_insert`], inserting `Value::default()`.
+    ///
+    /// [`or_insert`]: crate::map::Entry::or_insert
+    pub fn or_default(self) -> Result<&'m Value, Top<MapError>>
+    where
+        Value: Default,
+    {
+        self.or_insert_with(Value::default)
+    }
+}
+
+impl<'m, Key, Value, H> OccupiedEntry<'m, Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    /// Returns the key of the entry.
+    pub fn key(&self) -> &Key {
+        self.key.inner()
+    }
+
+    /// Returns the value of the entry.
+    pub fn get(&self) -> &Value {
+        // The record was present upon creating the entry, and the `Map`
+        // is borrowed by the entry ever since
+        interact::get(self.map.root(), query(&self.key))
+            .unwrap()
+            .unwrap()
+    }
+
+    /// Converts the entry into a reference to its value, borrowing
+    /// the [`Map`] for the lifetime of the entry.
+    ///
+    /// [`Map`]: crate::map::Map
+    pub fn into_ref(self) -> &'m Value {
+        let map: &'m Map<Key, Value, H> = self.map;
+
+        interact::get(map.root(), query(&self.key))
+            .unwrap()
+            .unwrap()
+    }
+
+    /// Sets the value of the entry, returning the previous value.
+    ///
+    /// # Errors
+    ///
+    /// If `value` cannot be hashed, [`HashError`] is returned.
+    ///
+    /// [`HashError`]: crate::map::errors::MapError::HashError
+    pub fn insert(mut self, value: Value) -> Result<Value, Top<MapError>>
+    where
+        Key: Clone,
+    {
+        self.write(value)
+    }
+
+    /// Removes the record from the [`Map`], returning its value.
+    ///
+    /// [`Map`]: crate::map::Map
+    pub fn remove(self) -> Value {
+        let update = Update {
+            path: Path::from(self.key.digest()),
+            action: Action::Remove,
+        };
+
+        // Neither the record nor its path can be missing (see `get`)
+        self.map.update(update).unwrap().unwrap()
+    }
+
+    fn write(&mut self, value: Value) -> Result<Value, Top<MapError>>
+    where
+        Key: Clone,
+    {
+        let value = Wrap::new(value).pot(MapError::HashError, here!())?;
+
+        let update = Update {
+            path: Path::from(self.key.digest()),
+            action: Action::Insert(self.key.clone(), value),
+        };
+
+        Ok(self.map.update(update)?.unwrap())
+    }
+}
+
+impl<'m, Key, Value, H> VacantEntry<'m, Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    /// Returns the key of the entry.
+    pub fn key(&self) -> &Key {
+        self.key.inner()
+    }
+
+    /// Takes ownership of the key of the entry.
+    pub fn into_key(self) -> Key {
+        self.key.take()
+    }
+
+    /// Inserts `value` under the key of the entry, returning
+    /// a reference to it.
+    ///
+    /// # Errors
+    ///
+    /// If `value` cannot be hashed, [`HashError`] is returned.
+    ///
+    /// [`HashError`]: crate::map::errors::MapError::HashError
+    pub fn insert(self, value: Value) -> Result<&'m Value, Top<MapError>> {
+        let value = Wrap::new(value).pot(MapError::HashError, here!())?;
+        let query = query(&self.key);
+
+        let update = Update {
+            path: Path::from(self.key.digest()),
+            action: Action::Insert(self.key, value),
+        };
+
+        self.map.update(update)?;
+
+        let map: &'m Map<Key, Value, H> = self.map;
+        Ok(interact::get(map.root(), query).unwrap().unwrap())
+    }
+}
+
+fn query<Key, H>(key: &Wrap<Key, H>) -> Query
+where
+    Key: Field,
+    H: Hasher,
+{
+    Query {
+        path: Path::from(key.digest()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    #[test]
+    fn or_insert() {
+        let mut map: Map<u32, u32> = Map::new();
+        let mut reference: HashMap<u32, u32> = HashMap::new();
+
+        for key in [3, 1, 3, 4, 1, 5, 9, 2, 6, 5, 3, 5] {
+            let value = *map.entry(key).unwrap().or_insert(key * 10).unwrap();
+            let expected = *reference.entry(key).or_insert(key * 10);
+
+            assert_eq!(value, expected);
+        }
+
+        map.check_tree();
+        map.assert_records(reference);
+    }
+
+    #[test]
+    fn and_modify_or_insert() {
+        let mut map: Map<u32, u32> = Map::new();
+        let mut reference: HashMap<u32, u32> = HashMap::new();
+
+        for key in (0..256).chain((0..256).step_by(3)).chain(0..16) {
+            let value = *map
+                .entry(key)
+                .unwrap()
+                .and_modify(|count| *count += 1)
+                .unwrap()
+                .or_insert(1)
+                .unwrap();
+
+            let expected = *reference
+                .entry(key)
+                .and_modify(|count| *count += 1)
+                .or_insert(1);
+
+            assert_eq!(value, expected);
+        }
+
+        map.check_tree();
+
+        let mut control: Map<u32, u32> = Map::new();
+
+        for (key, value) in reference.iter() {
+            control.insert(*key, *value).unwrap();
+        }
+
+        assert_eq!(map.commit(), control.commit());
+        map.assert_records(reference);
+    }
+
+    #[test]
+    fn or_insert_with() {
+        let mut map: Map<u32, u32> = Map::new();
+        map.insert(1, 1).unwrap();
+
+        let mut called = false;
+
+        map.entry(1)
+            .unwrap()
+            .or_insert_with(|| {
+                called = true;
+                2
+            })
+            .unwrap();
+
+        assert!(!called);
+        assert_eq!(*map.entry(2).unwrap().or_default().unwrap(), 0);
+        map.assert_records([(1, 1), (2, 0)]);
+    }
+
+    #[test]
+    fn occupied_vacant() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for key in 0..64 {
+            map.insert(key, key).unwrap();
+        }
+
+        match map.entry(7).unwrap() {
+            Entry::Occupied(entry) => {
+                assert_eq!(entry.key(), &7);
+                assert_eq!(entry.get(), &7);
+                assert_eq!(entry.insert(8).unwrap(), 7);
+            }
+            Entry::Vacant(..) => panic!("Expected an `Occupied` entry"),
+        }
+
+        match map.entry(8).unwrap() {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), 8),
+            Entry::Vacant(..) => panic!("Expected an `Occupied` entry"),
+        }
+
+        match map.entry(8).unwrap() {
+            Entry::Occupied(..) => panic!("Expected a `Vacant` entry"),
+            Entry::Vacant(entry) => assert_eq!(entry.into_key(), 8),
+        }
+
+        let mut reference: Map<u32, u32> = Map::new();
+
+        for key in (0..64).filter(|key| *key != 8) {
+            reference
+                .insert(key, if key == 7 { 8 } else { key })
+                .unwrap();
+        }
+
+        map.check_tree();
+        assert_eq!(map.commit(), reference.commit());
+    }
+
+    #[test]
+    fn stub() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for key in 0..64 {
+            map.insert(key, key).unwrap();
+        }
+
+        let mut export = map.export([0]).unwrap();
+
+        assert!(export.entry(0).is_ok());
+
+        assert!(matches!(
+            export.entry(1).err().unwrap().top(),
+            MapError::BranchUnknown
+        ));
+    }
+}
@@ -1,13 +1,48 @@
-use doomstack::Doom;
+use crate::hashing::HasherError;
+
+use doomstack::{Doom, Top};
 
 #[derive(Doom)]
 pub enum MapError {
     #[doom(description("Failed to hash field"))]
     HashError,
+    #[doom(description("Failed to hash key at index {}: {:?}", index, source))]
+    KeyHashError {
+        index: usize,
+        source: Top<HasherError>,
+    },
     #[doom(description("Attempted to operate on an unknown branch"))]
     BranchUnknown,
     #[doom(description("Attempted to import incompatible map"))]
     MapIncompatible,
+    #[doom(description("Failed to deserialize map"))]
+    DeserializeFailed,
+    #[doom(description("Map exceeds record limit"))]
+    TooManyRecords,
+    #[doom(description("Map exceeds depth limit"))]
+    TooDeep,
+    #[doom(description("Key is present in the map"))]
+    KeyPresent,
+    #[doom(description("Proof does not match key or commitment"))]
+    ProofInvalid,
+}
+
+#[derive(Doom)]
+pub enum ProofError {
+    #[doom(description("Failed to hash item"))]
+    HashError,
+    #[doom(description("Item is present"))]
+    ItemPresent,
+    #[doom(description("Item is absent"))]
+    ItemAbsent,
+    #[doom(description("Value mismatch"))]
+    ValueMismatch,
+    #[doom(description("Leaf outside of the item's path"))]
+    OutOfPath,
+    #[doom(description("Root mismatch"))]
+    RootMismatch,
+    #[doom(description("Regions differ"))]
+    RegionMismatch,
 }
 
 #[derive(Doom)]
@@ -0,0 +1,171 @@
+use crate::{
+    common::{store::Field, tree::Prefix},
+    hashing::Hasher,
+    map::store::Node,
+};
+
+/// A summary of which regions of a [`Map`] are known locally, obtained
+/// through [`Map::export_manifest`].
+///
+/// An exported [`Map`] (see [`Map::export`]) replaces the branches it
+/// does not include with `Stub`s. An `ExportManifest` tells them apart
+/// from the concrete branches without walking the [`Map`], e.g. to
+/// decide which branches to request next.
+///
+/// [`Map`]: crate::map::Map
+/// [`Map::export`]: crate::map::Map::export
+/// [`Map::export_manifest`]: crate::map::Map::export_manifest
+///
+/// # Examples
+///
+/// ```
+/// use zebra::{map::Map, tree::Prefix};
+///
+/// let mut map = Map::new();
+///
+/// for key in 0..16 {
+///     map.insert(key, key).unwrap();
+/// }
+///
+/// let manifest = map.export_manifest();
+///
+/// assert_eq!(manifest.concrete_prefixes, vec![Prefix::root()]);
+/// assert!(manifest.stub_prefixes.is_empty());
+/// assert_eq!(manifest.record_count, 16);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportManifest {
+    /// The locations of the largest subtrees that contain no `Stub`,
+    /// from left to right.
+    pub concrete_prefixes: Vec<Prefix>,
+    /// The locations of all `Stub`s, from left to right.
+    pub stub_prefixes: Vec<Prefix>,
+    /// The number of records stored in the concrete subtrees.
+    pub record_count: usize,
+}
+
+impl ExportManifest {
+    pub(crate) fn new<Key, Value, H>(root: &Node<Key, Value, H>) -> Self
+    where
+        Key: Field,
+        Value: Field,
+        H: Hasher,
+    {
+        let mut manifest = ExportManifest {
+            concrete_prefixes: Vec::new(),
+            stub_prefixes: Vec::new(),
+            record_count: 0,
+        };
+
+        if manifest.visit(root, Prefix::root()) {
+            manifest.concrete_prefixes.push(Prefix::root());
+        }
+
+        manifest
+    }
+
+    // Returns `true` if the subtree at `location` contains no `Stub`, in which
+    // case the caller is responsible for recording `location` as concrete
+    fn visit<Key, Value, H>(&mut self, node: &Node<Key, Value, H>, location: Prefix) -> bool
+    where
+        Key: Field,
+        Value: Field,
+        H: Hasher,
+    {
+        match node {
+            Node::Empty => true,
+            Node::Leaf(_) => {
+                self.record_count += 1;
+                true
+            }
+            Node::Stub(_) => {
+                self.stub_prefixes.push(location);
+                false
+            }
+            Node::Internal(internal) => {
+                let mark = self.concrete_prefixes.len();
+
+                let left = self.visit(internal.left(), location.left());
+                let right = self.visit(internal.right(), location.right());
+
+                if left && right {
+                    return true;
+                }
+
+                if left {
+                    // Keep `concrete_prefixes` sorted from left to right
+                    self.concrete_prefixes.insert(mark, location.left());
+                }
+
+                if right {
+                    self.concrete_prefixes.push(location.right());
+                }
+
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        common::tree::{Path, Prefix},
+        map::Map,
+    };
+
+    #[test]
+    fn half_export() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for key in 0..256 {
+            map.insert(key, key).unwrap();
+        }
+
+        let left = (0..256)
+            .filter(|key| {
+                Prefix::root().left().contains(&Path::from(
+                    talk::crypto::primitives::hash::hash(key).unwrap(),
+                ))
+            })
+            .collect::<Vec<u32>>();
+
+        let export = map.export(left.iter()).unwrap();
+        let manifest = export.export_manifest();
+
+        assert_eq!(manifest.concrete_prefixes, vec![Prefix::root().left()]);
+        assert_eq!(manifest.stub_prefixes, vec![Prefix::root().right()]);
+        assert_eq!(manifest.record_count, left.len());
+    }
+
+    #[test]
+    fn single_export() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for key in 0..256 {
+            map.insert(key, key).unwrap();
+        }
+
+        let export = map.export([0]).unwrap();
+        let manifest = export.export_manifest();
+
+        // Every region branches off the path of `0`, except for the one holding `0`
+        let path = Path::from(talk::crypto::primitives::hash::hash(&0u32).unwrap());
+
+        let regions = manifest
+            .concrete_prefixes
+            .iter()
+            .chain(manifest.stub_prefixes.iter());
+
+        assert!(regions
+            .clone()
+            .all(|region| region.ancestor(1).contains(&path)));
+        assert_eq!(regions.filter(|region| region.contains(&path)).count(), 1);
+
+        assert!(manifest
+            .stub_prefixes
+            .iter()
+            .all(|stub| !stub.contains(&path)));
+        assert!(manifest.record_count >= 1);
+    }
+}
@@ -1,7 +1,7 @@
-use crate::{common::store::Field, map::store::Wrap};
+use crate::{common::store::Field, hashing::Hasher, map::store::Wrap};
 
 #[derive(Debug)]
-pub(crate) enum Action<Key: Field, Value: Field> {
-    Insert(Wrap<Key>, Wrap<Value>),
+pub(crate) enum Action<Key: Field, Value: Field, H: Hasher> {
+    Insert(Wrap<Key, H>, Wrap<Value, H>),
     Remove,
 }
@@ -3,6 +3,7 @@ use crate::{
         store::Field,
         tree::{Direction, Path},
     },
+    hashing::Hasher,
     map::{
         errors::MapError,
         interact::{Action, Update},
@@ -12,15 +13,19 @@ use crate::{
 
 use doomstack::{here, Doom, ResultExt, Top};
 
-fn branch<Key, Value>(
-    left: Node<Key, Value>,
-    right: Node<Key, Value>,
+// The updated node, along with the value previously associated with the updated key
+type Applied<Key, Value, H> = (Node<Key, Value, H>, Result<Option<Value>, Top<MapError>>);
+
+fn branch<Key, Value, H>(
+    left: Node<Key, Value, H>,
+    right: Node<Key, Value, H>,
     depth: u8,
-    update: Update<Key, Value>,
-) -> (Node<Key, Value>, Result<Option<Value>, Top<MapError>>)
+    update: Update<Key, Value, H>,
+) -> Applied<Key, Value, H>
 where
     Key: Field,
     Value: Field,
+    H: Hasher,
 {
     let (left, right, get) = if update.path[depth] == Direction::Left {
         let (left, get) = recur(left, depth + 1, update);
@@ -40,14 +45,15 @@ where
     (node, get)
 }
 
-fn recur<Key, Value>(
-    node: Node<Key, Value>,
+fn recur<Key, Value, H>(
+    node: Node<Key, Value, H>,
     depth: u8,
-    update: Update<Key, Value>,
-) -> (Node<Key, Value>, Result<Option<Value>, Top<MapError>>)
+    update: Update<Key, Value, H>,
+) -> Applied<Key, Value, H>
 where
     Key: Field,
     Value: Field,
+    H: Hasher,
 {
     match (node, update) {
         (
@@ -109,13 +115,72 @@ where
     }
 }
 
-pub(crate) fn apply<Key, Value>(
-    root: Node<Key, Value>,
-    update: Update<Key, Value>,
-) -> (Node<Key, Value>, Result<Option<Value>, Top<MapError>>)
+pub(crate) fn apply<Key, Value, H>(
+    root: Node<Key, Value, H>,
+    update: Update<Key, Value, H>,
+) -> Applied<Key, Value, H>
 where
     Key: Field,
     Value: Field,
+    H: Hasher,
 {
     recur(root, 0, update)
 }
+
+fn recur_all<Key, Value, H>(
+    node: Node<Key, Value, H>,
+    depth: u8,
+    mut updates: Vec<Update<Key, Value, H>>,
+) -> Node<Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    match updates.len() {
+        0 => return node,
+        1 => return recur(node, depth, updates.pop().unwrap()).0,
+        _ => {}
+    }
+
+    let (left, right) = match node {
+        Node::Empty => (Node::Empty, Node::Empty),
+        Node::Internal(internal) => internal.children(),
+        Node::Leaf(leaf) => {
+            if Path::from(leaf.key().digest())[depth] == Direction::Left {
+                (Node::Leaf(leaf), Node::Empty)
+            } else {
+                (Node::Empty, Node::Leaf(leaf))
+            }
+        }
+        Node::Stub(_) => unreachable!("`apply_all`: update paths must not reach a `Stub`"),
+    };
+
+    let (left_updates, right_updates): (Vec<_>, Vec<_>) = updates
+        .into_iter()
+        .partition(|update| update.path[depth] == Direction::Left);
+
+    let left = recur_all(left, depth + 1, left_updates);
+    let right = recur_all(right, depth + 1, right_updates);
+
+    match (&left, &right) {
+        (Node::Empty, Node::Empty) => Node::Empty,
+        (Node::Leaf { .. }, Node::Empty) => left,
+        (Node::Empty, Node::Leaf { .. }) => right,
+        _ => Node::internal(left, right),
+    }
+}
+
+// Applies all `updates` in a single traversal. `updates` must have distinct
+// paths, none of which reaches a `Stub`.
+pub(crate) fn apply_all<Key, Value, H>(
+    root: Node<Key, Value, H>,
+    updates: Vec<Update<Key, Value, H>>,
+) -> Node<Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    recur_all(root, 0, updates)
+}
@@ -0,0 +1,109 @@
+use crate::{
+    common::{
+        store::Field,
+        tree::{Direction, Path},
+    },
+    hashing::Hasher,
+    map::{
+        errors::MapError,
+        store::{Leaf, Node},
+    },
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+fn find<Key, Value, H>(
+    node: &Node<Key, Value, H>,
+    depth: u8,
+    path: Path,
+) -> Result<Option<&Leaf<Key, Value, H>>, Top<MapError>>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    match node {
+        Node::Empty => Ok(None),
+        Node::Internal(internal) => {
+            if path[depth] == Direction::Left {
+                find(internal.left(), depth + 1, path)
+            } else {
+                find(internal.right(), depth + 1, path)
+            }
+        }
+        Node::Leaf(leaf) => {
+            if path.reaches(leaf.key().digest()) {
+                Ok(Some(leaf))
+            } else {
+                Ok(None)
+            }
+        }
+        Node::Stub(_) => MapError::BranchUnknown.fail().spot(here!()),
+    }
+}
+
+// Keeps `leaf` if a leaf with the same key and value lies under `other`
+fn intersect<Key, Value, H>(
+    leaf: &Leaf<Key, Value, H>,
+    other: &Node<Key, Value, H>,
+    depth: u8,
+) -> Result<Node<Key, Value, H>, Top<MapError>>
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+    H: Hasher,
+{
+    let path = Path::from(leaf.key().digest());
+
+    match find(other, depth, path)? {
+        Some(found) if found.hash() == leaf.hash() => Ok(Node::Leaf(leaf.clone())),
+        _ => Ok(Node::Empty),
+    }
+}
+
+fn recur<Key, Value, H>(
+    lho: &Node<Key, Value, H>,
+    rho: &Node<Key, Value, H>,
+    depth: u8,
+) -> Result<Node<Key, Value, H>, Top<MapError>>
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+    H: Hasher,
+{
+    if lho.hash() == rho.hash() {
+        return Ok(lho.clone()); // Whole subtree in common, including its stubs (if any)
+    }
+
+    match (lho, rho) {
+        (Node::Empty, _) | (_, Node::Empty) => Ok(Node::Empty),
+        (Node::Stub(_), _) | (_, Node::Stub(_)) => MapError::BranchUnknown.fail().spot(here!()),
+
+        (Node::Leaf(leaf), other) | (other, Node::Leaf(leaf)) => intersect(leaf, other, depth),
+
+        (Node::Internal(lho), Node::Internal(rho)) => {
+            let left = recur(lho.left(), rho.left(), depth + 1)?;
+            let right = recur(lho.right(), rho.right(), depth + 1)?;
+
+            // Preserve compactness, as in `drain_filter`
+            Ok(match (&left, &right) {
+                (Node::Empty, Node::Empty) => Node::Empty,
+                (Node::Leaf(_), Node::Empty) => left,
+                (Node::Empty, Node::Leaf(_)) => right,
+                _ => Node::internal(left, right),
+            })
+        }
+    }
+}
+
+pub(crate) fn common<Key, Value, H>(
+    lho: &Node<Key, Value, H>,
+    rho: &Node<Key, Value, H>,
+) -> Result<Node<Key, Value, H>, Top<MapError>>
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+    H: Hasher,
+{
+    recur(lho, rho, 0)
+}
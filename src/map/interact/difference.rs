@@ -0,0 +1,59 @@
+use crate::{
+    common::store::Field,
+    hashing::Hasher,
+    map::{errors::MapError, interact::union::children, store::Node},
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+fn recur<Key, Value, H>(
+    lho: &Node<Key, Value, H>,
+    rho: &Node<Key, Value, H>,
+    depth: u8,
+    empty: &Node<Key, Value, H>,
+) -> Result<Node<Key, Value, H>, Top<MapError>>
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+    H: Hasher,
+{
+    if lho.hash() == rho.hash() {
+        return Ok(Node::Empty); // Whole subtree in common
+    }
+
+    match (lho, rho) {
+        (Node::Empty, _) => Ok(Node::Empty),
+        (node, Node::Empty) => Ok(node.clone()),
+        (Node::Stub(_), _) | (_, Node::Stub(_)) => MapError::BranchUnknown.fail().spot(here!()),
+
+        // Either a different key, or the same key with a different value
+        (Node::Leaf(leaf), Node::Leaf(_)) => Ok(Node::Leaf(leaf.clone())),
+
+        (lho, rho) => {
+            let (lho_left, lho_right) = children(lho, depth, empty);
+            let (rho_left, rho_right) = children(rho, depth, empty);
+
+            let left = recur(lho_left, rho_left, depth + 1, empty)?;
+            let right = recur(lho_right, rho_right, depth + 1, empty)?;
+
+            Ok(match (&left, &right) {
+                (Node::Empty, Node::Empty) => Node::Empty,
+                (Node::Leaf(_), Node::Empty) => left,
+                (Node::Empty, Node::Leaf(_)) => right,
+                _ => Node::internal(left, right),
+            })
+        }
+    }
+}
+
+pub(crate) fn difference<Key, Value, H>(
+    lho: &Node<Key, Value, H>,
+    rho: &Node<Key, Value, H>,
+) -> Result<Node<Key, Value, H>, Top<MapError>>
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+    H: Hasher,
+{
+    recur(lho, rho, 0, &Node::Empty)
+}
@@ -0,0 +1,79 @@
+use crate::{
+    common::store::Field,
+    hashing::Hasher,
+    map::{errors::MapError, store::Node},
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+use std::mem;
+
+fn check<Key, Value, H>(node: &Node<Key, Value, H>) -> Result<(), Top<MapError>>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    match node {
+        Node::Internal(internal) => {
+            check(internal.left())?;
+            check(internal.right())
+        }
+        Node::Stub(_) => MapError::BranchUnknown.fail().spot(here!()),
+        _ => Ok(()),
+    }
+}
+
+fn recur<Key, Value, H, F>(
+    node: Node<Key, Value, H>,
+    pred: &mut F,
+    drained: &mut Vec<(Key, Value)>,
+) -> Node<Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+    F: FnMut(&Key, &Value) -> bool,
+{
+    match node {
+        Node::Internal(internal) => {
+            let (left, right) = internal.children();
+
+            let left = recur(left, pred, drained);
+            let right = recur(right, pred, drained);
+
+            match (&left, &right) {
+                (Node::Empty, Node::Empty) => Node::Empty,
+                (Node::Leaf { .. }, Node::Empty) => left,
+                (Node::Empty, Node::Leaf { .. }) => right,
+                _ => Node::internal(left, right),
+            }
+        }
+        Node::Leaf(leaf) if pred(leaf.key().inner(), leaf.value().inner()) => {
+            let (key, value) = leaf.fields();
+            drained.push((key.take(), value.take()));
+            Node::Empty
+        }
+        node => node,
+    }
+}
+
+pub(crate) fn drain_filter<Key, Value, H, F>(
+    root: &mut Node<Key, Value, H>,
+    mut pred: F,
+) -> Result<Vec<(Key, Value)>, Top<MapError>>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+    F: FnMut(&Key, &Value) -> bool,
+{
+    // Stubs are ruled out before anything is drained, so that `root` is
+    // left untouched on failure
+    check(root)?;
+
+    let mut drained = Vec::new();
+    *root = recur(mem::replace(root, Node::Empty), &mut pred, &mut drained);
+
+    Ok(drained)
+}
@@ -1,8 +1,9 @@
 use crate::{
     common::{
         store::Field,
-        tree::{Direction, Path},
+        tree::{Direction, Path, Prefix},
     },
+    hashing::Hasher,
     map::{
         errors::MapError,
         store::{Internal, Leaf, Node},
@@ -20,14 +21,15 @@ fn split(paths: &[Path], depth: u8) -> (&[Path], &[Path]) {
     (left, right)
 }
 
-pub(crate) fn recur<Key, Value>(
-    node: &Node<Key, Value>,
+pub(crate) fn recur<Key, Value, H>(
+    node: &Node<Key, Value, H>,
     depth: u8,
     paths: &[Path],
-) -> Result<Node<Key, Value>, Top<MapError>>
+) -> Result<Node<Key, Value, H>, Top<MapError>>
 where
     Key: Field + Clone,
     Value: Field + Clone,
+    H: Hasher,
 {
     match node {
         Node::Internal(internal) if !paths.is_empty() => {
@@ -53,13 +55,79 @@ where
     }
 }
 
-pub(crate) fn export<Key, Value>(
-    root: &Node<Key, Value>,
+pub(crate) fn export<Key, Value, H>(
+    root: &Node<Key, Value, H>,
     paths: &[Path],
-) -> Result<Node<Key, Value>, Top<MapError>>
+) -> Result<Node<Key, Value, H>, Top<MapError>>
 where
     Key: Field + Clone,
     Value: Field + Clone,
+    H: Hasher,
 {
     recur(root, 0, paths)
 }
+
+// Copies the whole subtree rooted at `node`, which must not contain `Stub`s
+fn copy<Key, Value, H>(node: &Node<Key, Value, H>) -> Result<Node<Key, Value, H>, Top<MapError>>
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+    H: Hasher,
+{
+    match node {
+        Node::Internal(internal) => Ok(Node::Internal(Internal::raw(
+            internal.hash(),
+            copy(internal.left())?,
+            copy(internal.right())?,
+        ))),
+        Node::Stub(_) => MapError::BranchUnknown.fail().spot(here!()),
+        node => Ok(node.clone()),
+    }
+}
+
+pub(crate) fn export_prefix<Key, Value, H>(
+    node: &Node<Key, Value, H>,
+    prefix: Prefix,
+    depth: u8,
+) -> Result<Node<Key, Value, H>, Top<MapError>>
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+    H: Hasher,
+{
+    if depth == prefix.depth() {
+        return copy(node);
+    }
+
+    match node {
+        Node::Internal(internal) => {
+            let stub = |node: &Node<Key, Value, H>| match node {
+                Node::Empty => Node::Empty,
+                node => Node::stub(node.hash()),
+            };
+
+            let (left, right) = if prefix[depth] == Direction::Left {
+                (
+                    export_prefix(internal.left(), prefix, depth + 1)?,
+                    stub(internal.right()),
+                )
+            } else {
+                (
+                    stub(internal.left()),
+                    export_prefix(internal.right(), prefix, depth + 1)?,
+                )
+            };
+
+            Ok(Node::Internal(Internal::raw(internal.hash(), left, right)))
+        }
+        // The subtree under `prefix` is either empty or this leaf: in both
+        // cases, `leaf` is needed to prove it
+        Node::Leaf(leaf) => Ok(Node::Leaf(Leaf::raw(
+            leaf.hash(),
+            leaf.key().clone(),
+            leaf.value().clone(),
+        ))),
+        Node::Empty => Ok(Node::Empty),
+        Node::Stub(_) => MapError::BranchUnknown.fail().spot(here!()),
+    }
+}
@@ -0,0 +1,61 @@
+use crate::{
+    common::{store::Field, tree::Path},
+    hashing::Hasher,
+    map::{
+        errors::MapError,
+        interact::export,
+        store::{Internal, Leaf, Node},
+    },
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+fn recur<Key, Value, H>(
+    node: &Node<Key, Value, H>,
+    keys: &Node<Key, ()>,
+    depth: u8,
+) -> Result<Node<Key, Value, H>, Top<MapError>>
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+    H: Hasher,
+{
+    match (node, keys) {
+        (Node::Empty, _) => Ok(Node::Empty), // `Node::Empty` is cheaper to clone than `Node::Stub`
+        (node, Node::Empty) => Ok(Node::stub(node.hash())), // No key to keep under `node`
+        (_, Node::Stub(_)) => MapError::BranchUnknown.fail().spot(here!()),
+
+        (node, Node::Leaf(leaf)) => {
+            // Only one key to keep under `node`: fall back to exporting its path
+            let path = Path::from(leaf.key().digest());
+            export::recur(node, depth, &[path])
+        }
+
+        (Node::Internal(internal), Node::Internal(keys)) => {
+            let left = recur(internal.left(), keys.left(), depth + 1)?;
+            let right = recur(internal.right(), keys.right(), depth + 1)?;
+
+            Ok(Node::Internal(Internal::raw(internal.hash(), left, right))) // `internal.hash()` is guaranteed to be correct, no need to recompute
+        }
+        (Node::Leaf(leaf), Node::Internal(_)) => Ok(Node::Leaf(Leaf::raw(
+            // `leaf` is either a kept key or a proof of exclusion
+            // for the keys under `keys`, and needs to be cloned
+            leaf.hash(),
+            leaf.key().clone(),
+            leaf.value().clone(),
+        ))),
+        (Node::Stub(_), Node::Internal(_)) => MapError::BranchUnknown.fail().spot(here!()),
+    }
+}
+
+pub(crate) fn filter<Key, Value, H>(
+    root: &Node<Key, Value, H>,
+    keys: &Node<Key, ()>,
+) -> Result<Node<Key, Value, H>, Top<MapError>>
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+    H: Hasher,
+{
+    recur(root, keys, 0)
+}
@@ -1,18 +1,20 @@
 use crate::{
     common::{store::Field, tree::Direction},
+    hashing::Hasher,
     map::{errors::MapError, interact::Query, store::Node},
 };
 
 use doomstack::{here, Doom, ResultExt, Top};
 
-fn recur<Key, Value>(
-    node: &Node<Key, Value>,
+fn recur<Key, Value, H>(
+    node: &Node<Key, Value, H>,
     depth: u8,
     query: Query,
 ) -> Result<Option<&Value>, Top<MapError>>
 where
     Key: Field,
     Value: Field,
+    H: Hasher,
 {
     match node {
         Node::Empty => Ok(None),
@@ -34,13 +36,14 @@ where
     }
 }
 
-pub(crate) fn get<Key, Value>(
-    root: &Node<Key, Value>,
+pub(crate) fn get<Key, Value, H>(
+    root: &Node<Key, Value, H>,
     query: Query,
 ) -> Result<Option<&Value>, Top<MapError>>
 where
     Key: Field,
     Value: Field,
+    H: Hasher,
 {
     recur(root, 0, query)
 }
@@ -1,14 +1,16 @@
 use crate::{
     common::store::Field,
+    hashing::Hasher,
     map::{errors::MapError, store::Node},
 };
 
 use doomstack::{here, Doom, ResultExt, Top};
 
-fn recur<Key, Value>(destination: &mut Node<Key, Value>, source: Node<Key, Value>)
+fn recur<Key, Value, H>(destination: &mut Node<Key, Value, H>, source: Node<Key, Value, H>)
 where
     Key: Field,
     Value: Field,
+    H: Hasher,
 {
     match (destination, source) {
         (destination, source) if destination.is_stub() => {
@@ -23,13 +25,14 @@ where
     }
 }
 
-pub(crate) fn import<Key, Value>(
-    destination_root: &mut Node<Key, Value>,
-    source_root: Node<Key, Value>,
+pub(crate) fn import<Key, Value, H>(
+    destination_root: &mut Node<Key, Value, H>,
+    source_root: Node<Key, Value, H>,
 ) -> Result<(), Top<MapError>>
 where
     Key: Field,
     Value: Field,
+    H: Hasher,
 {
     if source_root.hash() == destination_root.hash() {
         recur(destination_root, source_root);
@@ -0,0 +1,72 @@
+use crate::{
+    common::{
+        store::Field,
+        tree::{Direction, Path, Prefix},
+    },
+    hashing::Hasher,
+    map::{errors::MapError, store::Node},
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+fn collect<Key, Value, H>(
+    node: &Node<Key, Value, H>,
+    keys: &mut Vec<Key>,
+) -> Result<(), Top<MapError>>
+where
+    Key: Field + Clone,
+    Value: Field,
+    H: Hasher,
+{
+    match node {
+        Node::Empty => Ok(()),
+        Node::Internal(internal) => {
+            collect(internal.left(), keys)?;
+            collect(internal.right(), keys)
+        }
+        Node::Leaf(leaf) => {
+            keys.push(leaf.key().inner().clone());
+            Ok(())
+        }
+        Node::Stub(_) => MapError::BranchUnknown.fail().spot(here!()),
+    }
+}
+
+pub(crate) fn keys_under<Key, Value, H>(
+    root: &Node<Key, Value, H>,
+    prefix: Prefix,
+) -> Result<Vec<Key>, Top<MapError>>
+where
+    Key: Field + Clone,
+    Value: Field,
+    H: Hasher,
+{
+    let mut node = root;
+
+    for direction in prefix {
+        node = match node {
+            Node::Internal(internal) => {
+                if direction == Direction::Left {
+                    internal.left()
+                } else {
+                    internal.right()
+                }
+            }
+            Node::Leaf(leaf) => {
+                // The subtree under `prefix` is either empty or this leaf
+                return if prefix.contains(&Path::from(leaf.key().digest())) {
+                    Ok(vec![leaf.key().inner().clone()])
+                } else {
+                    Ok(Vec::new())
+                };
+            }
+            Node::Empty => return Ok(Vec::new()),
+            Node::Stub(_) => return MapError::BranchUnknown.fail().spot(here!()),
+        };
+    }
+
+    let mut keys = Vec::new();
+    collect(node, &mut keys)?;
+
+    Ok(keys)
+}
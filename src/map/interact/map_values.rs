@@ -0,0 +1,51 @@
+use crate::{
+    common::store::Field,
+    hashing::Hasher,
+    map::{
+        errors::MapError,
+        store::{Node, Wrap},
+    },
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+fn recur<Key, Value, Mapped, H, F>(
+    node: &Node<Key, Value, H>,
+    f: &F,
+) -> Result<Node<Key, Mapped, H>, Top<MapError>>
+where
+    Key: Field + Clone,
+    Value: Field,
+    H: Hasher,
+    Mapped: Field,
+    F: Fn(&Value) -> Mapped,
+{
+    match node {
+        Node::Empty => Ok(Node::Empty),
+        Node::Internal(internal) => {
+            let left = recur(internal.left(), f)?;
+            let right = recur(internal.right(), f)?;
+
+            Ok(Node::internal(left, right)) // Children hashes changed, `Internal` hash needs to be recomputed
+        }
+        Node::Leaf(leaf) => {
+            let value = Wrap::new(f(leaf.value().inner())).pot(MapError::HashError, here!())?;
+            Ok(Node::leaf(leaf.key().clone(), value)) // `key` (and hence its path) is unchanged
+        }
+        Node::Stub(_) => MapError::BranchUnknown.fail().spot(here!()),
+    }
+}
+
+pub(crate) fn map_values<Key, Value, Mapped, H, F>(
+    root: &Node<Key, Value, H>,
+    f: F,
+) -> Result<Node<Key, Mapped, H>, Top<MapError>>
+where
+    Key: Field + Clone,
+    Value: Field,
+    H: Hasher,
+    Mapped: Field,
+    F: Fn(&Value) -> Mapped,
+{
+    recur(root, &f)
+}
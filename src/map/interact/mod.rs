@@ -1,15 +1,31 @@
 mod action;
 mod apply;
+mod common;
+mod difference;
+mod drain_filter;
 mod export;
+mod filter;
 mod get;
 mod import;
+mod keys_under;
+mod map_values;
 mod query;
+mod shared_nodes;
+mod union;
 mod update;
 
-pub(crate) use apply::apply;
-pub(crate) use export::export;
+pub(crate) use apply::{apply, apply_all};
+pub(crate) use common::common;
+pub(crate) use difference::difference;
+pub(crate) use drain_filter::drain_filter;
+pub(crate) use export::{export, export_prefix};
+pub(crate) use filter::filter;
 pub(crate) use get::get;
 pub(crate) use import::import;
+pub(crate) use keys_under::keys_under;
+pub(crate) use map_values::map_values;
+pub(crate) use shared_nodes::shared_nodes;
+pub(crate) use union::union;
 
 pub(crate) use action::Action;
 pub(crate) use query::Query;
@@ -1,20 +1,22 @@
-use crate::common::{data::Bytes, store::Field, tree::Path};
+use crate::{
+    common::{data::Bytes, store::Field, tree::Path},
+    hashing::{Hasher, HasherError},
+};
 
 use doomstack::Top;
 
-use talk::crypto::primitives::{hash, hash::HashError};
-
 #[derive(Debug)]
 pub(crate) struct Query {
     pub path: Path,
 }
 
 impl Query {
-    pub fn new<Key>(key: &Key) -> Result<Self, Top<HashError>>
+    pub fn new<H, Key>(key: &Key) -> Result<Self, Top<HasherError>>
     where
+        H: Hasher,
         Key: Field,
     {
-        let hash: Bytes = hash::hash(key)?.into();
+        let hash: Bytes = H::hash(key)?.into();
 
         Ok(Query {
             path: Path::from(hash),
@@ -0,0 +1,42 @@
+use crate::{common::store::Field, hashing::Hasher, map::store::Node};
+
+// Counts the internal nodes of two matching subtrees, looking
+// past `Stub`s in either subtree through the other
+fn count<Key, Value, H>(lho: &Node<Key, Value, H>, rho: &Node<Key, Value, H>) -> usize
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    match (lho, rho) {
+        (Node::Internal(lho), Node::Internal(rho)) => {
+            1 + count(lho.left(), rho.left()) + count(lho.right(), rho.right())
+        }
+        (Node::Internal(internal), Node::Stub(_)) | (Node::Stub(_), Node::Internal(internal)) => {
+            1 + count(internal.left(), internal.left()) + count(internal.right(), internal.right())
+        }
+        _ => 0,
+    }
+}
+
+pub(crate) fn shared_nodes<Key, Value, H>(
+    lho: &Node<Key, Value, H>,
+    rho: &Node<Key, Value, H>,
+) -> usize
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    if lho.hash() == rho.hash() {
+        // Matching subtrees are identical: hashes need not be compared any further
+        return count(lho, rho);
+    }
+
+    match (lho, rho) {
+        (Node::Internal(lho), Node::Internal(rho)) => {
+            shared_nodes(lho.left(), rho.left()) + shared_nodes(lho.right(), rho.right())
+        }
+        _ => 0,
+    }
+}
@@ -0,0 +1,88 @@
+use crate::{
+    common::{
+        store::Field,
+        tree::{Direction, Path},
+    },
+    hashing::Hasher,
+    map::{errors::MapError, store::Node},
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+// Splits `node` into its children at `depth`, a `Leaf` being pushed one
+// level down (next to `empty`) to be compared with the children of
+// another `Internal`
+pub(crate) fn children<'n, Key, Value, H>(
+    node: &'n Node<Key, Value, H>,
+    depth: u8,
+    empty: &'n Node<Key, Value, H>,
+) -> (&'n Node<Key, Value, H>, &'n Node<Key, Value, H>)
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    match node {
+        Node::Internal(internal) => (internal.left(), internal.right()),
+        Node::Leaf(leaf) => {
+            if Path::from(leaf.key().digest())[depth] == Direction::Left {
+                (node, empty)
+            } else {
+                (empty, node)
+            }
+        }
+        _ => (empty, empty),
+    }
+}
+
+fn recur<Key, Value, H>(
+    lho: &Node<Key, Value, H>,
+    rho: &Node<Key, Value, H>,
+    depth: u8,
+    empty: &Node<Key, Value, H>,
+) -> Result<Node<Key, Value, H>, Top<MapError>>
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+    H: Hasher,
+{
+    if lho.hash() == rho.hash() {
+        return Ok(lho.clone());
+    }
+
+    match (lho, rho) {
+        (Node::Empty, node) | (node, Node::Empty) => Ok(node.clone()),
+        (Node::Stub(_), _) | (_, Node::Stub(_)) => MapError::BranchUnknown.fail().spot(here!()),
+
+        // Same key, different values: `lho` takes precedence
+        (Node::Leaf(lho), Node::Leaf(rho)) if lho.key().digest() == rho.key().digest() => {
+            Ok(Node::Leaf(lho.clone()))
+        }
+
+        (lho, rho) => {
+            let (lho_left, lho_right) = children(lho, depth, empty);
+            let (rho_left, rho_right) = children(rho, depth, empty);
+
+            let left = recur(lho_left, rho_left, depth + 1, empty)?;
+            let right = recur(lho_right, rho_right, depth + 1, empty)?;
+
+            Ok(match (&left, &right) {
+                (Node::Leaf(_), Node::Empty) => left,
+                (Node::Empty, Node::Leaf(_)) => right,
+                _ => Node::internal(left, right),
+            })
+        }
+    }
+}
+
+pub(crate) fn union<Key, Value, H>(
+    lho: &Node<Key, Value, H>,
+    rho: &Node<Key, Value, H>,
+) -> Result<Node<Key, Value, H>, Top<MapError>>
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+    H: Hasher,
+{
+    recur(lho, rho, 0, &Node::Empty)
+}
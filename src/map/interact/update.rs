@@ -1,24 +1,24 @@
 use crate::{
     common::{data::Bytes, store::Field, tree::Path},
+    hashing::{Hasher, HasherError},
     map::{interact::Action, store::Wrap},
 };
 
 use doomstack::Top;
 
-use talk::crypto::primitives::{hash, hash::HashError};
-
 #[derive(Debug)]
-pub(crate) struct Update<Key: Field, Value: Field> {
+pub(crate) struct Update<Key: Field, Value: Field, H: Hasher> {
     pub path: Path,
-    pub action: Action<Key, Value>,
+    pub action: Action<Key, Value, H>,
 }
 
-impl<Key, Value> Update<Key, Value>
+impl<Key, Value, H> Update<Key, Value, H>
 where
     Key: Field,
     Value: Field,
+    H: Hasher,
 {
-    pub fn insert(key: Key, value: Value) -> Result<Self, Top<HashError>> {
+    pub fn insert(key: Key, value: Value) -> Result<Self, Top<HasherError>> {
         let key = Wrap::new(key)?;
         let value = Wrap::new(value)?;
 
@@ -28,8 +28,8 @@ where
         })
     }
 
-    pub fn remove(key: &Key) -> Result<Self, Top<HashError>> {
-        let hash: Bytes = hash::hash(key)?.into();
+    pub fn remove(key: &Key) -> Result<Self, Top<HasherError>> {
+        let hash: Bytes = H::hash(key)?.into();
 
         Ok(Update {
             path: Path::from(hash),
@@ -1,19 +1,32 @@
 use crate::{
-    common::{data::Bytes, store::Field, tree::Path},
+    common::{
+        store::Field,
+        tree::{Direction, Path, Prefix},
+    },
+    hashing::{Blake3Hasher, Hasher},
     map::{
         errors::MapError,
-        interact::{self, Query, Update},
-        store::{self, Node},
+        interact::{self, Action, Query, Update},
+        store::{self, Bounds, Node, Violation, Wrap, MAX_DEPTH},
+        AbsenceProof, BloomSummary, Entry, ExportManifest, MapBatch, MapIntoIter, MapIter,
+        MapProof, NodeRef, RegionEqualityProof, Set,
     },
 };
 
-use doomstack::{here, ResultExt, Top};
+use bincode::Options;
+
+use doomstack::{here, Doom, ResultExt, Top};
 
-use serde::{de::Error as DeError, Deserialize, Deserializer, Serialize, Serializer};
+use serde::{
+    de::{DeserializeSeed, Error as DeError},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 
 use std::{
     borrow::{Borrow, BorrowMut},
     fmt::{Debug, Error, Formatter},
+    hash::{Hash as StdHash, Hasher as StdHasher},
+    iter,
 };
 
 use talk::{
@@ -148,8 +161,8 @@ use talk::{
 ///          k2   k3
 /// ```
 
-pub struct Map<Key: Field, Value: Field> {
-    root: Lender<Node<Key, Value>>,
+pub struct Map<Key: Field, Value: Field, H: Hasher = Blake3Hasher> {
+    root: Lender<Node<Key, Value, H>>,
 }
 
 impl<Key, Value> Map<Key, Value>
@@ -172,51 +185,188 @@ where
         }
     }
 
-    pub fn root_stub(commitment: Hash) -> Self {
-        Map {
-            root: Lender::new(Node::stub(commitment.into())),
-        }
-    }
+    /// Assembles a single partial `Map` committing to `commitment` out of
+    /// `proofs`, each a [`MapProof`] (see [`prove`]) paired with the key it
+    /// proves. Every proof is verified against `commitment`, then its branch
+    /// is merged into the result (as by [`import`]): the result can answer
+    /// queries for every key proven by any of `proofs`.
+    ///
+    /// A deniability proof ending at a leaf that stores another key only
+    /// carries the digests of that leaf: the result cannot answer queries
+    /// for the other key.
+    ///
+    /// # Errors
+    ///
+    /// If any of `proofs` does not verify for its key against `commitment`,
+    /// [`ProofInvalid`] is returned.
+    ///
+    /// If a key or value cannot be hashed, [`HashError`] is returned.
+    ///
+    /// [`MapProof`]: crate::map::MapProof
+    /// [`prove`]: crate::map::Map::prove
+    /// [`import`]: crate::map::Map::import
+    /// [`ProofInvalid`]: crate::map::errors::MapError::ProofInvalid
+    /// [`HashError`]: crate::map::errors::MapError::HashError
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Map;
+    ///
+    /// let mut map = Map::new();
+    ///
+    /// map.insert(1, "a");
+    /// map.insert(2, "b");
+    /// map.insert(3, "c");
+    ///
+    /// let proofs = vec![(1, map.prove(&1).unwrap()), (2, map.prove(&2).unwrap())];
+    /// let assembled = Map::from_proofs(map.commit(), proofs).unwrap();
+    ///
+    /// assert_eq!(assembled.commit(), map.commit());
+    /// assert_eq!(assembled.get(&1).unwrap(), Some(&"a"));
+    /// assert_eq!(assembled.get(&2).unwrap(), Some(&"b"));
+    /// assert!(assembled.get(&3).is_err()); // MapError::BranchUnknown
+    /// ```
+    pub fn from_proofs<I>(commitment: Hash, proofs: I) -> Result<Self, Top<MapError>>
+    where
+        I: IntoIterator<Item = (Key, MapProof<Key, Value>)>,
+    {
+        let mut map = Map::root_stub(commitment);
 
-    pub(crate) fn raw(root: Node<Key, Value>) -> Self {
-        Map {
-            root: Lender::new(root),
+        for (key, proof) in proofs {
+            proof.verify(commitment, &key)?;
+            map.import(Map::raw(proof.into_branch(key)?))?;
         }
+
+        Ok(map)
     }
 
-    /// Returns a cryptographic commitment to the contents of the `Map`.
-    /// Exporting a `Map`, even partially, preserves its commitment.
-    /// A `Map` can be imported only by another `Map` with matching
-    /// commitment.
+    /// Builds a `Map` storing `records`, trusting the `Path` supplied with
+    /// each key instead of hashing the key.
+    ///
+    /// This is a fast path for records that were validated beforehand (e.g.,
+    /// loaded from a trusted snapshot). The caller must guarantee that:
+    /// - each `Path` is the path of its key (see [`Path`]);
+    /// - `records` are sorted in the order of [`iter`], i.e., from left to
+    ///   right, and no two records share the same key.
+    ///
+    /// Violating either guarantee does not cause undefined behavior, but
+    /// yields a malformed `Map` (or a panic). Debug builds validate both
+    /// guarantees, and panic if they do not hold. In all builds, records
+    /// sharing the same path cause a panic.
+    ///
+    /// [`Path`]: crate::tree::Path
+    /// [`iter`]: crate::map::Map::iter
+    ///
+    /// # Errors
+    ///
+    /// If any value cannot be hashed, [`HashError`] is returned.
+    ///
+    /// [`HashError`]: crate::map::errors::MapError::HashError
     ///
     /// # Examples
     ///
     /// ```
-    /// use zebra::map::Map;
+    /// use zebra::{map::Map, tree::Path};
     ///
-    /// let mut map: Map<&str, i32> = Map::new();
-    /// map.insert("alice", 31);
-    /// map.insert("bob", 44);
+    /// let mut map = Map::new();
+    /// map.insert(1, "a").unwrap();
+    /// map.insert(2, "b").unwrap();
     ///
-    /// let export = map.export(["alice"]).unwrap();
-    /// assert_eq!(map.commit(), export.commit());
+    /// // Records obtained from a trusted source, in order
+    /// let records = map
+    ///     .iter()
+    ///     .map(|(key, value)| {
+    ///         let path = Path::from(talk::crypto::primitives::hash::hash(key).unwrap());
+    ///         (*key, *value, path)
+    ///     })
+    ///     .collect::<Vec<_>>();
+    ///
+    /// let rebuilt = Map::from_sorted_unchecked(records).unwrap();
+    /// assert_eq!(rebuilt.commit(), map.commit());
     /// ```
-    pub fn commit(&self) -> Hash {
-        let root: &Node<Key, Value> = self.root.borrow();
-        root.hash().into()
+    pub fn from_sorted_unchecked<I>(records: I) -> Result<Self, Top<MapError>>
+    where
+        I: IntoIterator<Item = (Key, Value, Path)>,
+    {
+        fn recursion<Key, Value>(
+            mut records: Vec<(Path, Wrap<Key>, Wrap<Value>)>,
+            depth: usize,
+        ) -> Node<Key, Value>
+        where
+            Key: Field,
+            Value: Field,
+        {
+            if records.len() <= 1 {
+                return match records.pop() {
+                    Some((_, key, value)) => Node::leaf(key, value),
+                    None => Node::Empty,
+                };
+            }
+
+            // Distinct paths diverge within `Path::LEN` bits. This is checked
+            // in all builds: past the last bit, duplicate (or unsorted)
+            // records would otherwise be split without bound
+            assert!(
+                depth < Path::LEN,
+                "`from_sorted_unchecked`: records are not sorted, or not unique"
+            );
+
+            // `records` are sorted from left to right: those lying in the
+            // left subtree come first
+            let split = records.partition_point(|(path, ..)| path[depth as u8] == Direction::Left);
+            let right = records.split_off(split);
+
+            Node::internal(recursion(records, depth + 1), recursion(right, depth + 1))
+        }
+
+        let records = records
+            .into_iter()
+            .map(|(key, value, path)| {
+                let key = Wrap::raw(path.into(), key);
+                let value = Wrap::new(value).pot(MapError::HashError, here!())?;
+
+                Ok((path, key, value))
+            })
+            .collect::<Result<Vec<_>, Top<MapError>>>()?;
+
+        #[cfg(debug_assertions)]
+        {
+            for (path, key, _) in records.iter() {
+                assert!(
+                    path.reaches(hash::hash(key.inner()).unwrap().into()),
+                    "`from_sorted_unchecked`: path does not match key"
+                );
+            }
+
+            for pair in records.windows(2) {
+                let divergence = pair[0]
+                    .0
+                    .into_iter()
+                    .zip(pair[1].0)
+                    .find(|(first, second)| first != second);
+
+                assert!(
+                    divergence == Some((Direction::Left, Direction::Right)),
+                    "`from_sorted_unchecked`: records are not sorted, or not unique"
+                );
+            }
+        }
+
+        Ok(Map::raw(recursion(records, 0)))
     }
 
-    /// Returns a reference to the value corresponding to the key.
+    /// Returns an [`AbsenceProof`] that `key` is not in the `Map`.
     ///
     /// # Errors
     ///
-    /// If the map did not have the key present but it cannot determine if the association exists or not
-    /// (e.g. locally part of the tree is missing, replaced by a `Stub`), [`BranchUnknown`] is returned.
+    /// If `key` is in the `Map`, [`KeyPresent`] is returned.
     ///
-    /// If the `Key` or `Value` cannot be hashed (via `drop::crypto::hash`), [`HashError`] is returned
+    /// If the path of `key` leads to a `Stub`, [`BranchUnknown`] is returned.
     ///
+    /// [`AbsenceProof`]: crate::map::AbsenceProof
+    /// [`KeyPresent`]: errors/enum.MapError.html
     /// [`BranchUnknown`]: errors/enum.MapError.html
-    /// [`HashError`]: errors/enum.MapError.html
     ///
     /// # Examples
     ///
@@ -224,29 +374,33 @@ where
     /// use zebra::map::Map;
     ///
     /// let mut map = Map::new();
+    ///
     /// map.insert(1, "a");
-    /// assert_eq!(map.get(&1).unwrap(), Some(&"a"));
-    /// assert_eq!(map.get(&2).unwrap(), None);
+    /// map.insert(2, "b");
+    ///
+    /// let proof = map.prove_absent(&3).unwrap();
+    /// assert!(proof.verify(map.commit(), &3).is_ok());
+    ///
+    /// assert!(map.prove_absent(&1).is_err()); // MapError::KeyPresent
     /// ```
-    pub fn get(&self, key: &Key) -> Result<Option<&Value>, Top<MapError>> {
-        let query = Query::new(key).pot(MapError::HashError, here!())?;
-        interact::get(self.root.borrow(), query)
+    pub fn prove_absent(&self, key: &Key) -> Result<AbsenceProof, Top<MapError>> {
+        let path = Path::from(hash::hash(key).pot(MapError::HashError, here!())?);
+        AbsenceProof::prove(self.root.borrow(), path)
     }
 
-    /// Inserts a key-value pair into the map.
-    ///
-    /// If the map did not have this key present, [`None`] is returned.
-    ///
-    /// If the map did have this key present, the value is updated, and the old value is returned.
+    /// Returns a [`MapProof`] of the value associated with `key` (existence),
+    /// or of the absence of `key` (deniability).
     ///
-    /// [`None`]: https://doc.rust-lang.org/std/option/enum.Option.html#variant.None
+    /// [`MapProof`]: crate::map::MapProof
     ///
     /// # Errors
     ///
-    /// If the portion of the map pertaining to the key is incomplete, i.e. there is a `Stub`
-    /// on the key's path), [`BranchUnknown`] is returned.
+    /// If `key` cannot be hashed, [`HashError`] is returned.
     ///
-    /// [`BranchUnknown`]: errors/enum.MapError.html
+    /// If the path of `key` runs through a `Stub`, [`BranchUnknown`] is returned.
+    ///
+    /// [`HashError`]: crate::map::errors::MapError::HashError
+    /// [`BranchUnknown`]: crate::map::errors::MapError::BranchUnknown
     ///
     /// # Examples
     ///
@@ -254,30 +408,38 @@ where
     /// use zebra::map::Map;
     ///
     /// let mut map = Map::new();
-    /// assert_eq!(map.insert("Alice", 1).unwrap(), None);
+    /// map.insert(1, "a").unwrap();
     ///
-    /// map.insert("Alice", 2);
-    /// assert_eq!(map.insert("Alice", 3).unwrap(), Some(2));
-    /// assert_eq!(map.get(&"Alice").unwrap(), Some(&3));
+    /// let proof = map.prove(&1).unwrap();
+    /// assert_eq!(proof.verify(map.commit(), &1).unwrap(), Some(&"a"));
     /// ```
-    pub fn insert(&mut self, key: Key, value: Value) -> Result<Option<Value>, Top<MapError>> {
-        let update = Update::insert(key, value).pot(MapError::HashError, here!())?;
-        self.update(update)
+    pub fn prove(&self, key: &Key) -> Result<MapProof<Key, Value>, Top<MapError>>
+    where
+        Value: Clone,
+    {
+        let path = Path::from(hash::hash(key).pot(MapError::HashError, here!())?);
+        MapProof::prove(self.root.borrow(), path)
     }
 
-    /// Removes a key from the map, returning the value at the key if the
-    /// key was previously in the map.
+    /// Returns a [`BloomSummary`] over the keys of the `Map`, using `bits`
+    /// bits. The number of bit positions set per key is chosen to minimize
+    /// the rate of false positives given `bits` and the number of keys.
     ///
-    /// If the map did not have this key present, [`None`] is returned.
+    /// Building a [`BloomSummary`] walks all the keys of the `Map`.
     ///
-    /// [`None`]: https://doc.rust-lang.org/std/option/enum.Option.html#variant.None
+    /// [`BloomSummary`]: crate::map::BloomSummary
     ///
     /// # Errors
     ///
-    /// If the portion of the map pertaining to the key is incomplete, i.e. there is a `Stub`
-    /// on the key's path, [`BranchUnknown`] is returned.
+    /// If the `Map` is not complete (see [`is_complete`]), [`BranchUnknown`]
+    /// is returned.
     ///
-    /// [`BranchUnknown`]: errors/enum.MapError.html
+    /// [`is_complete`]: crate::map::Map::is_complete
+    /// [`BranchUnknown`]: crate::map::errors::MapError::BranchUnknown
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` is 0.
     ///
     /// # Examples
     ///
@@ -286,42 +448,62 @@ where
     ///
     /// let mut map = Map::new();
     ///
-    /// map.insert(1, "a");
-    /// assert_eq!(map.remove(&1).unwrap(), Some("a"));
-    /// assert_eq!(map.remove(&1).unwrap(), None);
+    /// for key in 0..100 {
+    ///     map.insert(key, key).unwrap();
+    /// }
+    ///
+    /// let summary = map.bloom_summary(1024).unwrap();
+    /// assert!((0..100).all(|key| summary.might_contain(&key)));
     /// ```
-    pub fn remove(&mut self, key: &Key) -> Result<Option<Value>, Top<MapError>> {
-        let update = Update::remove(key).pot(MapError::HashError, here!())?;
-        self.update(update)
-    }
-
-    fn update(&mut self, update: Update<Key, Value>) -> Result<Option<Value>, Top<MapError>> {
-        let root = self.root.take();
-        let (root, result) = interact::apply(root, update);
-        self.root.restore(root);
-
-        result
+    pub fn bloom_summary(&self, bits: usize) -> Result<BloomSummary, Top<MapError>> {
+        BloomSummary::new(self.root.borrow(), bits)
     }
 
-    /// Exports a subset of the map containing only branches along the given keys.
-    /// Excluded branches are replaced by `Stub`s.
+    /// Returns a [`RegionEqualityProof`] that the `Map` and `other` store the
+    /// same records under `prefix`, or `None` if they differ under `prefix`
+    /// or if a `Stub` prevents either from being walked down to `prefix`.
     ///
-    /// The keys may be any borrowed form of the tree's key type, but
-    /// [`Serialize`] on the borrowed form *must* match that of
-    /// the key type.
+    /// [`RegionEqualityProof`]: crate::map::RegionEqualityProof
     ///
-    /// [`Serialize`]: https://docs.serde.rs/serde/trait.Serialize.html
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::{map::Map, tree::Prefix};
+    ///
+    /// let mut map = Map::new();
+    /// map.insert(1, "a").unwrap();
+    ///
+    /// let other = map.clone();
+    ///
+    /// let proof = map.prove_region_unchanged(&other, Prefix::root()).unwrap();
+    /// assert!(proof.verify(map.commit(), other.commit(), Prefix::root()).is_ok());
+    /// ```
+    pub fn prove_region_unchanged(
+        &self,
+        other: &Map<Key, Value>,
+        prefix: Prefix,
+    ) -> Option<RegionEqualityProof> {
+        RegionEqualityProof::prove(self.root.borrow(), other.root.borrow(), prefix)
+    }
+
+    /// Exports the `Map` restricted to the items of `keys`. The result is
+    /// the same as that of [`export`]ing every item of `keys`, but is
+    /// obtained by walking the `Map` and `keys` in lockstep, skipping
+    /// subtrees where `keys` has no item. This is more efficient than
+    /// [`export`] when `keys` is large.
     ///
     /// # Errors
-    /// If the it cannot be determined if the key does or does not exist
-    /// (e.g. locally part of the map is missing, replaced by a `Stub`), [`BranchUnknown`] is returned.
     ///
+    /// If either the `Map` or `keys` is missing a branch that is needed
+    /// for the export, [`BranchUnknown`] is returned.
+    ///
+    /// [`export`]: crate::map::Map::export
     /// [`BranchUnknown`]: errors/enum.MapError.html
+    ///
     /// # Examples
     ///
     /// ```
-    /// use zebra::map::Map;
-    /// use zebra::map::errors::MapError;
+    /// use zebra::map::{Map, Set};
     ///
     /// let mut map = Map::new();
     ///
@@ -329,624 +511,2946 @@ where
     /// map.insert(2, "b");
     /// map.insert(3, "c");
     ///
-    /// let submap = map.export([&1]).unwrap();
+    /// let mut keys = Set::new();
+    /// keys.insert(1);
+    /// keys.insert(3);
     ///
-    /// assert_eq!(submap.get(&1).unwrap(), Some(&"a"));
-    /// assert!(submap.get(&2).is_err()); // MapError::BranchUnknown
-    /// assert!(submap.get(&3).is_err()); // MapError::BranchUnknown
+    /// let filtered = map.filter_to(&keys).unwrap();
     ///
-    /// assert_eq!(map.get(&1).unwrap(), Some(&"a"));
-    /// assert_eq!(map.get(&2).unwrap(), Some(&"b"));
-    /// assert_eq!(map.get(&3).unwrap(), Some(&"c"));
+    /// assert_eq!(filtered.commit(), map.commit());
+    /// assert_eq!(filtered.get(&1).unwrap(), Some(&"a"));
+    /// assert!(filtered.get(&2).is_err()); // MapError::BranchUnknown
+    /// assert_eq!(filtered.get(&3).unwrap(), Some(&"c"));
     /// ```
-    pub fn export<I, K>(&self, keys: I) -> Result<Map<Key, Value>, Top<MapError>>
+    pub fn filter_to(&self, keys: &Set<Key>) -> Result<Map<Key, Value>, Top<MapError>>
     where
         Key: Clone,
         Value: Clone,
-        I: IntoIterator<Item = K>,
-        K: Borrow<Key>,
     {
-        let paths: Result<Vec<Path>, Top<MapError>> = keys
-            .into_iter()
-            .map(|key| {
-                hash::hash(key.borrow())
-                    .map(|digest| Path::from(Bytes::from(digest)))
-                    .pot(MapError::HashError, here!())
-            })
-            .collect();
+        let root = interact::filter(self.root.borrow(), keys.map().root.borrow())?;
+        Ok(Map::raw(root))
+    }
+}
 
-        let mut paths = paths?;
-        paths.sort();
+impl<Key, Value, H> Map<Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    /// Creates an empty `Map` using `H` (rather than the default
+    /// [`Blake3Hasher`]) to hash its keys, values and nodes.
+    ///
+    /// [`Blake3Hasher`]: crate::hashing::Blake3Hasher
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "sha256")]
+    /// # {
+    /// use zebra::{hashing::Sha256Hasher, map::Map};
+    ///
+    /// let mut map: Map<&str, i32, Sha256Hasher> = Map::with_hasher();
+    /// map.insert("alice", 31).unwrap();
+    ///
+    /// assert_eq!(map.get(&"alice").unwrap(), Some(&31));
+    /// # }
+    /// ```
+    pub fn with_hasher() -> Self {
+        Map {
+            root: Lender::new(Node::Empty),
+        }
+    }
 
-        let root = interact::export(self.root.borrow(), &paths)?;
+    pub fn root_stub(commitment: Hash) -> Self {
+        Map {
+            root: Lender::new(Node::stub(commitment.into())),
+        }
+    }
 
-        Ok(Map {
+    pub(crate) fn raw(root: Node<Key, Value, H>) -> Self {
+        Map {
             root: Lender::new(root),
-        })
+        }
     }
 
-    /// Computes the union of two *compatible* maps.
-    /// Two `Map`s are compatible if they share the same underlying key-value associations.
+    pub(crate) fn root(&self) -> &Node<Key, Value, H> {
+        self.root.borrow()
+    }
+
+    /// Returns a cryptographic commitment to the contents of the `Map`.
+    /// Exporting a `Map`, even partially, preserves its commitment.
+    /// A `Map` can be imported only by another `Map` with matching
+    /// commitment.
     ///
-    /// Concretely, it replaces `Stub`s in the first map with the concrete information
-    /// in the second map. The first map is therefore extended with the missing information
-    /// (key-value associations) that the second map possesses.
+    /// # Examples
     ///
-    /// This can be used as a method to merge (and condense) multiple maps into one.
+    /// ```
+    /// use zebra::map::Map;
     ///
-    /// # Errors
-    /// If the maps are not compatible, [`MapIncompatible`] is returned.
+    /// let mut map: Map<&str, i32> = Map::new();
+    /// map.insert("alice", 31);
+    /// map.insert("bob", 44);
     ///
-    /// [`MapIncompatible`]: errors/enum.MapError.html
+    /// let export = map.export(["alice"]).unwrap();
+    /// assert_eq!(map.commit(), export.commit());
+    /// ```
+    pub fn commit(&self) -> Hash {
+        let root: &Node<Key, Value, H> = self.root.borrow();
+        root.hash().into()
+    }
+
+    /// Returns the number of records stored locally in the `Map`.
+    ///
+    /// Records hidden behind a `Stub` (e.g., in an [`export`]ed `Map`)
+    /// are not counted: `len` under-counts a `Map` that is not
+    /// complete (see [`is_complete`]).
+    ///
+    /// [`export`]: crate::map::Map::export
+    /// [`is_complete`]: crate::map::Map::is_complete
     ///
     /// # Examples
     ///
     /// ```
     /// use zebra::map::Map;
-    /// use zebra::map::errors::MapError;
     ///
     /// let mut map = Map::new();
+    /// map.insert(1, "a").unwrap();
+    /// map.insert(2, "b").unwrap();
     ///
-    /// map.insert(1, "a");
-    /// map.insert(2, "b");
-    /// map.insert(3, "c");
+    /// assert_eq!(map.len(), 2);
+    /// assert_eq!(map.export([1]).unwrap().len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        fn recursion<Key, Value, H>(node: &Node<Key, Value, H>) -> usize
+        where
+            Key: Field,
+            Value: Field,
+            H: Hasher,
+        {
+            match node {
+                Node::Internal(internal) => {
+                    recursion(internal.left()) + recursion(internal.right())
+                }
+                Node::Leaf(_) => 1,
+                Node::Empty | Node::Stub(_) => 0,
+            }
+        }
+
+        recursion(self.root.borrow())
+    }
+
+    /// Returns `true` if the `Map` stores no record locally. As with
+    /// [`len`], records hidden behind a `Stub` are not accounted for.
     ///
-    /// let mut first_submap = map.export([&1]).unwrap();
-    /// let second_submap = map.export([&2]).unwrap();
+    /// [`len`]: crate::map::Map::len
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `false` if any branch of the `Map` is unknown, i.e., replaced
+    /// by a `Stub` (e.g., because the `Map` was [`export`]ed).
     ///
-    /// first_submap.import(second_submap).unwrap();
+    /// [`export`]: crate::map::Map::export
     ///
-    /// assert_eq!(first_submap.get(&1).unwrap(), Some(&"a"));
-    /// assert_eq!(first_submap.get(&2).unwrap(), Some(&"b"));
-    /// assert!(first_submap.get(&3).is_err());
+    /// # Examples
     ///
-    /// let mut incompatible_map = Map::new();
+    /// ```
+    /// use zebra::map::Map;
     ///
-    /// incompatible_map.insert(3, "c");
+    /// let mut map = Map::new();
+    /// map.insert(1, "a").unwrap();
+    /// map.insert(2, "b").unwrap();
     ///
-    /// // MapError::MapIncompatible
-    /// assert!(first_submap.import(incompatible_map).is_err())
+    /// assert!(map.is_complete());
+    /// assert!(!map.export([1]).unwrap().is_complete());
     /// ```
-    pub fn import(&mut self, mut other: Map<Key, Value>) -> Result<(), Top<MapError>> {
+    pub fn is_complete(&self) -> bool {
+        fn recursion<Key, Value, H>(node: &Node<Key, Value, H>) -> bool
+        where
+            Key: Field,
+            Value: Field,
+            H: Hasher,
+        {
+            match node {
+                Node::Internal(internal) => {
+                    recursion(internal.left()) && recursion(internal.right())
+                }
+                Node::Stub(_) => false,
+                Node::Empty | Node::Leaf(_) => true,
+            }
+        }
+
+        recursion(self.root.borrow())
+    }
+
+    /// Returns an iterator over the records stored locally in the `Map`,
+    /// from left to right (i.e., in the order of their paths). Records hidden behind a `Stub` are
+    /// skipped (see [`is_complete`]).
+    ///
+    /// The iterator is lazy: it walks the `Map` as it is advanced, without
+    /// collecting its records up front. Two `Map`s storing the same records
+    /// iterate over them in the same order.
+    ///
+    /// [`is_complete`]: crate::map::Map::is_complete
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Map;
+    ///
+    /// let mut map = Map::new();
+    /// map.insert(1, "a").unwrap();
+    /// map.insert(2, "b").unwrap();
+    ///
+    /// let mut records = map.iter().collect::<Vec<_>>();
+    /// records.sort();
+    ///
+    /// assert_eq!(records, vec![(&1, &"a"), (&2, &"b")]);
+    /// ```
+    pub fn iter(&self) -> MapIter<'_, Key, Value, H> {
+        MapIter::new(self.root.borrow())
+    }
+
+    /// Returns an iterator over the stubs of the `Map` (i.e., the branches
+    /// it does not store locally), from left to right. Each stub is yielded
+    /// along with its location and the commitment to its subtree.
+    ///
+    /// A stubbed branch can be requested from a peer storing it, checked
+    /// against its commitment, then [`import`]ed.
+    ///
+    /// [`import`]: crate::map::Map::import
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Map;
+    ///
+    /// let mut map = Map::new();
+    ///
+    /// for key in 0..16 {
+    ///     map.insert(key, key).unwrap();
+    /// }
+    ///
+    /// assert_eq!(map.iter_stubs().count(), 0);
+    ///
+    /// let export = map.export([0]).unwrap();
+    ///
+    /// for (location, commitment) in export.iter_stubs() {
+    ///     assert_eq!(map.node_at(location).unwrap().commitment(), commitment);
+    /// }
+    /// ```
+    pub fn iter_stubs(&self) -> impl Iterator<Item = (Prefix, Hash)> + '_ {
+        let mut stack: Vec<(&Node<Key, Value, H>, Prefix)> =
+            vec![(self.root.borrow(), Prefix::root())];
+
+        iter::from_fn(move || {
+            while let Some((node, location)) = stack.pop() {
+                match node {
+                    Node::Internal(internal) => {
+                        // Push `right` first, so that `left` is visited first
+                        stack.push((internal.right(), location.right()));
+                        stack.push((internal.left(), location.left()));
+                    }
+                    Node::Stub(stub) => return Some((location, stub.hash().into())),
+                    Node::Empty | Node::Leaf(_) => {}
+                }
+            }
+
+            None
+        })
+    }
+
+    /// Returns a read-only [`NodeRef`] to the root of the `Map`.
+    ///
+    /// [`NodeRef`]: crate::map::NodeRef
+    pub fn root_ref(&self) -> NodeRef<'_, Key, Value, H> {
+        NodeRef::new(self.root.borrow(), Prefix::root())
+    }
+
+    /// Returns a read-only [`NodeRef`] to the node of the `Map` at `location`,
+    /// or `None` if the path to `location` runs through a non-internal node.
+    ///
+    /// [`NodeRef`]: crate::map::NodeRef
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::{map::Map, tree::Prefix};
+    ///
+    /// let mut map = Map::new();
+    /// map.insert(1, "a").unwrap();
+    ///
+    /// // The root of a single-record map is a leaf
+    /// assert!(map.node_at(Prefix::root()).is_some());
+    /// assert!(map.node_at(Prefix::root().left()).is_none());
+    /// ```
+    pub fn node_at(&self, location: Prefix) -> Option<NodeRef<'_, Key, Value, H>> {
+        let mut node = self.root_ref();
+
+        for direction in location {
+            node = node.child(direction)?;
+        }
+
+        Some(node)
+    }
+
+    /// Returns all keys of the `Map` whose path starts with `prefix`, i.e.,
+    /// the keys stored in the subtree at `prefix`.
+    ///
+    /// # Errors
+    ///
+    /// If a `Stub` lies on the path to `prefix` or within its subtree,
+    /// [`BranchUnknown`] is returned.
+    ///
+    /// [`BranchUnknown`]: errors/enum.MapError.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::{map::Map, tree::Prefix};
+    ///
+    /// let mut map = Map::new();
+    ///
+    /// for key in 0..16 {
+    ///     map.insert(key, key).unwrap();
+    /// }
+    ///
+    /// let left = map.keys_under(Prefix::root().left()).unwrap();
+    /// let right = map.keys_under(Prefix::root().right()).unwrap();
+    ///
+    /// assert_eq!(left.len() + right.len(), 16);
+    /// ```
+    pub fn keys_under(&self, prefix: Prefix) -> Result<Vec<Key>, Top<MapError>>
+    where
+        Key: Clone,
+    {
+        interact::keys_under(self.root.borrow(), prefix)
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// # Errors
+    ///
+    /// If the map did not have the key present but it cannot determine if the association exists or not
+    /// (e.g. locally part of the tree is missing, replaced by a `Stub`), [`BranchUnknown`] is returned.
+    ///
+    /// If `key` cannot be hashed, [`KeyHashError`] is returned (with `index` 0),
+    /// carrying the error that prevented hashing it.
+    ///
+    /// [`BranchUnknown`]: errors/enum.MapError.html
+    /// [`KeyHashError`]: crate::map::errors::MapError::KeyHashError
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Map;
+    ///
+    /// let mut map = Map::new();
+    /// map.insert(1, "a");
+    /// assert_eq!(map.get(&1).unwrap(), Some(&"a"));
+    /// assert_eq!(map.get(&2).unwrap(), None);
+    /// ```
+    pub fn get(&self, key: &Key) -> Result<Option<&Value>, Top<MapError>> {
+        let query = Query::new::<H, _>(key)
+            .map_err(|source| MapError::KeyHashError { index: 0, source }.into_top())
+            .spot(here!())?;
+
+        interact::get(self.root.borrow(), query)
+    }
+
+    /// Like [`get`], but looks up the record at `path` directly, without
+    /// hashing a key. This is useful when the hash of the key is already
+    /// known (e.g., received from a peer), or expensive to compute.
+    ///
+    /// The caller is responsible for `path` being the hash of the intended
+    /// key (under the `Map`'s [`Hasher`]): the record returned, if any, is
+    /// the one whose key hashes to `path`.
+    ///
+    /// # Errors
+    ///
+    /// If the `Map` cannot determine whether a record exists at `path`
+    /// (e.g., part of the tree is replaced by a `Stub`), [`BranchUnknown`]
+    /// is returned.
+    ///
+    /// [`get`]: crate::map::Map::get
+    /// [`Hasher`]: crate::hashing::Hasher
+    /// [`BranchUnknown`]: crate::map::errors::MapError::BranchUnknown
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::{
+    ///     hashing::{Blake3Hasher, Hasher},
+    ///     map::Map,
+    ///     tree::Path,
+    /// };
+    ///
+    /// let mut map = Map::new();
+    /// map.insert(1u32, "a").unwrap();
+    ///
+    /// let path = Path::from(Blake3Hasher::hash(&1u32).unwrap());
+    /// assert_eq!(map.get_by_path(path).unwrap(), Some(&"a"));
+    /// ```
+    pub fn get_by_path(&self, path: Path) -> Result<Option<&Value>, Top<MapError>> {
+        interact::get(self.root.borrow(), Query { path })
+    }
+
+    /// Returns `true` if the map contains `key`. This is the idiomatic
+    /// membership test, equivalent to `get(key).map(|value| value.is_some())`.
+    ///
+    /// # Errors
+    ///
+    /// If the map did not have the key present but it cannot determine if the association exists or not
+    /// (e.g. locally part of the tree is missing, replaced by a `Stub`), [`BranchUnknown`] is returned.
+    ///
+    /// If the `Key` cannot be hashed (via `drop::crypto::hash`), [`HashError`] is returned
+    ///
+    /// [`BranchUnknown`]: errors/enum.MapError.html
+    /// [`HashError`]: errors/enum.MapError.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Map;
+    ///
+    /// let mut map = Map::new();
+    /// map.insert(1, "a");
+    /// assert!(map.contains_key(&1).unwrap());
+    /// assert!(!map.contains_key(&2).unwrap());
+    /// ```
+    pub fn contains_key(&self, key: &Key) -> Result<bool, Top<MapError>> {
+        let query = Query::new::<H, _>(key).pot(MapError::HashError, here!())?;
+        interact::get(self.root.borrow(), query).map(|value| value.is_some())
+    }
+
+    /// Inserts a key-value pair into the map.
+    ///
+    /// If the map did not have this key present, [`None`] is returned.
+    ///
+    /// If the map did have this key present, the value is updated, and the old value is returned.
+    ///
+    /// [`None`]: https://doc.rust-lang.org/std/option/enum.Option.html#variant.None
+    ///
+    /// # Errors
+    ///
+    /// If the portion of the map pertaining to the key is incomplete, i.e. there is a `Stub`
+    /// on the key's path), [`BranchUnknown`] is returned.
+    ///
+    /// [`BranchUnknown`]: errors/enum.MapError.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Map;
+    ///
+    /// let mut map = Map::new();
+    /// assert_eq!(map.insert("Alice", 1).unwrap(), None);
+    ///
+    /// map.insert("Alice", 2);
+    /// assert_eq!(map.insert("Alice", 3).unwrap(), Some(2));
+    /// assert_eq!(map.get(&"Alice").unwrap(), Some(&3));
+    /// ```
+    pub fn insert(&mut self, key: Key, value: Value) -> Result<Option<Value>, Top<MapError>> {
+        let update = Update::insert(key, value).pot(MapError::HashError, here!())?;
+        self.update(update)
+    }
+
+    /// Returns the [`Entry`] of `key`, for in-place inspection and
+    /// modification (see [`HashMap::entry`]).
+    ///
+    /// # Errors
+    ///
+    /// If `key` cannot be hashed, [`HashError`] is returned.
+    ///
+    /// If the portion of the map pertaining to the key is incomplete, i.e. there is a `Stub`
+    /// on the key's path, [`BranchUnknown`] is returned.
+    ///
+    /// [`Entry`]: crate::map::Entry
+    /// [`HashMap::entry`]: std::collections::HashMap::entry
+    /// [`HashError`]: crate::map::errors::MapError::HashError
+    /// [`BranchUnknown`]: crate::map::errors::MapError::BranchUnknown
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Map;
+    ///
+    /// let mut map = Map::new();
+    ///
+    /// for word in ["a", "b", "a"] {
+    ///     map.entry(word)
+    ///         .unwrap()
+    ///         .and_modify(|count| *count += 1)
+    ///         .unwrap()
+    ///         .or_insert(1)
+    ///         .unwrap();
+    /// }
+    ///
+    /// assert_eq!(map.get(&"a").unwrap(), Some(&2));
+    /// assert_eq!(map.get(&"b").unwrap(), Some(&1));
+    /// ```
+    pub fn entry(&mut self, key: Key) -> Result<Entry<'_, Key, Value, H>, Top<MapError>> {
+        Entry::new(self, key)
+    }
+
+    /// Removes a key from the map, returning the value at the key if the
+    /// key was previously in the map.
+    ///
+    /// If the map did not have this key present, [`None`] is returned.
+    ///
+    /// [`None`]: https://doc.rust-lang.org/std/option/enum.Option.html#variant.None
+    ///
+    /// # Errors
+    ///
+    /// If the portion of the map pertaining to the key is incomplete, i.e. there is a `Stub`
+    /// on the key's path, [`BranchUnknown`] is returned.
+    ///
+    /// [`BranchUnknown`]: errors/enum.MapError.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Map;
+    ///
+    /// let mut map = Map::new();
+    ///
+    /// map.insert(1, "a");
+    /// assert_eq!(map.remove(&1).unwrap(), Some("a"));
+    /// assert_eq!(map.remove(&1).unwrap(), None);
+    /// ```
+    pub fn remove(&mut self, key: &Key) -> Result<Option<Value>, Top<MapError>> {
+        let update = Update::remove(key).pot(MapError::HashError, here!())?;
+        self.update(update)
+    }
+
+    /// Stages insertions and removals on a [`MapBatch`] (passed to `f`),
+    /// then applies all of them at once, in a single traversal of the map.
+    /// The result is the same as that of applying every staged operation
+    /// in order (for any key, only the last staged operation matters).
+    ///
+    /// No operation is applied if any of them fails.
+    ///
+    /// # Errors
+    ///
+    /// If `f` fails, its error is returned.
+    ///
+    /// If the portion of the map pertaining to any of the staged keys is
+    /// incomplete, i.e. there is a `Stub` on the key's path,
+    /// [`BranchUnknown`] is returned.
+    ///
+    /// [`MapBatch`]: crate::map::MapBatch
+    /// [`BranchUnknown`]: errors/enum.MapError.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Map;
+    ///
+    /// let mut map = Map::new();
+    /// map.insert(1, "a").unwrap();
+    ///
+    /// map.batch(|batch| {
+    ///     batch.insert(2, "b")?;
+    ///     batch.insert(3, "c")?;
+    ///     batch.remove(&1)?;
+    ///     Ok(())
+    /// })
+    /// .unwrap();
+    ///
+    /// assert_eq!(map.get(&1).unwrap(), None);
+    /// assert_eq!(map.get(&2).unwrap(), Some(&"b"));
+    /// assert_eq!(map.get(&3).unwrap(), Some(&"c"));
+    /// ```
+    pub fn batch<F>(&mut self, f: F) -> Result<(), Top<MapError>>
+    where
+        F: FnOnce(&mut MapBatch<Key, Value, H>) -> Result<(), Top<MapError>>,
+    {
+        let mut batch = MapBatch::new();
+        f(&mut batch)?;
+
+        let updates = batch.finalize();
+
+        for update in updates.iter() {
+            // Fails if `update.path` reaches a `Stub`
+            interact::get(self.root.borrow(), Query { path: update.path })?;
+        }
+
+        let root = self.root.take();
+        let root = interact::apply_all(root, updates);
+        self.root.restore(root);
+
+        Ok(())
+    }
+
+    /// Inserts all `entries` (see [`insert`]) in a single traversal of the
+    /// `Map`, rather than one traversal per entry. If `entries` contains the
+    /// same key more than once, the last value prevails.
+    ///
+    /// The resulting `Map` (and its commitment) is the same as if `entries`
+    /// were inserted one by one. No entry is inserted if any of them fails.
+    ///
+    /// [`insert`]: crate::map::Map::insert
+    ///
+    /// # Errors
+    ///
+    /// If any key or value cannot be hashed, [`HashError`] is returned.
+    ///
+    /// If the path of any key runs through a `Stub`, [`BranchUnknown`]
+    /// is returned.
+    ///
+    /// [`HashError`]: crate::map::errors::MapError::HashError
+    /// [`BranchUnknown`]: crate::map::errors::MapError::BranchUnknown
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Map;
+    ///
+    /// let mut map = Map::new();
+    /// map.insert_many((0..1024).map(|key| (key, key + 1))).unwrap();
+    ///
+    /// assert_eq!(map.len(), 1024);
+    /// assert_eq!(map.get(&0).unwrap(), Some(&1));
+    /// ```
+    pub fn insert_many<I>(&mut self, entries: I) -> Result<(), Top<MapError>>
+    where
+        I: IntoIterator<Item = (Key, Value)>,
+    {
+        self.batch(|batch| {
+            for (key, value) in entries {
+                batch.insert(key, value)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Removes all records for which `pred` returns `true`, returning them
+    /// (in no particular order). The map is traversed only once.
+    ///
+    /// # Errors
+    ///
+    /// If the map contains a `Stub`, [`BranchUnknown`] is returned and
+    /// no record is removed.
+    ///
+    /// [`BranchUnknown`]: errors/enum.MapError.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Map;
+    ///
+    /// let mut map = Map::new();
+    ///
+    /// map.insert(1, "a").unwrap();
+    /// map.insert(2, "b").unwrap();
+    ///
+    /// let drained = map.drain_filter(|key, _| *key == 1).unwrap();
+    ///
+    /// assert_eq!(drained, vec![(1, "a")]);
+    /// assert_eq!(map.get(&1).unwrap(), None);
+    /// assert_eq!(map.get(&2).unwrap(), Some(&"b"));
+    /// ```
+    pub fn drain_filter<F>(&mut self, pred: F) -> Result<Vec<(Key, Value)>, Top<MapError>>
+    where
+        F: FnMut(&Key, &Value) -> bool,
+    {
+        let mut root = self.root.take();
+        let drained = interact::drain_filter(&mut root, pred);
+        self.root.restore(root);
+
+        drained
+    }
+
+    /// Retains only the records for which `f` returns `true`, removing
+    /// all others, as [`drain_filter`] would with the opposite predicate.
+    ///
+    /// # Errors
+    ///
+    /// If the map contains a `Stub`, [`BranchUnknown`] is returned and
+    /// no record is removed.
+    ///
+    /// [`drain_filter`]: crate::map::Map::drain_filter
+    /// [`BranchUnknown`]: errors/enum.MapError.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Map;
+    ///
+    /// let mut map = Map::new();
+    ///
+    /// map.insert(1, "a").unwrap();
+    /// map.insert(2, "b").unwrap();
+    ///
+    /// map.retain(|key, _| *key == 1).unwrap();
+    ///
+    /// assert_eq!(map.get(&1).unwrap(), Some(&"a"));
+    /// assert_eq!(map.get(&2).unwrap(), None);
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F) -> Result<(), Top<MapError>>
+    where
+        F: FnMut(&Key, &Value) -> bool,
+    {
+        self.drain_filter(|key, value| !f(key, value))?;
+        Ok(())
+    }
+
+    // Applies `writes` one by one (an insertion for `Some((key, value))`,
+    // a removal of `path` for `None`), failing if any of them reaches a
+    // `Stub`. Also fails if the compaction of the updated branches depends
+    // on the contents of a `Stub`, i.e., whether it is a leaf (see
+    // `TransitionProof::verify`). On failure, the `Map` is left partially
+    // updated.
+    pub(crate) fn update_strict<I>(&mut self, writes: I) -> Result<(), Top<MapError>>
+    where
+        I: IntoIterator<Item = (Path, Option<(Key, Value)>)>,
+    {
+        let mut paths = Vec::new();
+
+        for (path, write) in writes {
+            let update = match write {
+                Some((key, value)) => {
+                    Update::insert(key, value).pot(MapError::HashError, here!())?
+                }
+                None => Update {
+                    path,
+                    action: Action::Remove,
+                },
+            };
+
+            paths.push(update.path);
+            self.update(update)?;
+        }
+
+        for path in paths {
+            let mut node: &Node<Key, Value, H> = self.root.borrow();
+            let mut depth = 0;
+
+            while let Node::Internal(internal) = node {
+                if matches!(
+                    (internal.left(), internal.right()),
+                    (Node::Stub(_), Node::Empty) | (Node::Empty, Node::Stub(_))
+                ) {
+                    return MapError::BranchUnknown.fail().spot(here!());
+                }
+
+                node = if path[depth] == Direction::Left {
+                    internal.left()
+                } else {
+                    internal.right()
+                };
+
+                depth += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn update(
+        &mut self,
+        update: Update<Key, Value, H>,
+    ) -> Result<Option<Value>, Top<MapError>> {
+        let root = self.root.take();
+        let (root, result) = interact::apply(root, update);
+        self.root.restore(root);
+
+        result
+    }
+
+    /// Exports a subset of the map containing only branches along the given keys.
+    /// Excluded branches are replaced by `Stub`s.
+    ///
+    /// The keys may be any borrowed form of the tree's key type, but
+    /// [`Serialize`] on the borrowed form *must* match that of
+    /// the key type.
+    ///
+    /// [`Serialize`]: https://docs.serde.rs/serde/trait.Serialize.html
+    ///
+    /// # Errors
+    /// If the it cannot be determined if the key does or does not exist
+    /// (e.g. locally part of the map is missing, replaced by a `Stub`), [`BranchUnknown`] is returned.
+    ///
+    /// If a key cannot be hashed, [`KeyHashError`] is returned, carrying the position
+    /// of the first such key in `keys` (along with the error that prevented hashing it).
+    ///
+    /// [`BranchUnknown`]: errors/enum.MapError.html
+    /// [`KeyHashError`]: crate::map::errors::MapError::KeyHashError
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Map;
+    /// use zebra::map::errors::MapError;
+    ///
+    /// let mut map = Map::new();
+    ///
+    /// map.insert(1, "a");
+    /// map.insert(2, "b");
+    /// map.insert(3, "c");
+    ///
+    /// let submap = map.export([&1]).unwrap();
+    ///
+    /// assert_eq!(submap.get(&1).unwrap(), Some(&"a"));
+    /// assert!(submap.get(&2).is_err()); // MapError::BranchUnknown
+    /// assert!(submap.get(&3).is_err()); // MapError::BranchUnknown
+    ///
+    /// assert_eq!(map.get(&1).unwrap(), Some(&"a"));
+    /// assert_eq!(map.get(&2).unwrap(), Some(&"b"));
+    /// assert_eq!(map.get(&3).unwrap(), Some(&"c"));
+    /// ```
+    pub fn export<I, K>(&self, keys: I) -> Result<Map<Key, Value, H>, Top<MapError>>
+    where
+        Key: Clone,
+        Value: Clone,
+        I: IntoIterator<Item = K>,
+        K: Borrow<Key>,
+    {
+        let paths: Result<Vec<Path>, Top<MapError>> = keys
+            .into_iter()
+            .enumerate()
+            .map(|(index, key)| {
+                Query::new::<H, _>(key.borrow())
+                    .map(|query| query.path)
+                    .map_err(|source| MapError::KeyHashError { index, source }.into_top())
+                    .spot(here!())
+            })
+            .collect();
+
+        let mut paths = paths?;
+        paths.sort();
+
+        let root = interact::export(self.root.borrow(), &paths)?;
+
+        Ok(Map {
+            root: Lender::new(root),
+        })
+    }
+
+    /// Exports a subset of the map containing the whole subtree at `prefix`
+    /// (i.e., all records whose key path starts with `prefix`), along with
+    /// the branch leading to it. Other branches are replaced by `Stub`s,
+    /// so that the commitment of the export matches that of the map.
+    ///
+    /// If no record lies under `prefix`, the export is enough to prove it.
+    ///
+    /// # Errors
+    ///
+    /// If a `Stub` lies on the path to `prefix` or within its subtree,
+    /// [`BranchUnknown`] is returned.
+    ///
+    /// [`BranchUnknown`]: errors/enum.MapError.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::{map::Map, tree::Prefix};
+    ///
+    /// let mut map = Map::new();
+    ///
+    /// for key in 0..16 {
+    ///     map.insert(key, key).unwrap();
+    /// }
+    ///
+    /// let prefix = Prefix::root().left();
+    /// let export = map.export_prefix(prefix).unwrap();
+    ///
+    /// assert_eq!(export.commit(), map.commit());
+    /// assert_eq!(export.keys_under(prefix).unwrap(), map.keys_under(prefix).unwrap());
+    /// assert!(export.keys_under(Prefix::root().right()).is_err());
+    /// ```
+    pub fn export_prefix(&self, prefix: Prefix) -> Result<Map<Key, Value, H>, Top<MapError>>
+    where
+        Key: Clone,
+        Value: Clone,
+    {
+        let root = interact::export_prefix(self.root.borrow(), prefix, 0)?;
+
+        Ok(Map {
+            root: Lender::new(root),
+        })
+    }
+
+    /// Returns an [`ExportManifest`] listing the concrete and stubbed
+    /// regions of the `Map` (see [`export`]).
+    ///
+    /// [`ExportManifest`]: crate::map::ExportManifest
+    /// [`export`]: crate::map::Map::export
+    pub fn export_manifest(&self) -> ExportManifest {
+        ExportManifest::new(self.root.borrow())
+    }
+
+    /// Computes the union of two *compatible* maps.
+    /// Two `Map`s are compatible if they share the same underlying key-value associations.
+    ///
+    /// Concretely, it replaces `Stub`s in the first map with the concrete information
+    /// in the second map. The first map is therefore extended with the missing information
+    /// (key-value associations) that the second map possesses.
+    ///
+    /// This can be used as a method to merge (and condense) multiple maps into one.
+    ///
+    /// # Errors
+    /// If the maps are not compatible, [`MapIncompatible`] is returned.
+    ///
+    /// [`MapIncompatible`]: errors/enum.MapError.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Map;
+    /// use zebra::map::errors::MapError;
+    ///
+    /// let mut map = Map::new();
+    ///
+    /// map.insert(1, "a");
+    /// map.insert(2, "b");
+    /// map.insert(3, "c");
+    ///
+    /// let mut first_submap = map.export([&1]).unwrap();
+    /// let second_submap = map.export([&2]).unwrap();
+    ///
+    /// first_submap.import(second_submap).unwrap();
+    ///
+    /// assert_eq!(first_submap.get(&1).unwrap(), Some(&"a"));
+    /// assert_eq!(first_submap.get(&2).unwrap(), Some(&"b"));
+    /// assert!(first_submap.get(&3).is_err());
+    ///
+    /// let mut incompatible_map = Map::new();
+    ///
+    /// incompatible_map.insert(3, "c");
+    ///
+    /// // MapError::MapIncompatible
+    /// assert!(first_submap.import(incompatible_map).is_err())
+    /// ```
+    pub fn import(&mut self, mut other: Map<Key, Value, H>) -> Result<(), Top<MapError>> {
         interact::import(self.root.borrow_mut(), other.root.take())
     }
-}
 
-impl<Key, Value> Debug for Map<Key, Value>
-where
-    Key: Field,
-    Value: Field,
-{
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        write!(f, "Map(commitment: {:?})", self.commit())
+    /// Checks each of `claims` against the `Map` (typically, a proof obtained
+    /// through [`export`]) and `commitment`, returning one boolean per claim.
+    ///
+    /// A claim `(key, Some(value))` holds if `key` is associated with `value`;
+    /// a claim `(key, None)` holds if `key` is absent. A claim does not hold
+    /// if the `Map` does not commit to `commitment`, or if the `Map` lacks the
+    /// branch pertaining to its key (or the key cannot be hashed).
+    ///
+    /// [`export`]: crate::map::Map::export
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Map;
+    ///
+    /// let mut map = Map::new();
+    ///
+    /// map.insert(1, 1);
+    /// map.insert(2, 2);
+    ///
+    /// let proof = map.export([&1, &3]).unwrap();
+    ///
+    /// let claims = [(1, Some(1)), (1, Some(2)), (2, Some(2)), (3, None)];
+    /// let results = proof.verify_membership_batch(map.commit(), &claims);
+    ///
+    /// assert_eq!(results, vec![true, false, false, true]);
+    /// ```
+    pub fn verify_membership_batch(
+        &self,
+        commitment: Hash,
+        claims: &[(Key, Option<Value>)],
+    ) -> Vec<bool>
+    where
+        Value: PartialEq,
+    {
+        if self.commit() != commitment {
+            return vec![false; claims.len()];
+        }
+
+        claims
+            .iter()
+            .map(|(key, value)| match self.get(key) {
+                Ok(found) => found == value.as_ref(),
+                Err(_) => false,
+            })
+            .collect()
+    }
+
+    /// Returns a new `Map` with the same keys, where each value is
+    /// obtained by applying `f` to the corresponding value in this map.
+    ///
+    /// Since values change, the commitment of the new `Map` will in
+    /// general differ from the commitment of this map.
+    ///
+    /// # Errors
+    ///
+    /// If the map is incomplete, i.e. it contains a `Stub`, [`BranchUnknown`] is returned.
+    ///
+    /// If any of the new values cannot be hashed, [`HashError`] is returned.
+    ///
+    /// [`BranchUnknown`]: errors/enum.MapError.html
+    /// [`HashError`]: errors/enum.MapError.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Map;
+    ///
+    /// let mut map = Map::new();
+    ///
+    /// map.insert(1, 2);
+    /// map.insert(3, 4);
+    ///
+    /// let doubled = map.map_values(|value| value * 2).unwrap();
+    ///
+    /// assert_eq!(doubled.get(&1).unwrap(), Some(&4));
+    /// assert_eq!(doubled.get(&3).unwrap(), Some(&8));
+    /// ```
+    pub fn map_values<Mapped, F>(&self, f: F) -> Result<Map<Key, Mapped, H>, Top<MapError>>
+    where
+        Key: Clone,
+        Mapped: Field,
+        F: Fn(&Value) -> Mapped,
+    {
+        let root = interact::map_values(self.root.borrow(), f)?;
+        Ok(Map::raw(root))
+    }
+
+    /// Returns a new `Map` containing only the records that are present,
+    /// with the same value, in both `self` and `other`.
+    ///
+    /// The two `Map`s are traversed in lockstep: subtrees with matching
+    /// commitments are copied whole, without being visited.
+    ///
+    /// # Errors
+    ///
+    /// If either `Map` is missing a branch needed to compare them (see
+    /// [`export`]), [`BranchUnknown`] is returned.
+    ///
+    /// [`export`]: crate::map::Map::export
+    /// [`BranchUnknown`]: crate::map::errors::MapError::BranchUnknown
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Map;
+    ///
+    /// let mut first = Map::new();
+    /// first.insert(1, "a").unwrap();
+    /// first.insert(2, "b").unwrap();
+    /// first.insert(3, "c").unwrap();
+    ///
+    /// let mut second = Map::new();
+    /// second.insert(2, "b").unwrap();
+    /// second.insert(3, "d").unwrap();
+    ///
+    /// let common = first.common(&second).unwrap();
+    ///
+    /// assert_eq!(common.get(&1).unwrap(), None);
+    /// assert_eq!(common.get(&2).unwrap(), Some(&"b"));
+    /// assert_eq!(common.get(&3).unwrap(), None);
+    /// ```
+    pub fn common(&self, other: &Map<Key, Value, H>) -> Result<Map<Key, Value, H>, Top<MapError>>
+    where
+        Key: Clone,
+        Value: Clone,
+    {
+        let root = interact::common(self.root.borrow(), other.root.borrow())?;
+        Ok(Map::raw(root))
+    }
+
+    /// Counts the internal nodes that the `Map` shares with `other`, i.e.,
+    /// the internal nodes with identical hashes at the same location. As
+    /// in [`Table::diff`], subtrees with matching hashes are not compared
+    /// any further: sharing is cheap to measure between similar maps.
+    ///
+    /// Sharing only makes sense between maps over the same universe of keys
+    /// and values (e.g., versions of the same map). Internal nodes that are
+    /// replaced by `Stub`s in both maps are not counted.
+    ///
+    /// [`Table::diff`]: crate::database::Table::diff
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Map;
+    ///
+    /// let mut first = Map::new();
+    ///
+    /// for key in 0..16 {
+    ///     first.insert(key, key).unwrap();
+    /// }
+    ///
+    /// // All internal nodes of `first` are shared with itself
+    /// let all = first.shared_nodes(&first);
+    ///
+    /// let mut second = first.clone();
+    /// assert_eq!(first.shared_nodes(&second), all);
+    ///
+    /// second.insert(0, 1).unwrap();
+    /// assert!(first.shared_nodes(&second) < all);
+    /// ```
+    pub fn shared_nodes(&self, other: &Map<Key, Value, H>) -> usize {
+        interact::shared_nodes(self.root.borrow(), other.root.borrow())
+    }
+
+    // Records of either `self` or `other`, those of `self` taking
+    // precedence on matching keys (see `Set::union`)
+    pub(crate) fn union(
+        &self,
+        other: &Map<Key, Value, H>,
+    ) -> Result<Map<Key, Value, H>, Top<MapError>>
+    where
+        Key: Clone,
+        Value: Clone,
+    {
+        let root = interact::union(self.root.borrow(), other.root.borrow())?;
+        Ok(Map::raw(root))
+    }
+
+    // Records of `self` that are not in `other` with the same value
+    // (see `Set::difference`)
+    pub(crate) fn difference(
+        &self,
+        other: &Map<Key, Value, H>,
+    ) -> Result<Map<Key, Value, H>, Top<MapError>>
+    where
+        Key: Clone,
+        Value: Clone,
+    {
+        let root = interact::difference(self.root.borrow(), other.root.borrow())?;
+        Ok(Map::raw(root))
+    }
+
+    /// Deserializes a `Map` from `bytes` (as serialized by `bincode`),
+    /// rejecting it if it holds more than `max_records` key-value pairs
+    /// or if any of its nodes lies deeper than `max_depth` (the root lying
+    /// at depth 0).
+    ///
+    /// Limits are enforced while the `Map` is being deserialized, so that
+    /// an oversized `Map` is rejected before being fully allocated. As no
+    /// well-formed `Map` is deeper than 256, `max_depth` is capped at 256.
+    ///
+    /// # Errors
+    ///
+    /// If the `Map` exceeds `max_records`, [`TooManyRecords`] is returned.
+    ///
+    /// If the `Map` exceeds `max_depth`, [`TooDeep`] is returned.
+    ///
+    /// If `bytes` cannot be deserialized, or encode a `Map` with flawed
+    /// topology, [`DeserializeFailed`] is returned.
+    ///
+    /// [`TooManyRecords`]: errors/enum.MapError.html
+    /// [`TooDeep`]: errors/enum.MapError.html
+    /// [`DeserializeFailed`]: errors/enum.MapError.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Map;
+    ///
+    /// let mut map = Map::new();
+    ///
+    /// map.insert(1, 10);
+    /// map.insert(2, 20);
+    /// map.insert(3, 30);
+    ///
+    /// let bytes = bincode::serialize(&map).unwrap();
+    ///
+    /// assert!(Map::<i32, i32>::deserialize_bounded(&bytes, 3, 256).is_ok());
+    /// assert!(Map::<i32, i32>::deserialize_bounded(&bytes, 2, 256).is_err()); // MapError::TooManyRecords
+    /// ```
+    pub fn deserialize_bounded<'de>(
+        bytes: &'de [u8],
+        max_records: usize,
+        max_depth: usize,
+    ) -> Result<Self, Top<MapError>>
+    where
+        Key: Deserialize<'de>,
+        Value: Deserialize<'de>,
+    {
+        let bounds = Bounds::new(max_records, max_depth.min(MAX_DEPTH));
+
+        let root = bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .deserialize_seed(bounds.seed(), bytes); // Deserializes and computes node hashes
+
+        let root = match (root, bounds.violation()) {
+            (Ok(root), _) => root,
+            (Err(_), Some(Violation::TooManyRecords)) => {
+                return MapError::TooManyRecords.fail().spot(here!());
+            }
+            (Err(_), Some(Violation::TooDeep)) => {
+                return MapError::TooDeep.fail().spot(here!());
+            }
+            (Err(_), None) => {
+                return MapError::DeserializeFailed.fail().spot(here!());
+            }
+        };
+
+        store::check(&root).pot(MapError::DeserializeFailed, here!())?; // Checks correctness of tree topology
+
+        Ok(Map::raw(root))
+    }
+}
+
+impl<Key, Value, H> Debug for Map<Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "Map(commitment: {:?})", self.commit())
+    }
+}
+
+impl<Key, Value, H> Clone for Map<Key, Value, H>
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+    H: Hasher,
+{
+    fn clone(&self) -> Self {
+        let root: &Node<Key, Value, H> = self.root.borrow();
+        Map::raw(root.clone())
+    }
+}
+
+/// Two `Map`s are equal if their commitments are equal (see [`commit`]).
+///
+/// As commitments are collision-resistant, equal `Map`s hold the same
+/// records. Since a `Stub` commits to the branch it replaces, however, a
+/// `Map` is also equal to any of its exports (see [`export`]), even though
+/// the latter stores fewer records locally.
+///
+/// [`commit`]: crate::map::Map::commit
+/// [`export`]: crate::map::Map::export
+///
+/// # Examples
+///
+/// ```
+/// use zebra::map::Map;
+///
+/// let mut first = Map::new();
+/// first.insert(1, "a").unwrap();
+/// first.insert(2, "b").unwrap();
+///
+/// let mut second = Map::new();
+/// second.insert(2, "b").unwrap();
+/// second.insert(1, "a").unwrap();
+///
+/// assert_eq!(first, second);
+/// assert_eq!(first, first.export([1]).unwrap());
+///
+/// second.insert(3, "c").unwrap();
+/// assert_ne!(first, second);
+/// ```
+impl<Key, Value, H> PartialEq for Map<Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.commit() == other.commit()
+    }
+}
+
+impl<Key, Value, H> Eq for Map<Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+}
+
+/// Hashes the commitment of the `Map` (consistently with [`PartialEq`]).
+///
+/// [`PartialEq`]: std::cmp::PartialEq
+impl<Key, Value, H> StdHash for Map<Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    fn hash<S>(&self, state: &mut S)
+    where
+        S: StdHasher,
+    {
+        self.commit().hash(state)
+    }
+}
+
+/// Builds a `Map` from an iterator of key-value pairs, in a single batch
+/// (see [`insert_many`]). If a key appears more than once, its last value
+/// is kept.
+///
+/// # Panics
+///
+/// Panics if a key or value cannot be hashed. Use [`insert_many`] to
+/// handle hashing errors instead.
+///
+/// [`insert_many`]: crate::map::Map::insert_many
+///
+/// # Examples
+///
+/// ```
+/// use zebra::map::Map;
+///
+/// let map: Map<u32, u32> = (0..16).map(|key| (key, key + 1)).collect();
+///
+/// assert_eq!(map.len(), 16);
+/// assert_eq!(map.get(&0).unwrap(), Some(&1));
+/// ```
+impl<Key, Value, H> iter::FromIterator<(Key, Value)> for Map<Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    fn from_iter<I>(entries: I) -> Self
+    where
+        I: IntoIterator<Item = (Key, Value)>,
+    {
+        let mut map = Map::with_hasher();
+
+        map.insert_many(entries)
+            .expect("Failed to hash a record collected into a `Map`");
+
+        map
+    }
+}
+
+impl<'m, Key, Value, H> IntoIterator for &'m Map<Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    type Item = (&'m Key, &'m Value);
+    type IntoIter = MapIter<'m, Key, Value, H>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<Key, Value, H> IntoIterator for Map<Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    type Item = (Key, Value);
+    type IntoIter = MapIntoIter<Key, Value, H>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        MapIntoIter::new(self.root.take())
+    }
+}
+
+impl<Key, Value, H> Serialize for Map<Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.root.serialize(serializer)
+    }
+}
+
+impl<'de, Key, Value, H> Deserialize<'de> for Map<Key, Value, H>
+where
+    Key: Field + Deserialize<'de>,
+    Value: Field + Deserialize<'de>,
+    H: Hasher,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Deserializing a `Node` recurs once per level: bounding depth
+        // prevents deeply nested encodings from overflowing the stack
+        let bounds = Bounds::new(usize::MAX, MAX_DEPTH);
+        let root = bounds.seed().deserialize(deserializer)?; // Deserializes and computes node hashes
+
+        store::check(&root) // Checks correctness of tree topology
+            .map_err(|err| DeError::custom(err))?;
+
+        Ok(Map {
+            root: Lender::new(root),
+        }) // If a `Map` is `Deserialize`d, then it is correct
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        common::{store::hash, tree::Direction},
+        map::store::{self, Internal},
+    };
+
+    use std::{
+        collections::{HashMap, HashSet},
+        fmt::Debug,
+        hash::Hash,
+    };
+
+    impl<Key, Value, H> Map<Key, Value, H>
+    where
+        Key: Field,
+        Value: Field,
+        H: Hasher,
+    {
+        pub(crate) fn check_tree(&self) {
+            store::check(self.root.borrow()).unwrap();
+        }
+
+        pub(crate) fn collect_records(&self) -> HashMap<Key, Value>
+        where
+            Key: Field + Clone + Eq + Hash,
+            Value: Field + Clone,
+            H: Hasher,
+        {
+            fn recursion<Key, Value, H>(
+                node: &Node<Key, Value, H>,
+                collector: &mut HashMap<Key, Value>,
+            ) where
+                Key: Field + Clone + Eq + Hash,
+                Value: Field + Clone,
+                H: Hasher,
+            {
+                match node {
+                    Node::Internal(internal) => {
+                        recursion(internal.left(), collector);
+                        recursion(internal.right(), collector);
+                    }
+                    Node::Leaf(leaf) => {
+                        collector.insert(leaf.key().inner().clone(), leaf.value().inner().clone());
+                    }
+                    Node::Empty | Node::Stub(_) => {}
+                }
+            }
+
+            let mut collector = HashMap::new();
+            recursion(self.root.borrow(), &mut collector);
+            collector
+        }
+
+        pub fn assert_records<I>(&self, reference: I)
+        where
+            Key: Field + Debug + Clone + Eq + Hash,
+            Value: Field + Debug + Clone + Eq + Hash,
+            H: Hasher,
+            I: IntoIterator<Item = (Key, Value)>,
+        {
+            let actual: HashSet<(Key, Value)> = self.collect_records().into_iter().collect();
+
+            let reference: HashSet<(Key, Value)> = reference.into_iter().collect();
+
+            let differences: HashSet<(Key, Value)> = reference
+                .symmetric_difference(&actual)
+                .map(|r| r.clone())
+                .collect();
+
+            assert_eq!(differences, HashSet::new());
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let map: Map<u32, u32> = Map::new();
+
+        map.check_tree();
+        map.assert_records([]);
+    }
+
+    #[test]
+    fn insert() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            assert_eq!(map.insert(key, value).unwrap(), None);
+
+            map.check_tree();
+            map.assert_records((0..=key).map(|i| (i, i)));
+        }
+    }
+
+    #[test]
+    fn insert_then_get() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        for key in 0..2048 {
+            if key < 1024 {
+                assert_eq!(map.get(&key).unwrap(), Some(&key))
+            } else {
+                assert_eq!(map.get(&key).unwrap(), None)
+            }
+        }
+    }
+
+    #[test]
+    fn insert_then_remove_half() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        for key in 512..1024 {
+            assert_eq!(map.remove(&key).unwrap(), Some(key));
+
+            map.check_tree();
+            map.assert_records(
+                (0..512)
+                    .map(|i| (i, i))
+                    .chain(((key + 1)..1024).map(|i| (i, i))),
+            );
+        }
+    }
+
+    #[test]
+    fn insert_then_remove_half_then_get() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        for key in 512..1024 {
+            map.remove(&key).unwrap();
+        }
+
+        for key in 0..2048 {
+            if key < 512 {
+                assert_eq!(map.get(&key).unwrap(), Some(&key))
+            } else {
+                assert_eq!(map.get(&key).unwrap(), None)
+            }
+        }
+    }
+
+    #[test]
+    fn insert_then_remove_half_then_increment() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        for key in 512..1024 {
+            map.remove(&key).unwrap();
+        }
+
+        for (key, value) in (0..512).map(|i| (i, i + 1)) {
+            assert_eq!(map.insert(key, value).unwrap(), Some(key));
+
+            map.check_tree();
+            map.assert_records((0..512).map(|i| if i <= key { (i, i + 1) } else { (i, i) }));
+        }
+    }
+
+    #[test]
+    fn insert_then_remove_half_then_increment_then_get() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        for key in 512..1024 {
+            map.remove(&key).unwrap();
+        }
+
+        for (key, value) in (0..512).map(|i| (i, i + 1)) {
+            map.insert(key, value).unwrap();
+        }
+
+        for key in 0..2048 {
+            if key < 512 {
+                assert_eq!(map.get(&key).unwrap(), Some(&(key + 1)))
+            } else {
+                assert_eq!(map.get(&key).unwrap(), None)
+            }
+        }
+    }
+
+    #[test]
+    fn insert_then_remove_half_then_remove_other_half() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        for key in 512..1024 {
+            map.remove(&key).unwrap();
+        }
+
+        for key in 0..512 {
+            assert_eq!(map.remove(&key).unwrap(), Some(key));
+
+            map.check_tree();
+            map.assert_records(((key + 1)..512).map(|i| (i, i)));
+        }
+    }
+
+    #[test]
+    fn export_none() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let export = map.export::<[u32; 0], u32>([]).unwrap(); // Explicit type arguments are to aid type inference on an empty array
+
+        assert_eq!(map.commit(), export.commit());
+        export.check_tree();
+        export.assert_records([]);
+    }
+
+    #[test]
+    fn export_single() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let export = map.export([33]).unwrap();
+
+        assert_eq!(map.commit(), export.commit());
+        export.check_tree();
+        export.assert_records([(33, 33)]);
+    }
+
+    #[test]
+    fn export_single_then_get() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let export = map.export([33]).unwrap();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            if key == 33 {
+                assert_eq!(export.get(&key).unwrap(), Some(&value));
+            } else {
+                export.get(&key).unwrap_err();
+            }
+        }
+    }
+
+    #[test]
+    fn export_outsider_then_get() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let export = map.export([1025]).unwrap();
+
+        assert_eq!(export.get(&1025).unwrap(), None);
+    }
+
+    #[test]
+    fn export_half() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let export = map.export(0..512).unwrap();
+
+        assert_eq!(map.commit(), export.commit());
+        export.check_tree();
+        export.assert_records((0..512).map(|i| (i, i)));
+    }
+
+    #[test]
+    fn export_half_then_get() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let export = map.export(0..512).unwrap();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            if key < 512 {
+                assert_eq!(export.get(&key).unwrap(), Some(&value));
+            } else {
+                export.get(&key).unwrap_err();
+            }
+        }
+    }
+
+    #[test]
+    fn export_all() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let export = map.export(0..1024).unwrap();
+
+        assert_eq!(map.commit(), export.commit());
+        export.check_tree();
+        export.assert_records((0..1024).map(|i| (i, i)));
+    }
+
+    #[test]
+    fn export_all_then_get() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let export = map.export(0..1024).unwrap();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            assert_eq!(export.get(&key).unwrap(), Some(&value));
+        }
+    }
+
+    #[test]
+    fn export_all_then_get_outsider() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let export = map.export(0..1024).unwrap();
+
+        assert_eq!(export.get(&1025).unwrap(), None);
+    }
+
+    #[test]
+    fn export_overlap_then_get() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..512).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let export = map.export(256..1024).unwrap();
+
+        assert_eq!(map.commit(), export.commit());
+        export.check_tree();
+
+        for (key, value) in (256..1024).map(|i| (i, i)) {
+            if key < 512 {
+                assert_eq!(export.get(&key).unwrap(), Some(&value));
+            } else {
+                assert_eq!(export.get(&key).unwrap(), None);
+            }
+        }
+    }
+
+    #[test]
+    fn export_hash_error() {
+        use serde::{ser::Error, Serializer};
+
+        // A key that fails to serialize (hence, to hash) if equal to `u32::MAX`
+        #[derive(Clone)]
+        struct Faulty(u32);
+
+        impl Serialize for Faulty {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                if self.0 == u32::MAX {
+                    Err(S::Error::custom("faulty key"))
+                } else {
+                    self.0.serialize(serializer)
+                }
+            }
+        }
+
+        let mut map: Map<Faulty, u32> = Map::new();
+
+        for key in 0..64 {
+            map.insert(Faulty(key), key).unwrap();
+        }
+
+        let keys = (0..64).map(|i| Faulty(if i == 13 || i == 42 { u32::MAX } else { i }));
+        let error = map.export(keys).map(|_| ()).unwrap_err();
+
+        assert!(matches!(
+            error.top(),
+            MapError::KeyHashError { index: 13, .. }
+        ));
+
+        assert!(matches!(
+            map.get(&Faulty(u32::MAX)).unwrap_err().top(),
+            MapError::KeyHashError { index: 0, .. }
+        ));
+
+        assert!(map.export((0..64).map(Faulty)).is_ok());
+    }
+
+    #[test]
+    fn export_prefix() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..512).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let mut prefixes = vec![Prefix::root()];
+
+        for _ in 0..4 {
+            prefixes = prefixes
+                .into_iter()
+                .flat_map(|prefix| [prefix.left(), prefix.right()])
+                .collect();
+        }
+
+        for prefix in prefixes {
+            let export = map.export_prefix(prefix).unwrap();
+
+            assert_eq!(export.commit(), map.commit());
+            export.check_tree();
+
+            for (key, value) in (0..1024).map(|i| (i, i)) {
+                let path = Path::from(talk::crypto::primitives::hash::hash(&key).unwrap());
+
+                if prefix.contains(&path) {
+                    let expected = if key < 512 { Some(&value) } else { None };
+                    assert_eq!(export.get(&key).unwrap(), expected);
+                }
+            }
+
+            let mut keys = export.keys_under(prefix).unwrap();
+            let mut reference = map.keys_under(prefix).unwrap();
+
+            keys.sort();
+            reference.sort();
+
+            assert_eq!(keys, reference);
+        }
+
+        let export = map.export_prefix(Prefix::root()).unwrap();
+
+        assert_eq!(export.commit(), map.commit());
+        export.assert_records((0..512).map(|i| (i, i)));
+    }
+
+    #[test]
+    fn export_prefix_sparse() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        let export = map.export_prefix(Prefix::root().left()).unwrap();
+        assert_eq!(export.commit(), map.commit());
+
+        map.insert(0, 0).unwrap();
+
+        let path = Path::from(talk::crypto::primitives::hash::hash(&0u32).unwrap());
+
+        // Deeper than any divergence: the subtree of the leaf's prefix holds the
+        // leaf, and that of its sibling is empty (as proven by the leaf)
+        let deep = (0..32).fold(Prefix::root(), |prefix, depth| {
+            if path[depth] == Direction::Left {
+                prefix.left()
+            } else {
+                prefix.right()
+            }
+        });
+
+        let sibling = if path[32] == Direction::Left {
+            deep.right()
+        } else {
+            deep.left()
+        };
+
+        let export = map.export_prefix(deep).unwrap();
+
+        assert_eq!(export.commit(), map.commit());
+        assert_eq!(export.get(&0).unwrap(), Some(&0));
+
+        let export = map.export_prefix(sibling).unwrap();
+
+        assert_eq!(export.commit(), map.commit());
+        assert_eq!(export.keys_under(sibling).unwrap(), Vec::<u32>::new());
+
+        // A prefix reaching an `Empty` subtree
+        for key in 1..16 {
+            map.insert(key, key).unwrap();
+        }
+
+        let mut frontier = vec![map.root_ref()];
+
+        let empty = loop {
+            let node = frontier.pop().expect("no `Empty` subtree");
+
+            if node.is_empty() {
+                break node.location();
+            }
+
+            frontier.extend(
+                [Direction::Left, Direction::Right]
+                    .iter()
+                    .filter_map(|direction| node.child(*direction)),
+            );
+        };
+
+        let export = map.export_prefix(empty).unwrap();
+
+        assert_eq!(export.commit(), map.commit());
+        assert!(export.keys_under(empty).unwrap().is_empty());
     }
-}
 
-impl<Key, Value> Clone for Map<Key, Value>
-where
-    Key: Field + Clone,
-    Value: Field + Clone,
-{
-    fn clone(&self) -> Self {
-        let root: &Node<Key, Value> = self.root.borrow();
-        Map::raw(root.clone())
+    #[test]
+    fn export_prefix_stub() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..64).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let export = map.export_prefix(Prefix::root().left()).unwrap();
+
+        assert!(export.export_prefix(Prefix::root().left().left()).is_ok());
+
+        assert!(matches!(
+            export
+                .export_prefix(Prefix::root().right())
+                .map(|_| ())
+                .unwrap_err()
+                .top(),
+            MapError::BranchUnknown
+        ));
+
+        assert!(matches!(
+            export
+                .export_prefix(Prefix::root())
+                .map(|_| ())
+                .unwrap_err()
+                .top(),
+            MapError::BranchUnknown
+        ));
     }
-}
 
-impl<Key, Value> Serialize for Map<Key, Value>
-where
-    Key: Field,
-    Value: Field,
-{
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        self.root.serialize(serializer)
+    #[test]
+    fn filter_to() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let kept = (0..1024).step_by(3).take(300).collect::<Vec<u32>>();
+        let mut keys = Set::new();
+
+        for key in kept.iter().cloned() {
+            keys.insert(key).unwrap();
+        }
+
+        let filtered = map.filter_to(&keys).unwrap();
+        let export = map.export(&kept).unwrap();
+
+        assert_eq!(filtered.commit(), map.commit());
+        filtered.check_tree();
+        filtered.assert_records(kept.iter().map(|key| (*key, *key)));
+
+        assert_eq!(
+            bincode::serialize(&filtered).unwrap(),
+            bincode::serialize(&export).unwrap()
+        );
     }
-}
 
-impl<'de, Key, Value> Deserialize<'de> for Map<Key, Value>
-where
-    Key: Field + Deserialize<'de>,
-    Value: Field + Deserialize<'de>,
-{
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let root = Node::deserialize(deserializer)?; // Deserializes and computes node hashes
+    #[test]
+    fn filter_to_absent() {
+        let mut map: Map<u32, u32> = Map::new();
 
-        store::check(&root) // Checks correctness of tree topology
-            .map_err(|err| DeError::custom(err))?;
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
 
-        Ok(Map {
-            root: Lender::new(root),
-        }) // If a `Map` is `Deserialize`d, then it is correct
+        let mut keys = Set::new();
+
+        for key in 1000..1100 {
+            keys.insert(key).unwrap();
+        }
+
+        let filtered = map.filter_to(&keys).unwrap();
+        let export = map.export(1000..1100).unwrap();
+
+        assert_eq!(filtered.commit(), map.commit());
+        filtered.check_tree();
+
+        for key in 1000..1100 {
+            assert_eq!(filtered.get(&key).unwrap(), export.get(&key).unwrap());
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn filter_to_stub() {
+        let mut map: Map<u32, u32> = Map::new();
 
-    use crate::{
-        common::store::hash,
-        map::store::{self, Internal},
-    };
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
 
-    use std::{
-        collections::{HashMap, HashSet},
-        fmt::Debug,
-        hash::Hash,
-    };
+        let export = map.export(0..512).unwrap();
 
-    impl<Key, Value> Map<Key, Value>
-    where
-        Key: Field,
-        Value: Field,
-    {
-        pub(crate) fn check_tree(&self) {
-            store::check(self.root.borrow()).unwrap();
+        let mut keys = Set::new();
+        keys.insert(0).unwrap();
+        keys.insert(1000).unwrap();
+
+        assert!(export.filter_to(&keys).is_err());
+    }
+
+    #[test]
+    fn batch() {
+        let mut sequential: Map<u32, u32> = Map::new();
+        let mut batched: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..512).map(|i| (i, i)) {
+            sequential.insert(key, value).unwrap();
+            batched.insert(key, value).unwrap();
         }
 
-        pub(crate) fn collect_records(&self) -> HashMap<Key, Value>
-        where
-            Key: Field + Clone + Eq + Hash,
-            Value: Field + Clone,
-        {
-            fn recursion<Key, Value>(node: &Node<Key, Value>, collector: &mut HashMap<Key, Value>)
-            where
-                Key: Field + Clone + Eq + Hash,
-                Value: Field + Clone,
-            {
-                match node {
-                    Node::Internal(internal) => {
-                        recursion(internal.left(), collector);
-                        recursion(internal.right(), collector);
-                    }
-                    Node::Leaf(leaf) => {
-                        collector.insert(leaf.key().inner().clone(), leaf.value().inner().clone());
-                    }
-                    Node::Empty | Node::Stub(_) => {}
+        let operations = (0..1024u32).map(|i| match i % 4 {
+            0 => (i / 2, Some(i)),
+            1 => (i / 2, None),
+            2 => (i, Some(i + 1)),
+            _ => ((i * 7) % 768, None),
+        });
+
+        for (key, value) in operations.clone() {
+            match value {
+                Some(value) => {
+                    sequential.insert(key, value).unwrap();
+                }
+                None => {
+                    sequential.remove(&key).unwrap();
                 }
             }
+        }
 
-            let mut collector = HashMap::new();
-            recursion(self.root.borrow(), &mut collector);
-            collector
+        batched
+            .batch(|batch| {
+                for (key, value) in operations {
+                    match value {
+                        Some(value) => batch.insert(key, value)?,
+                        None => batch.remove(&key)?,
+                    }
+                }
+
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(batched.commit(), sequential.commit());
+        batched.check_tree();
+
+        let records = sequential.collect_records();
+        batched.assert_records(records);
+    }
+
+    #[test]
+    fn batch_stub() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..512).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
         }
 
-        pub fn assert_records<I>(&self, reference: I)
-        where
-            Key: Field + Debug + Clone + Eq + Hash,
-            Value: Field + Debug + Clone + Eq + Hash,
-            I: IntoIterator<Item = (Key, Value)>,
-        {
-            let actual: HashSet<(Key, Value)> = self.collect_records().into_iter().collect();
+        let mut export = map.export(0..256).unwrap();
 
-            let reference: HashSet<(Key, Value)> = reference.into_iter().collect();
+        assert!(export
+            .batch(|batch| {
+                batch.insert(0, 1)?;
+                batch.insert(511, 0)?;
+                Ok(())
+            })
+            .is_err());
+
+        // No operation is applied
+        assert_eq!(export.commit(), map.commit());
+        assert_eq!(export.get(&0).unwrap(), Some(&0));
+    }
+
+    #[test]
+    fn import_disjoint_singles() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let mut main = map.export([33]).unwrap();
+        let secondary = map.export([34]).unwrap();
+
+        main.import(secondary).unwrap();
+
+        assert_eq!(map.commit(), main.commit());
+        main.check_tree();
+        main.assert_records([(33, 33), (34, 34)]);
+    }
+
+    #[test]
+    fn import_same_singles() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let mut main = map.export([33]).unwrap();
+        let secondary = map.export([33]).unwrap();
+
+        main.import(secondary).unwrap();
+
+        assert_eq!(map.commit(), main.commit());
+        main.check_tree();
+        main.assert_records([(33, 33)]);
+    }
+
+    #[test]
+    fn import_disjoint_halves() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let mut main = map.export(0..512).unwrap();
+        let secondary = map.export(512..1024).unwrap();
+
+        main.import(secondary).unwrap();
+
+        assert_eq!(map.commit(), main.commit());
+        main.check_tree();
+        main.assert_records((0..1024).map(|i| (i, i)));
+    }
+
+    #[test]
+    fn import_overlapping_halves() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let mut main = map.export(0..512).unwrap();
+        let secondary = map.export(256..768).unwrap();
+
+        main.import(secondary).unwrap();
+
+        assert_eq!(map.commit(), main.commit());
+        main.check_tree();
+        main.assert_records((0..768).map(|i| (i, i)));
+    }
+
+    #[test]
+    fn import_same_halves() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let mut main = map.export(0..512).unwrap();
+        let secondary = map.export(0..512).unwrap();
+
+        main.import(secondary).unwrap();
+
+        assert_eq!(map.commit(), main.commit());
+        main.check_tree();
+        main.assert_records((0..512).map(|i| (i, i)));
+    }
+
+    #[test]
+    fn import_mismatched() {
+        let mut first: Map<u32, u32> = Map::new();
+        let mut second: Map<u32, u32> = Map::new();
 
-            let differences: HashSet<(Key, Value)> = reference
-                .symmetric_difference(&actual)
-                .map(|r| r.clone())
-                .collect();
+        for (key, value) in (0..128).map(|i| (i, i)) {
+            first.insert(key, value).unwrap();
+        }
 
-            assert_eq!(differences, HashSet::new());
+        for (key, value) in (64..192).map(|i| (i, i)) {
+            second.insert(key, value).unwrap();
         }
-    }
 
-    #[test]
-    fn empty() {
-        let map: Map<u32, u32> = Map::new();
+        let mut first_export = first.export([1]).unwrap();
+        let second_export = second.export([2]).unwrap();
 
-        map.check_tree();
-        map.assert_records([]);
+        assert!(first_export.import(second_export).is_err());
     }
 
     #[test]
-    fn insert() {
+    fn import_len() {
         let mut map: Map<u32, u32> = Map::new();
 
         for (key, value) in (0..1024).map(|i| (i, i)) {
-            assert_eq!(map.insert(key, value).unwrap(), None);
+            map.insert(key, value).unwrap();
+        }
 
-            map.check_tree();
-            map.assert_records((0..=key).map(|i| (i, i)));
+        let mut main = map.export(0..100).unwrap();
+        assert_eq!(main.len(), 100);
+
+        // `len` is recomputed from the merged tree, where imported
+        // branches replace stubs
+        for range in [50..300, 300..700, 700..1024] {
+            main.import(map.export(range).unwrap()).unwrap();
         }
+
+        assert!(main.is_complete());
+        assert_eq!(main.len(), map.len());
+        assert_eq!(main.commit(), map.commit());
     }
 
-    #[test]
-    fn insert_then_get() {
-        let mut map: Map<u32, u32> = Map::new();
+    fn internal_nodes(map: &Map<u32, u32>) -> usize {
+        let mut stack = vec![map.root_ref()];
+        let mut count = 0;
 
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            map.insert(key, value).unwrap();
-        }
+        while let Some(node) = stack.pop() {
+            if let Some(left) = node.child(Direction::Left) {
+                count += 1;
 
-        for key in 0..2048 {
-            if key < 1024 {
-                assert_eq!(map.get(&key).unwrap(), Some(&key))
-            } else {
-                assert_eq!(map.get(&key).unwrap(), None)
+                stack.push(left);
+                stack.push(node.child(Direction::Right).unwrap());
             }
         }
+
+        count
     }
 
     #[test]
-    fn insert_then_remove_half() {
+    fn shared_nodes_identical() {
         let mut map: Map<u32, u32> = Map::new();
 
+        assert_eq!(map.shared_nodes(&map.clone()), 0);
+
         for (key, value) in (0..1024).map(|i| (i, i)) {
             map.insert(key, value).unwrap();
         }
 
-        for key in 512..1024 {
-            assert_eq!(map.remove(&key).unwrap(), Some(key));
+        let all = internal_nodes(&map);
+        assert_eq!(map.shared_nodes(&map.clone()), all);
 
-            map.check_tree();
-            map.assert_records(
-                (0..512)
-                    .map(|i| (i, i))
-                    .chain(((key + 1)..1024).map(|i| (i, i))),
-            );
+        let mut other: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).rev().map(|i| (i, i)) {
+            other.insert(key, value).unwrap();
         }
+
+        assert_eq!(map.shared_nodes(&other), all);
+        assert_eq!(other.shared_nodes(&map), all);
+
+        // Internal nodes are counted through the `Stub`s of either map
+        let export = map.export(0..16).unwrap();
+
+        assert_eq!(map.shared_nodes(&export), all);
+        assert_eq!(export.shared_nodes(&map), all);
+        assert!(export.shared_nodes(&export.clone()) < all);
     }
 
     #[test]
-    fn insert_then_remove_half_then_get() {
-        let mut map: Map<u32, u32> = Map::new();
+    fn shared_nodes_disjoint() {
+        let mut lho: Map<u32, u32> = Map::new();
+        let mut rho: Map<u32, u32> = Map::new();
 
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            map.insert(key, value).unwrap();
+        for key in 0..1024 {
+            lho.insert(key, key).unwrap();
+            rho.insert(key + 1024, key).unwrap();
         }
 
-        for key in 512..1024 {
-            map.remove(&key).unwrap();
-        }
+        assert_eq!(lho.shared_nodes(&rho), 0);
 
-        for key in 0..2048 {
-            if key < 512 {
-                assert_eq!(map.get(&key).unwrap(), Some(&key))
-            } else {
-                assert_eq!(map.get(&key).unwrap(), None)
-            }
+        // Same keys, different values
+        let mut rho: Map<u32, u32> = Map::new();
+
+        for key in 0..1024 {
+            rho.insert(key, key + 1).unwrap();
         }
+
+        assert_eq!(lho.shared_nodes(&rho), 0);
     }
 
     #[test]
-    fn insert_then_remove_half_then_increment() {
-        let mut map: Map<u32, u32> = Map::new();
+    fn shared_nodes_partial() {
+        let mut lho: Map<u32, u32> = Map::new();
 
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            map.insert(key, value).unwrap();
+        for key in 0..1024 {
+            lho.insert(key, key).unwrap();
         }
 
-        for key in 512..1024 {
-            map.remove(&key).unwrap();
-        }
+        let all = internal_nodes(&lho);
 
-        for (key, value) in (0..512).map(|i| (i, i + 1)) {
-            assert_eq!(map.insert(key, value).unwrap(), Some(key));
+        let mut rho = lho.clone();
+        rho.insert(0, 1).unwrap();
 
-            map.check_tree();
-            map.assert_records((0..512).map(|i| if i <= key { (i, i + 1) } else { (i, i) }));
+        // Only the internal nodes on the path to `0` differ
+        let path = Path::from(talk::crypto::primitives::hash::hash(&0u32).unwrap());
+        let mut node = lho.root_ref();
+        let mut unshared = 0;
+
+        while node.leaf().is_none() {
+            unshared += 1;
+            node = node.child(path[node.location().depth()]).unwrap();
         }
+
+        assert_eq!(lho.shared_nodes(&rho), all - unshared);
+        assert_eq!(rho.shared_nodes(&lho), all - unshared);
     }
 
     #[test]
-    fn insert_then_remove_half_then_increment_then_get() {
+    fn from_proofs() {
         let mut map: Map<u32, u32> = Map::new();
 
         for (key, value) in (0..1024).map(|i| (i, i)) {
             map.insert(key, value).unwrap();
         }
 
-        for key in 512..1024 {
-            map.remove(&key).unwrap();
-        }
+        let proofs = [33, 512, 1000]
+            .iter()
+            .map(|&key| (key, map.prove(&key).unwrap()))
+            .collect::<Vec<_>>();
 
-        for (key, value) in (0..512).map(|i| (i, i + 1)) {
-            map.insert(key, value).unwrap();
+        let assembled = Map::from_proofs(map.commit(), proofs).unwrap();
+
+        assert_eq!(assembled.commit(), map.commit());
+        assembled.check_tree();
+        assembled.assert_records([(33, 33), (512, 512), (1000, 1000)]);
+
+        assert_eq!(assembled.get(&33).unwrap(), Some(&33));
+        assert_eq!(assembled.get(&512).unwrap(), Some(&512));
+        assert_eq!(assembled.get(&1000).unwrap(), Some(&1000));
+
+        // Deniability proofs are assembled as well
+        let proofs = (1024..1040)
+            .map(|key| (key, map.prove(&key).unwrap()))
+            .collect::<Vec<_>>();
+
+        let assembled = Map::from_proofs(map.commit(), proofs).unwrap();
+
+        assert_eq!(assembled.commit(), map.commit());
+        assembled.check_tree();
+
+        // Keys whose proof ends at another leaf cannot be answered
+        for key in 1024..1040 {
+            assert!(!matches!(assembled.get(&key), Ok(Some(..))));
         }
+    }
 
-        for key in 0..2048 {
-            if key < 512 {
-                assert_eq!(map.get(&key).unwrap(), Some(&(key + 1)))
-            } else {
-                assert_eq!(map.get(&key).unwrap(), None)
-            }
+    #[test]
+    fn from_proofs_invalid() {
+        let mut first: Map<u32, u32> = Map::new();
+        let mut second: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..128).map(|i| (i, i)) {
+            first.insert(key, value).unwrap();
+            second.insert(key, value + 1).unwrap();
         }
+
+        // Proof against another commitment
+        let proofs = vec![
+            (1, first.prove(&1).unwrap()),
+            (2, second.prove(&2).unwrap()),
+        ];
+
+        assert!(matches!(
+            Map::from_proofs(first.commit(), proofs)
+                .map(|_| ())
+                .unwrap_err()
+                .top(),
+            MapError::ProofInvalid
+        ));
+
+        // Proof for another key
+        let proofs = vec![(1, first.prove(&2).unwrap())];
+
+        assert!(matches!(
+            Map::from_proofs(first.commit(), proofs)
+                .map(|_| ())
+                .unwrap_err()
+                .top(),
+            MapError::ProofInvalid
+        ));
     }
 
     #[test]
-    fn insert_then_remove_half_then_remove_other_half() {
+    fn verify_membership_batch() {
         let mut map: Map<u32, u32> = Map::new();
 
-        for (key, value) in (0..1024).map(|i| (i, i)) {
+        for (key, value) in (0..128).map(|i| (i, i)) {
             map.insert(key, value).unwrap();
         }
 
-        for key in 512..1024 {
-            map.remove(&key).unwrap();
-        }
+        let proof = map.export((0..16).chain(128..144)).unwrap();
 
-        for key in 0..512 {
-            assert_eq!(map.remove(&key).unwrap(), Some(key));
+        let claims: Vec<(u32, Option<u32>)> = (0..8)
+            .map(|key| (key, Some(key))) // Correct
+            .chain((8..16).map(|key| (key, Some(key + 1)))) // Wrong value
+            .chain((8..12).map(|key| (key, None))) // Present key claimed absent
+            .chain((128..136).map(|key| (key, None))) // Correctly absent
+            .chain((136..144).map(|key| (key, Some(key)))) // Absent key claimed present
+            .collect();
 
-            map.check_tree();
-            map.assert_records(((key + 1)..512).map(|i| (i, i)));
-        }
+        let expected: Vec<bool> = (0..8)
+            .map(|_| true)
+            .chain((8..16).map(|_| false))
+            .chain((8..12).map(|_| false))
+            .chain((128..136).map(|_| true))
+            .chain((136..144).map(|_| false))
+            .collect();
+
+        assert_eq!(
+            proof.verify_membership_batch(map.commit(), &claims),
+            expected
+        );
+
+        let mut other = map.clone();
+        other.insert(128, 128).unwrap();
+
+        assert_eq!(
+            proof.verify_membership_batch(other.commit(), &claims),
+            vec![false; claims.len()]
+        );
+
+        // Keys outside of the proof cannot be verified
+        let outside = (16..128).map(|key| (key, Some(key))).collect::<Vec<_>>();
+
+        assert!(proof
+            .verify_membership_batch(map.commit(), &outside)
+            .into_iter()
+            .any(|valid| !valid));
     }
 
     #[test]
-    fn export_none() {
+    fn iter_stubs() {
         let mut map: Map<u32, u32> = Map::new();
 
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            map.insert(key, value).unwrap();
+        for key in 0..256 {
+            map.insert(key, key).unwrap();
         }
 
-        let export = map.export::<[u32; 0], u32>([]).unwrap(); // Explicit type arguments are to aid type inference on an empty array
+        assert_eq!(map.iter_stubs().count(), 0);
 
-        assert_eq!(map.commit(), export.commit());
-        export.check_tree();
-        export.assert_records([]);
-    }
+        let mut export = map.export([0, 1]).unwrap();
+        let stubs = export.iter_stubs().collect::<Vec<_>>();
 
-    #[test]
-    fn export_single() {
-        let mut map: Map<u32, u32> = Map::new();
+        assert!(!stubs.is_empty());
 
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            map.insert(key, value).unwrap();
+        for (location, commitment) in stubs.iter() {
+            assert!(export.node_at(*location).unwrap().is_stub());
+            assert_eq!(map.node_at(*location).unwrap().commitment(), *commitment);
         }
 
-        let export = map.export([33]).unwrap();
+        // Fetching exactly the stubbed branches completes the export
+        for (location, _) in stubs {
+            let keys = map.keys_under(location).unwrap();
+            export.import(map.export(keys).unwrap()).unwrap();
+        }
 
-        assert_eq!(map.commit(), export.commit());
-        export.check_tree();
-        export.assert_records([(33, 33)]);
+        assert!(export.is_complete());
+        assert_eq!(export.iter_stubs().count(), 0);
+        assert_eq!(export.commit(), map.commit());
     }
 
     #[test]
-    fn export_single_then_get() {
+    fn keys_under() {
         let mut map: Map<u32, u32> = Map::new();
 
         for (key, value) in (0..1024).map(|i| (i, i)) {
             map.insert(key, value).unwrap();
         }
 
-        let export = map.export([33]).unwrap();
+        // Group keys by the top three bits of their path
+        let mut groups: HashMap<usize, Vec<u32>> = HashMap::new();
 
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            if key == 33 {
-                assert_eq!(export.get(&key).unwrap(), Some(&value));
-            } else {
-                export.get(&key).unwrap_err();
-            }
+        for key in 0..1024u32 {
+            let path = Path::from(talk::crypto::primitives::hash::hash(&key).unwrap());
+            groups.entry(path.digit(0, 3)).or_default().push(key);
+        }
+
+        assert_eq!(groups.len(), 8);
+
+        for (group, mut keys) in groups {
+            let prefix = (0..3).fold(Prefix::root(), |prefix, bit| {
+                if (group >> (2 - bit)) & 1 == 1 {
+                    prefix.left()
+                } else {
+                    prefix.right()
+                }
+            });
+
+            let mut under = map.keys_under(prefix).unwrap();
+
+            under.sort_unstable();
+            keys.sort_unstable();
+
+            assert_eq!(under, keys);
         }
+
+        let mut all = map.keys_under(Prefix::root()).unwrap();
+        all.sort_unstable();
+        assert_eq!(all, (0..1024).collect::<Vec<_>>());
+
+        // Prefixes below a leaf
+        let mut single: Map<u32, u32> = Map::new();
+        single.insert(0, 0).unwrap();
+
+        let path = Path::from(talk::crypto::primitives::hash::hash(&0u32).unwrap());
+
+        let (on_path, off_path) = match path[0] {
+            Direction::Left => (Prefix::root().left(), Prefix::root().right()),
+            Direction::Right => (Prefix::root().right(), Prefix::root().left()),
+        };
+
+        assert_eq!(single.keys_under(on_path).unwrap(), vec![0]);
+        assert!(single.keys_under(off_path).unwrap().is_empty());
+
+        // Stubs
+        let export = map.export([0]).unwrap();
+
+        assert!(matches!(
+            export.keys_under(Prefix::root()).unwrap_err().top(),
+            MapError::BranchUnknown
+        ));
     }
 
     #[test]
-    fn export_outsider_then_get() {
+    fn len() {
         let mut map: Map<u32, u32> = Map::new();
 
-        for (key, value) in (0..1024).map(|i| (i, i)) {
+        assert_eq!(map.len(), 0);
+        assert!(map.is_empty());
+        assert!(map.is_complete());
+
+        for (key, value) in (0..256).map(|i| (i, i)) {
             map.insert(key, value).unwrap();
         }
 
-        let export = map.export([1025]).unwrap();
+        assert_eq!(map.len(), 256);
+        assert!(!map.is_empty());
+        assert!(map.is_complete());
 
-        assert_eq!(export.get(&1025).unwrap(), None);
+        // Exported maps only count the records they hold
+        let export = map.export(0..64).unwrap();
+
+        assert_eq!(export.len(), export.collect_records().len());
+        assert!(export.len() >= 64 && export.len() < 256);
+        assert!(!export.is_complete());
+
+        let export = map.export::<_, u32>([]).unwrap();
+
+        assert_eq!(export.len(), 0);
+        assert!(export.is_empty());
+        assert!(!export.is_complete());
+
+        let export = map.export(0..256).unwrap();
+
+        assert_eq!(export.len(), 256);
+        assert!(export.is_complete());
+
+        // Removals are reflected
+        for key in 0..128 {
+            map.remove(&key).unwrap();
+        }
+
+        assert_eq!(map.len(), 128);
     }
 
     #[test]
-    fn export_half() {
+    fn get_by_path() {
         let mut map: Map<u32, u32> = Map::new();
 
-        for (key, value) in (0..1024).map(|i| (i, i)) {
+        for (key, value) in (0..256).map(|i| (i, i + 1)) {
             map.insert(key, value).unwrap();
         }
 
-        let export = map.export(0..512).unwrap();
+        let path = |key: u32| Path::from(talk::crypto::primitives::hash::hash(&key).unwrap());
 
-        assert_eq!(map.commit(), export.commit());
-        export.check_tree();
-        export.assert_records((0..512).map(|i| (i, i)));
+        for key in 0..512 {
+            assert_eq!(map.get_by_path(path(key)).unwrap(), map.get(&key).unwrap());
+        }
+
+        let export = map.export([0, 256]).unwrap();
+
+        for key in 0..512 {
+            match (export.get_by_path(path(key)), export.get(&key)) {
+                (Ok(by_path), Ok(by_key)) => assert_eq!(by_path, by_key),
+                (Err(path_error), Err(key_error)) => {
+                    assert!(matches!(path_error.top(), MapError::BranchUnknown));
+                    assert!(matches!(key_error.top(), MapError::BranchUnknown));
+                }
+                _ => panic!("`get_by_path` and `get` disagree on key {}", key),
+            }
+        }
     }
 
     #[test]
-    fn export_half_then_get() {
+    fn contains_key() {
         let mut map: Map<u32, u32> = Map::new();
 
-        for (key, value) in (0..1024).map(|i| (i, i)) {
+        for (key, value) in (0..256).map(|i| (i, i)) {
             map.insert(key, value).unwrap();
         }
 
-        let export = map.export(0..512).unwrap();
+        for key in 0..512 {
+            assert_eq!(map.contains_key(&key).unwrap(), key < 256);
+        }
 
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            if key < 512 {
-                assert_eq!(export.get(&key).unwrap(), Some(&value));
-            } else {
-                export.get(&key).unwrap_err();
+        let export = map.export([0, 256]).unwrap();
+
+        assert!(export.contains_key(&0).unwrap());
+        assert!(!export.contains_key(&256).unwrap());
+
+        for key in 1..256 {
+            match (export.contains_key(&key), export.get(&key)) {
+                (Ok(contained), Ok(value)) => assert_eq!(contained, value.is_some()),
+                (Err(contains_error), Err(get_error)) => {
+                    assert!(matches!(contains_error.top(), MapError::BranchUnknown));
+                    assert!(matches!(get_error.top(), MapError::BranchUnknown));
+                }
+                _ => panic!("`contains_key` and `get` disagree on key {}", key),
             }
         }
     }
 
     #[test]
-    fn export_all() {
+    fn iter() {
         let mut map: Map<u32, u32> = Map::new();
+        assert_eq!(map.iter().count(), 0);
 
-        for (key, value) in (0..1024).map(|i| (i, i)) {
+        for (key, value) in (0..256).map(|i| (i, i + 1)) {
             map.insert(key, value).unwrap();
         }
 
-        let export = map.export(0..1024).unwrap();
+        let records = map
+            .iter()
+            .map(|(key, value)| (*key, *value))
+            .collect::<HashMap<_, _>>();
 
-        assert_eq!(map.commit(), export.commit());
-        export.check_tree();
-        export.assert_records((0..1024).map(|i| (i, i)));
+        assert_eq!(records, map.collect_records());
+
+        // Records are visited left to right
+        let paths = map
+            .iter()
+            .map(|(key, _)| Path::from(talk::crypto::primitives::hash::hash(key).unwrap()))
+            .collect::<Vec<_>>();
+
+        for pair in paths.windows(2) {
+            let divergence = pair[0]
+                .into_iter()
+                .zip(pair[1])
+                .find(|(first, second)| first != second);
+
+            assert_eq!(divergence, Some((Direction::Left, Direction::Right)));
+        }
+
+        // Insertion order does not affect iteration order
+        let mut other: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..256).rev().map(|i| (i, i + 1)) {
+            other.insert(key, value).unwrap();
+        }
+
+        assert!(map.iter().eq(other.iter()));
+
+        // Stubs are skipped
+        let export = map.export(0..64).unwrap();
+
+        assert_eq!(export.iter().count(), export.len());
+        assert!(export
+            .iter()
+            .all(|(key, value)| map.get(key).unwrap() == Some(value)));
     }
 
     #[test]
-    fn export_all_then_get() {
-        let mut map: Map<u32, u32> = Map::new();
+    fn equality() {
+        let first: Map<u32, u32> = (0..256).map(|i| (i, i)).collect();
+        let second: Map<u32, u32> = (0..256).rev().map(|i| (i, i)).collect();
 
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            map.insert(key, value).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(Map::<u32, u32>::new(), Map::new());
+
+        let mut third = second.clone();
+        third.insert(0, 1).unwrap();
+
+        assert_ne!(first, third);
+        assert_ne!(first, Map::new());
+
+        // Stubs commit to the branches they replace
+        let export = first.export(0..16).unwrap();
+
+        assert!(!export.is_complete());
+        assert_eq!(first, export);
+        assert_eq!(Map::<u32, u32>::root_stub(first.commit()), first);
+
+        let mut maps = HashMap::new();
+        maps.insert(first.clone(), "first");
+        maps.insert(third.clone(), "third");
+
+        assert_eq!(maps.get(&second), Some(&"first"));
+        assert_eq!(maps.get(&export), Some(&"first"));
+        assert_eq!(maps.get(&third), Some(&"third"));
+        assert_eq!(maps.get(&Map::new()), None);
+
+        // Inserting an equal `Map` replaces the existing entry
+        maps.insert(export, "export");
+
+        assert_eq!(maps.len(), 2);
+        assert_eq!(maps.get(&first), Some(&"export"));
+    }
+
+    #[test]
+    fn from_iter_into_iter() {
+        let mut reference: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i + 1)) {
+            reference.insert(key, value).unwrap();
         }
 
-        let export = map.export(0..1024).unwrap();
+        let map: Map<u32, u32> = (0..1024).map(|i| (i, i + 1)).collect();
 
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            assert_eq!(export.get(&key).unwrap(), Some(&value));
+        map.check_tree();
+        assert_eq!(map.commit(), reference.commit());
+
+        let mut borrowed = HashMap::new();
+
+        for (key, value) in &map {
+            borrowed.insert(*key, *value);
         }
+
+        assert_eq!(borrowed, reference.collect_records());
+
+        let owned = map.clone().into_iter().collect::<Vec<_>>();
+
+        assert!(owned.iter().map(|(key, value)| (key, value)).eq(map.iter()));
+
+        let rebuilt: Map<u32, u32> = owned.into_iter().collect();
+        assert_eq!(rebuilt.commit(), reference.commit());
     }
 
     #[test]
-    fn export_all_then_get_outsider() {
-        let mut map: Map<u32, u32> = Map::new();
+    fn from_iter_duplicates() {
+        let map: Map<u32, u32> = [(1, 1), (2, 2), (1, 3)].iter().cloned().collect();
+
+        map.check_tree();
+        map.assert_records([(1, 3), (2, 2)]);
+    }
 
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            map.insert(key, value).unwrap();
-        }
+    #[test]
+    fn into_iter_stub() {
+        let map: Map<u32, u32> = (0..256).map(|i| (i, i + 1)).collect();
+        let export = map.export(0..64).unwrap();
 
-        let export = map.export(0..1024).unwrap();
+        let records = export.into_iter().collect::<HashMap<_, _>>();
 
-        assert_eq!(export.get(&1025).unwrap(), None);
+        assert_eq!(
+            records,
+            (0..64).map(|i| (i, i + 1)).collect::<HashMap<_, _>>()
+        );
     }
 
     #[test]
-    fn export_overlap_then_get() {
+    fn from_sorted_unchecked() {
         let mut map: Map<u32, u32> = Map::new();
 
-        for (key, value) in (0..512).map(|i| (i, i)) {
+        for (key, value) in (0..1024).map(|i| (i, i + 1)) {
             map.insert(key, value).unwrap();
         }
 
-        let export = map.export(256..1024).unwrap();
+        let records = map
+            .iter()
+            .map(|(key, value)| {
+                let path = Path::from(talk::crypto::primitives::hash::hash(key).unwrap());
+                (*key, *value, path)
+            })
+            .collect::<Vec<_>>();
 
-        assert_eq!(map.commit(), export.commit());
-        export.check_tree();
+        let rebuilt = Map::from_sorted_unchecked(records).unwrap();
 
-        for (key, value) in (256..1024).map(|i| (i, i)) {
-            if key < 512 {
-                assert_eq!(export.get(&key).unwrap(), Some(&value));
-            } else {
-                assert_eq!(export.get(&key).unwrap(), None);
-            }
-        }
+        rebuilt.check_tree();
+        assert_eq!(rebuilt.commit(), map.commit());
+        assert!(rebuilt.iter().eq(map.iter()));
+
+        let empty: Map<u32, u32> = Map::from_sorted_unchecked(vec![]).unwrap();
+        assert_eq!(empty.commit(), Map::<u32, u32>::new().commit());
     }
 
     #[test]
-    fn import_disjoint_singles() {
-        let mut map: Map<u32, u32> = Map::new();
-
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            map.insert(key, value).unwrap();
-        }
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "records are not sorted")]
+    fn from_sorted_unchecked_unsorted() {
+        let mut records = (0..16u32)
+            .map(|key| {
+                let path = Path::from(talk::crypto::primitives::hash::hash(&key).unwrap());
+                (key, key, path)
+            })
+            .collect::<Vec<_>>();
 
-        let mut main = map.export([33]).unwrap();
-        let secondary = map.export([34]).unwrap();
+        // Sort from right to left
+        records.sort_by_key(|(_, _, path)| *path);
 
-        main.import(secondary).unwrap();
+        let _ = Map::from_sorted_unchecked(records);
+    }
 
-        assert_eq!(map.commit(), main.commit());
-        main.check_tree();
-        main.assert_records([(33, 33), (34, 34)]);
+    #[test]
+    #[should_panic(expected = "records are not sorted, or not unique")]
+    fn from_sorted_unchecked_duplicate() {
+        let path = Path::from(talk::crypto::primitives::hash::hash(&0u32).unwrap());
+        let _ = Map::from_sorted_unchecked(vec![(0u32, 0u32, path), (0, 1, path)]);
     }
 
     #[test]
-    fn import_same_singles() {
+    fn insert_many() {
         let mut map: Map<u32, u32> = Map::new();
+        let mut reference: Map<u32, u32> = Map::new();
 
-        for (key, value) in (0..1024).map(|i| (i, i)) {
+        for (key, value) in (0..256).map(|i| (i, i)) {
             map.insert(key, value).unwrap();
+            reference.insert(key, value).unwrap();
         }
 
-        let mut main = map.export([33]).unwrap();
-        let secondary = map.export([33]).unwrap();
+        map.insert_many((128..1024).map(|i| (i, i + 1))).unwrap();
 
-        main.import(secondary).unwrap();
+        for (key, value) in (128..1024).map(|i| (i, i + 1)) {
+            reference.insert(key, value).unwrap();
+        }
 
-        assert_eq!(map.commit(), main.commit());
-        main.check_tree();
-        main.assert_records([(33, 33)]);
+        map.check_tree();
+        assert_eq!(map.commit(), reference.commit());
+
+        map.assert_records((0..1024).map(|i| (i, if i < 128 { i } else { i + 1 })));
+
+        // Stubbed branches are left untouched
+        let mut export = map.export(0..64).unwrap();
+
+        assert!(matches!(
+            export
+                .insert_many((0..1024).map(|i| (i, i)))
+                .unwrap_err()
+                .top(),
+            MapError::BranchUnknown
+        ));
+
+        assert_eq!(export.commit(), map.commit());
     }
 
     #[test]
-    fn import_disjoint_halves() {
+    fn drain_filter() {
         let mut map: Map<u32, u32> = Map::new();
 
-        for (key, value) in (0..1024).map(|i| (i, i)) {
+        for (key, value) in (0..1024).map(|i| (i, i + 1)) {
             map.insert(key, value).unwrap();
         }
 
-        let mut main = map.export(0..512).unwrap();
-        let secondary = map.export(512..1024).unwrap();
+        let mut drained = map.drain_filter(|key, _| key % 2 == 0).unwrap();
+        drained.sort_unstable();
 
-        main.import(secondary).unwrap();
+        assert_eq!(
+            drained,
+            (0..1024)
+                .filter(|key| key % 2 == 0)
+                .map(|key| (key, key + 1))
+                .collect::<Vec<_>>()
+        );
 
-        assert_eq!(map.commit(), main.commit());
-        main.check_tree();
-        main.assert_records((0..1024).map(|i| (i, i)));
-    }
+        map.check_tree();
+        map.assert_records(
+            (0..1024)
+                .filter(|key| key % 2 == 1)
+                .map(|key| (key, key + 1)),
+        );
 
-    #[test]
-    fn import_overlapping_halves() {
-        let mut map: Map<u32, u32> = Map::new();
+        // The result is the same as that of removing every drained key
+        let mut reference: Map<u32, u32> = Map::new();
 
-        for (key, value) in (0..1024).map(|i| (i, i)) {
-            map.insert(key, value).unwrap();
+        for (key, value) in (0..1024).filter(|key| key % 2 == 1).map(|i| (i, i + 1)) {
+            reference.insert(key, value).unwrap();
         }
 
-        let mut main = map.export(0..512).unwrap();
-        let secondary = map.export(256..768).unwrap();
+        assert_eq!(map.commit(), reference.commit());
 
-        main.import(secondary).unwrap();
+        // Draining everything collapses the map
+        assert_eq!(map.drain_filter(|_, _| true).unwrap().len(), 512);
+        assert_eq!(map.commit(), Map::<u32, u32>::new().commit());
 
-        assert_eq!(map.commit(), main.commit());
-        main.check_tree();
-        main.assert_records((0..768).map(|i| (i, i)));
+        // Stubs
+        let mut export = reference.export([1]).unwrap();
+
+        assert!(matches!(
+            export.drain_filter(|_, _| true).unwrap_err().top(),
+            MapError::BranchUnknown
+        ));
+
+        assert_eq!(export.commit(), reference.commit());
+        assert_eq!(export.get(&1).unwrap(), Some(&2));
     }
 
     #[test]
-    fn import_same_halves() {
+    fn retain() {
         let mut map: Map<u32, u32> = Map::new();
 
-        for (key, value) in (0..1024).map(|i| (i, i)) {
+        for (key, value) in (0..1024).map(|i| (i, i + 1)) {
             map.insert(key, value).unwrap();
         }
 
-        let mut main = map.export(0..512).unwrap();
-        let secondary = map.export(0..512).unwrap();
+        map.retain(|key, value| key % 3 == 0 && *value < 512)
+            .unwrap();
 
-        main.import(secondary).unwrap();
+        let retained = (0..1024).filter(|key| key % 3 == 0 && key + 1 < 512);
 
-        assert_eq!(map.commit(), main.commit());
-        main.check_tree();
-        main.assert_records((0..512).map(|i| (i, i)));
-    }
+        map.check_tree();
+        map.assert_records(retained.clone().map(|key| (key, key + 1)));
 
-    #[test]
-    fn import_mismatched() {
-        let mut first: Map<u32, u32> = Map::new();
-        let mut second: Map<u32, u32> = Map::new();
+        // Removed leaves are compacted as by `remove`
+        let mut reference: Map<u32, u32> = Map::new();
 
-        for (key, value) in (0..128).map(|i| (i, i)) {
-            first.insert(key, value).unwrap();
+        for key in retained {
+            reference.insert(key, key + 1).unwrap();
         }
 
-        for (key, value) in (64..192).map(|i| (i, i)) {
-            second.insert(key, value).unwrap();
-        }
+        assert_eq!(map.commit(), reference.commit());
 
-        let mut first_export = first.export([1]).unwrap();
-        let second_export = second.export([2]).unwrap();
+        // Retaining a single record collapses the map to a leaf
+        map.retain(|key, _| *key == 3).unwrap();
+        map.check_tree();
+        map.assert_records([(3, 4)]);
 
-        assert!(first_export.import(second_export).is_err());
+        // Retaining nothing empties the map
+        map.retain(|_, _| false).unwrap();
+        assert_eq!(map.commit(), Map::<u32, u32>::new().commit());
+
+        // Stubs
+        let mut export = reference.export([0]).unwrap();
+
+        assert!(matches!(
+            export.retain(|_, _| false).unwrap_err().top(),
+            MapError::BranchUnknown
+        ));
+
+        assert_eq!(export.commit(), reference.commit());
+        assert_eq!(export.get(&0).unwrap(), Some(&1));
     }
 
     #[test]
@@ -1219,4 +3723,255 @@ mod tests {
         let serialized = bincode::serialize(&original).unwrap();
         assert!(bincode::deserialize::<Map<u32, u32>>(&serialized).is_err());
     }
+
+    #[test]
+    fn map_values_empty() {
+        let map: Map<u32, u32> = Map::new();
+        let mapped = map.map_values(|value| value.to_string()).unwrap();
+
+        mapped.check_tree();
+        mapped.assert_records([]);
+
+        assert_eq!(mapped.commit(), map.commit());
+    }
+
+    #[test]
+    fn map_values_to_string() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let mapped = map.map_values(|value| value.to_string()).unwrap();
+
+        mapped.check_tree();
+        mapped.assert_records((0..1024).map(|i| (i, i.to_string())));
+
+        assert_ne!(mapped.commit(), map.commit());
+
+        let mut reference: Map<u32, String> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i.to_string())) {
+            reference.insert(key, value).unwrap();
+        }
+
+        assert_eq!(mapped.commit(), reference.commit());
+    }
+
+    #[test]
+    fn map_values_stub() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let export = map.export([0]).unwrap();
+
+        match export.map_values(|value| value.to_string()) {
+            Err(e) if matches!(e.top(), MapError::BranchUnknown) => (),
+            _ => panic!("`map_values` does not fail on incomplete map"),
+        }
+    }
+
+    #[test]
+    fn common_partial() {
+        let mut first: Map<u32, u32> = Map::new();
+        let mut second: Map<u32, u32> = Map::new();
+
+        for key in 0..1024 {
+            first.insert(key, key).unwrap();
+        }
+
+        // Keys in 512..1024 overlap, half of them with a different value
+        for key in 512..1536 {
+            second
+                .insert(key, if key % 2 == 0 { key } else { key + 1 })
+                .unwrap();
+        }
+
+        let common = first.common(&second).unwrap();
+        let reference = (512..1024).filter(|key| key % 2 == 0).map(|key| (key, key));
+
+        common.check_tree();
+        common.assert_records(reference.clone());
+
+        let mut expected: Map<u32, u32> = Map::new();
+
+        for (key, value) in reference {
+            expected.insert(key, value).unwrap();
+        }
+
+        assert_eq!(common.commit(), expected.commit());
+        assert_eq!(second.common(&first).unwrap().commit(), expected.commit());
+    }
+
+    #[test]
+    fn common_full() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for key in 0..1024 {
+            map.insert(key, key).unwrap();
+        }
+
+        let common = map.common(&map.clone()).unwrap();
+
+        assert_eq!(common.commit(), map.commit());
+        common.assert_records((0..1024).map(|i| (i, i)));
+    }
+
+    #[test]
+    fn common_disjoint() {
+        let mut first: Map<u32, u32> = Map::new();
+        let mut second: Map<u32, u32> = Map::new();
+
+        for key in 0..1024 {
+            first.insert(key, key).unwrap();
+            second.insert(key + 1024, key).unwrap();
+        }
+
+        let common = first.common(&second).unwrap();
+
+        assert!(common.is_empty());
+        assert_eq!(common.commit(), Map::<u32, u32>::new().commit());
+    }
+
+    #[test]
+    fn common_stub() {
+        let mut first: Map<u32, u32> = Map::new();
+        let mut second: Map<u32, u32> = Map::new();
+
+        for key in 0..1024 {
+            first.insert(key, key).unwrap();
+            second.insert(key, key + 1).unwrap();
+        }
+
+        let export = first.export([0]).unwrap();
+
+        match export.common(&second) {
+            Err(e) if matches!(e.top(), MapError::BranchUnknown) => (),
+            _ => panic!("`common` does not fail on incomplete map"),
+        }
+    }
+
+    #[test]
+    fn deserialize_bounded_within() {
+        let mut original: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            original.insert(key, value).unwrap();
+        }
+
+        let serialized = bincode::serialize(&original).unwrap();
+        let deserialized = Map::<u32, u32>::deserialize_bounded(&serialized, 1024, 256).unwrap();
+
+        assert_eq!(original.commit(), deserialized.commit());
+        deserialized.check_tree();
+        deserialized.assert_records((0..1024).map(|i| (i, i)));
+    }
+
+    #[test]
+    fn deserialize_bounded_too_many_records() {
+        let mut original: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            original.insert(key, value).unwrap();
+        }
+
+        let serialized = bincode::serialize(&original).unwrap();
+
+        match Map::<u32, u32>::deserialize_bounded(&serialized, 1023, 256) {
+            Err(e) if matches!(e.top(), MapError::TooManyRecords) => (),
+            _ => panic!("`deserialize_bounded` accepts too many records"),
+        }
+    }
+
+    #[test]
+    fn deserialize_bounded_too_deep() {
+        let mut original: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            original.insert(key, value).unwrap();
+        }
+
+        let serialized = bincode::serialize(&original).unwrap();
+
+        match Map::<u32, u32>::deserialize_bounded(&serialized, 1024, 4) {
+            Err(e) if matches!(e.top(), MapError::TooDeep) => (),
+            _ => panic!("`deserialize_bounded` accepts too deep a map"),
+        }
+    }
+
+    #[test]
+    fn deserialize_deeply_nested() {
+        // A chain of `Internal` tags (`bincode` encodes variant indices as
+        // little-endian `u32`s) that never reaches a leaf
+        let bytes = [1u8, 0, 0, 0].repeat(1 << 20);
+
+        assert!(bincode::deserialize::<Map<u32, u32>>(&bytes).is_err());
+
+        match Map::<u32, u32>::deserialize_bounded(&bytes, usize::MAX, usize::MAX) {
+            Err(e) if matches!(e.top(), MapError::TooDeep) => (),
+            _ => panic!("`deserialize_bounded` accepts too deep a map"),
+        }
+
+        // Depth 256 is reachable, depth 257 is not
+        let mut bytes = [1u8, 0, 0, 0].repeat(256);
+        bytes.extend([0u8; 4].repeat(257));
+
+        match Map::<u32, u32>::deserialize_bounded(&bytes, usize::MAX, usize::MAX) {
+            Err(e) if matches!(e.top(), MapError::DeserializeFailed) => (), // Flawed topology
+            _ => panic!("`deserialize_bounded` rejects a map of depth 256"),
+        }
+
+        let mut bytes = [1u8, 0, 0, 0].repeat(257);
+        bytes.extend([0u8; 4].repeat(258));
+
+        match Map::<u32, u32>::deserialize_bounded(&bytes, usize::MAX, usize::MAX) {
+            Err(e) if matches!(e.top(), MapError::TooDeep) => (),
+            _ => panic!("`deserialize_bounded` accepts a map of depth 257"),
+        }
+    }
+
+    #[test]
+    fn deserialize_bounded_stubs() {
+        let mut original: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            original.insert(key, value).unwrap();
+        }
+
+        let export = original.export(0..16).unwrap();
+        let serialized = bincode::serialize(&export).unwrap();
+        let deserialized = Map::<u32, u32>::deserialize_bounded(&serialized, 16, 256).unwrap();
+
+        assert_eq!(original.commit(), deserialized.commit());
+        deserialized.check_tree();
+        deserialized.assert_records((0..16).map(|i| (i, i)));
+    }
+
+    #[test]
+    fn deserialize_bounded_flawed() {
+        let mut original: Map<u32, u32> = Map::new();
+
+        original.insert(3, 3).unwrap();
+        original.insert(4, 4).unwrap();
+
+        let root = match original.root.take() {
+            Node::Internal(internal) => {
+                let hash = internal.hash();
+                let (left, right) = internal.children();
+                Node::Internal(Internal::raw(hash, right, left))
+            }
+            _ => unreachable!(),
+        };
+
+        let serialized = bincode::serialize(&Map::raw(root)).unwrap();
+
+        match Map::<u32, u32>::deserialize_bounded(&serialized, 1024, 256) {
+            Err(e) if matches!(e.top(), MapError::DeserializeFailed) => (),
+            _ => panic!("`deserialize_bounded` accepts flawed topology"),
+        }
+    }
 }
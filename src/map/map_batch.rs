@@ -0,0 +1,70 @@
+use crate::{
+    common::{store::Field, tree::Path},
+    hashing::{Blake3Hasher, Hasher},
+    map::{errors::MapError, interact::Update},
+};
+
+use doomstack::{here, ResultExt, Top};
+
+use std::collections::HashMap;
+
+/// A set of insertions and removals staged for a [`Map`], applied all at
+/// once (in a single traversal of the [`Map`]) by [`Map::batch`].
+///
+/// [`Map`]: crate::map::Map
+/// [`Map::batch`]: crate::map::Map::batch
+pub struct MapBatch<Key: Field, Value: Field, H: Hasher = Blake3Hasher> {
+    updates: Vec<Update<Key, Value, H>>,
+}
+
+impl<Key, Value, H> MapBatch<Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    pub(crate) fn new() -> Self {
+        MapBatch {
+            updates: Vec::new(),
+        }
+    }
+
+    /// Stages the insertion of a key-value pair.
+    ///
+    /// # Errors
+    ///
+    /// If `key` or `value` cannot be hashed, [`HashError`] is returned.
+    ///
+    /// [`HashError`]: crate::map::errors::MapError::HashError
+    pub fn insert(&mut self, key: Key, value: Value) -> Result<(), Top<MapError>> {
+        let update = Update::insert(key, value).pot(MapError::HashError, here!())?;
+        self.updates.push(update);
+
+        Ok(())
+    }
+
+    /// Stages the removal of a key.
+    ///
+    /// # Errors
+    ///
+    /// If `key` cannot be hashed, [`HashError`] is returned.
+    ///
+    /// [`HashError`]: crate::map::errors::MapError::HashError
+    pub fn remove(&mut self, key: &Key) -> Result<(), Top<MapError>> {
+        let update = Update::remove(key).pot(MapError::HashError, here!())?;
+        self.updates.push(update);
+
+        Ok(())
+    }
+
+    // Returns the staged updates, keeping only the last update for each key
+    pub(crate) fn finalize(self) -> Vec<Update<Key, Value, H>> {
+        let updates: HashMap<Path, Update<Key, Value, H>> = self
+            .updates
+            .into_iter()
+            .map(|update| (update.path, update))
+            .collect();
+
+        updates.into_values().collect()
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{
+    common::store::Field,
+    hashing::{Blake3Hasher, Hasher},
+    map::store::Node,
+};
+
+/// An iterator over the records of a [`Map`], obtained through
+/// [`Map::iter`] (or by iterating over a `&Map`).
+///
+/// Records are yielded from left to right. Branches the [`Map`] does not
+/// store locally (i.e., `Stub`s) are skipped.
+///
+/// [`Map`]: crate::map::Map
+/// [`Map::iter`]: crate::map::Map::iter
+pub struct MapIter<'m, Key: Field, Value: Field, H: Hasher = Blake3Hasher> {
+    stack: Vec<&'m Node<Key, Value, H>>,
+}
+
+/// An iterator over the owned records of a [`Map`], obtained by
+/// iterating over a `Map` by value.
+///
+/// Records are yielded from left to right. Branches the [`Map`] does not
+/// store locally (i.e., `Stub`s) are skipped.
+///
+/// [`Map`]: crate::map::Map
+pub struct MapIntoIter<Key: Field, Value: Field, H: Hasher = Blake3Hasher> {
+    stack: Vec<Node<Key, Value, H>>,
+}
+
+impl<'m, Key, Value, H> MapIter<'m, Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    pub(crate) fn new(root: &'m Node<Key, Value, H>) -> Self {
+        MapIter { stack: vec![root] }
+    }
+}
+
+impl<'m, Key, Value, H> Iterator for MapIter<'m, Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    type Item = (&'m Key, &'m Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node) = self.stack.pop() {
+            match node {
+                Node::Internal(internal) => {
+                    // Push `right` first, so that `left` is visited first
+                    self.stack.push(internal.right());
+                    self.stack.push(internal.left());
+                }
+                Node::Leaf(leaf) => return Some((leaf.key().inner(), leaf.value().inner())),
+                Node::Empty | Node::Stub(_) => {}
+            }
+        }
+
+        None
+    }
+}
+
+impl<Key, Value, H> MapIntoIter<Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    pub(crate) fn new(root: Node<Key, Value, H>) -> Self {
+        MapIntoIter { stack: vec![root] }
+    }
+}
+
+impl<Key, Value, H> Iterator for MapIntoIter<Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    type Item = (Key, Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node) = self.stack.pop() {
+            match node {
+                Node::Internal(internal) => {
+                    let (left, right) = internal.children();
+
+                    // Push `right` first, so that `left` is visited first
+                    self.stack.push(right);
+                    self.stack.push(left);
+                }
+                Node::Leaf(leaf) => {
+                    let (key, value) = leaf.fields();
+                    return Some((key.take(), value.take()));
+                }
+                Node::Empty | Node::Stub(_) => {}
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,316 @@
+use crate::{
+    auth_path::AuthPath,
+    common::{
+        store::{hash as node_hash, Field},
+        tree::{Direction, Path},
+    },
+    map::{
+        errors::MapError,
+        store::{Node, Wrap},
+    },
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+use serde::{Deserialize, Serialize};
+
+use std::marker::PhantomData;
+
+use talk::crypto::primitives::{
+    hash,
+    hash::{Hash, HASH_LENGTH},
+};
+
+/// A proof that a key is associated with a value in a [`Map`] (existence),
+/// or that the key is absent from it (deniability), obtained through
+/// [`Map::prove`].
+///
+/// The proof carries the [`AuthPath`] along the key's path from where the
+/// path ends up to the root. An existence proof ends at the leaf storing
+/// the key, and carries its value. A deniability proof ends either at an
+/// empty subtree, or at a leaf storing a different key, of which it only
+/// carries the digests.
+///
+/// Unlike a [`Map`] obtained through [`export`], a `MapProof` only
+/// stores sibling digests, and does not need a [`Map`] to be verified.
+///
+/// [`Map`]: crate::map::Map
+/// [`Map::prove`]: crate::map::Map::prove
+/// [`AuthPath`]: crate::auth_path::AuthPath
+/// [`export`]: crate::map::Map::export
+///
+/// # Examples
+///
+/// ```
+/// use zebra::map::Map;
+///
+/// let mut map = Map::new();
+/// map.insert(1, "a").unwrap();
+/// map.insert(2, "b").unwrap();
+///
+/// let proof = map.prove(&1).unwrap();
+/// assert_eq!(proof.verify(map.commit(), &1).unwrap(), Some(&"a"));
+///
+/// let proof = map.prove(&3).unwrap();
+/// assert_eq!(proof.verify(map.commit(), &3).unwrap(), None);
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapProof<Key, Value> {
+    auth_path: AuthPath,
+    terminal: Terminal<Value>,
+    _key: PhantomData<Key>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Terminal<Value> {
+    Empty,
+    Present { value: Value },
+    Absent { key: Hash, value: Hash },
+}
+
+impl<Key, Value> MapProof<Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    pub(crate) fn prove(root: &Node<Key, Value>, path: Path) -> Result<Self, Top<MapError>>
+    where
+        Value: Clone,
+    {
+        let mut siblings = Vec::new();
+        let mut node = root;
+
+        let terminal = loop {
+            match node {
+                Node::Empty => break Terminal::Empty,
+                Node::Internal(internal) => {
+                    node = if path[siblings.len() as u8] == Direction::Left {
+                        siblings.push(internal.right().hash().into());
+                        internal.left()
+                    } else {
+                        siblings.push(internal.left().hash().into());
+                        internal.right()
+                    };
+                }
+                Node::Leaf(leaf) => {
+                    break if path.reaches(leaf.key().digest()) {
+                        Terminal::Present {
+                            value: leaf.value().inner().clone(),
+                        }
+                    } else {
+                        Terminal::Absent {
+                            key: leaf.key().digest().into(),
+                            value: leaf.value().digest().into(),
+                        }
+                    };
+                }
+                Node::Stub(_) => return MapError::BranchUnknown.fail().spot(here!()),
+            }
+        };
+
+        let directions = path.into_iter().take(siblings.len()).collect::<Vec<_>>();
+
+        let directions = directions.into_iter().rev();
+        let siblings = siblings.into_iter().rev().collect();
+
+        Ok(MapProof {
+            auth_path: AuthPath::new(directions, siblings),
+            terminal,
+            _key: PhantomData,
+        })
+    }
+
+    // Builds the branch proven for `key` (assuming the proof was verified
+    // for `key`), replacing siblings with `Stub`s. A leaf storing a key
+    // other than `key` is also replaced with a `Stub`, as the proof only
+    // carries its digests
+    pub(crate) fn into_branch(self, key: Key) -> Result<Node<Key, Value>, Top<MapError>> {
+        let mut node = match self.terminal {
+            Terminal::Empty => Node::Empty,
+            Terminal::Present { value } => {
+                let key = Wrap::new(key).pot(MapError::HashError, here!())?;
+                let value = Wrap::new(value).pot(MapError::HashError, here!())?;
+
+                Node::leaf(key, value)
+            }
+            Terminal::Absent { key, value } => {
+                Node::stub(node_hash::leaf(key.into(), value.into()))
+            }
+        };
+
+        for (direction, sibling) in self
+            .auth_path
+            .directions()
+            .zip(self.auth_path.siblings().iter())
+        {
+            let sibling = Node::stub((*sibling).into());
+
+            node = match direction {
+                Direction::Left => Node::internal(node, sibling),
+                Direction::Right => Node::internal(sibling, node),
+            };
+        }
+
+        Ok(node)
+    }
+
+    /// Returns the [`AuthPath`] from the end of the proven key's path
+    /// to the root.
+    ///
+    /// [`AuthPath`]: crate::auth_path::AuthPath
+    pub fn auth_path(&self) -> &AuthPath {
+        &self.auth_path
+    }
+
+    /// Verifies the proof for `key` against `commitment`, returning the
+    /// value associated with `key` (if the proof is an existence proof),
+    /// or `None` (if the proof is a deniability proof).
+    ///
+    /// # Errors
+    ///
+    /// If `key` or the proven value cannot be hashed, [`HashError`] is
+    /// returned.
+    ///
+    /// If the proof does not follow the path of `key`, or does not match
+    /// `commitment`, [`ProofInvalid`] is returned.
+    ///
+    /// [`HashError`]: crate::map::errors::MapError::HashError
+    /// [`ProofInvalid`]: crate::map::errors::MapError::ProofInvalid
+    pub fn verify(&self, commitment: Hash, key: &Key) -> Result<Option<&Value>, Top<MapError>> {
+        let digest = hash::hash(key).pot(MapError::HashError, here!())?;
+        let path = Path::from(digest);
+
+        if self.auth_path.len() >= 8 * HASH_LENGTH {
+            return MapError::ProofInvalid.fail().spot(here!());
+        }
+
+        let depth = self.auth_path.len() as u8;
+
+        // The proof must follow the path of `key`
+        if !self.auth_path.directions().eq(path
+            .into_iter()
+            .take(depth as usize)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev())
+        {
+            return MapError::ProofInvalid.fail().spot(here!());
+        }
+
+        let (terminal, value) = match &self.terminal {
+            Terminal::Empty => (node_hash::empty(), None),
+            Terminal::Present { value } => {
+                let value_digest = hash::hash(value).pot(MapError::HashError, here!())?;
+                (
+                    node_hash::leaf(digest.into(), value_digest.into()),
+                    Some(value),
+                )
+            }
+            Terminal::Absent {
+                key: leaf_key,
+                value: leaf_value,
+            } => {
+                if *leaf_key == digest || !Path::deepeq(&path, &Path::from(*leaf_key), depth) {
+                    return MapError::ProofInvalid.fail().spot(here!());
+                }
+
+                (
+                    node_hash::leaf((*leaf_key).into(), (*leaf_value).into()),
+                    None,
+                )
+            }
+        };
+
+        self.auth_path
+            .verify(commitment, terminal.into(), |left, right| {
+                node_hash::internal(left.into(), right.into()).into()
+            })
+            .pot(MapError::ProofInvalid, here!())?;
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map::{errors::MapError, Map, MapProof};
+
+    #[test]
+    fn existence() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..64).map(|i| (i, i + 1)) {
+            map.insert(key, value).unwrap();
+        }
+
+        for key in 0..64 {
+            let proof = map.prove(&key).unwrap();
+            assert_eq!(proof.verify(map.commit(), &key).unwrap(), Some(&(key + 1)));
+
+            // The proof does not hold for other keys, nor for other commitments
+            assert!(proof.verify(map.commit(), &(key + 1)).is_err());
+            assert!(proof.verify(Map::<u32, u32>::new().commit(), &key).is_err());
+        }
+    }
+
+    #[test]
+    fn deniability() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..64).map(|i| (i, i + 1)) {
+            map.insert(key, value).unwrap();
+        }
+
+        for key in 64..128 {
+            let proof = map.prove(&key).unwrap();
+            assert_eq!(proof.verify(map.commit(), &key).unwrap(), None);
+
+            let mut other = map.clone();
+            other.insert(key, key + 1).unwrap();
+
+            assert!(matches!(
+                proof.verify(other.commit(), &key).unwrap_err().top(),
+                MapError::ProofInvalid
+            ));
+        }
+
+        let empty: Map<u32, u32> = Map::new();
+        let proof = empty.prove(&0).unwrap();
+
+        assert!(proof.auth_path().is_empty());
+        assert_eq!(proof.verify(empty.commit(), &0).unwrap(), None);
+    }
+
+    #[test]
+    fn serialize() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..64).map(|i| (i, i + 1)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let proof = map.prove(&0).unwrap();
+
+        let serialized = bincode::serialize(&proof).unwrap();
+        let proof: MapProof<u32, u32> = bincode::deserialize(&serialized).unwrap();
+
+        assert_eq!(proof.verify(map.commit(), &0).unwrap(), Some(&1));
+    }
+
+    #[test]
+    fn stub() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..64).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let export = map.export([0]).unwrap();
+
+        assert!(export.prove(&0).is_ok());
+        assert!(matches!(
+            export.prove(&64).unwrap_err().top(),
+            MapError::BranchUnknown
+        ));
+    }
+}
@@ -2,12 +2,34 @@
 
 mod interact;
 
+mod absence_proof;
+mod bloom_summary;
+mod entry;
+mod export_manifest;
 mod map;
+mod map_batch;
+mod map_iter;
+mod map_proof;
+mod node_ref;
+mod radix_map;
+mod region_equality_proof;
 mod set;
+mod versioned_map;
 
 pub(crate) mod store;
 
 pub mod errors;
 
+pub use absence_proof::AbsenceProof;
+pub use bloom_summary::BloomSummary;
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use export_manifest::ExportManifest;
 pub use map::Map;
+pub use map_batch::MapBatch;
+pub use map_iter::{MapIntoIter, MapIter};
+pub use map_proof::MapProof;
+pub use node_ref::NodeRef;
+pub use radix_map::{RadixMap, RadixProof};
+pub use region_equality_proof::RegionEqualityProof;
 pub use set::Set;
+pub use versioned_map::VersionedMap;
@@ -0,0 +1,215 @@
+use crate::{
+    common::{
+        store::Field,
+        tree::{Direction, Prefix},
+    },
+    hashing::{Blake3Hasher, Hasher},
+    map::store::Node,
+};
+
+use talk::crypto::primitives::hash::Hash;
+
+/// A read-only reference to a node of a [`Map`], obtained through
+/// [`Map::node_at`].
+///
+/// A `NodeRef` allows to navigate the underlying Merkle-prefix tree
+/// one [`Direction`] at a time (e.g., to implement custom proof schemes)
+/// without exposing the internal representation of the tree.
+///
+/// [`Map`]: crate::map::Map
+/// [`Map::node_at`]: crate::map::Map::node_at
+/// [`Direction`]: crate::tree::Direction
+///
+/// # Examples
+///
+/// ```
+/// use zebra::{map::Map, tree::Direction};
+///
+/// let mut map = Map::new();
+/// map.insert(1, "a").unwrap();
+/// map.insert(2, "b").unwrap();
+///
+/// let root = map.root_ref();
+/// assert_eq!(root.commitment(), map.commit());
+///
+/// // With two keys, the root is internal
+/// assert!(root.child(Direction::Left).is_some());
+/// assert!(root.leaf().is_none());
+/// ```
+pub struct NodeRef<'m, Key: Field, Value: Field, H: Hasher = Blake3Hasher> {
+    node: &'m Node<Key, Value, H>,
+    location: Prefix,
+}
+
+impl<'m, Key, Value, H> NodeRef<'m, Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    pub(crate) fn new(node: &'m Node<Key, Value, H>, location: Prefix) -> Self {
+        NodeRef { node, location }
+    }
+
+    /// Returns the position of the node in the tree.
+    pub fn location(&self) -> Prefix {
+        self.location
+    }
+
+    /// Returns the cryptographic commitment to the subtree rooted at the node.
+    pub fn commitment(&self) -> Hash {
+        self.node.hash().into()
+    }
+
+    /// Returns the child of the node in `direction`, or
+    /// `None` if the node is not internal.
+    pub fn child(&self, direction: Direction) -> Option<NodeRef<'m, Key, Value, H>> {
+        match self.node {
+            Node::Internal(internal) => Some(match direction {
+                Direction::Left => NodeRef::new(internal.left(), self.location.left()),
+                Direction::Right => NodeRef::new(internal.right(), self.location.right()),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns the key-value pair stored at the node, or
+    /// `None` if the node is not a leaf.
+    pub fn leaf(&self) -> Option<(&'m Key, &'m Value)> {
+        match self.node {
+            Node::Leaf(leaf) => Some((leaf.key().inner(), leaf.value().inner())),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the subtree rooted at the node is empty.
+    pub fn is_empty(&self) -> bool {
+        self.node.is_empty()
+    }
+
+    /// Returns `true` if the node is unknown (i.e., the
+    /// [`Map`] was exported without this branch).
+    ///
+    /// [`Map`]: crate::map::Map
+    pub fn is_stub(&self) -> bool {
+        self.node.is_stub()
+    }
+}
+
+impl<'m, Key, Value, H> Clone for NodeRef<'m, Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'m, Key, Value, H> Copy for NodeRef<'m, Key, Value, H>
+where
+    Key: Field,
+    Value: Field,
+    H: Hasher,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        common::tree::{Direction, Path, Prefix},
+        map::Map,
+    };
+
+    use talk::crypto::primitives::hash;
+
+    #[test]
+    fn empty() {
+        let map: Map<u32, u32> = Map::new();
+        let root = map.root_ref();
+
+        assert!(root.is_empty());
+        assert!(root.leaf().is_none());
+        assert!(root.child(Direction::Left).is_none());
+        assert_eq!(root.commitment(), map.commit());
+    }
+
+    #[test]
+    fn navigate_to_leaf() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        for key in [0, 33, 512, 1023] {
+            let path = Path::from(hash::hash(&key).unwrap());
+            let mut node = map.root_ref();
+
+            for direction in path {
+                node = match node.child(direction) {
+                    Some(child) => child,
+                    None => break,
+                };
+            }
+
+            assert_eq!(node.leaf(), Some((&key, &key)));
+            assert!(node.location().contains(&path));
+        }
+    }
+
+    #[test]
+    fn node_at() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let left = map.root_ref().child(Direction::Left).unwrap();
+        let left_right = left.child(Direction::Right).unwrap();
+
+        let at = map.node_at(Prefix::root().left().right()).unwrap();
+
+        assert_eq!(at.commitment(), left_right.commitment());
+        assert_eq!(at.location(), Prefix::root().left().right());
+    }
+
+    #[test]
+    fn stub() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for (key, value) in (0..1024).map(|i| (i, i)) {
+            map.insert(key, value).unwrap();
+        }
+
+        let export = map.export([0]).unwrap();
+        let path = Path::from(hash::hash(&0u32).unwrap());
+
+        let mut node = export.root_ref();
+        let mut stubs = 0;
+
+        for direction in path {
+            let sibling = match direction {
+                Direction::Left => node.child(Direction::Right),
+                Direction::Right => node.child(Direction::Left),
+            };
+
+            if let Some(sibling) = sibling {
+                if sibling.is_stub() {
+                    stubs += 1;
+                }
+            }
+
+            node = match node.child(direction) {
+                Some(child) => child,
+                None => break,
+            };
+        }
+
+        assert!(stubs > 0);
+        assert_eq!(node.leaf(), Some((&0, &0)));
+        assert_eq!(export.root_ref().commitment(), map.commit());
+    }
+}
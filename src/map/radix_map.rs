@@ -0,0 +1,645 @@
+use crate::{
+    common::{
+        data::Bytes,
+        store::{hash as node_hash, Field},
+        tree::Path,
+    },
+    map::{
+        errors::{MapError, ProofError},
+        store::Wrap,
+    },
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+use serde::{Deserialize, Serialize};
+
+use std::{array, mem};
+
+use talk::crypto::primitives::{
+    hash,
+    hash::{Hash, HASH_LENGTH},
+};
+
+/// A map implemented using a Merkle-prefix tree whose internal nodes
+/// have `RADIX` children (16 by default), one per `log2(RADIX)`-bit digit
+/// of a key's path.
+///
+/// With respect to a (binary) [`Map`], a `RadixMap` reaches its leaves in
+/// `log2(RADIX)` times fewer levels, at the cost of `RADIX - 1` sibling
+/// digests (rather than one) per level of a [`RadixProof`]. Commitments
+/// differ from those of a [`Map`] storing the same records, regardless
+/// of `RADIX`.
+///
+/// `RADIX` must be a power of two between 2 and 256.
+///
+/// [`Map`]: crate::map::Map
+/// [`RadixProof`]: crate::map::RadixProof
+///
+/// # Examples
+///
+/// ```
+/// use zebra::map::RadixMap;
+///
+/// let mut map: RadixMap<u32, u32> = RadixMap::new();
+///
+/// map.insert(1, 2).unwrap();
+/// assert_eq!(map.get(&1).unwrap(), Some(&2));
+///
+/// let proof = map.prove(&1).unwrap();
+/// assert!(proof.verify(map.commit(), &1, Some(&2)).is_ok());
+/// ```
+#[derive(Debug, Clone)]
+pub struct RadixMap<Key: Field, Value: Field, const RADIX: usize = 16> {
+    root: Node<Key, Value, RADIX>,
+}
+
+#[derive(Debug, Clone)]
+enum Node<Key: Field, Value: Field, const RADIX: usize> {
+    Empty,
+    Internal {
+        hash: Bytes,
+        children: Box<[Node<Key, Value, RADIX>; RADIX]>,
+    },
+    Leaf {
+        hash: Bytes,
+        key: Wrap<Key>,
+        value: Wrap<Value>,
+    },
+}
+
+/// A proof that a key is associated with a value in a [`RadixMap`]
+/// (or that the key is absent), obtained through [`RadixMap::prove`].
+///
+/// [`RadixMap`]: crate::map::RadixMap
+/// [`RadixMap::prove`]: crate::map::RadixMap::prove
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RadixProof<const RADIX: usize = 16> {
+    levels: Vec<Vec<Hash>>,
+    terminal: Terminal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Terminal {
+    Empty,
+    Leaf { key: Hash, value: Hash },
+}
+
+const fn bits(radix: usize) -> u8 {
+    assert!(
+        radix.is_power_of_two() && radix >= 2 && radix <= 256,
+        "`RADIX` must be a power of two between 2 and 256"
+    );
+
+    radix.trailing_zeros() as u8
+}
+
+impl<Key, Value, const RADIX: usize> RadixMap<Key, Value, RADIX>
+where
+    Key: Field,
+    Value: Field,
+{
+    const BITS: u8 = bits(RADIX);
+
+    /// Creates an empty `RadixMap`.
+    pub fn new() -> Self {
+        RadixMap { root: Node::Empty }
+    }
+
+    /// Returns a cryptographic commitment to the contents of the `RadixMap`.
+    pub fn commit(&self) -> Hash {
+        self.root.hash().into()
+    }
+
+    /// Returns a reference to the value associated with `key`, if any.
+    ///
+    /// # Errors
+    ///
+    /// If `key` cannot be hashed, [`HashError`] is returned.
+    ///
+    /// [`HashError`]: crate::map::errors::MapError::HashError
+    pub fn get(&self, key: &Key) -> Result<Option<&Value>, Top<MapError>> {
+        let path = RadixMap::<Key, Value, RADIX>::path(key)?;
+
+        let mut node = &self.root;
+        let mut level = 0;
+
+        loop {
+            match node {
+                Node::Empty => return Ok(None),
+                Node::Internal { children, .. } => {
+                    node = &children[path.digit(level, Self::BITS)];
+                    level += 1;
+                }
+                Node::Leaf { key, value, .. } => {
+                    return Ok(if path.reaches(key.digest()) {
+                        Some(value.inner())
+                    } else {
+                        None
+                    });
+                }
+            }
+        }
+    }
+
+    /// Associates `key` with `value`, returning the value previously
+    /// associated with `key`, if any.
+    ///
+    /// # Errors
+    ///
+    /// If `key` or `value` cannot be hashed, [`HashError`] is returned.
+    ///
+    /// [`HashError`]: crate::map::errors::MapError::HashError
+    pub fn insert(&mut self, key: Key, value: Value) -> Result<Option<Value>, Top<MapError>> {
+        let key = Wrap::new(key).pot(MapError::HashError, here!())?;
+        let value = Wrap::new(value).pot(MapError::HashError, here!())?;
+
+        let path = Path::from(key.digest());
+
+        Ok(Node::insert(&mut self.root, 0, path, key, value))
+    }
+
+    /// Removes `key`, returning the value it was associated with, if any.
+    ///
+    /// # Errors
+    ///
+    /// If `key` cannot be hashed, [`HashError`] is returned.
+    ///
+    /// [`HashError`]: crate::map::errors::MapError::HashError
+    pub fn remove(&mut self, key: &Key) -> Result<Option<Value>, Top<MapError>> {
+        let path = RadixMap::<Key, Value, RADIX>::path(key)?;
+        Ok(Node::remove(&mut self.root, 0, path))
+    }
+
+    /// Returns a [`RadixProof`] of the value associated with `key`
+    /// (or of the absence of `key`).
+    ///
+    /// # Errors
+    ///
+    /// If `key` cannot be hashed, [`HashError`] is returned.
+    ///
+    /// [`RadixProof`]: crate::map::RadixProof
+    /// [`HashError`]: crate::map::errors::MapError::HashError
+    pub fn prove(&self, key: &Key) -> Result<RadixProof<RADIX>, Top<MapError>> {
+        let path = RadixMap::<Key, Value, RADIX>::path(key)?;
+
+        let mut levels = Vec::new();
+        let mut node = &self.root;
+
+        loop {
+            match node {
+                Node::Empty => {
+                    return Ok(RadixProof {
+                        levels,
+                        terminal: Terminal::Empty,
+                    })
+                }
+                Node::Internal { children, .. } => {
+                    let digit = path.digit(levels.len(), Self::BITS);
+
+                    levels.push(
+                        children
+                            .iter()
+                            .enumerate()
+                            .filter(|(index, _)| *index != digit)
+                            .map(|(_, child)| child.hash().into())
+                            .collect(),
+                    );
+
+                    node = &children[digit];
+                }
+                Node::Leaf { key, value, .. } => {
+                    return Ok(RadixProof {
+                        levels,
+                        terminal: Terminal::Leaf {
+                            key: key.digest().into(),
+                            value: value.digest().into(),
+                        },
+                    })
+                }
+            }
+        }
+    }
+
+    fn path(key: &Key) -> Result<Path, Top<MapError>> {
+        let digest = hash::hash(key).pot(MapError::HashError, here!())?;
+        Ok(Path::from(digest))
+    }
+}
+
+impl<Key, Value, const RADIX: usize> Default for RadixMap<Key, Value, RADIX>
+where
+    Key: Field,
+    Value: Field,
+{
+    fn default() -> Self {
+        RadixMap::new()
+    }
+}
+
+impl<Key, Value, const RADIX: usize> Node<Key, Value, RADIX>
+where
+    Key: Field,
+    Value: Field,
+{
+    const BITS: u8 = bits(RADIX);
+
+    fn leaf(key: Wrap<Key>, value: Wrap<Value>) -> Self {
+        Node::Leaf {
+            hash: node_hash::leaf(key.digest(), value.digest()),
+            key,
+            value,
+        }
+    }
+
+    fn internal(children: Box<[Node<Key, Value, RADIX>; RADIX]>) -> Self {
+        let hashes = children.iter().map(Node::hash).collect::<Vec<_>>();
+
+        Node::Internal {
+            hash: node_hash::radix_internal(&hashes),
+            children,
+        }
+    }
+
+    fn hash(&self) -> Bytes {
+        match self {
+            Node::Empty => node_hash::empty(),
+            Node::Internal { hash, .. } => *hash,
+            Node::Leaf { hash, .. } => *hash,
+        }
+    }
+
+    fn insert(
+        node: &mut Self,
+        level: usize,
+        path: Path,
+        key: Wrap<Key>,
+        value: Wrap<Value>,
+    ) -> Option<Value> {
+        match node {
+            Node::Empty => {
+                *node = Node::leaf(key, value);
+                None
+            }
+            Node::Leaf { key: present, .. } if *present == key => {
+                match mem::replace(node, Node::leaf(key, value)) {
+                    Node::Leaf { value, .. } => Some(value.take()),
+                    _ => unreachable!(),
+                }
+            }
+            Node::Leaf { key: present, .. } => {
+                // Push the present leaf one level down, then insert below
+                let digit = Path::from(present.digest()).digit(level, Self::BITS);
+
+                let mut children = Box::new(array::from_fn(|_| Node::Empty));
+                children[digit] = mem::replace(node, Node::Empty);
+
+                *node = Node::internal(children);
+                Node::insert(node, level, path, key, value)
+            }
+            Node::Internal { children, .. } => {
+                let digit = path.digit(level, Self::BITS);
+                let previous = Node::insert(&mut children[digit], level + 1, path, key, value);
+
+                Node::rehash(node);
+                previous
+            }
+        }
+    }
+
+    fn remove(node: &mut Self, level: usize, path: Path) -> Option<Value> {
+        match node {
+            Node::Empty => None,
+            Node::Leaf { key, .. } => {
+                if path.reaches(key.digest()) {
+                    match mem::replace(node, Node::Empty) {
+                        Node::Leaf { value, .. } => Some(value.take()),
+                        _ => unreachable!(),
+                    }
+                } else {
+                    None
+                }
+            }
+            Node::Internal { children, .. } => {
+                let digit = path.digit(level, Self::BITS);
+                let removed = Node::remove(&mut children[digit], level + 1, path)?;
+
+                // To preserve compactness, an `Internal` left with a
+                // single `Leaf` below is replaced by the `Leaf`
+                let mut occupied = children
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, child)| !matches!(child, Node::Empty));
+
+                let single = match (occupied.next(), occupied.next()) {
+                    (Some((index, Node::Leaf { .. })), None) => Some(index),
+                    _ => None,
+                };
+
+                if let Some(index) = single {
+                    *node = mem::replace(&mut children[index], Node::Empty);
+                } else {
+                    Node::rehash(node);
+                }
+
+                Some(removed)
+            }
+        }
+    }
+
+    fn rehash(node: &mut Self) {
+        if let Node::Internal { hash, children } = node {
+            let hashes = children.iter().map(Node::hash).collect::<Vec<_>>();
+            *hash = node_hash::radix_internal(&hashes);
+        }
+    }
+}
+
+impl<const RADIX: usize> RadixProof<RADIX> {
+    const BITS: u8 = bits(RADIX);
+
+    /// Returns the number of levels between the root and the end of the
+    /// proven key's path, each contributing `RADIX - 1` sibling digests.
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Returns `true` if the proven key's path ends at the root.
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    /// Verifies that, in the [`RadixMap`] with commitment `commitment`,
+    /// `key` is associated with `value` (if `value` is `Some`), or
+    /// `key` is absent (if `value` is `None`).
+    ///
+    /// # Errors
+    ///
+    /// If `key` or `value` cannot be hashed, [`HashError`] is returned.
+    ///
+    /// If `value` is `None` and the proof shows that `key` is present,
+    /// [`ItemPresent`] is returned.
+    ///
+    /// If `value` is `Some` and the proof shows that `key` is absent,
+    /// [`ItemAbsent`] is returned.
+    ///
+    /// If the proof shows that `key` is associated with a value other than
+    /// `value`, [`ValueMismatch`] is returned.
+    ///
+    /// If the proof is malformed, or does not follow the path of `key`,
+    /// [`OutOfPath`] is returned.
+    ///
+    /// If the proof does not match `commitment`, [`RootMismatch`] is returned.
+    ///
+    /// [`RadixMap`]: crate::map::RadixMap
+    /// [`HashError`]: crate::map::errors::ProofError::HashError
+    /// [`ItemPresent`]: crate::map::errors::ProofError::ItemPresent
+    /// [`ItemAbsent`]: crate::map::errors::ProofError::ItemAbsent
+    /// [`ValueMismatch`]: crate::map::errors::ProofError::ValueMismatch
+    /// [`OutOfPath`]: crate::map::errors::ProofError::OutOfPath
+    /// [`RootMismatch`]: crate::map::errors::ProofError::RootMismatch
+    pub fn verify<Key, Value>(
+        &self,
+        commitment: Hash,
+        key: &Key,
+        value: Option<&Value>,
+    ) -> Result<(), Top<ProofError>>
+    where
+        Key: Serialize,
+        Value: Serialize,
+    {
+        let digest = hash::hash(key).pot(ProofError::HashError, here!())?;
+        let path = Path::from(digest);
+
+        if self.levels.len() * (Self::BITS as usize) >= 8 * HASH_LENGTH
+            || self.levels.iter().any(|level| level.len() != RADIX - 1)
+        {
+            return ProofError::OutOfPath.fail().spot(here!());
+        }
+
+        let terminal = match self.terminal {
+            Terminal::Empty => {
+                if value.is_some() {
+                    return ProofError::ItemAbsent.fail().spot(here!());
+                }
+
+                node_hash::empty()
+            }
+            Terminal::Leaf {
+                key: leaf_key,
+                value: leaf_value,
+            } => {
+                if leaf_key == digest {
+                    match value {
+                        None => return ProofError::ItemPresent.fail().spot(here!()),
+                        Some(value) => {
+                            let value = hash::hash(value).pot(ProofError::HashError, here!())?;
+
+                            if value != leaf_value {
+                                return ProofError::ValueMismatch.fail().spot(here!());
+                            }
+                        }
+                    }
+                } else {
+                    let depth = (self.levels.len() * (Self::BITS as usize)) as u8;
+
+                    if !Path::deepeq(&path, &Path::from(leaf_key), depth) {
+                        return ProofError::OutOfPath.fail().spot(here!());
+                    }
+
+                    if value.is_some() {
+                        return ProofError::ItemAbsent.fail().spot(here!());
+                    }
+                }
+
+                node_hash::leaf(leaf_key.into(), leaf_value.into())
+            }
+        };
+
+        let root =
+            self.levels
+                .iter()
+                .enumerate()
+                .rev()
+                .fold(terminal, |hash, (level, siblings)| {
+                    let digit = path.digit(level, Self::BITS);
+
+                    let mut children = siblings
+                        .iter()
+                        .map(|sibling| Bytes::from(*sibling))
+                        .collect::<Vec<_>>();
+
+                    children.insert(digit, hash);
+                    node_hash::radix_internal(&children)
+                });
+
+        if root != commitment.into() {
+            return ProofError::RootMismatch.fail().spot(here!());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::map::{Map, Set};
+
+    use std::collections::HashMap;
+
+    fn check<const RADIX: usize>() {
+        let mut map: RadixMap<u32, u32, RADIX> = RadixMap::new();
+        let mut reference = HashMap::new();
+
+        for key in 0..512 {
+            assert_eq!(map.insert(key, key).unwrap(), None);
+            reference.insert(key, key);
+        }
+
+        for key in (0..512).filter(|key| key % 3 == 0) {
+            assert_eq!(map.insert(key, key + 1).unwrap(), Some(key));
+            reference.insert(key, key + 1);
+        }
+
+        for key in (0..512).filter(|key| key % 5 == 0) {
+            assert_eq!(map.remove(&key).unwrap(), reference.remove(&key));
+        }
+
+        assert_eq!(map.remove(&1024).unwrap(), None);
+
+        for key in 0..1024 {
+            assert_eq!(map.get(&key).unwrap(), reference.get(&key));
+
+            let proof = map.prove(&key).unwrap();
+            proof
+                .verify(map.commit(), &key, reference.get(&key))
+                .unwrap();
+        }
+
+        // Commitments only depend on the records
+        let mut other: RadixMap<u32, u32, RADIX> = RadixMap::new();
+
+        for (key, value) in reference.iter() {
+            other.insert(*key, *value).unwrap();
+        }
+
+        assert_eq!(map.commit(), other.commit());
+
+        // Removing all records restores the empty root
+        for key in 0..512 {
+            map.remove(&key).unwrap();
+        }
+
+        assert_eq!(map.commit(), RadixMap::<u32, u32, RADIX>::new().commit());
+    }
+
+    #[test]
+    fn binary() {
+        check::<2>();
+    }
+
+    #[test]
+    fn quaternary() {
+        check::<4>();
+    }
+
+    #[test]
+    fn nibble() {
+        check::<16>();
+    }
+
+    #[test]
+    fn byte() {
+        check::<256>();
+    }
+
+    #[test]
+    fn wrong_claims() {
+        let mut map: RadixMap<u32, u32> = RadixMap::new();
+
+        for key in 0..256 {
+            map.insert(key, key).unwrap();
+        }
+
+        let proof = map.prove(&0).unwrap();
+
+        assert!(matches!(
+            proof.verify(map.commit(), &0, Some(&1)).unwrap_err().top(),
+            ProofError::ValueMismatch
+        ));
+
+        assert!(matches!(
+            proof
+                .verify(map.commit(), &0, None::<&u32>)
+                .unwrap_err()
+                .top(),
+            ProofError::ItemPresent
+        ));
+
+        assert!(proof.verify(map.commit(), &1, Some(&1)).is_err());
+
+        let absent = map.prove(&256).unwrap();
+        absent.verify(map.commit(), &256, None::<&u32>).unwrap();
+
+        assert!(matches!(
+            absent
+                .verify(map.commit(), &256, Some(&256))
+                .unwrap_err()
+                .top(),
+            ProofError::ItemAbsent
+        ));
+
+        let mut other = map.clone();
+        other.insert(0, 1).unwrap();
+
+        assert!(matches!(
+            proof
+                .verify(other.commit(), &0, Some(&0))
+                .unwrap_err()
+                .top(),
+            ProofError::RootMismatch
+        ));
+    }
+
+    #[test]
+    fn shorter_than_binary() {
+        let mut set: Set<u32> = Set::new();
+        let mut binary: RadixMap<u32, (), 2> = RadixMap::new();
+        let mut nibble: RadixMap<u32, (), 16> = RadixMap::new();
+
+        for key in 0..4096 {
+            set.insert(key).unwrap();
+            binary.insert(key, ()).unwrap();
+            nibble.insert(key, ()).unwrap();
+        }
+
+        let (mut binary_levels, mut nibble_levels) = (0, 0);
+
+        for key in 4096..8192 {
+            // A binary `RadixMap` has the same shape as a `Map`
+            let binary_proof = binary.prove(&key).unwrap();
+            assert_eq!(
+                binary_proof.len(),
+                set.prove_absent(&key).unwrap().auth_path().len()
+            );
+
+            binary_levels += binary_proof.len();
+            nibble_levels += nibble.prove(&key).unwrap().len();
+        }
+
+        assert!(nibble_levels < binary_levels);
+
+        // Commitments differ from those of a binary `Map`
+        let mut map: Map<u32, ()> = Map::new();
+        map.insert(0, ()).unwrap();
+        map.insert(1, ()).unwrap();
+
+        let mut radix: RadixMap<u32, (), 2> = RadixMap::new();
+        radix.insert(0, ()).unwrap();
+        radix.insert(1, ()).unwrap();
+
+        assert_ne!(map.commit(), radix.commit());
+    }
+}
@@ -0,0 +1,343 @@
+use crate::{
+    auth_path::AuthPath,
+    common::{
+        data::Bytes,
+        store::{hash as node_hash, Field},
+        tree::{Direction, Path, Prefix},
+    },
+    map::{errors::ProofError, store::Node},
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+use serde::{Deserialize, Serialize};
+
+use talk::crypto::primitives::hash::Hash;
+
+/// A proof that two [`Map`]s store the same records under a [`Prefix`],
+/// obtained through [`Map::prove_region_unchanged`].
+///
+/// For each [`Map`], the proof carries the [`AuthPath`] from the root down
+/// to the subtree at the [`Prefix`] or, if the [`Map`] is too shallow for
+/// that subtree to exist, down to the empty subtree or leaf where the
+/// [`Prefix`] ends. Both [`Map`]s agree on the region if the two subtrees
+/// match.
+///
+/// [`Map`]: crate::map::Map
+/// [`Prefix`]: crate::tree::Prefix
+/// [`AuthPath`]: crate::auth_path::AuthPath
+/// [`Map::prove_region_unchanged`]: crate::map::Map::prove_region_unchanged
+///
+/// # Examples
+///
+/// ```
+/// use zebra::{map::Map, tree::Prefix};
+///
+/// let mut before = Map::new();
+///
+/// for key in 0..16 {
+///     before.insert(key, key).unwrap();
+/// }
+///
+/// let mut after = before.clone();
+/// after.insert(16, 16).unwrap();
+///
+/// // Only one half of the map is affected by the insertion
+/// let (proof, prefix) = [Prefix::root().left(), Prefix::root().right()]
+///     .iter()
+///     .find_map(|prefix| Some((before.prove_region_unchanged(&after, *prefix)?, *prefix)))
+///     .unwrap();
+///
+/// assert!(proof.verify(before.commit(), after.commit(), prefix).is_ok());
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegionEqualityProof {
+    first: Side,
+    second: Side,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Side {
+    auth_path: AuthPath,
+    terminal: Terminal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Terminal {
+    Subtree(Hash),
+    Empty,
+    Leaf { key: Hash, value: Hash },
+}
+
+impl RegionEqualityProof {
+    pub(crate) fn prove<Key, Value>(
+        first: &Node<Key, Value>,
+        second: &Node<Key, Value>,
+        prefix: Prefix,
+    ) -> Option<RegionEqualityProof>
+    where
+        Key: Field,
+        Value: Field,
+    {
+        let first = Side::prove(first, prefix)?;
+        let second = Side::prove(second, prefix)?;
+
+        if first.region(prefix).ok()? != second.region(prefix).ok()? {
+            return None;
+        }
+
+        Some(RegionEqualityProof { first, second })
+    }
+
+    /// Verifies that the [`Map`]s with commitments `first` and `second`
+    /// store the same records under `prefix`.
+    ///
+    /// # Errors
+    ///
+    /// If either side of the proof does not follow `prefix`, [`OutOfPath`]
+    /// is returned.
+    ///
+    /// If either side of the proof does not match its commitment,
+    /// [`RootMismatch`] is returned.
+    ///
+    /// If the two regions differ, [`RegionMismatch`] is returned.
+    ///
+    /// [`Map`]: crate::map::Map
+    /// [`OutOfPath`]: crate::map::errors::ProofError::OutOfPath
+    /// [`RootMismatch`]: crate::map::errors::ProofError::RootMismatch
+    /// [`RegionMismatch`]: crate::map::errors::ProofError::RegionMismatch
+    pub fn verify(&self, first: Hash, second: Hash, prefix: Prefix) -> Result<(), Top<ProofError>> {
+        let first = self.first.verify(first, prefix)?;
+        let second = self.second.verify(second, prefix)?;
+
+        if first != second {
+            return ProofError::RegionMismatch.fail().spot(here!());
+        }
+
+        Ok(())
+    }
+}
+
+impl Side {
+    fn prove<Key, Value>(root: &Node<Key, Value>, prefix: Prefix) -> Option<Side>
+    where
+        Key: Field,
+        Value: Field,
+    {
+        let mut siblings = Vec::new();
+        let mut node = root;
+
+        let terminal = loop {
+            if siblings.len() == prefix.depth() as usize {
+                match node {
+                    Node::Stub(_) => return None,
+                    node => break Terminal::Subtree(node.hash().into()),
+                }
+            }
+
+            match node {
+                Node::Empty => break Terminal::Empty,
+                Node::Internal(internal) => {
+                    node = if prefix[siblings.len() as u8] == Direction::Left {
+                        siblings.push(internal.right().hash().into());
+                        internal.left()
+                    } else {
+                        siblings.push(internal.left().hash().into());
+                        internal.right()
+                    };
+                }
+                Node::Leaf(leaf) => {
+                    break Terminal::Leaf {
+                        key: leaf.key().digest().into(),
+                        value: leaf.value().digest().into(),
+                    }
+                }
+                Node::Stub(_) => return None,
+            }
+        };
+
+        let directions = prefix
+            .into_iter()
+            .take(siblings.len())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev();
+
+        let siblings = siblings.into_iter().rev().collect();
+
+        Some(Side {
+            auth_path: AuthPath::new(directions, siblings),
+            terminal,
+        })
+    }
+
+    // Returns the digest of the subtree at `prefix`
+    fn region(&self, prefix: Prefix) -> Result<Bytes, Top<ProofError>> {
+        let depth = self.auth_path.len();
+
+        if depth > prefix.depth() as usize {
+            return ProofError::OutOfPath.fail().spot(here!());
+        }
+
+        // The `AuthPath` must follow `prefix`
+        if !self.auth_path.directions().eq(prefix
+            .into_iter()
+            .take(depth)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev())
+        {
+            return ProofError::OutOfPath.fail().spot(here!());
+        }
+
+        let ancestor = prefix.ancestor(prefix.depth() - depth as u8);
+
+        match self.terminal {
+            Terminal::Subtree(hash) if depth == prefix.depth() as usize => Ok(hash.into()),
+            Terminal::Subtree(_) => ProofError::OutOfPath.fail().spot(here!()),
+            Terminal::Empty => Ok(node_hash::empty()),
+            Terminal::Leaf { key, value } => {
+                // The leaf must lie along `prefix` ...
+                if !ancestor.contains(&Path::from(key)) {
+                    return ProofError::OutOfPath.fail().spot(here!());
+                }
+
+                // ... and belongs to the region only if it lies under `prefix`
+                if prefix.contains(&Path::from(key)) {
+                    Ok(node_hash::leaf(key.into(), value.into()))
+                } else {
+                    Ok(node_hash::empty())
+                }
+            }
+        }
+    }
+
+    fn terminal_digest(&self) -> Hash {
+        match self.terminal {
+            Terminal::Subtree(hash) => hash,
+            Terminal::Empty => node_hash::empty().into(),
+            Terminal::Leaf { key, value } => node_hash::leaf(key.into(), value.into()).into(),
+        }
+    }
+
+    fn verify(&self, commitment: Hash, prefix: Prefix) -> Result<Bytes, Top<ProofError>> {
+        let region = self.region(prefix)?;
+
+        self.auth_path
+            .verify(commitment, self.terminal_digest(), |left, right| {
+                node_hash::internal(left.into(), right.into()).into()
+            })
+            .pot(ProofError::RootMismatch, here!())?;
+
+        Ok(region)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::map::Map;
+
+    fn regions(depth: u8) -> Vec<Prefix> {
+        let mut regions = vec![Prefix::root()];
+
+        for _ in 0..depth {
+            regions = regions
+                .into_iter()
+                .flat_map(|prefix| vec![prefix.left(), prefix.right()])
+                .collect();
+        }
+
+        regions
+    }
+
+    #[test]
+    fn shared_subtree() {
+        let mut first: Map<u32, u32> = Map::new();
+
+        for key in 0..256 {
+            first.insert(key, key).unwrap();
+        }
+
+        let mut second = first.clone();
+
+        // Modify records only in the left half of `second`
+        let left = Prefix::root().left();
+
+        let modified = (0..256)
+            .filter(|key| {
+                left.contains(&Path::from(
+                    talk::crypto::primitives::hash::hash(key).unwrap(),
+                ))
+            })
+            .take(8)
+            .collect::<Vec<_>>();
+
+        for key in modified.iter() {
+            second.insert(*key, key + 1).unwrap();
+        }
+
+        let right = Prefix::root().right();
+
+        let proof = first.prove_region_unchanged(&second, right).unwrap();
+        proof
+            .verify(first.commit(), second.commit(), right)
+            .unwrap();
+
+        assert!(first.prove_region_unchanged(&second, left).is_none());
+
+        // The proof is bound to its region and commitments
+        assert!(proof.verify(first.commit(), second.commit(), left).is_err());
+        assert!(proof.verify(first.commit(), first.commit(), right).is_err());
+
+        // Every region under `right` is unchanged as well
+        for region in regions(4) {
+            if region[0] == Direction::Right {
+                let proof = first.prove_region_unchanged(&second, region).unwrap();
+                proof
+                    .verify(first.commit(), second.commit(), region)
+                    .unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn shallow() {
+        let mut first: Map<u32, u32> = Map::new();
+        first.insert(0, 0).unwrap();
+
+        let mut second = first.clone();
+        second.insert(1, 1).unwrap();
+
+        // `first` holds a single leaf: every deep region ends early, at the root
+        let mut unchanged = 0;
+
+        for region in regions(6) {
+            if let Some(proof) = first.prove_region_unchanged(&second, region) {
+                proof
+                    .verify(first.commit(), second.commit(), region)
+                    .unwrap();
+                unchanged += 1;
+            }
+        }
+
+        // Only the regions holding key `1` differ
+        assert_eq!(unchanged, 63);
+    }
+
+    #[test]
+    fn stub() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for key in 0..64 {
+            map.insert(key, key).unwrap();
+        }
+
+        let export = map.export::<_, u32>([]).unwrap();
+
+        assert!(map
+            .prove_region_unchanged(&export, Prefix::root().left())
+            .is_none());
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     common::store::Field,
-    map::{errors::MapError, Map},
+    map::{errors::MapError, AbsenceProof, BloomSummary, Map},
 };
 
 use doomstack::Top;
@@ -25,6 +25,42 @@ where
         Set(Map::new())
     }
 
+    /// Creates a `Set` holding the keys of `map`.
+    ///
+    /// Keys keep their position in the tree, and are not hashed again.
+    /// The commitment of the `Set` differs from that of `map` (its leaves
+    /// hold no value), but equals that of a `Set` built by [`insert`]ing
+    /// the same keys.
+    ///
+    /// # Errors
+    ///
+    /// If `map` is incomplete, i.e. it contains a `Stub`, [`BranchUnknown`]
+    /// is returned.
+    ///
+    /// [`insert`]: crate::map::Set::insert
+    /// [`BranchUnknown`]: crate::map::errors::MapError::BranchUnknown
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::{Map, Set};
+    ///
+    /// let mut map = Map::new();
+    /// map.insert(1, "a").unwrap();
+    ///
+    /// let set = Set::from_map_keys(&map).unwrap();
+    ///
+    /// assert!(set.contains(&1).unwrap());
+    /// assert!(!set.contains(&2).unwrap());
+    /// ```
+    pub fn from_map_keys<Value>(map: &Map<Item, Value>) -> Result<Self, Top<MapError>>
+    where
+        Item: Clone,
+        Value: Field,
+    {
+        Ok(Set(map.map_values(|_| ())?))
+    }
+
     pub fn root_stub(commitment: Hash) -> Self {
         Set(Map::root_stub(commitment))
     }
@@ -33,6 +69,22 @@ where
         self.0.commit()
     }
 
+    /// Returns the number of items stored locally in the `Set`
+    /// (see [`Map::len`]).
+    ///
+    /// [`Map::len`]: crate::map::Map::len
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if the `Set` stores no item locally
+    /// (see [`Map::is_empty`]).
+    ///
+    /// [`Map::is_empty`]: crate::map::Map::is_empty
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     pub fn contains(&self, item: &Item) -> Result<bool, Top<MapError>> {
         Ok(self.0.get(item)?.is_some())
     }
@@ -57,6 +109,81 @@ where
     pub fn import(&mut self, other: Set<Item>) -> Result<(), Top<MapError>> {
         self.0.import(other.0)
     }
+
+    pub fn prove_absent(&self, item: &Item) -> Result<AbsenceProof, Top<MapError>> {
+        self.0.prove_absent(item)
+    }
+
+    /// Returns a new `Set` containing the items of both `self` and `other`.
+    ///
+    /// The two `Set`s are traversed in lockstep: subtrees with matching
+    /// commitments are copied whole, without being visited.
+    ///
+    /// # Errors
+    ///
+    /// If either `Set` is missing a branch needed to combine them (see
+    /// [`export`]), [`BranchUnknown`] is returned.
+    ///
+    /// [`export`]: crate::map::Set::export
+    /// [`BranchUnknown`]: crate::map::errors::MapError::BranchUnknown
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use zebra::map::Set;
+    ///
+    /// let mut first = Set::new();
+    /// first.insert(1).unwrap();
+    ///
+    /// let mut second = Set::new();
+    /// second.insert(2).unwrap();
+    ///
+    /// let union = first.union(&second).unwrap();
+    ///
+    /// assert!(union.contains(&1).unwrap());
+    /// assert!(union.contains(&2).unwrap());
+    /// ```
+    pub fn union(&self, other: &Set<Item>) -> Result<Set<Item>, Top<MapError>>
+    where
+        Item: Clone,
+    {
+        Ok(Set(self.0.union(&other.0)?))
+    }
+
+    /// Returns a new `Set` containing the items in both `self` and `other`
+    /// (see [`union`] for details and errors).
+    ///
+    /// [`union`]: crate::map::Set::union
+    pub fn intersection(&self, other: &Set<Item>) -> Result<Set<Item>, Top<MapError>>
+    where
+        Item: Clone,
+    {
+        Ok(Set(self.0.common(&other.0)?))
+    }
+
+    /// Returns a new `Set` containing the items in `self` but not
+    /// in `other` (see [`union`] for details and errors).
+    ///
+    /// [`union`]: crate::map::Set::union
+    pub fn difference(&self, other: &Set<Item>) -> Result<Set<Item>, Top<MapError>>
+    where
+        Item: Clone,
+    {
+        Ok(Set(self.0.difference(&other.0)?))
+    }
+
+    /// Returns a [`BloomSummary`] over the items of the `Set`, using
+    /// `bits` bits (see [`Map::bloom_summary`]).
+    ///
+    /// [`BloomSummary`]: crate::map::BloomSummary
+    /// [`Map::bloom_summary`]: crate::map::Map::bloom_summary
+    pub fn bloom_summary(&self, bits: usize) -> Result<BloomSummary, Top<MapError>> {
+        self.0.bloom_summary(bits)
+    }
+
+    pub(crate) fn map(&self) -> &Map<Item, ()> {
+        &self.0
+    }
 }
 
 impl<Item> Debug for Set<Item>
@@ -67,3 +194,135 @@ where
         write!(f, "Set(commitment: {:?})", self.commit())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set<I>(items: I) -> Set<u32>
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        let mut set = Set::new();
+
+        for item in items {
+            set.insert(item).unwrap();
+        }
+
+        set
+    }
+
+    fn check(actual: Set<u32>, expected: Set<u32>) {
+        actual.map().check_tree();
+        assert_eq!(actual.commit(), expected.commit());
+        assert_eq!(actual.len(), expected.len());
+    }
+
+    #[test]
+    fn from_map_keys() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for key in (0..1024).step_by(2) {
+            map.insert(key, key + 1).unwrap();
+        }
+
+        let keys = Set::from_map_keys(&map).unwrap();
+        keys.map().check_tree();
+
+        for key in 0..1024 {
+            assert_eq!(
+                keys.contains(&key).unwrap(),
+                map.contains_key(&key).unwrap()
+            );
+        }
+
+        assert_ne!(keys.commit(), map.commit());
+        check(keys, set((0..1024).step_by(2)));
+
+        check(
+            Set::from_map_keys(&Map::<u32, u32>::new()).unwrap(),
+            Set::new(),
+        );
+
+        let export = map.export([0]).unwrap();
+
+        assert!(matches!(
+            Set::from_map_keys(&export).err().unwrap().top(),
+            MapError::BranchUnknown
+        ));
+    }
+
+    #[test]
+    fn disjoint() {
+        let first = set(0..512);
+        let second = set(512..1024);
+
+        check(first.union(&second).unwrap(), set(0..1024));
+        check(second.union(&first).unwrap(), set(0..1024));
+
+        check(first.intersection(&second).unwrap(), Set::new());
+
+        check(first.difference(&second).unwrap(), first.clone());
+        check(second.difference(&first).unwrap(), second.clone());
+    }
+
+    #[test]
+    fn overlapping() {
+        let first = set(0..768);
+        let second = set(256..1024);
+
+        check(first.union(&second).unwrap(), set(0..1024));
+        check(first.intersection(&second).unwrap(), set(256..768));
+        check(first.difference(&second).unwrap(), set(0..256));
+        check(second.difference(&first).unwrap(), set(768..1024));
+
+        // Sparse overlap, reaching leaves at different depths
+        let odd = set((0..1024).filter(|item| item % 2 == 1));
+
+        check(
+            first.union(&odd).unwrap(),
+            set((0..1024).filter(|item| *item < 768 || item % 2 == 1)),
+        );
+        check(
+            first.intersection(&odd).unwrap(),
+            set((0..768).filter(|item| item % 2 == 1)),
+        );
+        check(
+            first.difference(&odd).unwrap(),
+            set((0..768).filter(|item| item % 2 == 0)),
+        );
+    }
+
+    #[test]
+    fn identical() {
+        let first = set(0..1024);
+        let second = first.clone();
+
+        check(first.union(&second).unwrap(), set(0..1024));
+        check(first.intersection(&second).unwrap(), set(0..1024));
+        check(first.difference(&second).unwrap(), Set::new());
+    }
+
+    #[test]
+    fn empty() {
+        let first = set(0..1024);
+
+        check(first.union(&Set::new()).unwrap(), set(0..1024));
+        check(Set::new().union(&first).unwrap(), set(0..1024));
+        check(first.intersection(&Set::new()).unwrap(), Set::new());
+        check(first.difference(&Set::new()).unwrap(), set(0..1024));
+        check(Set::new().difference(&first).unwrap(), Set::new());
+    }
+
+    #[test]
+    fn stub() {
+        let first = set(0..1024);
+        let second = set(512..1536);
+
+        let export = first.export([0]).unwrap();
+
+        assert!(export.union(&second).is_err());
+        assert!(export.intersection(&second).is_err());
+        assert!(export.difference(&second).is_err());
+    }
+}
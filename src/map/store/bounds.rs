@@ -0,0 +1,227 @@
+use crate::{
+    common::store::Field,
+    hashing::Hasher,
+    map::store::{Internal, Leaf, Node, Stub},
+};
+
+use serde::{
+    de::{
+        DeserializeSeed, Deserializer, EnumAccess, Error as DeError, SeqAccess, VariantAccess,
+        Visitor,
+    },
+    Deserialize,
+};
+
+use std::{
+    cell::Cell,
+    fmt::{Formatter, Result as FmtResult},
+    marker::PhantomData,
+};
+
+// Paths are 256 bits long: no well-formed `Node` lies any deeper. Bounding
+// depth also bounds the recursion of deserialization, so that a malicious
+// encoding cannot overflow the stack.
+pub(crate) const MAX_DEPTH: usize = 256;
+
+const NODE_VARIANTS: &[&str] = &["Empty", "Internal", "Leaf", "Stub"];
+const CHILDREN_FIELDS: &[&str] = &["left", "right"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Violation {
+    TooManyRecords,
+    TooDeep,
+}
+
+/// Limits enforced while a `Node` is being deserialized, so that
+/// oversized trees are rejected before being fully allocated.
+pub(crate) struct Bounds {
+    max_records: usize,
+    max_depth: usize,
+    records: Cell<usize>,
+    violation: Cell<Option<Violation>>,
+}
+
+pub(crate) struct NodeSeed<'b, Key: Field, Value: Field, H: Hasher> {
+    bounds: &'b Bounds,
+    depth: usize,
+    _fields: PhantomData<(Key, Value, H)>,
+}
+
+struct ChildrenSeed<'b, Key: Field, Value: Field, H: Hasher> {
+    bounds: &'b Bounds,
+    depth: usize,
+    _fields: PhantomData<(Key, Value, H)>,
+}
+
+#[derive(Deserialize)]
+#[serde(variant_identifier)]
+enum Tag {
+    Empty,
+    Internal,
+    Leaf,
+    Stub,
+}
+
+impl Bounds {
+    pub fn new(max_records: usize, max_depth: usize) -> Self {
+        Bounds {
+            max_records,
+            max_depth,
+            records: Cell::new(0),
+            violation: Cell::new(None),
+        }
+    }
+
+    pub fn seed<Key, Value, H>(&self) -> NodeSeed<'_, Key, Value, H>
+    where
+        Key: Field,
+        Value: Field,
+        H: Hasher,
+    {
+        NodeSeed {
+            bounds: self,
+            depth: 0,
+            _fields: PhantomData,
+        }
+    }
+
+    pub fn violation(&self) -> Option<Violation> {
+        self.violation.get()
+    }
+
+    fn violate<E>(&self, violation: Violation) -> E
+    where
+        E: DeError,
+    {
+        self.violation.set(Some(violation));
+
+        match violation {
+            Violation::TooManyRecords => E::custom("map exceeds record limit"),
+            Violation::TooDeep => E::custom("map exceeds depth limit"),
+        }
+    }
+}
+
+impl<'b, 'de, Key, Value, H> DeserializeSeed<'de> for NodeSeed<'b, Key, Value, H>
+where
+    Key: Field + Deserialize<'de>,
+    Value: Field + Deserialize<'de>,
+    H: Hasher,
+{
+    type Value = Node<Key, Value, H>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Checked before reading: a node that is too deep is rejected
+        // without deserializing any of its contents
+        if self.depth > self.bounds.max_depth {
+            return Err(self.bounds.violate(Violation::TooDeep));
+        }
+
+        deserializer.deserialize_enum("Node", NODE_VARIANTS, self)
+    }
+}
+
+impl<'b, 'de, Key, Value, H> Visitor<'de> for NodeSeed<'b, Key, Value, H>
+where
+    Key: Field + Deserialize<'de>,
+    Value: Field + Deserialize<'de>,
+    H: Hasher,
+{
+    type Value = Node<Key, Value, H>;
+
+    fn expecting(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str("enum Node")
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        let (tag, variant) = data.variant::<Tag>()?;
+
+        match tag {
+            Tag::Empty => {
+                variant.unit_variant()?;
+                Ok(Node::Empty)
+            }
+            Tag::Internal => {
+                let internal = variant.newtype_variant_seed(ChildrenSeed {
+                    bounds: self.bounds,
+                    depth: self.depth,
+                    _fields: PhantomData,
+                })?;
+
+                Ok(Node::Internal(internal))
+            }
+            Tag::Leaf => {
+                let records = self.bounds.records.get() + 1;
+
+                if records > self.bounds.max_records {
+                    return Err(self.bounds.violate(Violation::TooManyRecords));
+                }
+
+                self.bounds.records.set(records);
+
+                let leaf = variant.newtype_variant::<Leaf<Key, Value, H>>()?; // Deserializes and computes leaf hash
+                Ok(Node::Leaf(leaf))
+            }
+            Tag::Stub => {
+                let stub = variant.newtype_variant::<Stub>()?;
+                Ok(Node::Stub(stub))
+            }
+        }
+    }
+}
+
+impl<'b, 'de, Key, Value, H> DeserializeSeed<'de> for ChildrenSeed<'b, Key, Value, H>
+where
+    Key: Field + Deserialize<'de>,
+    Value: Field + Deserialize<'de>,
+    H: Hasher,
+{
+    type Value = Internal<Key, Value, H>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("Children", CHILDREN_FIELDS, self)
+    }
+}
+
+impl<'b, 'de, Key, Value, H> Visitor<'de> for ChildrenSeed<'b, Key, Value, H>
+where
+    Key: Field + Deserialize<'de>,
+    Value: Field + Deserialize<'de>,
+    H: Hasher,
+{
+    type Value = Internal<Key, Value, H>;
+
+    fn expecting(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str("struct Children")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let child = || NodeSeed {
+            bounds: self.bounds,
+            depth: self.depth + 1,
+            _fields: PhantomData,
+        };
+
+        let left = seq
+            .next_element_seed(child())?
+            .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+
+        let right = seq
+            .next_element_seed(child())?
+            .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+
+        Ok(Internal::new(left, right)) // Computes internal hash
+    }
+}
@@ -3,6 +3,7 @@ use crate::{
         store::Field,
         tree::{Path, Prefix},
     },
+    hashing::Hasher,
     map::{
         errors::TopologyError,
         store::{Internal, Leaf, Node},
@@ -11,10 +12,13 @@ use crate::{
 
 use doomstack::{here, Doom, ResultExt, Top};
 
-fn check_internal<Key, Value>(internal: &Internal<Key, Value>) -> Result<(), Top<TopologyError>>
+fn check_internal<Key, Value, H>(
+    internal: &Internal<Key, Value, H>,
+) -> Result<(), Top<TopologyError>>
 where
     Key: Field,
     Value: Field,
+    H: Hasher,
 {
     match (internal.left(), internal.right()) {
         (Node::Empty, Node::Empty)
@@ -24,13 +28,14 @@ where
     }
 }
 
-fn check_leaf<Key, Value>(
-    leaf: &Leaf<Key, Value>,
+fn check_leaf<Key, Value, H>(
+    leaf: &Leaf<Key, Value, H>,
     location: Prefix,
 ) -> Result<(), Top<TopologyError>>
 where
     Key: Field,
     Value: Field,
+    H: Hasher,
 {
     if !location.contains(&Path::from(leaf.key().digest())) {
         TopologyError::PathViolation.fail().spot(here!())
@@ -39,13 +44,14 @@ where
     }
 }
 
-fn recursion<Key, Value>(
-    node: &Node<Key, Value>,
+fn recursion<Key, Value, H>(
+    node: &Node<Key, Value, H>,
     location: Prefix,
 ) -> Result<(), Top<TopologyError>>
 where
     Key: Field,
     Value: Field,
+    H: Hasher,
 {
     match node {
         Node::Internal(internal) => {
@@ -59,10 +65,11 @@ where
     }
 }
 
-pub(crate) fn check<Key, Value>(node: &Node<Key, Value>) -> Result<(), Top<TopologyError>>
+pub(crate) fn check<Key, Value, H>(node: &Node<Key, Value, H>) -> Result<(), Top<TopologyError>>
 where
     Key: Field,
     Value: Field,
+    H: Hasher,
 {
     recursion(&node, Prefix::root())
 }
@@ -1,9 +1,11 @@
 #![allow(dead_code)] // TODO: Remove this attribute, make sure there is no dead code.
 
+mod bounds;
 mod check;
 mod node;
 mod wrap;
 
+pub(crate) use bounds::{Bounds, Violation, MAX_DEPTH};
 pub(crate) use check::check;
-pub(crate) use node::{Internal, Leaf, Node};
+pub(crate) use node::{Internal, Leaf, Node, Stub};
 pub(crate) use wrap::Wrap;
@@ -3,41 +3,54 @@ use crate::{
         data::Bytes,
         store::{hash, Field},
     },
+    hashing::{Blake3Hasher, Hasher},
     map::store::Wrap,
 };
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 #[derive(Clone, Serialize, Deserialize)]
-pub(crate) enum Node<Key: Field, Value: Field> {
+#[serde(bound(
+    serialize = "",
+    deserialize = "Key: Deserialize<'de>, Value: Deserialize<'de>"
+))]
+pub(crate) enum Node<Key: Field, Value: Field, H: Hasher = Blake3Hasher> {
     Empty,
-    Internal(Internal<Key, Value>),
-    Leaf(Leaf<Key, Value>),
+    Internal(Internal<Key, Value, H>),
+    Leaf(Leaf<Key, Value, H>),
     Stub(Stub),
 }
 
 #[derive(Clone)]
-pub(crate) struct Internal<Key: Field, Value: Field> {
+pub(crate) struct Internal<Key: Field, Value: Field, H: Hasher = Blake3Hasher> {
     hash: Bytes,
-    children: Children<Key, Value>,
+    children: Children<Key, Value, H>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
-struct Children<Key: Field, Value: Field> {
-    left: Box<Node<Key, Value>>,
-    right: Box<Node<Key, Value>>,
+#[serde(bound(
+    serialize = "",
+    deserialize = "Key: Deserialize<'de>, Value: Deserialize<'de>"
+))]
+struct Children<Key: Field, Value: Field, H: Hasher> {
+    left: Box<Node<Key, Value, H>>,
+    right: Box<Node<Key, Value, H>>,
 }
 
 #[derive(Clone)]
-pub(crate) struct Leaf<Key: Field, Value: Field> {
+pub(crate) struct Leaf<Key: Field, Value: Field, H: Hasher = Blake3Hasher> {
     hash: Bytes,
-    fields: Fields<Key, Value>,
+    fields: Fields<Key, Value, H>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
-struct Fields<Key: Field, Value: Field> {
-    key: Wrap<Key>,
-    value: Wrap<Value>,
+#[serde(bound(
+    serialize = "",
+    deserialize = "Key: Deserialize<'de>, Value: Deserialize<'de>"
+))]
+struct Fields<Key: Field, Value: Field, H: Hasher> {
+    key: Wrap<Key, H>,
+    value: Wrap<Value, H>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -45,16 +58,17 @@ pub(crate) struct Stub {
     hash: Bytes,
 }
 
-impl<Key, Value> Node<Key, Value>
+impl<Key, Value, H> Node<Key, Value, H>
 where
     Key: Field,
     Value: Field,
+    H: Hasher,
 {
-    pub fn internal(left: Node<Key, Value>, right: Node<Key, Value>) -> Self {
+    pub fn internal(left: Node<Key, Value, H>, right: Node<Key, Value, H>) -> Self {
         Node::Internal(Internal::new(left, right))
     }
 
-    pub fn leaf(key: Wrap<Key>, value: Wrap<Value>) -> Self {
+    pub fn leaf(key: Wrap<Key, H>, value: Wrap<Value, H>) -> Self {
         Node::Leaf(Leaf::new(key, value))
     }
 
@@ -64,7 +78,7 @@ where
 
     pub fn hash(&self) -> Bytes {
         match self {
-            Node::Empty => hash::empty(),
+            Node::Empty => hash::empty_with::<H>(),
             Node::Internal(internal) => internal.hash(),
             Node::Leaf(leaf) => leaf.hash(),
             Node::Stub(stub) => stub.hash(),
@@ -100,24 +114,25 @@ where
     }
 }
 
-impl<Key, Value> Internal<Key, Value>
+impl<Key, Value, H> Internal<Key, Value, H>
 where
     Key: Field,
     Value: Field,
+    H: Hasher,
 {
-    pub fn new(left: Node<Key, Value>, right: Node<Key, Value>) -> Self {
+    pub fn new(left: Node<Key, Value, H>, right: Node<Key, Value, H>) -> Self {
         Internal::from_children(Children {
             left: Box::new(left),
             right: Box::new(right),
         })
     }
 
-    fn from_children(children: Children<Key, Value>) -> Self {
-        let hash = hash::internal(children.left.hash(), children.right.hash());
+    fn from_children(children: Children<Key, Value, H>) -> Self {
+        let hash = hash::internal_with::<H>(children.left.hash(), children.right.hash());
         Internal { hash, children }
     }
 
-    pub(crate) fn raw(hash: Bytes, left: Node<Key, Value>, right: Node<Key, Value>) -> Self {
+    pub(crate) fn raw(hash: Bytes, left: Node<Key, Value, H>, right: Node<Key, Value, H>) -> Self {
         Internal {
             hash,
             children: Children {
@@ -131,42 +146,43 @@ where
         self.hash
     }
 
-    pub fn children(self) -> (Node<Key, Value>, Node<Key, Value>) {
+    pub fn children(self) -> (Node<Key, Value, H>, Node<Key, Value, H>) {
         (*self.children.left, *self.children.right)
     }
 
-    pub fn left(&self) -> &Node<Key, Value> {
+    pub fn left(&self) -> &Node<Key, Value, H> {
         &*self.children.left
     }
 
-    pub fn left_mut(&mut self) -> &mut Node<Key, Value> {
+    pub fn left_mut(&mut self) -> &mut Node<Key, Value, H> {
         &mut *self.children.left
     }
 
-    pub fn right(&self) -> &Node<Key, Value> {
+    pub fn right(&self) -> &Node<Key, Value, H> {
         &*self.children.right
     }
 
-    pub fn right_mut(&mut self) -> &mut Node<Key, Value> {
+    pub fn right_mut(&mut self) -> &mut Node<Key, Value, H> {
         &mut *self.children.right
     }
 }
 
-impl<Key, Value> Leaf<Key, Value>
+impl<Key, Value, H> Leaf<Key, Value, H>
 where
     Key: Field,
     Value: Field,
+    H: Hasher,
 {
-    pub fn new(key: Wrap<Key>, value: Wrap<Value>) -> Self {
+    pub fn new(key: Wrap<Key, H>, value: Wrap<Value, H>) -> Self {
         Leaf::from_fields(Fields { key, value })
     }
 
-    fn from_fields(fields: Fields<Key, Value>) -> Self {
-        let hash = hash::leaf(fields.key.digest(), fields.value.digest());
+    fn from_fields(fields: Fields<Key, Value, H>) -> Self {
+        let hash = hash::leaf_with::<H>(fields.key.digest(), fields.value.digest());
         Leaf { hash, fields }
     }
 
-    pub(crate) fn raw(hash: Bytes, key: Wrap<Key>, value: Wrap<Value>) -> Self {
+    pub(crate) fn raw(hash: Bytes, key: Wrap<Key, H>, value: Wrap<Value, H>) -> Self {
         Leaf {
             hash: hash,
             fields: Fields { key, value },
@@ -177,15 +193,15 @@ where
         self.hash
     }
 
-    pub fn fields(self) -> (Wrap<Key>, Wrap<Value>) {
+    pub fn fields(self) -> (Wrap<Key, H>, Wrap<Value, H>) {
         (self.fields.key, self.fields.value)
     }
 
-    pub fn key(&self) -> &Wrap<Key> {
+    pub fn key(&self) -> &Wrap<Key, H> {
         &self.fields.key
     }
 
-    pub fn value(&self) -> &Wrap<Value> {
+    pub fn value(&self) -> &Wrap<Value, H> {
         &self.fields.value
     }
 }
@@ -200,10 +216,11 @@ impl Stub {
     }
 }
 
-impl<Key, Value> Serialize for Internal<Key, Value>
+impl<Key, Value, H> Serialize for Internal<Key, Value, H>
 where
     Key: Field,
     Value: Field,
+    H: Hasher,
 {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -213,10 +230,11 @@ where
     }
 }
 
-impl<'de, Key, Value> Deserialize<'de> for Internal<Key, Value>
+impl<'de, Key, Value, H> Deserialize<'de> for Internal<Key, Value, H>
 where
     Key: Field + Deserialize<'de>,
     Value: Field + Deserialize<'de>,
+    H: Hasher,
 {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -227,10 +245,11 @@ where
     }
 }
 
-impl<Key, Value> Serialize for Leaf<Key, Value>
+impl<Key, Value, H> Serialize for Leaf<Key, Value, H>
 where
     Key: Field,
     Value: Field,
+    H: Hasher,
 {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -240,10 +259,11 @@ where
     }
 }
 
-impl<'de, Key, Value> Deserialize<'de> for Leaf<Key, Value>
+impl<'de, Key, Value, H> Deserialize<'de> for Leaf<Key, Value, H>
 where
     Key: Field + Deserialize<'de>,
     Value: Field + Deserialize<'de>,
+    H: Hasher,
 {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
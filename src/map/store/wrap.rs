@@ -1,30 +1,36 @@
-use crate::common::{data::Bytes, store::Field};
+use crate::{
+    common::{data::Bytes, store::Field},
+    hashing::{Blake3Hasher, Hasher, HasherError},
+};
 
 use doomstack::Top;
 
 use serde::{de::Error as DeError, Deserialize, Deserializer, Serialize, Serializer};
 
-use talk::crypto::primitives::{hash, hash::HashError};
+use std::marker::PhantomData;
 
 #[derive(Debug, Clone)]
-pub(crate) struct Wrap<Inner: Field> {
+pub(crate) struct Wrap<Inner: Field, H: Hasher = Blake3Hasher> {
     digest: Bytes,
     inner: Inner,
+    _hasher: PhantomData<H>,
 }
 
-impl<Inner> Wrap<Inner>
+impl<Inner, H> Wrap<Inner, H>
 where
     Inner: Field,
+    H: Hasher,
 {
-    pub fn new(inner: Inner) -> Result<Self, Top<HashError>> {
-        Ok(Wrap {
-            digest: hash::hash(&inner)?.into(),
-            inner,
-        })
+    pub fn new(inner: Inner) -> Result<Self, Top<HasherError>> {
+        Ok(Wrap::raw(H::hash(&inner)?.into(), inner))
     }
 
     pub fn raw(digest: Bytes, inner: Inner) -> Self {
-        Wrap { digest, inner }
+        Wrap {
+            digest,
+            inner,
+            _hasher: PhantomData,
+        }
     }
 
     pub fn take(self) -> Inner {
@@ -40,20 +46,27 @@ where
     }
 }
 
-impl<Inner> PartialEq for Wrap<Inner>
+impl<Inner, H> PartialEq for Wrap<Inner, H>
 where
     Inner: Field,
+    H: Hasher,
 {
-    fn eq(&self, rho: &Wrap<Inner>) -> bool {
+    fn eq(&self, rho: &Wrap<Inner, H>) -> bool {
         self.digest == rho.digest
     }
 }
 
-impl<Inner> Eq for Wrap<Inner> where Inner: Field {}
+impl<Inner, H> Eq for Wrap<Inner, H>
+where
+    Inner: Field,
+    H: Hasher,
+{
+}
 
-impl<Inner> Serialize for Wrap<Inner>
+impl<Inner, H> Serialize for Wrap<Inner, H>
 where
     Inner: Field,
+    H: Hasher,
 {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -63,9 +76,10 @@ where
     }
 }
 
-impl<'de, Inner> Deserialize<'de> for Wrap<Inner>
+impl<'de, Inner, H> Deserialize<'de> for Wrap<Inner, H>
 where
     Inner: Field + Deserialize<'de>,
+    H: Hasher,
 {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
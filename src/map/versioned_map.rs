@@ -0,0 +1,280 @@
+use crate::{
+    common::{data::Bytes, store::Field},
+    map::{errors::MapError, Map},
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+use serde::{Deserialize, Serialize};
+
+use talk::crypto::primitives::hash::{self, Hash};
+
+/// A [`Map`] whose values carry a timestamp, for last-writer-wins
+/// replication: replicas that [`insert`] and [`merge`] in any order
+/// converge to the same records (and commitment).
+///
+/// Conflicting values for the same key are resolved in favour of the
+/// higher timestamp. Values with the same timestamp are resolved
+/// in favour of the higher digest, so that resolution never depends
+/// on which value was seen first.
+///
+/// [`Map`]: crate::map::Map
+/// [`insert`]: crate::map::VersionedMap::insert
+/// [`merge`]: crate::map::VersionedMap::merge
+///
+/// # Examples
+///
+/// ```
+/// use zebra::map::VersionedMap;
+///
+/// let mut alice = VersionedMap::new();
+/// alice.insert("x", 1, 10).unwrap();
+///
+/// let mut bob = VersionedMap::new();
+/// bob.insert("x", 2, 20).unwrap();
+///
+/// let mut merged = alice.clone();
+/// merged.merge(&bob).unwrap();
+///
+/// bob.merge(&alice).unwrap();
+///
+/// assert_eq!(merged.get(&"x").unwrap(), Some((&2, 20)));
+/// assert_eq!(merged.commit(), bob.commit());
+/// ```
+#[derive(Clone, Serialize, Deserialize)]
+pub struct VersionedMap<Key: Field, Value: Field>(Map<Key, (Value, u64)>);
+
+impl<Key, Value> VersionedMap<Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    pub fn new() -> Self {
+        VersionedMap(Map::new())
+    }
+
+    pub fn commit(&self) -> Hash {
+        self.0.commit()
+    }
+
+    /// Returns the number of records stored locally in the `VersionedMap`
+    /// (see [`Map::len`]).
+    ///
+    /// [`Map::len`]: crate::map::Map::len
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if the `VersionedMap` stores no record locally
+    /// (see [`Map::is_empty`]).
+    ///
+    /// [`Map::is_empty`]: crate::map::Map::is_empty
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the value associated with `key`, along with its timestamp.
+    pub fn get(&self, key: &Key) -> Result<Option<(&Value, u64)>, Top<MapError>> {
+        Ok(self
+            .0
+            .get(key)?
+            .map(|(value, timestamp)| (value, *timestamp)))
+    }
+
+    /// Associates `value` with `key` at `timestamp`, unless `key` is
+    /// already associated with a value that wins over `value` (see
+    /// [`VersionedMap`]). Returns `true` if `value` was inserted.
+    ///
+    /// # Errors
+    ///
+    /// If the portion of the `VersionedMap` pertaining to `key` is
+    /// incomplete, [`BranchUnknown`] is returned.
+    ///
+    /// If `key` cannot be hashed, [`KeyHashError`] is returned. If `value`
+    /// cannot be hashed, [`HashError`] is returned.
+    ///
+    /// [`VersionedMap`]: crate::map::VersionedMap
+    /// [`BranchUnknown`]: crate::map::errors::MapError::BranchUnknown
+    /// [`KeyHashError`]: crate::map::errors::MapError::KeyHashError
+    /// [`HashError`]: crate::map::errors::MapError::HashError
+    pub fn insert(
+        &mut self,
+        key: Key,
+        value: Value,
+        timestamp: u64,
+    ) -> Result<bool, Top<MapError>> {
+        let wins = match self.0.get(&key)? {
+            Some((current, current_timestamp)) => {
+                (timestamp, digest(&value)?) > (*current_timestamp, digest(current)?)
+            }
+            None => true,
+        };
+
+        if wins {
+            self.0.insert(key, (value, timestamp))?;
+        }
+
+        Ok(wins)
+    }
+
+    /// Merges the records of `other` into `self`, resolving conflicts as
+    /// in [`insert`]. Merging is commutative and idempotent: replicas
+    /// that merge the same `VersionedMap`s, in any order, end up with
+    /// the same commitment.
+    ///
+    /// # Errors
+    ///
+    /// If `other` is not complete (see [`Map::is_complete`]), or `self`
+    /// is missing a branch needed to merge a record, [`BranchUnknown`]
+    /// is returned.
+    ///
+    /// [`insert`]: crate::map::VersionedMap::insert
+    /// [`Map::is_complete`]: crate::map::Map::is_complete
+    /// [`BranchUnknown`]: crate::map::errors::MapError::BranchUnknown
+    pub fn merge(&mut self, other: &VersionedMap<Key, Value>) -> Result<(), Top<MapError>>
+    where
+        Key: Clone,
+        Value: Clone,
+    {
+        if !other.0.is_complete() {
+            return MapError::BranchUnknown.fail().spot(here!());
+        }
+
+        if self.commit() == other.commit() {
+            return Ok(());
+        }
+
+        for (key, (value, timestamp)) in other.0.iter() {
+            self.insert(key.clone(), value.clone(), *timestamp)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn map(&self) -> &Map<Key, (Value, u64)> {
+        &self.0
+    }
+}
+
+impl<Key, Value> Default for VersionedMap<Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    fn default() -> Self {
+        VersionedMap::new()
+    }
+}
+
+fn digest<Value>(value: &Value) -> Result<Bytes, Top<MapError>>
+where
+    Value: Field,
+{
+    Ok(hash::hash(value).pot(MapError::HashError, here!())?.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versioned<I>(records: I) -> VersionedMap<u32, u32>
+    where
+        I: IntoIterator<Item = (u32, u32, u64)>,
+    {
+        let mut map = VersionedMap::new();
+
+        for (key, value, timestamp) in records {
+            map.insert(key, value, timestamp).unwrap();
+        }
+
+        map
+    }
+
+    #[test]
+    fn last_writer_wins() {
+        let mut map = VersionedMap::new();
+
+        assert!(map.insert(0, 0, 10).unwrap());
+        assert!(!map.insert(0, 1, 5).unwrap());
+        assert_eq!(map.get(&0).unwrap(), Some((&0, 10)));
+
+        assert!(map.insert(0, 2, 20).unwrap());
+        assert_eq!(map.get(&0).unwrap(), Some((&2, 20)));
+
+        // Ties are broken the same way regardless of order
+        let mut first = versioned([(0, 3, 30)]);
+        first.insert(0, 4, 30).unwrap();
+
+        let mut second = versioned([(0, 4, 30)]);
+        second.insert(0, 3, 30).unwrap();
+
+        assert_eq!(first.get(&0).unwrap(), second.get(&0).unwrap());
+        assert_eq!(first.commit(), second.commit());
+    }
+
+    #[test]
+    fn merge_commutative() {
+        // Overlapping keys, conflicting timestamps (including ties)
+        let alice = versioned((0..512).map(|key| (key, key, (key % 4) as u64)));
+        let bob = versioned((256..768).map(|key| (key, key + 1, (key % 3) as u64)));
+
+        let mut alice_bob = alice.clone();
+        alice_bob.merge(&bob).unwrap();
+
+        let mut bob_alice = bob.clone();
+        bob_alice.merge(&alice).unwrap();
+
+        alice_bob.map().check_tree();
+
+        assert_eq!(alice_bob.commit(), bob_alice.commit());
+        assert_eq!(alice_bob.len(), 768);
+
+        // Ties are covered by `last_writer_wins`
+        for key in (256..512).filter(|key| key % 4 != key % 3) {
+            let expected = if key % 4 > key % 3 {
+                (key, (key % 4) as u64)
+            } else {
+                (key + 1, (key % 3) as u64)
+            };
+
+            assert_eq!(
+                alice_bob.get(&key).unwrap(),
+                Some((&expected.0, expected.1))
+            );
+        }
+    }
+
+    #[test]
+    fn merge_idempotent() {
+        let alice = versioned((0..256).map(|key| (key, key, 1)));
+        let bob = versioned((128..384).map(|key| (key, key + 1, 2)));
+
+        let mut merged = alice.clone();
+        merged.merge(&bob).unwrap();
+
+        let commit = merged.commit();
+
+        merged.merge(&bob).unwrap();
+        assert_eq!(merged.commit(), commit);
+
+        let snapshot = merged.clone();
+        merged.merge(&snapshot).unwrap();
+        assert_eq!(merged.commit(), commit);
+
+        merged.merge(&alice).unwrap();
+        assert_eq!(merged.commit(), commit);
+    }
+
+    #[test]
+    fn merge_incomplete() {
+        let mut alice = versioned((0..256).map(|key| (key, key, 1)));
+        let bob = versioned((128..384).map(|key| (key, key, 2)));
+
+        let bob = VersionedMap(bob.map().export([128]).unwrap());
+
+        assert!(matches!(
+            alice.merge(&bob).unwrap_err().top(),
+            MapError::BranchUnknown
+        ));
+    }
+}
@@ -0,0 +1,456 @@
+use crate::vector::{errors::ProofError, node, Node};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+use serde::{Deserialize, Serialize};
+
+use serde_bytes::ByteBuf;
+
+use std::collections::BTreeMap;
+
+use talk::crypto::primitives::{hash, hash::Hash};
+
+/// A proof that a batch of items is stored at given indices of a [`Vector`],
+/// obtained through [`Vector::prove_many`].
+///
+/// Unlike a collection of individual [`Proof`]s, a `BatchProof` carries
+/// every sibling digest at most once, and omits those that the verifier
+/// can compute from the proven items: the more the proven indices share
+/// ancestors, the smaller the proof.
+///
+/// [`Vector`]: crate::vector::Vector
+/// [`Vector::prove_many`]: crate::vector::Vector::prove_many
+/// [`Proof`]: crate::vector::Proof
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchProof {
+    len: usize,
+    packing: usize,
+    indices: Vec<usize>,
+    siblings: Vec<ByteBuf>,
+    hashes: Vec<Hash>,
+}
+
+impl BatchProof {
+    pub(in crate::vector) fn new<Item: Serialize>(
+        len: usize,
+        packing: usize,
+        indices: Vec<usize>,
+        siblings: Vec<&Item>,
+        hashes: Vec<Hash>,
+    ) -> Self {
+        let siblings = siblings
+            .into_iter()
+            .map(|item| ByteBuf::from(bincode::serialize(item).unwrap()))
+            .collect();
+
+        BatchProof {
+            len,
+            packing,
+            indices,
+            siblings,
+            hashes,
+        }
+    }
+
+    /// Returns the length of the [`Vector`] the `BatchProof` was generated
+    /// from (see [`Proof::len`]).
+    ///
+    /// [`Vector`]: crate::vector::Vector
+    /// [`Proof::len`]: crate::vector::Proof::len
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the `BatchProof` was generated from an empty
+    /// [`Vector`]. As a [`Vector`] cannot be empty, this is only possible
+    /// for a malformed (e.g., deserialized) `BatchProof`.
+    ///
+    /// [`Vector`]: crate::vector::Vector
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the proven indices, sorted and without duplicates.
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// Verifies that each of `items` is stored at its index of a [`Vector`]
+    /// of [`len`] items whose root is `root`.
+    ///
+    /// `items` must cover exactly the [`indices`] of the proof, in any order.
+    /// An index can be repeated, provided that it is always paired with the
+    /// same item. A proof for no index is trivially verified.
+    ///
+    /// # Errors
+    ///
+    /// If the same index is paired with different items, [`ItemMismatch`]
+    /// is returned.
+    ///
+    /// If `items` does not cover exactly the indices of the proof, or the
+    /// proof is malformed, [`OutOfPath`] is returned.
+    ///
+    /// If an item cannot be hashed, [`HashError`] is returned.
+    ///
+    /// If the proof does not lead from `items` to `root`, [`RootMismatch`]
+    /// is returned.
+    ///
+    /// [`Vector`]: crate::vector::Vector
+    /// [`len`]: crate::vector::BatchProof::len
+    /// [`indices`]: crate::vector::BatchProof::indices
+    /// [`ItemMismatch`]: crate::vector::errors::ProofError::ItemMismatch
+    /// [`OutOfPath`]: crate::vector::errors::ProofError::OutOfPath
+    /// [`HashError`]: crate::vector::errors::ProofError::HashError
+    /// [`RootMismatch`]: crate::vector::errors::ProofError::RootMismatch
+    pub fn verify<Item: Serialize + for<'de> Deserialize<'de>>(
+        &self,
+        root: Hash,
+        items: &[(usize, Item)],
+    ) -> Result<(), Top<ProofError>> {
+        let mut claimed: BTreeMap<usize, (&Item, Hash)> = BTreeMap::new();
+
+        for (index, item) in items {
+            let digest = hash::hash(item).pot(ProofError::HashError, here!())?;
+
+            if let Some((_, previous)) = claimed.insert(*index, (item, digest)) {
+                if previous != digest {
+                    return ProofError::ItemMismatch.fail().spot(here!());
+                }
+            }
+        }
+
+        if !claimed.keys().eq(self.indices.iter()) {
+            return ProofError::OutOfPath.fail().spot(here!());
+        }
+
+        if self.indices.is_empty() {
+            return Ok(());
+        }
+
+        // `claimed` is sorted and deduplicated, hence so are `self.indices`
+        if self.packing == 0 || *self.indices.last().unwrap() >= self.len {
+            return ProofError::OutOfPath.fail().spot(here!());
+        }
+
+        let mut siblings = self.siblings.iter();
+        let mut leaves = Vec::new();
+
+        let mut claimed = claimed.into_iter().peekable();
+
+        while let Some((index, _)) = claimed.peek() {
+            let chunk = index / self.packing;
+
+            let start = chunk * self.packing;
+            let end = std::cmp::min(start + self.packing, self.len);
+
+            let mut owned = Vec::new();
+            let mut chunk_items = Vec::new();
+
+            for index in start..end {
+                match claimed.next_if(|(next, _)| *next == index) {
+                    Some((_, (item, _))) => chunk_items.push(Some(item)),
+                    None => {
+                        let sibling = siblings
+                            .next()
+                            .ok_or_else(|| ProofError::OutOfPath.into_top())
+                            .spot(here!())?;
+
+                        let sibling = bincode::deserialize::<Item>(sibling.as_ref())
+                            .map_err(|_| ProofError::ItemMismatch.into_top())
+                            .spot(here!())?;
+
+                        owned.push(sibling);
+                        chunk_items.push(None);
+                    }
+                }
+            }
+
+            let mut owned = owned.iter();
+
+            let chunk_items = chunk_items
+                .into_iter()
+                .map(|item| item.unwrap_or_else(|| owned.next().unwrap()))
+                .collect::<Vec<&Item>>();
+
+            let leaf = if self.packing == 1 {
+                hash::hash(&Node::<&Item>::Item(chunk_items[0]))
+            } else {
+                hash::hash(&Node::<&[&Item]>::Item(chunk_items.as_slice()))
+            }
+            .pot(ProofError::HashError, here!())?;
+
+            leaves.push((chunk, leaf));
+        }
+
+        if siblings.next().is_some() {
+            return ProofError::OutOfPath.fail().spot(here!());
+        }
+
+        let mut hashes = self.hashes.iter().copied();
+
+        let computed = climb(self.len.div_ceil(self.packing), leaves, |_, _| {
+            hashes.next()
+        })
+        .ok_or_else(|| ProofError::OutOfPath.into_top())
+        .spot(here!())?;
+
+        if hashes.next().is_some() {
+            return ProofError::OutOfPath.fail().spot(here!());
+        }
+
+        if computed != root {
+            return ProofError::RootMismatch.fail().spot(here!());
+        }
+
+        Ok(())
+    }
+}
+
+// Returns the length of the (partial) bottom layer of a `Vector` with
+// `chunks` leaves, along with the length of each of its layers, bottom
+// to top (see `Vector::from_leaves`)
+fn layout(chunks: usize) -> (usize, Vec<usize>) {
+    let pow = std::cmp::max(
+        1,
+        chunks.checked_next_power_of_two().unwrap_or(usize::MAX) / 2,
+    );
+
+    let bottom = std::cmp::max(1, 2 * (chunks - pow));
+
+    let mut sizes = Vec::new();
+
+    let mut size = if chunks == bottom {
+        chunks
+    } else {
+        sizes.push(bottom);
+        pow
+    };
+
+    loop {
+        sizes.push(size);
+
+        if size == 1 {
+            break;
+        }
+
+        size /= 2;
+    }
+
+    (bottom, sizes)
+}
+
+// Folds `leaves` (each a chunk index and its digest, sorted by chunk index)
+// up to the root of a `Vector` with `chunks` leaves. The digest of every
+// sibling that cannot be computed from `leaves` is obtained from `sibling`,
+// given its layer and position, in the order a `BatchProof` stores them.
+// Returns `None` if `sibling` does, or if `leaves` is empty.
+pub(in crate::vector) fn climb<F>(
+    chunks: usize,
+    leaves: Vec<(usize, Hash)>,
+    mut sibling: F,
+) -> Option<Hash>
+where
+    F: FnMut(usize, usize) -> Option<Hash>,
+{
+    let (bottom, sizes) = layout(chunks);
+
+    // Leaves past the bottom layer lie one layer above
+    let (known, lifted): (Vec<_>, Vec<_>) =
+        leaves.into_iter().partition(|(chunk, _)| *chunk < bottom);
+
+    let mut known = known.into_iter().collect::<BTreeMap<_, _>>();
+
+    let mut lifted = Some(lifted);
+
+    for (layer, size) in sizes.into_iter().enumerate() {
+        if size == 1 {
+            break;
+        }
+
+        let mut parents = BTreeMap::new();
+        let mut nodes = known.into_iter().peekable();
+
+        while let Some((position, digest)) = nodes.next() {
+            let (left, right) = if position % 2 == 0 {
+                let right = match nodes.next_if(|(next, _)| *next == position + 1) {
+                    Some((_, right)) => right,
+                    None => sibling(layer, position + 1)?,
+                };
+
+                (digest, right)
+            } else {
+                (sibling(layer, position - 1)?, digest)
+            };
+
+            parents.insert(position / 2, node::hash_internal(left, right));
+        }
+
+        if let Some(lifted) = lifted.take() {
+            parents.extend(
+                lifted
+                    .into_iter()
+                    .map(|(chunk, digest)| (chunk - bottom / 2, digest)),
+            );
+        }
+
+        known = parents;
+    }
+
+    known.remove(&0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vector::{errors::ProofError, Vector};
+
+    #[test]
+    fn all_indices() {
+        for len in [1usize, 2, 3, 5, 8, 13, 37, 64] {
+            let vector = Vector::<_>::new((0..len as u32).collect()).unwrap();
+            let indices = (0..len).collect::<Vec<_>>();
+
+            let proof = vector.prove_many(&indices).unwrap();
+            let items = (0..len)
+                .map(|index| (index, index as u32))
+                .collect::<Vec<_>>();
+
+            proof.verify(vector.root(), &items).unwrap();
+
+            // Every sibling is computed from the items themselves
+            assert!(proof.hashes.is_empty());
+        }
+    }
+
+    #[test]
+    fn subsets() {
+        for len in [2usize, 7, 20, 33] {
+            let vector = Vector::<_, 3>::new((0..len as u32).collect()).unwrap();
+
+            for stride in 1..len {
+                let indices = (0..len).step_by(stride).collect::<Vec<_>>();
+                let proof = vector.prove_many(&indices).unwrap();
+
+                let items = indices
+                    .iter()
+                    .map(|index| (*index, *index as u32))
+                    .collect::<Vec<_>>();
+
+                proof.verify(vector.root(), &items).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn unsorted_duplicates() {
+        let vector = Vector::<_>::new((0..37u32).collect()).unwrap();
+
+        let proof = vector.prove_many(&[20, 3, 20, 7, 3]).unwrap();
+        assert_eq!(proof.indices(), &[3, 7, 20]);
+
+        proof
+            .verify(vector.root(), &[(7, 7u32), (20, 20), (3, 3), (7, 7)])
+            .unwrap();
+
+        assert!(matches!(
+            proof
+                .verify(vector.root(), &[(7, 7u32), (20, 20), (3, 3), (7, 8)])
+                .unwrap_err()
+                .top(),
+            ProofError::ItemMismatch
+        ));
+    }
+
+    #[test]
+    fn smaller() {
+        let vector = Vector::<_>::new((0..100u32).collect()).unwrap();
+
+        for indices in [vec![0, 1], vec![10, 11, 12, 13], vec![3, 5, 6, 40, 41, 99]] {
+            let batch = vector.prove_many(&indices).unwrap();
+
+            let individual = indices
+                .iter()
+                .map(|index| vector.prove(*index).unwrap().auth_path().len())
+                .sum::<usize>();
+
+            assert!(batch.hashes.len() < individual);
+
+            let batch_size = bincode::serialize(&batch).unwrap().len();
+
+            let individual_size = indices
+                .iter()
+                .map(|index| {
+                    bincode::serialize(&vector.prove(*index).unwrap())
+                        .unwrap()
+                        .len()
+                })
+                .sum::<usize>();
+
+            assert!(batch_size < individual_size);
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let vector = Vector::<_>::new((0..5u32).collect()).unwrap();
+        let proof = vector.prove_many(&[]).unwrap();
+
+        proof.verify::<u32>(vector.root(), &[]).unwrap();
+        assert!(proof.verify(vector.root(), &[(0, 0u32)]).is_err());
+    }
+
+    #[test]
+    fn tampered() {
+        for len in [3usize, 10, 37] {
+            let vector = Vector::<_, 2>::new((0..len as u32).collect()).unwrap();
+            let other = Vector::<_, 2>::new((1..len as u32 + 1).collect()).unwrap();
+
+            let proof = vector.prove_many(&[0, len - 1]).unwrap();
+
+            proof
+                .verify(vector.root(), &[(0, 0u32), (len - 1, len as u32 - 1)])
+                .unwrap();
+
+            // Wrong item
+            assert!(proof
+                .verify(vector.root(), &[(0, 1u32), (len - 1, len as u32 - 1)])
+                .is_err());
+
+            // Wrong root
+            assert!(proof
+                .verify(other.root(), &[(0, 0u32), (len - 1, len as u32 - 1)])
+                .is_err());
+
+            // Items at the wrong positions
+            assert!(matches!(
+                proof
+                    .verify(vector.root(), &[(1, 0u32), (len - 1, len as u32 - 1)])
+                    .unwrap_err()
+                    .top(),
+                ProofError::OutOfPath
+            ));
+
+            // Swapped items
+            assert!(proof
+                .verify(vector.root(), &[(0, len as u32 - 1), (len - 1, 0u32)])
+                .is_err());
+
+            // Missing item
+            assert!(proof.verify(vector.root(), &[(0, 0u32)]).is_err());
+
+            // Missing sibling digest
+            let mut truncated = proof.clone();
+
+            if truncated.hashes.pop().is_some() {
+                assert!(truncated
+                    .verify(vector.root(), &[(0, 0u32), (len - 1, len as u32 - 1)])
+                    .is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn out_of_range() {
+        let vector = Vector::<_>::new((0..5u32).collect()).unwrap();
+        assert!(vector.prove_many(&[0, 5]).is_err());
+    }
+}
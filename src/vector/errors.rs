@@ -4,6 +4,8 @@ use doomstack::Doom;
 pub enum VectorError {
     #[doom(description("Failed to hash item"))]
     HashError,
+    #[doom(description("Index out of range"))]
+    IndexOutOfRange,
 }
 
 #[derive(Doom)]
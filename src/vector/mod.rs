@@ -1,3 +1,4 @@
+mod batch_proof;
 mod node;
 mod proof;
 mod vector;
@@ -6,5 +7,8 @@ pub mod errors;
 
 use node::Node;
 
+pub use node::hash_internal;
+
+pub use batch_proof::BatchProof;
 pub use proof::Proof;
 pub use vector::Vector;
@@ -1,8 +1,16 @@
 use serde::Serialize;
-use talk::crypto::primitives::hash::Hash;
+use talk::crypto::primitives::{hash, hash::Hash};
 
 #[derive(Serialize)]
 pub(in crate::vector) enum Node<I: Serialize> {
     Internal(Hash, Hash),
     Item(I),
 }
+
+/// Returns the digest of an internal node of a [`Vector`] whose
+/// children have digests `left` and `right`.
+///
+/// [`Vector`]: crate::vector::Vector
+pub fn hash_internal(left: Hash, right: Hash) -> Hash {
+    hash::hash(&Node::<()>::Internal(left, right)).unwrap()
+}
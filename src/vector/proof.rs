@@ -1,8 +1,7 @@
-use bit_vec::BitVec;
-
 use crate::{
+    auth_path::AuthPath,
     common::tree::Direction,
-    vector::{errors::ProofError, Node},
+    vector::{errors::ProofError, node, Node},
 };
 
 use doomstack::{here, Doom, ResultExt, Top};
@@ -13,15 +12,23 @@ use serde_bytes::ByteBuf;
 
 use talk::crypto::primitives::{hash, hash::Hash};
 
+/// A proof that an item is stored at some index of a [`Vector`],
+/// obtained through [`Vector::prove`].
+///
+/// [`Vector`]: crate::vector::Vector
+/// [`Vector::prove`]: crate::vector::Vector::prove
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Proof {
-    path: BitVec,
-    proof: Vec<Hash>,
+    len: usize,
+    packing: usize,
+    auth_path: AuthPath,
     siblings: Option<(Vec<ByteBuf>, usize)>,
 }
 
 impl Proof {
     pub(in crate::vector) fn new<I, Item: Serialize>(
+        len: usize,
+        packing: usize,
         path: I,
         proof: Vec<Hash>,
         siblings: Option<(Vec<&Item>, usize)>,
@@ -29,11 +36,6 @@ impl Proof {
     where
         I: IntoIterator<Item = Direction>,
     {
-        let path = path
-            .into_iter()
-            .map(|direction| direction == Direction::Left)
-            .collect::<BitVec>();
-
         let siblings = match siblings {
             None => None,
             Some((vec, pos)) => {
@@ -46,18 +48,47 @@ impl Proof {
         };
 
         Proof {
-            path,
-            proof,
+            len,
+            packing,
+            auth_path: AuthPath::new(path, proof),
             siblings,
         }
     }
 
-    pub fn verify<Item: Serialize + for<'de> Deserialize<'de>>(
+    /// Returns the length of the [`Vector`] the `Proof` was generated from.
+    ///
+    /// The root of a [`Vector`] does not commit to its length: a verifier
+    /// expecting a [`Vector`] of known length should check it against
+    /// this value, lest the proof be crafted for a differently shaped tree.
+    ///
+    /// [`Vector`]: crate::vector::Vector
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the `Proof` was generated from an empty [`Vector`].
+    /// As a [`Vector`] cannot be empty, this is only possible for a
+    /// malformed (e.g., deserialized) `Proof`.
+    ///
+    /// [`Vector`]: crate::vector::Vector
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the [`AuthPath`] from the (possibly packed) leaf
+    /// storing the proven item to the root.
+    ///
+    /// [`AuthPath`]: crate::auth_path::AuthPath
+    pub fn auth_path(&self) -> &AuthPath {
+        &self.auth_path
+    }
+
+    /// Returns the digest of the (possibly packed) leaf storing `item`.
+    pub fn leaf_digest<Item: Serialize + for<'de> Deserialize<'de>>(
         &self,
-        root: Hash,
         item: &Item,
-    ) -> Result<(), Top<ProofError>> {
-        let mut hash = match &self.siblings {
+    ) -> Result<Hash, Top<ProofError>> {
+        match &self.siblings {
             Some((vec, pos)) => {
                 let vec: Vec<Item> = vec
                     .iter()
@@ -66,24 +97,102 @@ impl Proof {
                 let mut vec: Vec<&Item> = vec.iter().collect();
                 vec.insert(*pos, item);
                 hash::hash(&Node::<&[&Item]>::Item(vec.as_slice()))
-                    .pot(ProofError::HashError, here!())?
+                    .pot(ProofError::HashError, here!())
             }
-            None => hash::hash(&Node::<&Item>::Item(item)).pot(ProofError::HashError, here!())?,
-        };
-
-        for (direction, sibling_hash) in self.path.iter().zip(self.proof.iter().cloned()) {
-            let parent = match direction {
-                true => Node::<Item>::Internal(hash, sibling_hash),
-                false => Node::<Item>::Internal(sibling_hash, hash),
-            };
-
-            hash = hash::hash(&parent).unwrap();
+            None => hash::hash(&Node::<&Item>::Item(item)).pot(ProofError::HashError, here!()),
         }
+    }
 
-        if root != hash {
-            return ProofError::RootMismatch.fail().spot(here!());
+    /// Verifies that `item` is stored at `index` of a [`Vector`] of
+    /// [`len`] items whose root is `root`.
+    ///
+    /// # Errors
+    ///
+    /// If the path (or the packed siblings) carried by the proof do not
+    /// lead to `index`, [`OutOfPath`] is returned.
+    ///
+    /// If `item` cannot be hashed, [`HashError`] is returned.
+    ///
+    /// If the proof does not lead from `item` to `root`, [`RootMismatch`]
+    /// is returned.
+    ///
+    /// [`Vector`]: crate::vector::Vector
+    /// [`len`]: crate::vector::Proof::len
+    /// [`OutOfPath`]: crate::vector::errors::ProofError::OutOfPath
+    /// [`HashError`]: crate::vector::errors::ProofError::HashError
+    /// [`RootMismatch`]: crate::vector::errors::ProofError::RootMismatch
+    pub fn verify<Item: Serialize + for<'de> Deserialize<'de>>(
+        &self,
+        root: Hash,
+        index: usize,
+        item: &Item,
+    ) -> Result<(), Top<ProofError>> {
+        if !self.leads_to(index) {
+            return ProofError::OutOfPath.fail().spot(here!());
         }
 
+        let leaf = self.leaf_digest(item)?;
+
+        self.auth_path
+            .verify(root, leaf, node::hash_internal)
+            .pot(ProofError::RootMismatch, here!())?;
+
         Ok(())
     }
+
+    // Checks that the shape of the proof matches that of the path from
+    // `index` to the root of a `Vector` of `self.len` items
+    fn leads_to(&self, index: usize) -> bool {
+        if index >= self.len || self.packing == 0 {
+            return false;
+        }
+
+        let chunk = index / self.packing;
+        let chunk_len = std::cmp::min(self.packing, self.len - chunk * self.packing);
+
+        let siblings = match &self.siblings {
+            None => self.packing == 1,
+            Some((vec, pos)) => {
+                self.packing > 1 && *pos == index % self.packing && vec.len() + 1 == chunk_len
+            }
+        };
+
+        let (position, depth) = position(self.len.div_ceil(self.packing), chunk);
+
+        siblings
+            && self.auth_path.len() == depth
+            && self
+                .auth_path
+                .directions()
+                .enumerate()
+                .all(|(level, direction)| {
+                    let bit = (position >> level) & 1;
+                    direction
+                        == if bit == 0 {
+                            Direction::Left
+                        } else {
+                            Direction::Right
+                        }
+                })
+    }
+}
+
+// Returns the position of leaf `chunk` within its layer, and the depth
+// of that layer, in a `Vector` with `chunks` leaves (see `Vector::from_leaves`)
+fn position(chunks: usize, chunk: usize) -> (usize, usize) {
+    let pow = std::cmp::max(
+        1,
+        chunks.checked_next_power_of_two().unwrap_or(usize::MAX) / 2,
+    );
+
+    let bottom = std::cmp::max(1, 2 * (chunks - pow));
+    let depth = pow.trailing_zeros() as usize;
+
+    if chunks == bottom {
+        (chunk, chunks.trailing_zeros() as usize)
+    } else if chunk < bottom {
+        (chunk, depth + 1)
+    } else {
+        (chunk - bottom / 2, depth)
+    }
 }
@@ -1,12 +1,14 @@
 use crate::{
     common::tree::Direction,
-    vector::{errors::VectorError, Node, Proof},
+    vector::{batch_proof, errors::VectorError, BatchProof, Node, Proof},
 };
 
-use doomstack::{here, ResultExt, Top};
+use doomstack::{here, Doom, ResultExt, Top};
 
 use serde::{de::Error as DeError, Deserialize, Deserializer, Serialize, Serializer};
 
+use std::collections::BTreeSet;
+
 use talk::crypto::primitives::{hash, hash::Hash};
 
 #[derive(Debug, Clone)]
@@ -32,9 +34,35 @@ where
             panic!("called `PackedVector::new` with an empty `items`");
         }
 
-        let mut layers = Vec::new();
+        let nodes = Self::hash_chunks(&items, packing)?;
+        Ok(Self::from_leaves(nodes, items))
+    }
+
+    /// Concatenates `a` and `b` into a vector whose items are those of `a`
+    /// followed by those of `b`. Item digests are reused from both vectors:
+    /// only internal nodes (and, if the last chunk of `a` is not full,
+    /// the chunks straddling the merge point) are rehashed.
+    pub fn concat(a: Self, b: Self) -> Result<Self, Top<VectorError>> {
+        let boundary = a.items.len() / PACKING;
 
-        let mut nodes = items
+        let mut leaves = a.leaves();
+        let b_leaves = b.leaves();
+
+        let mut items = a.items;
+        items.extend(b.items);
+
+        if boundary == leaves.len() {
+            leaves.extend(b_leaves);
+        } else {
+            leaves.truncate(boundary);
+            leaves.extend(Self::hash_chunks(&items[boundary * PACKING..], PACKING)?);
+        }
+
+        Ok(Self::from_leaves(leaves, items))
+    }
+
+    fn hash_chunks(items: &[Item], packing: usize) -> Result<Vec<Hash>, Top<VectorError>> {
+        items
             .iter()
             .collect::<Vec<&Item>>()
             .chunks(packing)
@@ -46,7 +74,11 @@ where
                     hash::hash(&Node::<&[&Item]>::Item(chunk)).pot(VectorError::HashError, here!())
                 }
             })
-            .collect::<Result<Vec<Hash>, Top<VectorError>>>()?;
+            .collect::<Result<Vec<Hash>, Top<VectorError>>>()
+    }
+
+    fn from_leaves(mut nodes: Vec<Hash>, items: Vec<Item>) -> Self {
+        let mut layers = Vec::new();
 
         let pow = std::cmp::max(
             1,
@@ -90,20 +122,207 @@ where
 
         layers.push(layer);
 
-        Ok(Vector { layers, items })
+        Vector { layers, items }
+    }
+
+    // Returns the digests of all item chunks, in order. Chunks in the
+    // (partial) bottom layer are followed by those one layer above.
+    fn leaves(&self) -> Vec<Hash> {
+        let chunks = self.items.len().div_ceil(PACKING);
+        let first = &self.layers[0];
+
+        if first.len() == chunks {
+            first.clone()
+        } else {
+            first
+                .iter()
+                .chain(self.layers[1][first.len() / 2..].iter())
+                .copied()
+                .collect()
+        }
+    }
+
+    // Returns the digest of item chunk `chunk` (see `leaves`)
+    fn leaf(&self, chunk: usize) -> Hash {
+        let first = &self.layers[0];
+
+        if chunk < first.len() {
+            first[chunk]
+        } else {
+            self.layers[1][chunk - first.len() / 2]
+        }
     }
 
     pub fn set(&mut self, index: usize, item: Item) -> Result<(), Top<VectorError>> {
         assert!(index < self.items.len());
 
         self.items[index] = item;
+        self.refresh(index / PACKING)
+    }
+
+    /// Appends `item` to the back of the `Vector`.
+    ///
+    /// If `item` fits in the last (non-full) chunk of the `Vector`, only
+    /// that chunk and its ancestors are rehashed. Otherwise, the `Vector`
+    /// gains a chunk: as the tree is kept complete, the chunks one layer
+    /// above the bottom shift right by one position, and only the internal
+    /// nodes spanning the new or shifted chunks are rehashed (i.e., the
+    /// right spine, when the bottom layer is almost full). When the number
+    /// of chunks exceeds a power of two, the tree gains a level and all its
+    /// internal nodes are recomputed. No item is rehashed besides `item`.
+    /// To append many items, see [`extend`].
+    ///
+    /// # Errors
+    ///
+    /// If `item` cannot be hashed, [`HashError`] is returned and the `Vector`
+    /// is left unchanged.
+    ///
+    /// [`extend`]: crate::vector::Vector::extend
+    /// [`HashError`]: crate::vector::errors::VectorError::HashError
+    pub fn push(&mut self, item: Item) -> Result<(), Top<VectorError>> {
+        self.extend(std::iter::once(item))
+    }
+
+    /// Appends all `items` to the back of the `Vector`, in order.
+    ///
+    /// Digests are reused for all chunks of the `Vector`, except its last
+    /// one if not full: only new chunks are hashed, and the internal nodes
+    /// affected by the whole batch are rehashed once (see [`push`]).
+    ///
+    /// # Errors
+    ///
+    /// If any of `items` cannot be hashed, [`HashError`] is returned and the
+    /// `Vector` is left unchanged.
+    ///
+    /// [`push`]: crate::vector::Vector::push
+    /// [`HashError`]: crate::vector::errors::VectorError::HashError
+    pub fn extend<I>(&mut self, items: I) -> Result<(), Top<VectorError>>
+    where
+        I: IntoIterator<Item = Item>,
+    {
+        self.append(items.into_iter().collect()).map(|_| ())
+    }
+
+    // Appends `items`, returning the number of internal nodes rehashed
+    fn append(&mut self, items: Vec<Item>) -> Result<usize, Top<VectorError>> {
+        if items.is_empty() {
+            return Ok(0);
+        }
+
+        let len = self.items.len();
+        let chunks = len.div_ceil(PACKING);
+        let boundary = len / PACKING;
+
+        self.items.extend(items);
+
+        if self.items.len().div_ceil(PACKING) == chunks {
+            // All `items` fit in the last chunk, whose position in the tree
+            // is unchanged: only its path to the root is updated
+            if let Err(error) = self.refresh(chunks - 1) {
+                self.items.truncate(len);
+                return Err(error);
+            }
+
+            return Ok(self.layers.len() - 1);
+        }
+
+        let tail = match Self::hash_chunks(&self.items[boundary * PACKING..], PACKING) {
+            Ok(tail) => tail,
+            Err(error) => {
+                self.items.truncate(len);
+                return Err(error);
+            }
+        };
+
+        let grown = boundary + tail.len();
+
+        if chunks.next_power_of_two() == grown.next_power_of_two() {
+            return Ok(self.grow(boundary, tail));
+        }
+
+        // The tree gains a level: every chunk moves, and the tree is rebuilt
+        let appended = self.items.split_off(len);
+        let mut leaves = self.leaves();
+        self.items.extend(appended);
+
+        leaves.truncate(boundary);
+        leaves.extend(tail);
+
+        let items = std::mem::take(&mut self.items);
+        *self = Self::from_leaves(leaves, items);
+
+        Ok(grown - 1)
+    }
+
+    // Replaces all chunks from `first` on with `tail`, without changing the
+    // number of levels of the tree: only internal nodes spanning a replaced
+    // chunk, or a chunk shifted by the growth of the bottom layer, are
+    // rehashed. Returns the number of internal nodes rehashed.
+    fn grow(&mut self, first: usize, tail: Vec<Hash>) -> usize {
+        let bottom = self.layers[0].len();
+        let start = std::cmp::min(first, bottom);
+
+        // Chunks `start..` of the grown tree: those that were one layer above
+        // the bottom (if any), followed by `tail`
+        let shifted = first.saturating_sub(bottom);
+
+        let mut suffix = self.layers[1][bottom / 2..bottom / 2 + shifted].to_vec();
+        suffix.extend(tail);
+
+        let chunks = start + suffix.len();
+        let grown_bottom = 2 * (chunks - self.layers[1].len());
+
+        let mut suffix = suffix.into_iter();
+
+        self.layers[0].truncate(start);
+        self.layers[0].extend(suffix.by_ref().take(grown_bottom - start));
+
+        let mut from = start / 2;
+
+        let (lower, upper) = self.layers.split_at_mut(1);
+        let layer = &mut upper[0];
+
+        layer.truncate(from);
+
+        layer.extend(
+            lower[0][2 * from..]
+                .chunks(2)
+                .map(|pair| hash::hash(&Node::<Item>::Internal(pair[0], pair[1])).unwrap()),
+        );
+
+        layer.extend(suffix);
+
+        let mut rehashed = grown_bottom / 2 - from;
+
+        for index in 2..self.layers.len() {
+            from /= 2;
+
+            let (lower, upper) = self.layers.split_at_mut(index);
+            let (lower, layer) = (&lower[index - 1], &mut upper[0]);
+
+            for position in from..layer.len() {
+                layer[position] = hash::hash(&Node::<Item>::Internal(
+                    lower[2 * position],
+                    lower[2 * position + 1],
+                ))
+                .unwrap();
+            }
+
+            rehashed += layer.len() - from;
+        }
+
+        rehashed
+    }
+
+    // Rehashes item chunk `chunk`, then updates its path to the root
+    fn refresh(&mut self, chunk: usize) -> Result<(), Top<VectorError>> {
+        let start = chunk * PACKING;
 
         let mut node_hash = if PACKING == 1 {
-            hash::hash(&Node::<&Item>::Item(self.items.get(index).unwrap()))
+            hash::hash(&Node::<&Item>::Item(self.items.get(start).unwrap()))
                 .pot(VectorError::HashError, here!())?
         } else {
-            let chunk = ((index - index % PACKING)
-                ..std::cmp::min(index - index % PACKING + PACKING, self.items.len()))
+            let chunk = (start..std::cmp::min(start + PACKING, self.items.len()))
                 .map(|index| self.items.get(index).unwrap())
                 .collect::<Vec<_>>();
 
@@ -111,7 +330,7 @@ where
                 .pot(VectorError::HashError, here!())?
         };
 
-        let node_index = index / PACKING;
+        let node_index = chunk;
 
         let first_layer_len = self.layers[0].len();
         let mut layers = self.layers.iter_mut();
@@ -148,12 +367,37 @@ where
         self.layers.last().unwrap()[0]
     }
 
+    /// Returns the commitment of the `Vector`, i.e., its [`root`],
+    /// against which its [`Proof`]s are verified.
+    ///
+    /// A `Vector` is serialized as its items alone: the commitment of a
+    /// deserialized `Vector` is recomputed, and matches that of the original.
+    ///
+    /// [`root`]: crate::vector::Vector::root
+    /// [`Proof`]: crate::vector::Proof
+    pub fn commit(&self) -> Hash {
+        self.root()
+    }
+
     pub fn items(&self) -> &[Item] {
         &self.items
     }
 
-    pub fn prove(&self, index: usize) -> Proof {
-        assert!(index < self.items.len());
+    /// Returns a [`Proof`] that the item at `index` is stored in the
+    /// `Vector`, to be checked against [`root`] by [`Proof::verify`].
+    ///
+    /// # Errors
+    ///
+    /// If `index` is out of range, [`IndexOutOfRange`] is returned.
+    ///
+    /// [`Proof`]: crate::vector::Proof
+    /// [`root`]: crate::vector::Vector::root
+    /// [`Proof::verify`]: crate::vector::Proof::verify
+    /// [`IndexOutOfRange`]: crate::vector::errors::VectorError::IndexOutOfRange
+    pub fn prove(&self, index: usize) -> Result<Proof, Top<VectorError>> {
+        if index >= self.items.len() {
+            return VectorError::IndexOutOfRange.fail().spot(here!());
+        }
 
         let mut path: Vec<Direction> = Vec::new();
         let mut proof: Vec<Hash> = Vec::new();
@@ -198,7 +442,73 @@ where
             Some((siblings, index % PACKING))
         };
 
-        Proof::new(path, proof, siblings)
+        Ok(Proof::new(self.items.len(), PACKING, path, proof, siblings))
+    }
+
+    /// Returns a [`BatchProof`] that the items at all `indices` are stored
+    /// in the `Vector`, to be checked against [`root`] by [`BatchProof::verify`].
+    /// `indices` can be unsorted, and contain duplicates.
+    ///
+    /// # Errors
+    ///
+    /// If any of `indices` is out of range, [`IndexOutOfRange`] is returned.
+    ///
+    /// [`BatchProof`]: crate::vector::BatchProof
+    /// [`root`]: crate::vector::Vector::root
+    /// [`BatchProof::verify`]: crate::vector::BatchProof::verify
+    /// [`IndexOutOfRange`]: crate::vector::errors::VectorError::IndexOutOfRange
+    pub fn prove_many(&self, indices: &[usize]) -> Result<BatchProof, Top<VectorError>> {
+        let indices = indices.iter().copied().collect::<BTreeSet<_>>();
+
+        if indices
+            .last()
+            .is_some_and(|index| *index >= self.items.len())
+        {
+            return VectorError::IndexOutOfRange.fail().spot(here!());
+        }
+
+        let chunks = indices
+            .iter()
+            .map(|index| index / PACKING)
+            .collect::<BTreeSet<_>>();
+
+        let siblings = if PACKING == 1 {
+            Vec::new()
+        } else {
+            chunks
+                .iter()
+                .flat_map(|chunk| {
+                    chunk * PACKING..std::cmp::min((chunk + 1) * PACKING, self.items.len())
+                })
+                .filter(|index| !indices.contains(index))
+                .map(|index| &self.items[index])
+                .collect()
+        };
+
+        let leaves = chunks
+            .iter()
+            .map(|chunk| (*chunk, self.leaf(*chunk)))
+            .collect();
+
+        let mut hashes = Vec::new();
+
+        batch_proof::climb(
+            self.items.len().div_ceil(PACKING),
+            leaves,
+            |layer, position| {
+                let digest = self.layers[layer][position];
+                hashes.push(digest);
+                Some(digest)
+            },
+        );
+
+        Ok(BatchProof::new(
+            self.items.len(),
+            PACKING,
+            indices.into_iter().collect(),
+            siblings,
+            hashes,
+        ))
     }
 }
 
@@ -223,6 +533,12 @@ where
         D: Deserializer<'de>,
     {
         let items = Vec::<Item>::deserialize(deserializer)?;
+
+        // `Vector::new` panics on empty `items`
+        if items.is_empty() {
+            return Err(DeError::custom("empty `Vector`"));
+        }
+
         Ok(Vector::new(items).map_err(|err| DeError::custom(err))?)
     }
 }
@@ -416,8 +732,8 @@ mod tests {
             let vector = Vector::<_>::new((0..len).collect()).unwrap();
 
             for item in 0..len {
-                let proof = vector.prove(item);
-                proof.verify(vector.root(), &item).unwrap();
+                let proof = vector.prove(item).unwrap();
+                proof.verify(vector.root(), item, &item).unwrap();
             }
         }
     }
@@ -428,8 +744,8 @@ mod tests {
             let vector = Vector::<_, 2>::new((0..len).collect()).unwrap();
 
             for item in 0..len {
-                let proof = vector.prove(item);
-                proof.verify(vector.root(), &item).unwrap();
+                let proof = vector.prove(item).unwrap();
+                proof.verify(vector.root(), item, &item).unwrap();
             }
         }
     }
@@ -440,8 +756,8 @@ mod tests {
             let vector = Vector::<_, 3>::new((0..len).collect()).unwrap();
 
             for item in 0..len {
-                let proof = vector.prove(item);
-                proof.verify(vector.root(), &item).unwrap();
+                let proof = vector.prove(item).unwrap();
+                proof.verify(vector.root(), item, &item).unwrap();
             }
         }
     }
@@ -452,8 +768,85 @@ mod tests {
             let vector = Vector::<_, 4>::new((0..len).collect()).unwrap();
 
             for item in 0..len {
-                let proof = vector.prove(item);
-                proof.verify(vector.root(), &item).unwrap();
+                let proof = vector.prove(item).unwrap();
+                proof.verify(vector.root(), item, &item).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn proof_one_item() {
+        let vector = Vector::<_>::new(vec![0u32]).unwrap();
+        let proof = vector.prove(0).unwrap();
+
+        assert!(proof.auth_path().is_empty());
+        proof.verify(vector.root(), 0, &0u32).unwrap();
+    }
+
+    #[test]
+    fn proof_out_of_range() {
+        for len in [1, 2, 7, 64] {
+            let vector = Vector::<_, 3>::new((0..len as u32).collect()).unwrap();
+
+            assert!(matches!(
+                vector.prove(len).err().unwrap().top(),
+                VectorError::IndexOutOfRange
+            ));
+
+            let proof = vector.prove(len - 1).unwrap();
+
+            assert!(proof
+                .verify(vector.root(), len, &((len - 1) as u32))
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn proof_tampered() {
+        for len in [2, 3, 5, 6, 17, 64, 100] {
+            let vector = Vector::<_>::new((0..len as u32).collect()).unwrap();
+            let other = Vector::<_>::new((1..len as u32 + 1).collect()).unwrap();
+
+            for index in 0..len {
+                let item = index as u32;
+                let proof = vector.prove(index).unwrap();
+
+                proof.verify(vector.root(), index, &item).unwrap();
+
+                assert!(proof.verify(vector.root(), index, &(item + 1)).is_err());
+                assert!(proof.verify(other.root(), index, &item).is_err());
+
+                for wrong in 0..len {
+                    if wrong != index {
+                        assert!(proof.verify(vector.root(), wrong, &item).is_err());
+                    }
+                }
+
+                // The proof of another index does not prove `item`
+                let swapped = vector.prove((index + 1) % len).unwrap();
+                assert!(swapped.verify(vector.root(), index, &item).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn proof_tampered_3packed() {
+        for len in [2, 4, 10, 33] {
+            let vector = Vector::<_, 3>::new((0..len as u32).collect()).unwrap();
+
+            for index in 0..len {
+                let item = index as u32;
+                let proof = vector.prove(index).unwrap();
+
+                proof.verify(vector.root(), index, &item).unwrap();
+
+                assert!(proof.verify(vector.root(), index, &(item + 1)).is_err());
+
+                for wrong in 0..len {
+                    if wrong != index {
+                        assert!(proof.verify(vector.root(), wrong, &item).is_err());
+                    }
+                }
             }
         }
     }
@@ -472,6 +865,166 @@ mod tests {
         }
     }
 
+    #[test]
+    fn concat() {
+        for (a_len, b_len) in [(1, 1), (1, 4), (3, 5), (8, 8), (13, 50)] {
+            let a = Vector::<_>::new((0..a_len).collect()).unwrap();
+            let b = Vector::<_>::new((a_len..a_len + b_len).collect()).unwrap();
+
+            let control = Vector::<_>::new((0..a_len + b_len).collect()).unwrap();
+            let vector = Vector::concat(a, b).unwrap();
+
+            assert_eq!(vector.len(), a_len + b_len);
+            assert_eq!(vector.items(), control.items());
+            assert_eq!(vector.root(), control.root());
+
+            for item in [0, a_len - 1, a_len, a_len + b_len - 1] {
+                let proof = vector.prove(item).unwrap();
+                proof.verify(vector.root(), item, &item).unwrap();
+            }
+        }
+    }
+
+    fn check_push<const PACKING: usize>() {
+        let mut vector = Vector::<_, PACKING>::new(vec![0]).unwrap();
+
+        for len in 2..150 {
+            vector.push(len - 1).unwrap();
+
+            let control = Vector::<_, PACKING>::new((0..len).collect()).unwrap();
+
+            assert_eq!(vector.items(), control.items());
+            assert_eq!(vector.layers, control.layers);
+            assert_eq!(vector.commit(), control.commit());
+
+            for item in [0, len / 2, len - 1] {
+                let proof = vector.prove(item).unwrap();
+                proof.verify(vector.commit(), item, &item).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn push() {
+        check_push::<1>();
+        check_push::<2>();
+        check_push::<3>();
+    }
+
+    #[test]
+    fn extend() {
+        for step in [0, 1, 2, 5, 13] {
+            let mut vector = Vector::<_, 3>::new(vec![0]).unwrap();
+            let mut len = 1;
+
+            while len < 200 {
+                vector.extend(len..len + step).unwrap();
+                len += step;
+
+                let control = Vector::<_, 3>::new((0..len).collect()).unwrap();
+
+                assert_eq!(vector.items(), control.items());
+                assert_eq!(vector.layers, control.layers);
+
+                if step == 0 {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn append_rehashes() {
+        let mut vector = Vector::<_>::new(vec![0]).unwrap();
+
+        for len in 2..=1024usize {
+            let before = vector.layers.clone();
+            let rehashed = vector.append(vec![len - 1]).unwrap();
+
+            if len.is_power_of_two() || (len - 1).is_power_of_two() {
+                // Filling the bottom layer completes the right spine
+                if len.is_power_of_two() {
+                    assert_eq!(rehashed, len.trailing_zeros() as usize);
+                }
+
+                // Exceeding a power of two adds a level, rebuilding the tree
+                if (len - 1).is_power_of_two() {
+                    assert_eq!(rehashed, len - 1);
+                }
+            } else {
+                // Only internal nodes that changed are rehashed
+                let changed = vector.layers[1..]
+                    .iter()
+                    .zip(&before[1..])
+                    .map(|(layer, before)| {
+                        layer
+                            .iter()
+                            .zip(before)
+                            .filter(|(digest, before)| digest != before)
+                            .count()
+                    })
+                    .sum::<usize>();
+
+                assert!(rehashed <= changed);
+            }
+        }
+
+        let control = Vector::<_>::new((0..1024usize).collect()).unwrap();
+        assert_eq!(vector.layers, control.layers);
+    }
+
+    #[test]
+    fn concat_3packed() {
+        for (a_len, b_len) in [(1, 1), (3, 3), (4, 7), (6, 20), (20, 6)] {
+            let a = Vector::<_, 3>::new((0..a_len).collect()).unwrap();
+            let b = Vector::<_, 3>::new((a_len..a_len + b_len).collect()).unwrap();
+
+            let control = Vector::<_, 3>::new((0..a_len + b_len).collect()).unwrap();
+            let vector = Vector::concat(a, b).unwrap();
+
+            assert_eq!(vector.items(), control.items());
+            assert_eq!(vector.root(), control.root());
+
+            for item in [0, a_len - 1, a_len, a_len + b_len - 1] {
+                let proof = vector.prove(item).unwrap();
+                proof.verify(vector.root(), item, &item).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn serde_empty() {
+        let serialized = bincode::serialize(&Vec::<u32>::new()).unwrap();
+        assert!(bincode::deserialize::<Vector<u32>>(&serialized).is_err());
+    }
+
+    #[test]
+    fn serde_one_item() {
+        let original = Vector::<_>::new(vec![0u32]).unwrap();
+        let serialized = bincode::serialize(&original).unwrap();
+        let deserialized = bincode::deserialize::<Vector<u32>>(&serialized).unwrap();
+
+        assert_eq!(original.items(), deserialized.items());
+        assert_eq!(original.commit(), deserialized.commit());
+    }
+
+    #[test]
+    fn serde_large() {
+        let original = Vector::<_, 3>::new((0..10000u32).collect()).unwrap();
+        let serialized = bincode::serialize(&original).unwrap();
+        let deserialized = bincode::deserialize::<Vector<u32, 3>>(&serialized).unwrap();
+
+        assert_eq!(original.commit(), deserialized.commit());
+
+        for index in [0, 1, 4999, 9998, 9999] {
+            let proof = original.prove(index).unwrap();
+
+            proof
+                .verify(deserialized.commit(), index, &(index as u32))
+                .unwrap();
+        }
+    }
+
     #[test]
     fn serde() {
         let original = Vector::<_>::new((0..128).collect()).unwrap();
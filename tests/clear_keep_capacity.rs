@@ -0,0 +1,79 @@
+// Counting allocations requires replacing the global allocator, which would
+// affect every test sharing the binary: this test lives in its own binary.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use zebra::database::{Database, TableTransaction};
+
+// Counts allocations made by each thread
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|allocations| allocations.set(allocations.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(|allocations| allocations.get())
+}
+
+#[test]
+fn clear_keep_capacity() {
+    // Operations are applied sequentially, so that all allocations
+    // happen on the current thread
+    let reused: Database<u32, u32> = Database::with_parallel_threshold(usize::MAX);
+    let fresh: Database<u32, u32> = Database::with_parallel_threshold(usize::MAX);
+
+    let transaction = || {
+        let mut transaction = TableTransaction::new();
+
+        for (key, value) in (0..4096).map(|i| (i, i)) {
+            transaction.set(key, value).unwrap();
+        }
+
+        transaction
+    };
+
+    let mut table = reused.empty_table();
+    table.execute(transaction());
+
+    table.clear_keep_capacity();
+    assert_eq!(table.commit(), reused.empty_table().commit());
+
+    let transaction_reused = transaction();
+    let transaction_fresh = transaction();
+
+    let mut fresh_table = fresh.empty_table();
+
+    let before = allocations();
+    table.execute(transaction_reused);
+    let reused_allocations = allocations() - before;
+
+    let before = allocations();
+    fresh_table.execute(transaction_fresh);
+    let fresh_allocations = allocations() - before;
+
+    assert!(reused_allocations < fresh_allocations);
+
+    assert_eq!(table.commit(), fresh_table.commit());
+
+    for key in (0..4096).step_by(64) {
+        assert_eq!(table.get(&key).unwrap(), Some(key));
+    }
+}